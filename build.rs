@@ -38,6 +38,7 @@ fn main() -> anyhow::Result<()> {
             test_directory_module(out, "tests/misc_testsuite/memory64", strategy)?;
             test_directory_module(out, "tests/misc_testsuite/component-model", strategy)?;
             test_directory_module(out, "tests/misc_testsuite/function-references", strategy)?;
+            test_directory_module(out, "tests/misc_testsuite/inlining", strategy)?;
             // The testsuite of Winch is a subset of the official
             // WebAssembly test suite, until parity is reached. This
             // check is in place to prevent Cranelift from duplicating
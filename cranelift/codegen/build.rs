@@ -345,6 +345,7 @@ fn run_compilation(compilation: &IsleCompilation) -> Result<(), Errors> {
         // include!()s it. (See
         // https://github.com/rust-lang/rust/issues/47995.)
         options.exclude_global_allow_pragmas = true;
+        options.rule_coverage = cfg!(feature = "isle-coverage");
 
         isle::compile::from_files(file_paths, &options)?
     };
@@ -53,6 +53,18 @@ pub(crate) fn define() -> SettingGroup {
         true,
     );
 
+    settings.add_bool(
+        "enable_inlining",
+        "Inline small direct calls when callee bodies are available.",
+        r#"
+            Cranelift compiles one function at a time, so inlining is driven by the
+            embedder, which provides callee bodies to `Context::inline`. This setting
+            tells embedders which support it to do so. Only effective when `opt_level`
+            is `speed` or `speed_and_size`.
+        "#,
+        false,
+    );
+
     settings.add_bool(
         "enable_verifier",
         "Run the Cranelift IR verifier at strategic times during compilation.",
@@ -301,6 +313,27 @@ pub(crate) fn define() -> SettingGroup {
         true,
     );
 
+    settings.add_bool(
+        "enable_heap_bounds_check_elimination",
+        "Remove redundant heap bounds checks and hoist them out of loops.",
+        r#"
+            Every access to a dynamic heap compares its index against the
+            heap's current bound. Since heaps never shrink, a check that an
+            index is in bounds also covers later accesses to the same index,
+            or to that index plus a constant, with a smaller offset and size.
+            When this is enabled, frontends which support it drop the
+            redundant checks, or reuse the dominating check's condition for
+            the Spectre guard when heap Spectre mitigations are enabled.
+
+            The check of an index which steps by a constant in every
+            iteration of a counted loop is replaced with a single check of
+            the whole range of indices before the loop, when the loop has no
+            other side effects than the accesses. This has no effect when
+            `enable_pcc` is set.
+        "#,
+        false,
+    );
+
     settings.add_bool(
         "enable_table_access_spectre_mitigation",
         "Enable Spectre mitigation on table bounds checks.",
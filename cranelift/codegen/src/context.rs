@@ -14,6 +14,7 @@ use crate::dce::do_dce;
 use crate::dominator_tree::DominatorTree;
 use crate::egraph::EgraphPass;
 use crate::flowgraph::ControlFlowGraph;
+use crate::inline::{do_inline, InlineCallees, InlineOptions};
 use crate::ir::Function;
 use crate::isa::TargetIsa;
 use crate::legalizer::simple_legalize;
@@ -27,7 +28,6 @@ use crate::trace;
 use crate::unreachable_code::eliminate_unreachable_code;
 use crate::verifier::{verify_context, VerifierErrors, VerifierResult};
 use crate::{timing, CompileError};
-#[cfg(feature = "souper-harvest")]
 use alloc::string::String;
 use alloc::vec::Vec;
 use cranelift_control::ControlPlane;
@@ -54,6 +54,12 @@ pub struct Context {
 
     /// Flag: do we want a disassembly with the CompiledCode?
     pub want_disasm: bool,
+
+    /// Flag: do we want a log of the rewrites made by the egraph pass?
+    pub want_egraph_rewrites: bool,
+
+    /// The rewrites made by the egraph pass, if requested.
+    egraph_rewrites: Option<String>,
 }
 
 impl Context {
@@ -77,6 +83,8 @@ impl Context {
             loop_analysis: LoopAnalysis::new(),
             compiled_code: None,
             want_disasm: false,
+            want_egraph_rewrites: false,
+            egraph_rewrites: None,
         }
     }
 
@@ -88,6 +96,8 @@ impl Context {
         self.loop_analysis.clear();
         self.compiled_code = None;
         self.want_disasm = false;
+        self.want_egraph_rewrites = false;
+        self.egraph_rewrites = None;
     }
 
     /// Returns the compilation result for this function, available after any `compile` function
@@ -102,6 +112,22 @@ impl Context {
         self.want_disasm = val;
     }
 
+    /// Set the flag to request a log of the rewrites that the egraph pass
+    /// makes with the mid-end optimization rules.
+    pub fn set_egraph_rewrites(&mut self, val: bool) {
+        self.want_egraph_rewrites = val;
+    }
+
+    /// Returns the rewrites made by the egraph pass, one per line, if they
+    /// were requested with `set_egraph_rewrites` and the pass has run.
+    ///
+    /// Each line shows an instruction, prefixed by its source location if
+    /// it has one, followed by `=>` and an equivalent instruction or value
+    /// that a rule rewrote it to.
+    pub fn egraph_rewrites(&self) -> Option<&str> {
+        self.egraph_rewrites.as_deref()
+    }
+
     /// Compile the function, and emit machine code into a `Vec<u8>`.
     ///
     /// Run the function through all the passes necessary to generate
@@ -168,6 +194,7 @@ impl Context {
             self.func.display()
         );
 
+        self.egraph_rewrites = None;
         self.compute_cfg();
         if isa.flags().enable_nan_canonicalization() {
             self.canonicalize_nans(isa)?;
@@ -276,6 +303,20 @@ impl Context {
         Ok(())
     }
 
+    /// Inline direct calls to the small functions provided by `callees`.
+    ///
+    /// Returns whether any call was inlined.
+    pub fn inline<'a, FOI: Into<FlagsOrIsa<'a>>>(
+        &mut self,
+        callees: &mut dyn InlineCallees,
+        options: &InlineOptions,
+        fisa: FOI,
+    ) -> CodegenResult<bool> {
+        let inlined = do_inline(&mut self.func, callees, options);
+        self.verify_if(fisa)?;
+        Ok(inlined)
+    }
+
     /// Perform NaN canonicalizing rewrites on the function.
     pub fn canonicalize_nans(&mut self, isa: &dyn TargetIsa) -> CodegenResult<()> {
         do_nan_canonicalization(&mut self.func);
@@ -365,9 +406,13 @@ impl Context {
             &self.loop_analysis,
             &mut alias_analysis,
         );
+        if self.want_egraph_rewrites {
+            pass.record_rewrites();
+        }
         pass.run();
         log::debug!("egraph stats: {:?}", pass.stats);
         trace!("pinned_union_count: {}", pass.eclasses.pinned_union_count);
+        self.egraph_rewrites = pass.rewrites.take();
         trace!("After egraph optimization:\n{}", self.func.display());
 
         self.verify_if(fisa)
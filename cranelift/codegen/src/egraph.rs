@@ -15,6 +15,7 @@ use crate::opts::IsleContext;
 use crate::scoped_hash_map::{Entry as ScopedEntry, ScopedHashMap};
 use crate::trace;
 use crate::unionfind::UnionFind;
+use alloc::string::String;
 use cranelift_entity::packed_option::ReservedValue;
 use cranelift_entity::SecondaryMap;
 use smallvec::SmallVec;
@@ -64,6 +65,9 @@ pub struct EgraphPass<'a> {
     /// Union-find that maps all members of a Union tree (eclass) back
     /// to the *oldest* (lowest-numbered) `Value`.
     pub(crate) eclasses: UnionFind<Value>,
+    /// If requested, a log of the rewrites made by the mid-end rules,
+    /// one per line.
+    pub(crate) rewrites: Option<String>,
 }
 
 // The maximum number of rewrites we will take from a single call into ISLE.
@@ -84,6 +88,7 @@ where
     pub(crate) stats: &'opt mut Stats,
     pub(crate) alias_analysis: &'opt mut AliasAnalysis<'analysis>,
     pub(crate) alias_analysis_state: &'opt mut LastStores,
+    pub(crate) rewrites: Option<&'opt mut String>,
     // Held locally during optimization of one node (recursively):
     pub(crate) rewrite_depth: usize,
     pub(crate) subsume_values: FxHashSet<Value>,
@@ -207,6 +212,30 @@ where
         }
     }
 
+    /// Adds a rewrite of `inst` to `value` to the log of rewrites, if
+    /// one was requested.
+    fn record_rewrite(&mut self, inst: Inst, value: Value) {
+        use core::fmt::Write;
+
+        let Some(rewrites) = self.rewrites.as_deref_mut() else {
+            return;
+        };
+        let srcloc = self.func.srcloc(inst);
+        if !srcloc.is_default() {
+            write!(rewrites, "{srcloc} ").unwrap();
+        }
+        write!(rewrites, "{} => ", self.func.dfg.display_inst(inst)).unwrap();
+        match self.func.dfg.value_def(value) {
+            ValueDef::Result(def, _) => write!(rewrites, "{}", self.func.dfg.display_inst(def)),
+            _ => write!(rewrites, "{value}"),
+        }
+        .unwrap();
+        if self.subsume_values.contains(&value) {
+            rewrites.push_str(" (subsume)");
+        }
+        rewrites.push('\n');
+    }
+
     /// Optimizes an enode by applying any matching mid-end rewrite
     /// rules (or store-to-load forwarding, which is a special case),
     /// unioning together all possible optimized (or rewritten) forms
@@ -272,6 +301,7 @@ where
                 trace!(" -> same as orig value; skipping");
                 continue;
             }
+            isle_ctx.ctx.record_rewrite(inst, optimized_value);
             if isle_ctx.ctx.subsume_values.contains(&optimized_value) {
                 // Merge in the unionfind so canonicalization
                 // still works, but take *only* the subsuming
@@ -412,9 +442,16 @@ impl<'a> EgraphPass<'a> {
             stats: Stats::default(),
             eclasses: UnionFind::with_capacity(num_values),
             remat_values: FxHashSet::default(),
+            rewrites: None,
         }
     }
 
+    /// Requests a log of the rewrites made by the mid-end rules, which
+    /// is available in `rewrites` once the pass has run.
+    pub(crate) fn record_rewrites(&mut self) {
+        self.rewrites = Some(String::new());
+    }
+
     /// Run the process.
     pub fn run(&mut self) {
         self.remove_pure_and_optimize();
@@ -557,6 +594,7 @@ impl<'a> EgraphPass<'a> {
                             stats: &mut self.stats,
                             alias_analysis: self.alias_analysis,
                             alias_analysis_state: &mut alias_analysis_state,
+                            rewrites: self.rewrites.as_mut(),
                             optimized_values: Default::default(),
                         };
 
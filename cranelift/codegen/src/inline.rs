@@ -0,0 +1,498 @@
+//! A function inlining pass.
+//!
+//! Cranelift compiles one function at a time, so the bodies of callees are
+//! provided by the embedder through the [`InlineCallees`] trait. Direct `call`s
+//! to small enough callees are replaced with a copy of the callee's body, where
+//! the callee's `return`s become jumps to the rest of the caller's block.
+//!
+//! Only calls present in the caller before inlining are considered, so calls
+//! within inlined bodies are not themselves inlined.
+//!
+//! When the caller has block frequencies, calls that were never executed are
+//! not inlined, while calls executed at least as often as the caller itself
+//! may inline larger callees.
+
+use crate::entity::{EntityList, SecondaryMap};
+use crate::ir::instructions::InstructionMapper;
+use crate::ir::{
+    self, ArgumentPurpose, Block, BlockCall, Constant, ExtFuncData, ExternalName, FuncRef,
+    Function, GlobalValue, GlobalValueData, Immediate, Inst, InstBuilder, InstructionData,
+    JumpTable, JumpTableData, Opcode, RelSourceLoc, SigRef, StackSlot, Table, TableData, Value,
+    ValueList,
+};
+use crate::packed_option::PackedOption;
+use crate::{timing, trace};
+use alloc::vec::Vec;
+use smallvec::SmallVec;
+
+/// A provider of callee bodies for inlining.
+pub trait InlineCallees {
+    /// Returns the body of the function named `name`, if it is available for
+    /// inlining into `caller`.
+    ///
+    /// Names of the `ExternalName::User` kind refer to `caller`'s
+    /// [`user_named_funcs`](ir::function::FunctionParameters::user_named_funcs).
+    fn callee(&mut self, caller: &Function, name: &ExternalName) -> Option<&Function>;
+}
+
+/// The size heuristic used when inlining.
+#[derive(Clone, Copy, Debug)]
+pub struct InlineOptions {
+    /// The maximum number of instructions a callee can have to be inlined.
+    pub max_callee_size: usize,
+
+    /// The maximum number of instructions a callee can have to be inlined at
+    /// a call which, according to the caller's block frequencies, is executed
+    /// at least as often as the caller's entry block.
+    pub max_hot_callee_size: usize,
+
+    /// The number of instructions after which no more calls are inlined into
+    /// a caller.
+    pub max_caller_size: usize,
+}
+
+impl Default for InlineOptions {
+    fn default() -> Self {
+        Self {
+            max_callee_size: 24,
+            max_hot_callee_size: 96,
+            max_caller_size: 4096,
+        }
+    }
+}
+
+/// Inline direct calls in `func` to callees provided by `callees`.
+///
+/// Returns whether any call was inlined.
+pub(crate) fn do_inline(
+    func: &mut Function,
+    callees: &mut dyn InlineCallees,
+    options: &InlineOptions,
+) -> bool {
+    let _tt = timing::inline();
+
+    let mut calls = Vec::new();
+    let mut size = 0;
+    for block in func.layout.blocks() {
+        for inst in func.layout.block_insts(block) {
+            size += 1;
+            if func.dfg.insts[inst].opcode() == Opcode::Call {
+                calls.push(inst);
+            }
+        }
+    }
+
+    let entry_frequency = func
+        .layout
+        .entry_block()
+        .and_then(|block| func.layout.frequency(block))
+        .filter(|&frequency| frequency > 0);
+
+    let mut inlined = false;
+    for call in calls {
+        if size >= options.max_caller_size {
+            break;
+        }
+        let func_ref = match func.dfg.insts[call] {
+            InstructionData::Call { func_ref, .. } => func_ref,
+            _ => unreachable!(),
+        };
+        let call_frequency = entry_frequency.and_then(|_| {
+            let block = func.layout.inst_block(call).unwrap();
+            func.layout.frequency(block)
+        });
+        if call_frequency == Some(0) {
+            continue;
+        }
+        let max_callee_size = match (call_frequency, entry_frequency) {
+            (Some(call), Some(entry)) if call >= entry => options.max_hot_callee_size,
+            _ => options.max_callee_size,
+        };
+
+        let name = func.dfg.ext_funcs[func_ref].name.clone();
+        let callee = match callees.callee(func, &name) {
+            Some(callee) => callee,
+            None => continue,
+        };
+        let callee_size = callee
+            .layout
+            .blocks()
+            .map(|block| callee.layout.block_insts(block).count())
+            .sum::<usize>();
+        if callee_size > max_callee_size || !can_inline(func, call, func_ref, callee) {
+            continue;
+        }
+        trace!("inlining {} into {} at {}", callee.name, func.name, call);
+        inline_call(func, call, callee);
+        size += callee_size;
+        inlined = true;
+    }
+    inlined
+}
+
+/// Whether `callee` can be inlined at the `call` to `func_ref` in `func`.
+fn can_inline(func: &Function, call: Inst, func_ref: FuncRef, callee: &Function) -> bool {
+    let sig = func.dfg.ext_funcs[func_ref].signature;
+    if func.dfg.signatures[sig] != callee.signature {
+        return false;
+    }
+
+    // Entities which can't be copied into the caller, or which are only
+    // meaningful in the callee's own frame.
+    if !callee.dynamic_stack_slots.is_empty()
+        || !callee.memory_types.is_empty()
+        || callee.global_value_facts.values().any(Option::is_some)
+        || callee.dfg.facts.values().any(Option::is_some)
+    {
+        return false;
+    }
+    for block in callee.layout.blocks() {
+        for inst in callee.layout.block_insts(block) {
+            match callee.dfg.insts[inst].opcode() {
+                Opcode::ReturnCall
+                | Opcode::ReturnCallIndirect
+                | Opcode::GetFramePointer
+                | Opcode::GetStackPointer
+                | Opcode::GetReturnAddress => return false,
+                _ => {}
+            }
+        }
+    }
+
+    // The callee's `vmctx` global value refers to its own `vmctx` parameter,
+    // so it can only be reused in a caller which passes along its own.
+    let uses_vmctx = callee
+        .global_values
+        .values()
+        .any(|gv| matches!(gv, GlobalValueData::VMContext));
+    if uses_vmctx {
+        let index = match callee
+            .signature
+            .special_param_index(ArgumentPurpose::VMContext)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let arg = func.dfg.resolve_aliases(func.dfg.inst_args(call)[index]);
+        if func.special_param(ArgumentPurpose::VMContext) != Some(arg) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Replace `call` in `func` with the body of `callee`.
+fn inline_call(func: &mut Function, call: Inst, callee: &Function) {
+    let args: SmallVec<[Value; 8]> = func.dfg.inst_args(call).into();
+    let call_srcloc = func.srclocs[call];
+    let call_frequency = func.layout.frequency(func.layout.inst_block(call).unwrap());
+
+    // Split the caller's block after the call. The call's results become
+    // parameters of the new block, which the callee's returns jump to.
+    let cont = func.dfg.make_block();
+    let next = func
+        .layout
+        .next_inst(call)
+        .expect("calls are not terminators");
+    func.layout.split_block(cont, next);
+    if let Some(frequency) = call_frequency {
+        func.layout.set_frequency(cont, frequency);
+    }
+    let results = func.dfg.detach_results(call);
+    for i in 0..results.len(&func.dfg.value_lists) {
+        let result = results.as_slice(&func.dfg.value_lists)[i];
+        let ty = func.dfg.value_type(result);
+        let param = func.dfg.append_block_param(cont, ty);
+        func.dfg.change_to_alias(result, param);
+    }
+
+    let mut inliner = Inliner::new(func, callee, cont);
+    inliner.copy_entities();
+    let entry = inliner.copy_body(call_srcloc, call_frequency);
+    func.dfg.replace(call).jump(entry, &args);
+}
+
+struct Inliner<'a> {
+    func: &'a mut Function,
+    callee: &'a Function,
+    cont: Block,
+    values: SecondaryMap<Value, PackedOption<Value>>,
+    blocks: SecondaryMap<Block, PackedOption<Block>>,
+    stack_slots: SecondaryMap<StackSlot, PackedOption<StackSlot>>,
+    global_values: SecondaryMap<GlobalValue, PackedOption<GlobalValue>>,
+    sig_refs: SecondaryMap<SigRef, PackedOption<SigRef>>,
+    func_refs: SecondaryMap<FuncRef, PackedOption<FuncRef>>,
+    tables: SecondaryMap<Table, PackedOption<Table>>,
+}
+
+impl<'a> Inliner<'a> {
+    fn new(func: &'a mut Function, callee: &'a Function, cont: Block) -> Self {
+        Self {
+            func,
+            callee,
+            cont,
+            values: SecondaryMap::new(),
+            blocks: SecondaryMap::new(),
+            stack_slots: SecondaryMap::new(),
+            global_values: SecondaryMap::new(),
+            sig_refs: SecondaryMap::new(),
+            func_refs: SecondaryMap::new(),
+            tables: SecondaryMap::new(),
+        }
+    }
+
+    /// Copy the callee's preamble entities into the caller.
+    fn copy_entities(&mut self) {
+        let callee = self.callee;
+
+        for (slot, data) in callee.sized_stack_slots.iter() {
+            self.stack_slots[slot] = self.func.create_sized_stack_slot(data.clone()).into();
+        }
+
+        for (sig_ref, sig) in callee.dfg.signatures.iter() {
+            self.sig_refs[sig_ref] = self.func.import_signature(sig.clone()).into();
+        }
+
+        // Global values may refer to each other in any order, so create
+        // placeholders for all of them before filling them in.
+        for gv in callee.global_values.keys() {
+            self.global_values[gv] = self
+                .func
+                .create_global_value(GlobalValueData::VMContext)
+                .into();
+        }
+        for (gv, data) in callee.global_values.iter() {
+            let data = match *data {
+                GlobalValueData::VMContext => GlobalValueData::VMContext,
+                GlobalValueData::Load {
+                    base,
+                    offset,
+                    global_type,
+                    flags,
+                } => GlobalValueData::Load {
+                    base: self.map_global_value(base),
+                    offset,
+                    global_type,
+                    flags,
+                },
+                GlobalValueData::IAddImm {
+                    base,
+                    offset,
+                    global_type,
+                } => GlobalValueData::IAddImm {
+                    base: self.map_global_value(base),
+                    offset,
+                    global_type,
+                },
+                GlobalValueData::Symbol {
+                    ref name,
+                    offset,
+                    colocated,
+                    tls,
+                } => GlobalValueData::Symbol {
+                    name: self.map_external_name(name),
+                    offset,
+                    colocated,
+                    tls,
+                },
+                GlobalValueData::DynScaleTargetConst { vector_type } => {
+                    GlobalValueData::DynScaleTargetConst { vector_type }
+                }
+            };
+            let new_gv = self.map_global_value(gv);
+            self.func.global_values[new_gv] = data;
+        }
+
+        for (table, data) in callee.tables.iter() {
+            let data = TableData {
+                base_gv: self.map_global_value(data.base_gv),
+                min_size: data.min_size,
+                bound_gv: self.map_global_value(data.bound_gv),
+                element_size: data.element_size,
+                index_type: data.index_type,
+            };
+            self.tables[table] = self.func.create_table(data).into();
+        }
+
+        for (func_ref, data) in callee.dfg.ext_funcs.iter() {
+            let data = ExtFuncData {
+                name: self.map_external_name(&data.name),
+                signature: self.map_sig_ref(data.signature),
+                colocated: data.colocated,
+            };
+            self.func_refs[func_ref] = self.func.import_function(data).into();
+        }
+    }
+
+    /// Copy the callee's blocks and instructions into the caller, placing
+    /// them before the continuation block, and return the copy of the
+    /// callee's entry block.
+    ///
+    /// If the call has a frequency, the copied blocks get the callee's own
+    /// block frequencies scaled to it, or the call's frequency if the callee
+    /// doesn't have any.
+    fn copy_body(&mut self, call_srcloc: RelSourceLoc, call_frequency: Option<u64>) -> Block {
+        let callee = self.callee;
+        let callee_entry_frequency = callee
+            .layout
+            .entry_block()
+            .and_then(|block| callee.layout.frequency(block))
+            .filter(|&frequency| frequency > 0);
+
+        // Create all blocks and instruction results first since values can
+        // be used before their definition in layout order.
+        let mut insts = Vec::new();
+        for block in callee.layout.blocks() {
+            let new_block = self.func.dfg.make_block();
+            self.blocks[block] = new_block.into();
+            self.func.layout.insert_block(new_block, self.cont);
+            if callee.layout.is_cold(block) {
+                self.func.layout.set_cold(new_block);
+            }
+            if let Some(call_frequency) = call_frequency {
+                let frequency = match (callee_entry_frequency, callee.layout.frequency(block)) {
+                    (Some(entry), Some(frequency)) => {
+                        (u128::from(call_frequency) * u128::from(frequency) / u128::from(entry))
+                            .try_into()
+                            .unwrap_or(u64::MAX)
+                    }
+                    _ => call_frequency,
+                };
+                self.func.layout.set_frequency(new_block, frequency);
+            }
+            for &param in callee.dfg.block_params(block) {
+                let ty = callee.dfg.value_type(param);
+                self.values[param] = self.func.dfg.append_block_param(new_block, ty).into();
+            }
+
+            for inst in callee.layout.block_insts(block) {
+                let new_inst = self.func.dfg.make_inst(InstructionData::NullAry {
+                    opcode: Opcode::Nop,
+                });
+                for &result in callee.dfg.inst_results(inst) {
+                    let ty = callee.dfg.value_type(result);
+                    self.values[result] = self.func.dfg.append_result(new_inst, ty).into();
+                }
+                self.func.layout.append_inst(new_inst, new_block);
+                self.func.srclocs[new_inst] = self.map_srcloc(inst, call_srcloc);
+                insts.push((inst, new_inst));
+            }
+        }
+
+        for (inst, new_inst) in insts {
+            let data = if callee.dfg.insts[inst].opcode() == Opcode::Return {
+                let args: SmallVec<[Value; 8]> = callee
+                    .dfg
+                    .inst_args(inst)
+                    .iter()
+                    .map(|&arg| self.map_value(arg))
+                    .collect();
+                InstructionData::Jump {
+                    opcode: Opcode::Jump,
+                    destination: self.func.dfg.block_call(self.cont, &args),
+                }
+            } else {
+                callee.dfg.insts[inst].map(self)
+            };
+            self.func.dfg.insts[new_inst] = data;
+        }
+
+        let entry = callee.layout.entry_block().expect("callee has no body");
+        self.blocks[entry].unwrap()
+    }
+
+    fn map_srcloc(&mut self, inst: Inst, call_srcloc: RelSourceLoc) -> RelSourceLoc {
+        let srcloc = self.callee.srclocs[inst].expand(self.callee.params.base_srcloc());
+        if srcloc.is_default() {
+            return call_srcloc;
+        }
+        let base = self.func.params.ensure_base_srcloc(srcloc);
+        RelSourceLoc::from_base_offset(base, srcloc)
+    }
+
+    fn map_external_name(&mut self, name: &ExternalName) -> ExternalName {
+        match *name {
+            ExternalName::User(name_ref) => {
+                let name = self.callee.params.user_named_funcs()[name_ref].clone();
+                ExternalName::User(self.func.declare_imported_user_function(name))
+            }
+            ref name => name.clone(),
+        }
+    }
+
+    fn map_values(&mut self, values: &[Value]) -> SmallVec<[Value; 8]> {
+        values.iter().map(|&value| self.map_value(value)).collect()
+    }
+}
+
+impl InstructionMapper for Inliner<'_> {
+    fn map_value(&mut self, value: Value) -> Value {
+        let value = self.callee.dfg.resolve_aliases(value);
+        self.values[value].unwrap()
+    }
+
+    fn map_value_list(&mut self, value_list: ValueList) -> ValueList {
+        let callee = self.callee;
+        let values = self.map_values(value_list.as_slice(&callee.dfg.value_lists));
+        EntityList::from_slice(&values, &mut self.func.dfg.value_lists)
+    }
+
+    fn map_block_call(&mut self, block_call: BlockCall) -> BlockCall {
+        let callee = self.callee;
+        let pool = &callee.dfg.value_lists;
+        let block = self.blocks[block_call.block(pool)].unwrap();
+        let args = self.map_values(block_call.args_slice(pool));
+        self.func.dfg.block_call(block, &args)
+    }
+
+    fn map_stack_slot(&mut self, stack_slot: StackSlot) -> StackSlot {
+        self.stack_slots[stack_slot].unwrap()
+    }
+
+    fn map_dynamic_stack_slot(
+        &mut self,
+        _stack_slot: ir::DynamicStackSlot,
+    ) -> ir::DynamicStackSlot {
+        unreachable!("callees with dynamic stack slots are not inlined")
+    }
+
+    fn map_global_value(&mut self, global_value: GlobalValue) -> GlobalValue {
+        self.global_values[global_value].unwrap()
+    }
+
+    fn map_sig_ref(&mut self, sig_ref: SigRef) -> SigRef {
+        self.sig_refs[sig_ref].unwrap()
+    }
+
+    fn map_func_ref(&mut self, func_ref: FuncRef) -> FuncRef {
+        self.func_refs[func_ref].unwrap()
+    }
+
+    fn map_jump_table(&mut self, jump_table: JumpTable) -> JumpTable {
+        let callee = self.callee;
+        let data = &callee.dfg.jump_tables[jump_table];
+        let default = self.map_block_call(data.default_block());
+        let table: Vec<BlockCall> = data
+            .as_slice()
+            .iter()
+            .map(|&block_call| self.map_block_call(block_call))
+            .collect();
+        self.func
+            .create_jump_table(JumpTableData::new(default, &table))
+    }
+
+    fn map_table(&mut self, table: Table) -> Table {
+        self.tables[table].unwrap()
+    }
+
+    fn map_constant(&mut self, constant: Constant) -> Constant {
+        let data = self.callee.dfg.constants.get(constant).clone();
+        self.func.dfg.constants.insert(data)
+    }
+
+    fn map_immediate(&mut self, immediate: Immediate) -> Immediate {
+        let data = self.callee.dfg.immediates[immediate].clone();
+        self.func.dfg.immediates.push(data)
+    }
+}
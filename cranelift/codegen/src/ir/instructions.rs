@@ -20,7 +20,7 @@ use crate::ir::{
     self,
     condcodes::{FloatCC, IntCC},
     trapcode::TrapCode,
-    types, Block, FuncRef, MemFlags, SigRef, StackSlot, Type, Value,
+    types, Block, DynamicStackSlot, FuncRef, MemFlags, SigRef, StackSlot, Type, Value,
 };
 
 /// Some instructions use an external list of argument values because there is not enough space in
@@ -419,6 +419,19 @@ impl InstructionData {
         }
     }
 
+    /// If this instruction references a dynamic stack slot, return it
+    pub fn dynamic_stack_slot(&self) -> Option<DynamicStackSlot> {
+        match self {
+            &InstructionData::DynamicStackStore {
+                dynamic_stack_slot, ..
+            }
+            | &InstructionData::DynamicStackLoad {
+                dynamic_stack_slot, ..
+            } => Some(dynamic_stack_slot),
+            _ => None,
+        }
+    }
+
     /// Return information about a call instruction.
     ///
     /// Any instruction that can call another function reveals its call signature here.
@@ -471,6 +484,49 @@ impl InstructionData {
     }
 }
 
+/// A trait for translating the entities referenced by an instruction from one
+/// function into another.
+///
+/// This is used with [`InstructionData::map`] to copy instructions between
+/// functions, for example when inlining.
+pub trait InstructionMapper {
+    /// Map a value operand.
+    fn map_value(&mut self, value: Value) -> Value;
+
+    /// Map a list of value operands.
+    fn map_value_list(&mut self, value_list: ValueList) -> ValueList;
+
+    /// Map a branch destination along with its arguments.
+    fn map_block_call(&mut self, block_call: BlockCall) -> BlockCall;
+
+    /// Map a stack slot.
+    fn map_stack_slot(&mut self, stack_slot: StackSlot) -> StackSlot;
+
+    /// Map a dynamic stack slot.
+    fn map_dynamic_stack_slot(&mut self, stack_slot: ir::DynamicStackSlot) -> ir::DynamicStackSlot;
+
+    /// Map a global value.
+    fn map_global_value(&mut self, global_value: ir::GlobalValue) -> ir::GlobalValue;
+
+    /// Map a function signature.
+    fn map_sig_ref(&mut self, sig_ref: SigRef) -> SigRef;
+
+    /// Map an external function.
+    fn map_func_ref(&mut self, func_ref: FuncRef) -> FuncRef;
+
+    /// Map a jump table.
+    fn map_jump_table(&mut self, jump_table: ir::JumpTable) -> ir::JumpTable;
+
+    /// Map a table.
+    fn map_table(&mut self, table: ir::Table) -> ir::Table;
+
+    /// Map a constant from the constant pool.
+    fn map_constant(&mut self, constant: ir::Constant) -> ir::Constant;
+
+    /// Map an immediate from the immediate pool.
+    fn map_immediate(&mut self, immediate: ir::Immediate) -> ir::Immediate;
+}
+
 /// Information about call instructions.
 pub enum CallInfo<'a> {
     /// This is not a call instruction.
@@ -372,6 +372,22 @@ impl Layout {
     pub fn is_cold(&self, block: Block) -> bool {
         self.blocks[block].cold
     }
+
+    /// Record how many times a block was executed, for example according to
+    /// a profile.
+    ///
+    /// Frequencies are only used when one is known for the entry block. They
+    /// guide block placement, where blocks which were never executed are
+    /// treated like cold blocks, and inlining. They aren't passed to register
+    /// allocation.
+    pub fn set_frequency(&mut self, block: Block, frequency: u64) {
+        self.blocks[block].frequency = Some(frequency);
+    }
+
+    /// How many times was the given block executed, if known?
+    pub fn frequency(&self, block: Block) -> Option<u64> {
+        self.blocks[block].frequency
+    }
 }
 
 /// A single node in the linked-list of blocks.
@@ -383,6 +399,7 @@ struct BlockNode {
     first_inst: PackedOption<Inst>,
     last_inst: PackedOption<Inst>,
     cold: bool,
+    frequency: Option<u64>,
 }
 
 /// Iterate over blocks in layout order. See [crate::ir::layout::Layout::blocks].
@@ -674,7 +691,7 @@ impl<'f> DoubleEndedIterator for Insts<'f> {
 ///
 /// ```plain
 /// data = block_data * ;
-/// block_data = "block_id" , "cold" , "inst_count" , ( "inst_id" * ) ;
+/// block_data = "block_id" , "cold" , "frequency" , "inst_count" , ( "inst_id" * ) ;
 /// ```
 #[cfg(feature = "enable-serde")]
 mod serde {
@@ -692,7 +709,7 @@ mod serde {
         where
             S: Serializer,
         {
-            let size = self.blocks().count() * 3
+            let size = self.blocks().count() * 4
                 + self
                     .blocks()
                     .map(|block| self.block_insts(block).count())
@@ -701,6 +718,7 @@ mod serde {
             for block in self.blocks() {
                 seq.serialize_element(&block)?;
                 seq.serialize_element(&self.blocks[block].cold)?;
+                seq.serialize_element(&self.blocks[block].frequency)?;
                 seq.serialize_element(&u32::try_from(self.block_insts(block).count()).unwrap())?;
                 for inst in self.block_insts(block) {
                     seq.serialize_element(&inst)?;
@@ -746,6 +764,11 @@ mod serde {
                     .ok_or_else(|| Error::missing_field("cold"))?;
                 layout.blocks[block].cold = cold;
 
+                let frequency = access
+                    .next_element::<Option<u64>>()?
+                    .ok_or_else(|| Error::missing_field("frequency"))?;
+                layout.blocks[block].frequency = frequency;
+
                 let count = access
                     .next_element::<u32>()?
                     .ok_or_else(|| Error::missing_field("count"))?;
@@ -24,6 +24,9 @@ mod lower;
 mod pcc;
 pub mod settings;
 
+#[cfg(feature = "isle-coverage")]
+pub(crate) use lower::isle::generated_code::{ISLE_RULES, ISLE_RULE_COUNTS};
+
 use self::inst::EmitInfo;
 
 /// An AArch64 backend.
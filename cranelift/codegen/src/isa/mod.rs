@@ -78,6 +78,36 @@ pub mod unwind;
 
 mod call_conv;
 
+/// Adds the ISLE rules of the lowering of each enabled backend to `tables`.
+#[cfg(feature = "isle-coverage")]
+pub(crate) fn isle_rule_tables(tables: &mut Vec<crate::isle_coverage::RuleTable>) {
+    use crate::isle_coverage::RuleTable;
+    #[cfg(feature = "x86")]
+    tables.push(RuleTable {
+        compilation: "x64",
+        rules: &x64::ISLE_RULES,
+        counts: &x64::ISLE_RULE_COUNTS,
+    });
+    #[cfg(feature = "arm64")]
+    tables.push(RuleTable {
+        compilation: "aarch64",
+        rules: &aarch64::ISLE_RULES,
+        counts: &aarch64::ISLE_RULE_COUNTS,
+    });
+    #[cfg(feature = "riscv64")]
+    tables.push(RuleTable {
+        compilation: "riscv64",
+        rules: &riscv64::ISLE_RULES,
+        counts: &riscv64::ISLE_RULE_COUNTS,
+    });
+    #[cfg(feature = "s390x")]
+    tables.push(RuleTable {
+        compilation: "s390x",
+        rules: &s390x::ISLE_RULES,
+        counts: &s390x::ISLE_RULE_COUNTS,
+    });
+}
+
 /// Returns a builder that can create a corresponding `TargetIsa`
 /// or `Err(LookupError::SupportDisabled)` if not enabled.
 macro_rules! isa_builder {
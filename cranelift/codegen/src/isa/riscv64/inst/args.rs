@@ -1379,6 +1379,17 @@ impl AtomicOP {
             | AtomicOP::AmomaxuD => 0b011,
         }
     }
+
+    /// The type of the memory this operation accesses.
+    pub(crate) fn access_type(self) -> Type {
+        // `funct3` is the width of the access.
+        if self.funct3() == 0b010 {
+            I32
+        } else {
+            I64
+        }
+    }
+
     pub(crate) fn funct5(self) -> u32 {
         match self {
             AtomicOP::LrW => 0b00010,
@@ -18,7 +18,12 @@ use target_lexicon::{Architecture, Triple};
 mod abi;
 pub(crate) mod inst;
 mod lower;
+mod pcc;
 mod settings;
+
+#[cfg(feature = "isle-coverage")]
+pub(crate) use lower::isle::generated_code::{ISLE_RULES, ISLE_RULE_COUNTS};
+
 #[cfg(feature = "unwind")]
 use crate::isa::unwind::systemv;
 
@@ -0,0 +1,763 @@
+//! Proof-carrying code checking for RISC-V 64 VCode.
+
+use crate::fx::FxHashMap;
+use crate::ir::condcodes::IntCC;
+use crate::ir::pcc::*;
+use crate::ir::types::*;
+use crate::ir::MemFlags;
+use crate::ir::Type;
+use crate::isa::riscv64::inst::regs::zero_reg;
+use crate::isa::riscv64::inst::{
+    AMode, AluOPRRI, AluOPRRR, AtomicOP, Inst, IntegerCompare, LoadOP, StoreOP, VState,
+};
+use crate::isa::riscv64::lower::isle::generated_code::{VecAMode, VecElementWidth};
+use crate::machinst::pcc::*;
+use crate::machinst::{InsnIndex, VCode};
+use crate::machinst::{Reg, Writable};
+use crate::trace;
+use regalloc2::{Function as _, OperandKind, VReg};
+
+/// An inequality `lhs >= rhs` (`Loose`) or `lhs > rhs` (`Strict`)
+/// between two values, as consumed by `FactContext::apply_inequality`.
+#[derive(Clone, Debug)]
+struct Inequality {
+    lhs: Fact,
+    rhs: Fact,
+    kind: InequalityKind,
+}
+
+impl Inequality {
+    /// Build the inequality that holds when `rs1 <cc> rs2` holds, for
+    /// the unsigned condition codes.
+    fn from_compare(cc: IntCC, rs1: Fact, rs2: Fact) -> Option<Inequality> {
+        let (lhs, rhs, kind) = match cc {
+            IntCC::UnsignedGreaterThan => (rs1, rs2, InequalityKind::Strict),
+            IntCC::UnsignedGreaterThanOrEqual => (rs1, rs2, InequalityKind::Loose),
+            IntCC::UnsignedLessThan => (rs2, rs1, InequalityKind::Strict),
+            IntCC::UnsignedLessThanOrEqual => (rs2, rs1, InequalityKind::Loose),
+            _ => return None,
+        };
+        Some(Inequality { lhs, rhs, kind })
+    }
+
+    /// The inequality that holds when this one does not.
+    fn negate(self) -> Inequality {
+        let kind = match self.kind {
+            InequalityKind::Strict => InequalityKind::Loose,
+            InequalityKind::Loose => InequalityKind::Strict,
+        };
+        Inequality {
+            lhs: self.rhs,
+            rhs: self.lhs,
+            kind,
+        }
+    }
+
+    /// The `compare` fact describing a boolean that is set when this
+    /// inequality holds, if both sides are symbolic or constant.
+    fn compare_fact(&self) -> Option<Fact> {
+        let expr = |fact: &Fact| {
+            fact.as_symbol().cloned().or_else(|| {
+                let k = fact.as_const(64)?;
+                Some(Expr::constant(i64::try_from(k).ok()?))
+            })
+        };
+        let kind = match self.kind {
+            InequalityKind::Strict => IntCC::UnsignedGreaterThan,
+            InequalityKind::Loose => IntCC::UnsignedGreaterThanOrEqual,
+        };
+        Some(Fact::Compare {
+            kind,
+            lhs: expr(&self.lhs)?,
+            rhs: expr(&self.rhs)?,
+        })
+    }
+
+    /// Refine a fact on a value that is only used when this
+    /// inequality holds.
+    fn apply(&self, ctx: &FactContext, fact: &Fact) -> Fact {
+        ctx.apply_inequality(fact, &self.lhs, &self.rhs, self.kind)
+    }
+}
+
+/// A value computed by one instruction of a multi-instruction
+/// sequence, which cannot be described by a fact but which the
+/// instruction completing the sequence needs to know about.
+#[derive(Clone, Debug)]
+enum Partial {
+    /// A value shifted left by `amount`: the first half of a
+    /// zero-extension done as a pair of shifts.
+    Shl { value: Fact, amount: u8 },
+    /// `1` if the inequality holds, else `0`.
+    Bool(Inequality),
+    /// All ones if the inequality holds, else `0`: the mask that
+    /// `select_spectre_guard` is lowered to.
+    Mask(Inequality),
+}
+
+/// Flow-state between facts.
+#[derive(Clone, Debug, Default)]
+pub struct FactFlowState {
+    partials: FxHashMap<VReg, Partial>,
+}
+
+impl FactFlowState {
+    fn get(&self, vcode: &VCode<Inst>, reg: Reg) -> Option<&Partial> {
+        self.partials.get(&vcode.resolve_vreg_alias(reg.into()))
+    }
+
+    fn set(&mut self, vcode: &VCode<Inst>, reg: Writable<Reg>, partial: Partial) {
+        let vreg = vcode.resolve_vreg_alias(reg.to_reg().into());
+        self.partials.insert(vreg, partial);
+    }
+}
+
+pub(crate) fn check(
+    ctx: &FactContext,
+    vcode: &mut VCode<Inst>,
+    inst_idx: InsnIndex,
+    state: &mut FactFlowState,
+) -> PccResult<()> {
+    trace!("Checking facts on inst: {:?}", vcode[inst_idx]);
+
+    // Forget whatever we knew about the registers this instruction
+    // (re)defines; the cases below record anything new.
+    for operand in vcode.inst_operands(inst_idx) {
+        if operand.kind() == OperandKind::Def {
+            state.partials.remove(&operand.vreg());
+        }
+    }
+
+    match vcode[inst_idx] {
+        Inst::Args { .. } => {
+            // Defs on the args have "axiomatic facts": we trust the
+            // ABI code to pass through the values unharmed, so the
+            // facts given to us in the CLIF should still be true.
+            Ok(())
+        }
+
+        Inst::Load {
+            rd,
+            op,
+            flags,
+            ref from,
+        } => {
+            let (ty, rd) = match op {
+                LoadOP::Lb | LoadOP::Lbu => (I8, Some(rd.to_reg())),
+                LoadOP::Lh | LoadOP::Lhu => (I16, Some(rd.to_reg())),
+                LoadOP::Lw | LoadOP::Lwu => (I32, Some(rd.to_reg())),
+                LoadOP::Ld => (I64, Some(rd.to_reg())),
+                LoadOP::Flw => (F32, None),
+                LoadOP::Fld => (F64, None),
+            };
+            check_load(ctx, rd, flags, from, vcode, ty)
+        }
+        Inst::Store {
+            ref to,
+            op,
+            flags,
+            src,
+        } => {
+            let (ty, src) = match op {
+                StoreOP::Sb => (I8, Some(src)),
+                StoreOP::Sh => (I16, Some(src)),
+                StoreOP::Sw => (I32, Some(src)),
+                StoreOP::Sd => (I64, Some(src)),
+                StoreOP::Fsw => (F32, None),
+                StoreOP::Fsd => (F64, None),
+            };
+            check_store(ctx, src, flags, to, vcode, ty)
+        }
+        Inst::VecLoad {
+            eew,
+            ref from,
+            flags,
+            ref vstate,
+            ..
+        } => check_vec_access(ctx, flags, from, vcode, eew, vstate, false),
+        Inst::VecStore {
+            eew,
+            ref to,
+            flags,
+            ref vstate,
+            ..
+        } => check_vec_access(ctx, flags, to, vcode, eew, vstate, true),
+        // Atomic instructions don't carry `MemFlags`, so their accesses
+        // are always checked through the fact on the address register.
+        Inst::Atomic {
+            op, rd, addr, src, ..
+        } => {
+            let ty = op.access_type();
+            if op.is_load() {
+                check_atomic_load(ctx, rd.to_reg(), addr, vcode, ty)
+            } else {
+                ensure_no_fact(vcode, rd.to_reg())?;
+                // Only `sc` and `amoswap` store `src` itself.
+                let src = match op {
+                    AtomicOP::ScW | AtomicOP::ScD | AtomicOP::AmoswapW | AtomicOP::AmoswapD => {
+                        Some(src)
+                    }
+                    _ => None,
+                };
+                check_atomic_store(ctx, src, addr, vcode, ty)
+            }
+        }
+        Inst::AtomicLoad { rd, ty, p } => check_atomic_load(ctx, rd.to_reg(), p, vcode, ty),
+        Inst::AtomicStore { src, ty, p } => check_atomic_store(ctx, Some(src), p, vcode, ty),
+        Inst::AtomicRmwLoop { dst, ty, p, t0, .. }
+        | Inst::AtomicCas {
+            dst,
+            ty,
+            addr: p,
+            t0,
+            ..
+        } => {
+            ensure_no_fact(vcode, dst.to_reg())?;
+            ensure_no_fact(vcode, t0.to_reg())?;
+            // 8- and 16-bit values are accessed through their aligned
+            // 32-bit word.
+            let ty = if ty.bits() < 32 { I32 } else { ty };
+            check_atomic_store(ctx, None, p, vcode, ty)
+        }
+
+        Inst::Lui { rd, imm } => {
+            let constant = i64::from(imm.as_i32() << 12) as u64;
+            check_constant(ctx, vcode, rd, 64, constant)
+        }
+        Inst::LoadInlineConst { rd, ty, imm } => {
+            let bits = u16::try_from(ty.bits()).unwrap();
+            let constant = imm & (u64::MAX >> (64 - bits));
+            check_constant(ctx, vcode, rd, bits, constant)
+        }
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Addi,
+            rd,
+            rs,
+            imm12,
+        } if rs == zero_reg() => {
+            let constant = i64::from(imm12.as_i16()) as u64;
+            check_constant(ctx, vcode, rd, 64, constant)
+        }
+
+        Inst::Mov { rd, rm, .. } => check_unop(ctx, vcode, 64, rd, rm, |rm| Ok(rm.clone())),
+
+        Inst::AluRRR {
+            alu_op: AluOPRRR::Add,
+            rd,
+            rs1,
+            rs2,
+        } => check_binop(ctx, vcode, 64, rd, rs1, rs2, |rs1, rs2| {
+            clamp_range(ctx, 64, 64, ctx.add(rs1, rs2, 64))
+        }),
+        Inst::AluRRR {
+            alu_op: AluOPRRR::Addw,
+            rd,
+            rs1,
+            rs2,
+        } => check_binop(ctx, vcode, 32, rd, rs1, rs2, |rs1, rs2| {
+            clamp_range_32(ctx, ctx.add(rs1, rs2, 32))
+        }),
+        Inst::AluRRR {
+            alu_op:
+                alu_op @ (AluOPRRR::Adduw
+                | AluOPRRR::Sh1add
+                | AluOPRRR::Sh1adduw
+                | AluOPRRR::Sh2add
+                | AluOPRRR::Sh2adduw
+                | AluOPRRR::Sh3add
+                | AluOPRRR::Sh3adduw),
+            rd,
+            rs1,
+            rs2,
+        } => {
+            // Zba's address generation: `rd = (rs1 << amount) + rs2`,
+            // where the `.uw` forms first zero-extend the low 32 bits
+            // of `rs1`. `zext.w` is `add.uw` with a zero `rs2`.
+            let (amount, uext) = match alu_op {
+                AluOPRRR::Adduw => (0, true),
+                AluOPRRR::Sh1add => (1, false),
+                AluOPRRR::Sh1adduw => (1, true),
+                AluOPRRR::Sh2add => (2, false),
+                AluOPRRR::Sh2adduw => (2, true),
+                AluOPRRR::Sh3add => (3, false),
+                AluOPRRR::Sh3adduw => (3, true),
+                _ => unreachable!(),
+            };
+            check_output(ctx, vcode, rd, &[rs1, rs2], |vcode| {
+                let rs1 = get_fact_or_default(vcode, rs1, 64);
+                let rs1 = if uext {
+                    clamp_range(ctx, 64, 32, Some(rs1))?
+                } else {
+                    rs1
+                };
+                let shifted = fail_if_missing(ctx.shl(&rs1, 64, amount))?;
+                if rs2 == zero_reg() {
+                    return Ok(shifted);
+                }
+                let rs2 = get_fact_or_default(vcode, rs2, 64);
+                clamp_range(ctx, 64, 64, ctx.add(&shifted, &rs2, 64))
+            })
+        }
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Addi,
+            rd,
+            rs,
+            imm12,
+        } => check_unop(ctx, vcode, 64, rd, rs, |rs| {
+            clamp_range(ctx, 64, 64, ctx.offset(rs, 64, imm12.as_i16().into()))
+        }),
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Addiw,
+            rd,
+            rs,
+            imm12,
+        } => check_unop(ctx, vcode, 32, rd, rs, |rs| {
+            clamp_range_32(ctx, ctx.offset(rs, 32, imm12.as_i16().into()))
+        }),
+
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Slli,
+            rd,
+            rs,
+            imm12,
+        } => {
+            let amount = shift_amount(imm12.bits(), 64);
+            let value = get_fact_or_default(vcode, rs, 64);
+            state.set(vcode, rd, Partial::Shl { value, amount });
+            check_unop(ctx, vcode, 64, rd, rs, |rs| {
+                clamp_range(ctx, 64, 64, ctx.shl(rs, 64, amount.into()))
+            })
+        }
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Slliw,
+            rd,
+            rs,
+            imm12,
+        } => check_unop(ctx, vcode, 32, rd, rs, |rs| {
+            let amount = shift_amount(imm12.bits(), 32);
+            clamp_range_32(ctx, ctx.shl(rs, 32, amount.into()))
+        }),
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Srli,
+            rd,
+            rs,
+            imm12,
+        } => {
+            let amount = shift_amount(imm12.bits(), 64);
+            // A logical right shift leaves only the upper `64 -
+            // amount` bits; if the input was shifted left by the same
+            // amount, then those are the zero-extended low bits of the
+            // original value.
+            let value = match state.get(vcode, rs) {
+                Some(Partial::Shl {
+                    value,
+                    amount: shl_amount,
+                }) if *shl_amount == amount => Some(value.clone()),
+                _ => None,
+            };
+            check_output(ctx, vcode, rd, &[], |_vcode| {
+                clamp_range(ctx, 64, 64 - u16::from(amount), value)
+            })
+        }
+
+        Inst::Extend {
+            rd,
+            rn,
+            signed: false,
+            from_bits,
+            to_bits,
+        } if has_fact(vcode, rn) => check_unop(ctx, vcode, 64, rd, rn, |rn| {
+            clamp_range(
+                ctx,
+                64,
+                to_bits.into(),
+                ctx.uextend(rn, from_bits.into(), to_bits.into()),
+            )
+        }),
+
+        // The instructions `select_spectre_guard` is lowered to:
+        // first a comparison, then perhaps its negation, then the
+        // negation of that boolean into a mask, which in turn is
+        // perhaps inverted and finally applied to the value.
+        Inst::AluRRR {
+            alu_op: AluOPRRR::SltU,
+            rd,
+            rs1,
+            rs2,
+        } => {
+            let rs1 = get_fact_or_default(vcode, rs1, 64);
+            let rs2 = get_fact_or_default(vcode, rs2, 64);
+            let inequality = Inequality::from_compare(IntCC::UnsignedLessThan, rs1, rs2).unwrap();
+            check_bool(ctx, vcode, rd, &inequality)?;
+            state.set(vcode, rd, Partial::Bool(inequality));
+            Ok(())
+        }
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::SltiU,
+            rd,
+            rs,
+            imm12,
+        } => {
+            let rs = get_fact_or_default(vcode, rs, 64);
+            let imm = Fact::constant(64, i64::from(imm12.as_i16()) as u64);
+            let inequality = Inequality::from_compare(IntCC::UnsignedLessThan, rs, imm).unwrap();
+            check_bool(ctx, vcode, rd, &inequality)?;
+            state.set(vcode, rd, Partial::Bool(inequality));
+            Ok(())
+        }
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Xori,
+            rd,
+            rs,
+            imm12,
+        } if imm12.as_i16() == 1 && matches!(state.get(vcode, rs), Some(Partial::Bool(_))) => {
+            let Some(Partial::Bool(inequality)) = state.get(vcode, rs) else {
+                unreachable!()
+            };
+            let inequality = inequality.clone().negate();
+            check_bool(ctx, vcode, rd, &inequality)?;
+            state.set(vcode, rd, Partial::Bool(inequality));
+            Ok(())
+        }
+        Inst::AluRRImm12 {
+            alu_op: AluOPRRI::Xori,
+            rd,
+            rs,
+            imm12,
+        } if imm12.as_i16() == -1 && matches!(state.get(vcode, rs), Some(Partial::Mask(_))) => {
+            let Some(Partial::Mask(inequality)) = state.get(vcode, rs) else {
+                unreachable!()
+            };
+            let inequality = inequality.clone().negate();
+            state.set(vcode, rd, Partial::Mask(inequality));
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 64, 64, None))
+        }
+        Inst::AluRRR {
+            alu_op: AluOPRRR::Sub,
+            rd,
+            rs1,
+            rs2,
+        } if rs1 == zero_reg() => {
+            if let Some(Partial::Bool(inequality)) = state.get(vcode, rs2).cloned() {
+                let mask = Partial::Mask(inequality);
+                state.set(vcode, rd, mask);
+            }
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 64, 64, None))
+        }
+        Inst::AluRRR {
+            alu_op: op @ (AluOPRRR::And | AluOPRRR::Andn),
+            rd,
+            rs1,
+            rs2,
+        } => {
+            // `and` is commutative, so the mask may be on either side;
+            // `andn` inverts its second operand.
+            let (value, inequality) = match (op, state.get(vcode, rs1), state.get(vcode, rs2)) {
+                (AluOPRRR::And, _, Some(Partial::Mask(inequality))) => {
+                    (rs1, Some(inequality.clone()))
+                }
+                (AluOPRRR::And, Some(Partial::Mask(inequality)), _) => {
+                    (rs2, Some(inequality.clone()))
+                }
+                (AluOPRRR::Andn, _, Some(Partial::Mask(inequality))) => {
+                    (rs1, Some(inequality.clone().negate()))
+                }
+                _ => (rs1, None),
+            };
+            check_output(ctx, vcode, rd, &[], |vcode| match inequality {
+                // Either the value, when the inequality holds, or zero.
+                Some(inequality) => {
+                    let value = get_fact_or_default(vcode, value, 64);
+                    let value = inequality.apply(ctx, &value);
+                    let union = ctx.union(&value, &Fact::constant(64, 0));
+                    clamp_range(ctx, 64, 64, union)
+                }
+                None => clamp_range(ctx, 64, 64, None),
+            })
+        }
+
+        Inst::Select {
+            ref dst,
+            condition:
+                IntegerCompare {
+                    kind,
+                    rs1: cmp_rs1,
+                    rs2: cmp_rs2,
+                },
+            ref x,
+            ref y,
+        } if dst.only_reg().is_some() => {
+            let rd = dst.only_reg().unwrap();
+            let (x, y) = (x.only_reg().unwrap(), y.only_reg().unwrap());
+            let cmp_rs1 = get_fact_or_default(vcode, cmp_rs1, 64);
+            let cmp_rs2 = get_fact_or_default(vcode, cmp_rs2, 64);
+            let inequality = Inequality::from_compare(kind, cmp_rs1, cmp_rs2);
+            check_output(ctx, vcode, rd, &[], |vcode| {
+                let x = get_fact_or_default(vcode, x, 64);
+                let y = get_fact_or_default(vcode, y, 64);
+                // We support transitivity-based reasoning as for
+                // AArch64's `csel`: each side of the select may
+                // assume the comparison (or its negation) holds.
+                let (x, y) = match inequality {
+                    Some(inequality) => {
+                        let x = inequality.apply(ctx, &x);
+                        let y = inequality.negate().apply(ctx, &y);
+                        (x, y)
+                    }
+                    None => (x, y),
+                };
+                clamp_range(ctx, 64, 64, ctx.union(&x, &y))
+            })
+        }
+
+        Inst::AluRRR {
+            alu_op:
+                AluOPRRR::Subw
+                | AluOPRRR::Sllw
+                | AluOPRRR::Srlw
+                | AluOPRRR::Sraw
+                | AluOPRRR::Mulw
+                | AluOPRRR::Divw
+                | AluOPRRR::Divuw
+                | AluOPRRR::Remw
+                | AluOPRRR::Remuw
+                | AluOPRRR::Rolw
+                | AluOPRRR::Rorw
+                | AluOPRRR::Packw,
+            rd,
+            ..
+        }
+        | Inst::AluRRImm12 {
+            alu_op:
+                AluOPRRI::SrliW
+                | AluOPRRI::Sraiw
+                | AluOPRRI::Clzw
+                | AluOPRRI::Ctzw
+                | AluOPRRI::Cpopw
+                | AluOPRRI::Roriw,
+            rd,
+            ..
+        } => check_output(ctx, vcode, rd, &[], |_vcode| clamp_range_32(ctx, None)),
+        Inst::AluRRR { rd, .. } | Inst::AluRRImm12 { rd, .. } => {
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 64, 64, None))
+        }
+
+        Inst::Extend {
+            rd,
+            from_bits,
+            to_bits,
+            ..
+        } => check_output(ctx, vcode, rd, &[], |_vcode| {
+            clamp_range(ctx, to_bits.into(), from_bits.into(), None)
+        }),
+
+        _ if vcode.inst_defines_facts(inst_idx) => Err(PccError::UnsupportedFact),
+
+        _ => Ok(()),
+    }
+}
+
+/// Decode the amount of an immediate shift on values of the given
+/// width.
+fn shift_amount(imm: u32, width: u32) -> u8 {
+    u8::try_from(imm & (width - 1)).unwrap()
+}
+
+/// Check the fact on a boolean that is `1` if the inequality holds,
+/// else `0`: either it restates the comparison, or it is a range.
+fn check_bool(
+    ctx: &FactContext,
+    vcode: &mut VCode<Inst>,
+    rd: Writable<Reg>,
+    inequality: &Inequality,
+) -> PccResult<()> {
+    check_output(ctx, vcode, rd, &[], |vcode| {
+        match (
+            vcode.vreg_fact(rd.to_reg().into()),
+            inequality.compare_fact(),
+        ) {
+            (Some(Fact::Compare { .. }), Some(compare)) => Ok(compare),
+            _ => Ok(Fact::Range {
+                bit_width: 64,
+                min: 0,
+                max: 1,
+            }),
+        }
+    })
+}
+
+/// The result of the 32-bit ("word") instructions is sign-extended
+/// from bit 31, so facts only describe the low 32 bits, and only
+/// when the operation didn't wrap.
+fn clamp_range_32(ctx: &FactContext, fact: Option<Fact>) -> PccResult<Fact> {
+    let fact = fact.filter(|fact| match fact {
+        Fact::Range { max, .. } => *max <= u64::from(u32::MAX),
+        _ => true,
+    });
+    clamp_range(ctx, 32, 32, fact)
+}
+
+fn check_load(
+    ctx: &FactContext,
+    rd: Option<Reg>,
+    flags: MemFlags,
+    addr: &AMode,
+    vcode: &VCode<Inst>,
+    ty: Type,
+) -> PccResult<()> {
+    let result_fact = rd.and_then(|rd| vcode.vreg_fact(rd.into()));
+    let bits = u16::try_from(ty.bits()).unwrap();
+    check_addr(
+        ctx,
+        flags,
+        addr,
+        vcode,
+        ty,
+        LoadOrStore::Load {
+            result_fact,
+            from_bits: bits,
+            to_bits: bits,
+        },
+    )
+}
+
+fn check_store(
+    ctx: &FactContext,
+    rd: Option<Reg>,
+    flags: MemFlags,
+    addr: &AMode,
+    vcode: &VCode<Inst>,
+    ty: Type,
+) -> PccResult<()> {
+    let stored_fact = rd.and_then(|rd| vcode.vreg_fact(rd.into()));
+    check_addr(
+        ctx,
+        flags,
+        addr,
+        vcode,
+        ty,
+        LoadOrStore::Store { stored_fact },
+    )
+}
+
+fn check_atomic_load(
+    ctx: &FactContext,
+    rd: Reg,
+    addr: Reg,
+    vcode: &VCode<Inst>,
+    ty: Type,
+) -> PccResult<()> {
+    let flags = MemFlags::new().with_checked();
+    let addr = AMode::RegOffset(addr, 0, ty);
+    check_load(ctx, Some(rd), flags, &addr, vcode, ty)
+}
+
+fn check_atomic_store(
+    ctx: &FactContext,
+    src: Option<Reg>,
+    addr: Reg,
+    vcode: &VCode<Inst>,
+    ty: Type,
+) -> PccResult<()> {
+    let flags = MemFlags::new().with_checked();
+    let addr = AMode::RegOffset(addr, 0, ty);
+    check_store(ctx, src, flags, &addr, vcode, ty)
+}
+
+fn ensure_no_fact(vcode: &VCode<Inst>, reg: Reg) -> PccResult<()> {
+    if vcode.vreg_fact(reg.into()).is_some() {
+        Err(PccError::UnsupportedFact)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_vec_access(
+    ctx: &FactContext,
+    flags: MemFlags,
+    addr: &VecAMode,
+    vcode: &VCode<Inst>,
+    eew: VecElementWidth,
+    vstate: &VState,
+    is_store: bool,
+) -> PccResult<()> {
+    // Unit-stride accesses touch `avl` contiguous elements.
+    let lane_ty = Type::int(u16::try_from(eew.bits()).unwrap()).unwrap();
+    let lanes = u32::from(vstate.avl.unwrap_static().bits());
+    let ty = lane_ty.by(lanes).ok_or(PccError::UnimplementedInst)?;
+    let bits = u16::try_from(ty.bits()).unwrap();
+    let VecAMode::UnitStride { base } = addr;
+    let op = if is_store {
+        LoadOrStore::Store { stored_fact: None }
+    } else {
+        LoadOrStore::Load {
+            result_fact: None,
+            from_bits: bits,
+            to_bits: bits,
+        }
+    };
+    check_addr(ctx, flags, base, vcode, ty, op)
+}
+
+fn check_addr<'a>(
+    ctx: &FactContext,
+    flags: MemFlags,
+    addr: &AMode,
+    vcode: &VCode<Inst>,
+    ty: Type,
+    op: LoadOrStore<'a>,
+) -> PccResult<()> {
+    if !flags.checked() {
+        return Ok(());
+    }
+
+    trace!("check_addr: {:?}", addr);
+
+    let check = |addr: &Fact, ty: Type| -> PccResult<()> {
+        match op {
+            LoadOrStore::Load {
+                result_fact: None, ..
+            } => {
+                // Nothing to check about the loaded value (e.g., it
+                // lands in a float or vector register).
+                ctx.load(addr, ty)?;
+                Ok(())
+            }
+            LoadOrStore::Load {
+                result_fact,
+                from_bits,
+                to_bits,
+            } => {
+                let loaded_fact =
+                    clamp_range(ctx, to_bits, from_bits, ctx.load(addr, ty)?.cloned())?;
+                trace!(
+                    "checking a load: loaded_fact = {loaded_fact:?} result_fact = {result_fact:?}"
+                );
+                if ctx.subsumes_fact_optionals(Some(&loaded_fact), result_fact) {
+                    Ok(())
+                } else {
+                    Err(PccError::UnsupportedFact)
+                }
+            }
+            LoadOrStore::Store { stored_fact } => ctx.store(addr, ty, stored_fact),
+        }
+    };
+
+    match addr {
+        &AMode::RegOffset(base, offset, _) => {
+            let base = get_fact_or_default(vcode, base, 64);
+            let sum = fail_if_missing(ctx.offset(&base, 64, offset))?;
+            check(&sum, ty)
+        }
+        &AMode::Label(..) | &AMode::Const(..) => {
+            // Always accept: labels and constants must be within the
+            // generated code (else they won't be resolved).
+            Ok(())
+        }
+        &AMode::SPOffset(..) | &AMode::FPOffset(..) | &AMode::NominalSPOffset(..) => {
+            // We trust ABI code (for now!) and no lowering rules
+            // lower input value accesses directly to these.
+            Ok(())
+        }
+    }
+}
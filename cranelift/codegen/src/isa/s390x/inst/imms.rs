@@ -56,6 +56,11 @@ impl SImm20 {
         }
     }
 
+    /// The value.
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
     /// Bits for encoding.
     pub fn bits(&self) -> u32 {
         let encoded: u32 = self.value as u32;
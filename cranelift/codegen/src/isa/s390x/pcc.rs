@@ -0,0 +1,693 @@
+//! Proof-carrying code checking for s390x VCode.
+
+use crate::ir::pcc::*;
+use crate::ir::types::*;
+use crate::ir::Type;
+use crate::isa::s390x::inst::regs::zero_reg;
+use crate::isa::s390x::inst::{ALUOp, CmpOp, Cond, Inst, MemArg, ShiftOp};
+use crate::machinst::pcc::*;
+use crate::machinst::{InsnIndex, VCode};
+use crate::machinst::{Reg, Writable};
+use crate::trace;
+
+/// Flow-state between facts.
+#[derive(Clone, Debug, Default)]
+pub struct FactFlowState {
+    cmp_flags: Option<(Fact, Fact)>,
+}
+
+pub(crate) fn check(
+    ctx: &FactContext,
+    vcode: &mut VCode<Inst>,
+    inst_idx: InsnIndex,
+    state: &mut FactFlowState,
+) -> PccResult<()> {
+    trace!("Checking facts on inst: {:?}", vcode[inst_idx]);
+
+    // As on AArch64, we only persist the condition code for one
+    // instruction, because we can't exhaustively enumerate all the
+    // instructions that set it.
+    let cmp_flags = state.cmp_flags.take();
+    trace!(" * with cmp_flags = {cmp_flags:?}");
+
+    match vcode[inst_idx] {
+        Inst::Args { .. } => {
+            // Defs on the args have "axiomatic facts": we trust the
+            // ABI code to pass through the values unharmed, so the
+            // facts given to us in the CLIF should still be true.
+            Ok(())
+        }
+
+        // The 32-bit forms leave the upper half of the register
+        // unchanged, so the loaded value only ends up in the low 32
+        // bits.
+        Inst::Load32 { rd, ref mem } => check_load(ctx, Some(rd.to_reg()), mem, vcode, I32, 32),
+        Inst::Load32ZExt8 { rd, ref mem } => check_load(ctx, Some(rd.to_reg()), mem, vcode, I8, 32),
+        Inst::Load32ZExt16 { rd, ref mem } => {
+            check_load(ctx, Some(rd.to_reg()), mem, vcode, I16, 32)
+        }
+        Inst::Load64 { rd, ref mem } => check_load(ctx, Some(rd.to_reg()), mem, vcode, I64, 64),
+        Inst::Load64ZExt8 { rd, ref mem } => check_load(ctx, Some(rd.to_reg()), mem, vcode, I8, 64),
+        Inst::Load64ZExt16 { rd, ref mem } => {
+            check_load(ctx, Some(rd.to_reg()), mem, vcode, I16, 64)
+        }
+        Inst::Load64ZExt32 { rd, ref mem } => {
+            check_load(ctx, Some(rd.to_reg()), mem, vcode, I32, 64)
+        }
+        Inst::Load32SExt8 { rd, ref mem } | Inst::Load64SExt8 { rd, ref mem } => {
+            check_load(ctx, Some(rd.to_reg()), mem, vcode, I8, 8)
+        }
+        Inst::Load32SExt16 { rd, ref mem } | Inst::Load64SExt16 { rd, ref mem } => {
+            check_load(ctx, Some(rd.to_reg()), mem, vcode, I16, 16)
+        }
+        Inst::Load64SExt32 { rd, ref mem } => {
+            check_load(ctx, Some(rd.to_reg()), mem, vcode, I32, 32)
+        }
+        // A byte-reversed load doesn't load the value itself, so
+        // nothing is known about the result but its width.
+        Inst::LoadRev16 { rd, ref mem } => {
+            check_load(ctx, None, mem, vcode, I16, 16)?;
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 16, 16, None))
+        }
+        Inst::LoadRev32 { rd, ref mem } => {
+            check_load(ctx, None, mem, vcode, I32, 32)?;
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 32, 32, None))
+        }
+        Inst::LoadRev64 { rd, ref mem } => {
+            check_load(ctx, None, mem, vcode, I64, 64)?;
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 64, 64, None))
+        }
+        Inst::VecLoad { ref mem, .. }
+        | Inst::VecLoadRev { ref mem, .. }
+        | Inst::VecLoadByte16Rev { ref mem, .. }
+        | Inst::VecLoadByte32Rev { ref mem, .. }
+        | Inst::VecLoadByte64Rev { ref mem, .. }
+        | Inst::VecLoadElt16Rev { ref mem, .. }
+        | Inst::VecLoadElt32Rev { ref mem, .. }
+        | Inst::VecLoadElt64Rev { ref mem, .. } => check_load(ctx, None, mem, vcode, I8X16, 128),
+        Inst::VecLoadReplicate { size, ref mem, .. }
+        | Inst::VecLoadReplicateRev { size, ref mem, .. }
+        | Inst::VecLoadLane { size, ref mem, .. }
+        | Inst::VecLoadLaneUndef { size, ref mem, .. }
+        | Inst::VecLoadLaneRev { size, ref mem, .. }
+        | Inst::VecLoadLaneRevUndef { size, ref mem, .. } => {
+            let ty = lane_ty(size);
+            check_load(ctx, None, mem, vcode, ty, ty.bits().try_into().unwrap())
+        }
+
+        Inst::Store8 { rd, ref mem } => check_store(ctx, Some(rd), mem, vcode, I8),
+        Inst::Store16 { rd, ref mem } => check_store(ctx, Some(rd), mem, vcode, I16),
+        Inst::Store32 { rd, ref mem } => check_store(ctx, Some(rd), mem, vcode, I32),
+        Inst::Store64 { rd, ref mem } => check_store(ctx, Some(rd), mem, vcode, I64),
+        Inst::StoreImm8 { imm, ref mem } => check_store_imm(ctx, mem, vcode, I8, u64::from(imm)),
+        Inst::StoreImm16 { imm, ref mem } => {
+            check_store_imm(ctx, mem, vcode, I16, u64::from(imm as u16))
+        }
+        Inst::StoreImm32SExt16 { imm, ref mem } => {
+            check_store_imm(ctx, mem, vcode, I32, u64::from(i32::from(imm) as u32))
+        }
+        Inst::StoreImm64SExt16 { imm, ref mem } => {
+            check_store_imm(ctx, mem, vcode, I64, i64::from(imm) as u64)
+        }
+        // A byte-reversed store doesn't store the value itself, so
+        // only fields without facts may be written this way.
+        Inst::StoreRev16 { ref mem, .. } => check_store(ctx, None, mem, vcode, I16),
+        Inst::StoreRev32 { ref mem, .. } => check_store(ctx, None, mem, vcode, I32),
+        Inst::StoreRev64 { ref mem, .. } => check_store(ctx, None, mem, vcode, I64),
+        Inst::VecStore { ref mem, .. }
+        | Inst::VecStoreRev { ref mem, .. }
+        | Inst::VecStoreByte16Rev { ref mem, .. }
+        | Inst::VecStoreByte32Rev { ref mem, .. }
+        | Inst::VecStoreByte64Rev { ref mem, .. }
+        | Inst::VecStoreElt16Rev { ref mem, .. }
+        | Inst::VecStoreElt32Rev { ref mem, .. }
+        | Inst::VecStoreElt64Rev { ref mem, .. } => check_store(ctx, None, mem, vcode, I8X16),
+        Inst::VecStoreLane { size, ref mem, .. } | Inst::VecStoreLaneRev { size, ref mem, .. } => {
+            check_store(ctx, None, mem, vcode, lane_ty(size))
+        }
+        Inst::Mvc {
+            ref dst, ref src, ..
+        } => {
+            if dst.flags.checked() || src.flags.checked() {
+                Err(PccError::UnimplementedInst)
+            } else {
+                Ok(())
+            }
+        }
+
+        // Atomic read-modify-write operations store a value we know
+        // nothing about, and return the old one.
+        Inst::AtomicRmw {
+            alu_op,
+            rd,
+            ref mem,
+            ..
+        } => {
+            let bits = alu_op_bits(alu_op);
+            check_store(ctx, None, mem, vcode, Type::int(bits).unwrap())?;
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range_bits(ctx, bits))
+        }
+        Inst::AtomicCas32 { rd, ref mem, .. } => {
+            check_store(ctx, None, mem, vcode, I32)?;
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range_32(ctx, None))
+        }
+        Inst::AtomicCas64 { rd, ref mem, .. } => {
+            check_store(ctx, None, mem, vcode, I64)?;
+            check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 64, 64, None))
+        }
+
+        Inst::Mov32Imm { rd, imm } => check_constant(ctx, vcode, rd, 32, imm.into()),
+        Inst::Mov32SImm16 { rd, imm } => {
+            check_constant(ctx, vcode, rd, 32, u64::from(i32::from(imm) as u32))
+        }
+        Inst::Mov64SImm16 { rd, imm } => check_constant(ctx, vcode, rd, 64, i64::from(imm) as u64),
+        Inst::Mov64SImm32 { rd, imm } => check_constant(ctx, vcode, rd, 64, i64::from(imm) as u64),
+        Inst::Mov64UImm16Shifted { rd, imm } => {
+            let constant = u64::from(imm.bits) << (imm.shift * 16);
+            check_constant(ctx, vcode, rd, 64, constant)
+        }
+        Inst::Mov64UImm32Shifted { rd, imm } => {
+            let constant = u64::from(imm.bits) << (imm.shift * 32);
+            check_constant(ctx, vcode, rd, 64, constant)
+        }
+        Inst::Insert64UImm16Shifted { rd, ri, imm } => {
+            let shift = imm.shift * 16;
+            check_insert(
+                ctx,
+                vcode,
+                rd,
+                ri,
+                0xffff << shift,
+                u64::from(imm.bits) << shift,
+            )
+        }
+        Inst::Insert64UImm32Shifted { rd, ri, imm } => {
+            let shift = imm.shift * 32;
+            check_insert(
+                ctx,
+                vcode,
+                rd,
+                ri,
+                0xffff_ffff << shift,
+                u64::from(imm.bits) << shift,
+            )
+        }
+
+        Inst::Mov64 { rd, rm } => check_unop(ctx, vcode, 64, rd, rm, |rm| Ok(rm.clone())),
+
+        Inst::AluRRR {
+            alu_op: ALUOp::Add64 | ALUOp::AddLogical64,
+            rd,
+            rn,
+            rm,
+        }
+        | Inst::AluRR {
+            alu_op: ALUOp::Add64 | ALUOp::AddLogical64,
+            rd,
+            ri: rn,
+            rm,
+        } => check_binop(ctx, vcode, 64, rd, rn, rm, |rn, rm| {
+            clamp_range(ctx, 64, 64, ctx.add(rn, rm, 64))
+        }),
+        Inst::AluRRR {
+            alu_op: ALUOp::Add32 | ALUOp::AddLogical32,
+            rd,
+            rn,
+            rm,
+        }
+        | Inst::AluRR {
+            alu_op: ALUOp::Add32 | ALUOp::AddLogical32,
+            rd,
+            ri: rn,
+            rm,
+        } => check_binop(ctx, vcode, 32, rd, rn, rm, |rn, rm| {
+            clamp_range_32(ctx, ctx.add(rn, rm, 32))
+        }),
+        Inst::AluRR {
+            alu_op: ALUOp::AddLogical64Ext32,
+            rd,
+            ri,
+            rm,
+        } => check_binop(ctx, vcode, 64, rd, ri, rm, |ri, rm| {
+            let rm = clamp_range(ctx, 64, 32, Some(rm.clone()))?;
+            clamp_range(ctx, 64, 64, ctx.add(ri, &rm, 64))
+        }),
+        Inst::AluRRSImm16 {
+            alu_op: ALUOp::Add64,
+            rd,
+            rn,
+            imm,
+        }
+        | Inst::AluRSImm16 {
+            alu_op: ALUOp::Add64,
+            rd,
+            ri: rn,
+            imm,
+        } => check_unop(ctx, vcode, 64, rd, rn, |rn| {
+            clamp_range(ctx, 64, 64, ctx.offset(rn, 64, imm.into()))
+        }),
+        Inst::AluRSImm32 {
+            alu_op: ALUOp::Add64,
+            rd,
+            ri,
+            imm,
+        } => check_unop(ctx, vcode, 64, rd, ri, |ri| {
+            clamp_range(ctx, 64, 64, ctx.offset(ri, 64, imm.into()))
+        }),
+        Inst::AluRUImm32 {
+            alu_op: alu_op @ (ALUOp::AddLogical64 | ALUOp::SubLogical64),
+            rd,
+            ri,
+            imm,
+        } => check_unop(ctx, vcode, 64, rd, ri, |ri| {
+            let imm = i64::from(imm);
+            let offset = if matches!(alu_op, ALUOp::AddLogical64) {
+                imm
+            } else {
+                -imm
+            };
+            clamp_range(ctx, 64, 64, ctx.offset(ri, 64, offset))
+        }),
+        Inst::AluRRSImm16 {
+            alu_op: ALUOp::Add32,
+            rd,
+            rn,
+            imm,
+        }
+        | Inst::AluRSImm16 {
+            alu_op: ALUOp::Add32,
+            rd,
+            ri: rn,
+            imm,
+        } => check_unop(ctx, vcode, 32, rd, rn, |rn| {
+            clamp_range_32(ctx, ctx.offset(rn, 32, imm.into()))
+        }),
+        Inst::AluRSImm32 {
+            alu_op: ALUOp::Add32,
+            rd,
+            ri,
+            imm,
+        } => check_unop(ctx, vcode, 32, rd, ri, |ri| {
+            clamp_range_32(ctx, ctx.offset(ri, 32, imm.into()))
+        }),
+        Inst::AluRX {
+            alu_op: ALUOp::Add64 | ALUOp::AddLogical64,
+            rd,
+            ri,
+            ref mem,
+        } => {
+            let loaded = match addr_fact(ctx, mem, vcode)? {
+                Some(addr) => ctx.load(&addr, I64)?.cloned(),
+                None => None,
+            };
+            check_output(ctx, vcode, rd, &[ri], |vcode| {
+                let ri = get_fact_or_default(vcode, ri, 64);
+                let loaded = loaded.unwrap_or_else(|| Fact::max_range_for_width(64));
+                clamp_range(ctx, 64, 64, ctx.add(&ri, &loaded, 64))
+            })
+        }
+
+        Inst::ShiftRR {
+            shift_op: ShiftOp::LShL64,
+            rd,
+            rn,
+            shift_imm,
+            shift_reg,
+        } if shift_reg == zero_reg() => check_unop(ctx, vcode, 64, rd, rn, |rn| {
+            clamp_range(ctx, 64, 64, ctx.shl(rn, 64, (shift_imm & 63).into()))
+        }),
+        Inst::ShiftRR {
+            shift_op: ShiftOp::LShL32,
+            rd,
+            rn,
+            shift_imm,
+            shift_reg,
+        } if shift_reg == zero_reg() => check_unop(ctx, vcode, 32, rd, rn, |rn| {
+            clamp_range_32(ctx, ctx.shl(rn, 32, (shift_imm & 63).into()))
+        }),
+
+        Inst::Extend {
+            rd,
+            rn,
+            signed: false,
+            from_bits,
+            to_bits,
+        } if has_fact(vcode, rn) => check_unop(ctx, vcode, 64, rd, rn, |rn| {
+            clamp_range(
+                ctx,
+                to_bits.into(),
+                to_bits.into(),
+                ctx.uextend(rn, from_bits.into(), to_bits.into()),
+            )
+        }),
+
+        Inst::CmpRR {
+            op: CmpOp::CmpL64,
+            rn,
+            rm,
+        } => {
+            let rn = get_fact_or_default(vcode, rn, 64);
+            let rm = get_fact_or_default(vcode, rm, 64);
+            state.cmp_flags = Some((rn, rm));
+            Ok(())
+        }
+        Inst::CmpRUImm32 {
+            op: CmpOp::CmpL64,
+            rn,
+            imm,
+        } => {
+            let rn = get_fact_or_default(vcode, rn, 64);
+            let rm = Fact::constant(64, imm.into());
+            state.cmp_flags = Some((rn, rm));
+            Ok(())
+        }
+        Inst::CmpRX { op, ref mem, .. } => check_load(ctx, None, mem, vcode, cmp_op_mem_ty(op), 64),
+
+        Inst::CMov64 { rd, cond, ri, rm }
+            if cmp_flags.is_some() && cond_inequality(cond).is_some() =>
+        {
+            let (cmp_lhs, cmp_rhs) = cmp_flags.unwrap();
+            let (swap, kind) = cond_inequality(cond).unwrap();
+            trace!("CMov64: cmp {cond:?} ({cmp_lhs:?}, {cmp_rhs:?})");
+            let (lhs, rhs) = if swap {
+                (cmp_rhs, cmp_lhs)
+            } else {
+                (cmp_lhs, cmp_rhs)
+            };
+
+            check_output(ctx, vcode, rd, &[], |vcode| {
+                // We support the same transitivity-based reasoning as
+                // AArch64's `csel`. `rm` is selected when the
+                // condition holds, i.e. when lhs >= rhs (`Loose`) or
+                // lhs > rhs (`Strict`); otherwise `ri` is kept, and
+                // the converse inequality holds.
+                let rm = get_fact_or_default(vcode, rm, 64);
+                let rm = ctx.apply_inequality(&rm, &lhs, &rhs, kind);
+                let ri_kind = match kind {
+                    InequalityKind::Loose => InequalityKind::Strict,
+                    InequalityKind::Strict => InequalityKind::Loose,
+                };
+                let ri = get_fact_or_default(vcode, ri, 64);
+                let ri = ctx.apply_inequality(&ri, &rhs, &lhs, ri_kind);
+                clamp_range(ctx, 64, 64, ctx.union(&rm, &ri))
+            })
+        }
+
+        Inst::AluRRR { alu_op, rd, .. }
+        | Inst::AluRRSImm16 { alu_op, rd, .. }
+        | Inst::AluRR { alu_op, rd, .. }
+        | Inst::AluRSImm16 { alu_op, rd, .. }
+        | Inst::AluRSImm32 { alu_op, rd, .. }
+        | Inst::AluRUImm32 { alu_op, rd, .. }
+        | Inst::AluRUImm16Shifted { alu_op, rd, .. }
+        | Inst::AluRUImm32Shifted { alu_op, rd, .. } => {
+            check_output(ctx, vcode, rd, &[], |_vcode| {
+                clamp_range_bits(ctx, alu_op_bits(alu_op))
+            })
+        }
+        Inst::AluRX {
+            alu_op,
+            rd,
+            ref mem,
+            ..
+        } => {
+            check_load(ctx, None, mem, vcode, alu_op_mem_ty(alu_op), 64)?;
+            check_output(ctx, vcode, rd, &[], |_vcode| {
+                clamp_range_bits(ctx, alu_op_bits(alu_op))
+            })
+        }
+        Inst::ShiftRR { shift_op, rd, .. } => check_output(ctx, vcode, rd, &[], |_vcode| {
+            clamp_range_bits(ctx, shift_op_bits(shift_op))
+        }),
+
+        Inst::Extend {
+            rd,
+            from_bits,
+            to_bits,
+            ..
+        } => check_output(ctx, vcode, rd, &[], |_vcode| {
+            clamp_range(ctx, to_bits.into(), from_bits.into(), None)
+        }),
+
+        _ if vcode.inst_defines_facts(inst_idx) => Err(PccError::UnsupportedFact),
+
+        _ => Ok(()),
+    }
+}
+
+/// The width of the result of an ALU operation: the 32-bit forms
+/// only define the low half of the register.
+fn alu_op_bits(op: ALUOp) -> u16 {
+    match op {
+        ALUOp::Add32
+        | ALUOp::Add32Ext16
+        | ALUOp::AddLogical32
+        | ALUOp::Sub32
+        | ALUOp::Sub32Ext16
+        | ALUOp::SubLogical32
+        | ALUOp::Mul32
+        | ALUOp::Mul32Ext16
+        | ALUOp::And32
+        | ALUOp::Orr32
+        | ALUOp::Xor32
+        | ALUOp::NotAnd32
+        | ALUOp::NotOrr32
+        | ALUOp::NotXor32
+        | ALUOp::AndNot32
+        | ALUOp::OrrNot32 => 32,
+        _ => 64,
+    }
+}
+
+/// The type of the memory operand of an ALU operation.
+fn alu_op_mem_ty(op: ALUOp) -> Type {
+    match op {
+        ALUOp::Add32Ext16
+        | ALUOp::Add64Ext16
+        | ALUOp::Sub32Ext16
+        | ALUOp::Sub64Ext16
+        | ALUOp::Mul32Ext16
+        | ALUOp::Mul64Ext16 => I16,
+        ALUOp::Add64Ext32
+        | ALUOp::AddLogical64Ext32
+        | ALUOp::Sub64Ext32
+        | ALUOp::SubLogical64Ext32
+        | ALUOp::Mul64Ext32 => I32,
+        _ if alu_op_bits(op) == 32 => I32,
+        _ => I64,
+    }
+}
+
+/// The type of the memory operand of a comparison.
+fn cmp_op_mem_ty(op: CmpOp) -> Type {
+    match op {
+        CmpOp::CmpS32Ext16 | CmpOp::CmpS64Ext16 | CmpOp::CmpL32Ext16 | CmpOp::CmpL64Ext16 => I16,
+        CmpOp::CmpS32 | CmpOp::CmpS64Ext32 | CmpOp::CmpL32 | CmpOp::CmpL64Ext32 => I32,
+        CmpOp::CmpS64 | CmpOp::CmpL64 => I64,
+    }
+}
+
+/// The width of the result of a shift or rotate.
+fn shift_op_bits(op: ShiftOp) -> u16 {
+    match op {
+        ShiftOp::RotL32 | ShiftOp::LShL32 | ShiftOp::LShR32 | ShiftOp::AShR32 => 32,
+        ShiftOp::RotL64 | ShiftOp::LShL64 | ShiftOp::LShR64 | ShiftOp::AShR64 => 64,
+    }
+}
+
+/// The type of a vector lane of `size` bits.
+fn lane_ty(size: u32) -> Type {
+    Type::int(u16::try_from(size).unwrap()).unwrap()
+}
+
+/// The inequality between the values compared by an unsigned
+/// comparison that holds when `cond` does: the flag is set if the
+/// operands must be swapped, and the kind tells whether the
+/// inequality is strict.
+fn cond_inequality(cond: Cond) -> Option<(bool, InequalityKind)> {
+    match cond.bits() {
+        // High.
+        2 => Some((false, InequalityKind::Strict)),
+        // High or equal.
+        10 => Some((false, InequalityKind::Loose)),
+        // Low.
+        4 => Some((true, InequalityKind::Strict)),
+        // Low or equal.
+        12 => Some((true, InequalityKind::Loose)),
+        _ => None,
+    }
+}
+
+/// A range covering any value defined by an operation of the given
+/// width.
+fn clamp_range_bits(ctx: &FactContext, bits: u16) -> PccResult<Fact> {
+    if bits == 32 {
+        clamp_range_32(ctx, None)
+    } else {
+        clamp_range(ctx, 64, 64, None)
+    }
+}
+
+/// The 32-bit instructions leave the upper half of the register
+/// unchanged, so facts only describe the low 32 bits, and only when
+/// the operation didn't wrap.
+fn clamp_range_32(ctx: &FactContext, fact: Option<Fact>) -> PccResult<Fact> {
+    let fact = fact.filter(|fact| match fact {
+        Fact::Range { max, .. } => *max <= u64::from(u32::MAX),
+        _ => true,
+    });
+    clamp_range(ctx, 32, 32, fact)
+}
+
+/// Check the result of inserting `bits` under `mask` into the
+/// constant held by `ri`.
+fn check_insert(
+    ctx: &FactContext,
+    vcode: &mut VCode<Inst>,
+    rd: Writable<Reg>,
+    ri: Reg,
+    mask: u64,
+    bits: u64,
+) -> PccResult<()> {
+    match vcode
+        .vreg_fact(ri.into())
+        .and_then(|fact| fact.as_const(64))
+    {
+        Some(constant) => check_constant(ctx, vcode, rd, 64, (constant & !mask) | bits),
+        None => check_output(ctx, vcode, rd, &[], |_vcode| clamp_range(ctx, 64, 64, None)),
+    }
+}
+
+fn check_load(
+    ctx: &FactContext,
+    rd: Option<Reg>,
+    mem: &MemArg,
+    vcode: &VCode<Inst>,
+    ty: Type,
+    to_bits: u16,
+) -> PccResult<()> {
+    let result_fact = rd.and_then(|rd| vcode.vreg_fact(rd.into()));
+    let from_bits = u16::try_from(ty.bits()).unwrap();
+    check_addr(
+        ctx,
+        mem,
+        vcode,
+        ty,
+        LoadOrStore::Load {
+            result_fact,
+            from_bits,
+            to_bits,
+        },
+    )
+}
+
+fn check_store(
+    ctx: &FactContext,
+    rd: Option<Reg>,
+    mem: &MemArg,
+    vcode: &VCode<Inst>,
+    ty: Type,
+) -> PccResult<()> {
+    let stored_fact = rd.and_then(|rd| vcode.vreg_fact(rd.into()));
+    check_addr(ctx, mem, vcode, ty, LoadOrStore::Store { stored_fact })
+}
+
+fn check_store_imm(
+    ctx: &FactContext,
+    mem: &MemArg,
+    vcode: &VCode<Inst>,
+    ty: Type,
+    imm: u64,
+) -> PccResult<()> {
+    let stored_fact = Fact::constant(u16::try_from(ty.bits()).unwrap(), imm);
+    check_addr(
+        ctx,
+        mem,
+        vcode,
+        ty,
+        LoadOrStore::Store {
+            stored_fact: Some(&stored_fact),
+        },
+    )
+}
+
+fn check_addr<'a>(
+    ctx: &FactContext,
+    mem: &MemArg,
+    vcode: &VCode<Inst>,
+    ty: Type,
+    op: LoadOrStore<'a>,
+) -> PccResult<()> {
+    let Some(addr) = addr_fact(ctx, mem, vcode)? else {
+        return Ok(());
+    };
+
+    match op {
+        LoadOrStore::Load {
+            result_fact: None, ..
+        } => {
+            // Nothing to check about the loaded value (e.g., it
+            // lands in a vector register).
+            ctx.load(&addr, ty)?;
+            Ok(())
+        }
+        LoadOrStore::Load {
+            result_fact,
+            from_bits,
+            to_bits,
+        } => {
+            let loaded_fact = clamp_range(ctx, to_bits, from_bits, ctx.load(&addr, ty)?.cloned())?;
+            trace!("checking a load: loaded_fact = {loaded_fact:?} result_fact = {result_fact:?}");
+            if ctx.subsumes_fact_optionals(Some(&loaded_fact), result_fact) {
+                Ok(())
+            } else {
+                Err(PccError::UnsupportedFact)
+            }
+        }
+        LoadOrStore::Store { stored_fact } => ctx.store(&addr, ty, stored_fact),
+    }
+}
+
+/// Compute the fact on the address accessed through `mem`, or `None`
+/// if the access doesn't need to be checked.
+fn addr_fact(ctx: &FactContext, mem: &MemArg, vcode: &VCode<Inst>) -> PccResult<Option<Fact>> {
+    if !mem.get_flags().checked() {
+        return Ok(None);
+    }
+
+    trace!("addr_fact: {:?}", mem);
+
+    let base_index_disp = |base: Reg, index: Reg, disp: i64| -> PccResult<Fact> {
+        let base = get_fact_or_default(vcode, base, 64);
+        let sum = if index == zero_reg() {
+            base
+        } else {
+            let index = get_fact_or_default(vcode, index, 64);
+            fail_if_missing(ctx.add(&base, &index, 64))?
+        };
+        fail_if_missing(ctx.offset(&sum, 64, disp))
+    };
+
+    match *mem {
+        MemArg::BXD12 {
+            base, index, disp, ..
+        } => base_index_disp(base, index, disp.bits().into()).map(Some),
+        MemArg::BXD20 {
+            base, index, disp, ..
+        } => base_index_disp(base, index, disp.value().into()).map(Some),
+        MemArg::RegOffset { reg, off, .. } => {
+            let base = get_fact_or_default(vcode, reg, 64);
+            fail_if_missing(ctx.offset(&base, 64, off)).map(Some)
+        }
+        MemArg::Label { .. } | MemArg::Symbol { .. } => {
+            // Always accept: labels must be within the generated
+            // code, and symbols are resolved by the linker.
+            Ok(None)
+        }
+        MemArg::InitialSPOffset { .. } | MemArg::NominalSPOffset { .. } => {
+            // We trust ABI code (for now!) and no lowering rules
+            // lower input value accesses directly to these.
+            Ok(None)
+        }
+    }
+}
@@ -28,6 +28,9 @@ mod lower;
 mod pcc;
 pub mod settings;
 
+#[cfg(feature = "isle-coverage")]
+pub(crate) use lower::isle::generated_code::{ISLE_RULES, ISLE_RULE_COUNTS};
+
 pub use inst::unwind::systemv::create_cie;
 
 /// An X64 backend.
@@ -0,0 +1,68 @@
+//! Coverage of ISLE rules.
+//!
+//! With the `isle-coverage` feature, the Rust code generated from the ISLE
+//! sources counts how many times each rule is applied, which shows rules that
+//! are never applied by a set of tests or a corpus of programs.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// An ISLE rule and how many times it was applied.
+#[derive(Clone, Debug)]
+pub struct RuleCoverage {
+    /// The ISLE compilation the rule is part of: `opt` for the mid-end
+    /// optimizations, or the name of a backend for its lowering.
+    pub compilation: &'static str,
+    /// The term the rule is for.
+    pub term: &'static str,
+    /// Where the rule was defined, as `file.isle line 12`.
+    pub pos: &'static str,
+    /// How many times the rule was applied.
+    pub count: u64,
+}
+
+/// The rules of one ISLE compilation, as generated by `islec`.
+pub(crate) struct RuleTable {
+    pub(crate) compilation: &'static str,
+    /// The term and position of each rule.
+    pub(crate) rules: &'static [(&'static str, &'static str)],
+    /// How many times each rule was applied.
+    pub(crate) counts: &'static [AtomicU64],
+}
+
+fn rule_tables() -> Vec<RuleTable> {
+    let mut tables = Vec::new();
+    tables.push(RuleTable {
+        compilation: "opt",
+        rules: &crate::opts::generated_code::ISLE_RULES,
+        counts: &crate::opts::generated_code::ISLE_RULE_COUNTS,
+    });
+    crate::isa::isle_rule_tables(&mut tables);
+    tables
+}
+
+/// Returns how many times each ISLE rule of the mid-end and of the enabled
+/// backends was applied so far in this process.
+pub fn rule_coverage() -> Vec<RuleCoverage> {
+    let mut coverage = Vec::new();
+    for table in rule_tables() {
+        for (&(term, pos), count) in table.rules.iter().zip(table.counts) {
+            coverage.push(RuleCoverage {
+                compilation: table.compilation,
+                term,
+                pos,
+                count: count.load(Ordering::Relaxed),
+            });
+        }
+    }
+    coverage
+}
+
+/// Resets the counts of all ISLE rules to zero.
+pub fn reset_rule_coverage() {
+    for table in rule_tables() {
+        for count in table.counts {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+}
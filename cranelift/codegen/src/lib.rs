@@ -43,6 +43,7 @@ pub mod data_value;
 pub mod dbg;
 pub mod dominator_tree;
 pub mod flowgraph;
+pub mod inline;
 pub mod ir;
 pub mod isa;
 pub mod loop_analysis;
@@ -92,6 +93,9 @@ pub use crate::result::{CodegenError, CodegenResult, CompileError};
 #[cfg(feature = "incremental-cache")]
 pub mod incremental_cache;
 
+#[cfg(feature = "isle-coverage")]
+pub mod isle_coverage;
+
 /// Even when trace logging is disabled, the trace macro has a significant performance cost so we
 /// disable it by default.
 #[macro_export]
@@ -1750,12 +1750,10 @@ impl<M: ABIMachineSpec> Callee<M> {
 
     /// Produce an instruction that computes a dynamic stackslot address.
     pub fn dynamic_stackslot_addr(&self, slot: DynamicStackSlot, into_reg: Writable<Reg>) -> M::I {
+        // Dynamic types don't have a size to scale the offset by, so address the slot as bytes
+        // like sized stackslots.
         let stack_off = self.dynamic_stackslots[slot] as i64;
-        M::gen_get_stack_addr(
-            StackAMode::NominalSPOffset(stack_off, I64X2XN),
-            into_reg,
-            I64X2XN,
-        )
+        M::gen_get_stack_addr(StackAMode::NominalSPOffset(stack_off, I8), into_reg, I8)
     }
 
     /// Load from a spillslot.
@@ -59,6 +59,20 @@
 //! Furthermore, the [MachBuffer] machine-code sink performs final peephole-like
 //! branch editing that in practice elides empty blocks and simplifies some of
 //! the other redundancies that this scheme produces.
+//!
+//! When the function has block frequencies, e.g. from a profile, the DFS visits
+//! the successors of each block in order of increasing frequency, so that the
+//! most frequently executed successor immediately follows its predecessor in
+//! the reverse postorder. Blocks that were never executed are treated like cold
+//! blocks.
+//!
+//! Block frequencies are not passed to register allocation, which has no way to
+//! take them: regalloc2 weighs each use of a value only by the loop depth of its
+//! block, which it derives from the backedges in the lowered order. Frequencies
+//! can thus only change spill weights indirectly, through this order. As the
+//! DFS visits rarely executed successors first, paths that leave a loop, such
+//! as error handling, may follow the loop's backedge in the lowered order, in
+//! which case their uses no longer weigh like uses in the loop.
 
 use crate::dominator_tree::DominatorTree;
 use crate::entity::SecondaryMap;
@@ -189,12 +203,22 @@ impl BlockLoweringOrder {
             block_succ_range[block] = start..end;
         }
 
-        // Step 2: walk the postorder from the domtree in reverse to produce our desired node
-        // lowering order, identifying critical edges to split along the way.
+        // Step 2: walk the postorder from the domtree, or the one guided by block frequencies, in
+        // reverse to produce our desired node lowering order, identifying critical edges to split
+        // along the way.
+
+        let frequency_postorder = frequency_guided_postorder(f, &block_succs, &block_succ_range);
+        let postorder = frequency_postorder
+            .as_deref()
+            .unwrap_or(domtree.cfg_postorder());
+        let is_cold = |block| {
+            f.layout.is_cold(block)
+                || (frequency_postorder.is_some() && f.layout.frequency(block) == Some(0))
+        };
 
         let mut lowered_order = Vec::new();
 
-        for &block in domtree.cfg_postorder().iter().rev() {
+        for &block in postorder.iter().rev() {
             lowered_order.push(LoweredBlock::Orig { block });
 
             if block_out_count[block] > 1 {
@@ -248,7 +272,7 @@ impl BlockLoweringOrder {
                         lowered_succ_indices
                             .extend(block_succs[range].iter().map(|lb| lb_to_bindex[lb]));
 
-                        if f.layout.is_cold(block) {
+                        if is_cold(block) {
                             cold_blocks.insert(bindex);
                         }
 
@@ -273,7 +297,7 @@ impl BlockLoweringOrder {
                         // Edges inherit indirect branch and cold block metadata from their
                         // successor.
 
-                        if f.layout.is_cold(succ) {
+                        if is_cold(succ) {
                             cold_blocks.insert(bindex);
                         }
 
@@ -323,6 +347,57 @@ impl BlockLoweringOrder {
     }
 }
 
+/// Compute a postorder of the blocks of `f` in which the successors of each
+/// block are visited in order of increasing frequency, if the entry block was
+/// executed according to the function's block frequencies.
+///
+/// Blocks without a frequency are treated as if they were never executed.
+fn frequency_guided_postorder(
+    f: &Function,
+    block_succs: &[LoweredBlock],
+    block_succ_range: &SecondaryMap<Block, std::ops::Range<usize>>,
+) -> Option<Vec<Block>> {
+    let entry = f.layout.entry_block()?;
+    if !f.layout.frequency(entry).is_some_and(|freq| freq > 0) {
+        return None;
+    }
+
+    let mut postorder = Vec::new();
+    let mut visited = FxHashSet::default();
+    // Each entry is a block and its successors that remain to be visited, the
+    // most frequent one last.
+    let mut stack: Vec<(Block, SmallVec<[Block; 4]>)> = Vec::new();
+    let succs = |block: Block| {
+        let mut succs = SmallVec::<[Block; 4]>::from_iter(
+            block_succs[block_succ_range[block].clone()]
+                .iter()
+                .map(|lb| lb.orig_block().unwrap()),
+        );
+        // Successors are popped from the end, so the most frequent one, which
+        // is sorted first here, is visited last. That places it right after
+        // `block` in reverse postorder.
+        succs.sort_by_key(|&succ| core::cmp::Reverse(f.layout.frequency(succ).unwrap_or(0)));
+        succs
+    };
+
+    visited.insert(entry);
+    stack.push((entry, succs(entry)));
+    while let Some((_, remaining)) = stack.last_mut() {
+        match remaining.pop() {
+            Some(succ) => {
+                if visited.insert(succ) {
+                    stack.push((succ, succs(succ)));
+                }
+            }
+            None => {
+                let (block, _) = stack.pop().unwrap();
+                postorder.push(block);
+            }
+        }
+    }
+    Some(postorder)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -334,6 +409,14 @@ mod test {
     use crate::isa::CallConv;
 
     fn build_test_func(n_blocks: usize, edges: &[(usize, usize)]) -> BlockLoweringOrder {
+        build_test_func_with_frequencies(n_blocks, edges, &[])
+    }
+
+    fn build_test_func_with_frequencies(
+        n_blocks: usize,
+        edges: &[(usize, usize)],
+        frequencies: &[u64],
+    ) -> BlockLoweringOrder {
         assert!(n_blocks > 0);
 
         let name = UserFuncName::testcase("test0");
@@ -372,6 +455,10 @@ mod test {
             }
         }
 
+        for (i, &frequency) in frequencies.iter().enumerate() {
+            func.layout.set_frequency(blocks[i], frequency);
+        }
+
         let mut cfg = ControlFlowGraph::new();
         cfg.compute(&func);
         let dom_tree = DominatorTree::with_function(&func, &cfg);
@@ -388,6 +475,68 @@ mod test {
         assert_eq!(order.lowered_order.len(), 4);
     }
 
+    #[test]
+    fn test_blockorder_frequencies() {
+        let block_order = |order: &BlockLoweringOrder| {
+            order
+                .lowered_order
+                .iter()
+                .map(|lb| lb.orig_block().unwrap().as_u32())
+                .collect::<Vec<_>>()
+        };
+
+        // Without frequencies, block 2 follows the entry block.
+        let order = build_test_func(4, &[(0, 1), (0, 2), (1, 3), (2, 3)]);
+        assert_eq!(block_order(&order), [0, 2, 1, 3]);
+        assert!((0..4).all(|i| !order.is_cold(BlockIndex::new(i))));
+
+        // The more frequent block 1 follows the entry block.
+        let order =
+            build_test_func_with_frequencies(4, &[(0, 1), (0, 2), (1, 3), (2, 3)], &[10, 9, 1, 10]);
+        assert_eq!(block_order(&order), [0, 1, 2, 3]);
+        assert!((0..4).all(|i| !order.is_cold(BlockIndex::new(i))));
+
+        // Blocks that were never executed are cold.
+        let order = build_test_func_with_frequencies(
+            4,
+            &[(0, 1), (0, 2), (1, 3), (2, 3)],
+            &[10, 10, 0, 10],
+        );
+        assert_eq!(block_order(&order), [0, 1, 2, 3]);
+        assert!(order.is_cold(BlockIndex::new(2)));
+        assert!(!order.is_cold(BlockIndex::new(3)));
+    }
+
+    #[test]
+    fn test_blockorder_frequencies_loop_exit() {
+        //      0
+        //      |
+        //      1 <---.
+        //     / \    |
+        //    5   2   |
+        //       / \  |
+        //      4   3-'
+        let edges = [(0, 1), (1, 2), (1, 5), (2, 3), (2, 4), (3, 1)];
+        let block_order = |order: &BlockLoweringOrder| {
+            order
+                .lowered_order
+                .iter()
+                .map(|lb| lb.orig_block().unwrap().as_u32())
+                .collect::<Vec<_>>()
+        };
+
+        // Without frequencies, block 4 precedes the loop's backedge in 3, so
+        // register allocation considers it part of the loop.
+        let order = build_test_func(6, &edges);
+        assert_eq!(block_order(&order), [0, 1, 5, 2, 4, 3]);
+
+        // Block 4 leaves the loop but was never executed, so it follows the
+        // backedge, which keeps it out of the loop for register allocation.
+        let order = build_test_func_with_frequencies(6, &edges, &[1, 101, 100, 100, 0, 1]);
+        assert_eq!(block_order(&order), [0, 1, 2, 3, 4, 5]);
+        assert!(order.is_cold(BlockIndex::new(4)));
+    }
+
     #[test]
     fn test_blockorder_critedge() {
         //            0
@@ -529,6 +529,7 @@ bb_padding_log2_minus_one = 0
 regalloc_checker = false
 regalloc_verbose_logs = false
 enable_alias_analysis = true
+enable_inlining = false
 enable_verifier = true
 enable_pcc = false
 is_pic = false
@@ -546,6 +547,7 @@ enable_probestack = false
 probestack_func_adjusts_sp = false
 enable_jump_tables = true
 enable_heap_access_spectre_mitigation = true
+enable_heap_bounds_check_elimination = false
 enable_table_access_spectre_mitigation = true
 enable_incremental_compilation_cache_checks = false
 "#;
@@ -0,0 +1,197 @@
+;;! target = "x86_64"
+;;!
+;;! settings = [
+;;!   "enable_heap_access_spectre_mitigation=false",
+;;!   "enable_heap_bounds_check_elimination=true",
+;;! ]
+;;!
+;;! compile = false
+;;!
+;;! [globals.vmctx]
+;;! type = "i64"
+;;! vmctx = true
+;;!
+;;! [globals.heap_base]
+;;! type = "i64"
+;;! load = { base = "vmctx", offset = 0 }
+;;!
+;;! [globals.heap_bound]
+;;! type = "i64"
+;;! load = { base = "vmctx", offset = 8 }
+;;!
+;;! [[heaps]]
+;;! base = "heap_base"
+;;! min_size = 0
+;;! offset_guard_size = 0
+;;! index_type = "i64"
+;;! style = { kind = "dynamic", bound = "heap_bound" }
+
+(module
+  (memory i64 0)
+
+  (func (export "loads") (param i64) (result i32)
+    ;; Checks that `index + 12 <= bound`.
+    local.get 0
+    i32.load offset=8
+    ;; Covered by the first check.
+    local.get 0
+    i32.load offset=4
+    i32.add
+    ;; Also covered: the index is 4 bytes further along, but the access ends
+    ;; at the same byte.
+    local.get 0
+    i64.const 4
+    i64.add
+    i32.load offset=4
+    i32.add
+    ;; Ends past the range of the first check, needs its own check.
+    local.get 0
+    i32.load offset=16
+    i32.add
+  )
+
+  (func (export "sum") (param i64 i64) (result i32)
+    (local i64 i32)
+    ;; Sums the 32-bit integers at `p + 4 * i` for the parameters `p` and `n`,
+    ;; and `i` counting up from zero while `i + 1 < n`. The range of indices is
+    ;; known before the loop, so the check is hoisted out of it.
+    loop
+      local.get 3
+      local.get 0
+      local.get 2
+      i64.const 2
+      i64.shl
+      i64.add
+      i32.load
+      i32.add
+      local.set 3
+      local.get 2
+      i64.const 1
+      i64.add
+      local.tee 2
+      local.get 1
+      i64.lt_u
+      br_if 0
+    end
+    local.get 3
+  )
+)
+
+;; function u0:0(i64, i64 vmctx) -> i32 fast {
+;;     gv0 = vmctx
+;;     gv1 = load.i64 notrap aligned gv0+8
+;;     gv2 = load.i64 notrap aligned gv0
+;;
+;;                                 block0(v0: i64, v1: i64):
+;; @0038                               v3 = iconst.i64 12
+;; @0038                               v4 = uadd_overflow_trap v0, v3, heap_oob  ; v3 = 12
+;; @0038                               v5 = global_value.i64 gv1
+;; @0038                               v6 = icmp ugt v4, v5
+;; @0038                               trapnz v6, heap_oob
+;; @0038                               v7 = global_value.i64 gv2
+;; @0038                               v8 = iadd v7, v0
+;; @0038                               v9 = iconst.i64 8
+;; @0038                               v10 = iadd v8, v9  ; v9 = 8
+;; @0038                               v11 = load.i32 little heap v10
+;; @003d                               v12 = iconst.i64 8
+;; @003d                               v13 = iadd v0, v12  ; v12 = 8
+;; @003d                               v14 = global_value.i64 gv1
+;; @003d                               v15 = icmp ugt v13, v14
+;; @003d                               v16 = global_value.i64 gv2
+;; @003d                               v17 = iadd v16, v0
+;; @003d                               v18 = iconst.i64 4
+;; @003d                               v19 = iadd v17, v18  ; v18 = 4
+;; @003d                               v20 = load.i32 little heap v19
+;; @0040                               v21 = iadd v11, v20
+;; @0043                               v22 = iconst.i64 4
+;; @0045                               v23 = iadd v0, v22  ; v22 = 4
+;; @0046                               v24 = iconst.i64 8
+;; @0046                               v25 = iadd v23, v24  ; v24 = 8
+;; @0046                               v26 = global_value.i64 gv1
+;; @0046                               v27 = icmp ugt v25, v26
+;; @0046                               v28 = global_value.i64 gv2
+;; @0046                               v29 = iadd v28, v23
+;; @0046                               v30 = iconst.i64 4
+;; @0046                               v31 = iadd v29, v30  ; v30 = 4
+;; @0046                               v32 = load.i32 little heap v31
+;; @0049                               v33 = iadd v21, v32
+;; @004c                               v34 = iconst.i64 20
+;; @004c                               v35 = uadd_overflow_trap v0, v34, heap_oob  ; v34 = 20
+;; @004c                               v36 = global_value.i64 gv1
+;; @004c                               v37 = icmp ugt v35, v36
+;; @004c                               trapnz v37, heap_oob
+;; @004c                               v38 = global_value.i64 gv2
+;; @004c                               v39 = iadd v38, v0
+;; @004c                               v40 = iconst.i64 16
+;; @004c                               v41 = iadd v39, v40  ; v40 = 16
+;; @004c                               v42 = load.i32 little heap v41
+;; @004f                               v43 = iadd v33, v42
+;; @0050                               jump block1(v43)
+;;
+;;                                 block1(v2: i32):
+;; @0050                               return v2
+;; }
+;;
+;; function u0:1(i64, i64, i64 vmctx) -> i32 fast {
+;;     gv0 = vmctx
+;;     gv1 = load.i64 notrap aligned gv0+8
+;;     gv2 = load.i64 notrap aligned gv0
+;;
+;;                                 block0(v0: i64, v1: i64, v2: i64):
+;;                                     v7 -> v0
+;;                                     v22 -> v1
+;; @0053                               v4 = iconst.i64 0
+;; @0055                               v5 = iconst.i32 0
+;; @0063                               v25 = isub v1, v4  ; v4 = 0
+;; @0063                               v26 = iadd_imm v25, -1
+;; @0063                               v27 = icmp ult v4, v1  ; v4 = 0
+;; @0063                               v28 = icmp_imm eq v4, -1  ; v4 = 0
+;; @0063                               v29 = iconst.i64 0
+;; @0063                               v30 = select v28, v1, v29  ; v29 = 0
+;; @0063                               v31 = select v27, v26, v30
+;; @0063                               v32 = imul_imm v4, 4  ; v4 = 0
+;; @0063                               v33 = iadd v0, v32
+;; @0063                               v34 = global_value.i64 gv1
+;; @0063                               v35 = iconst.i64 4
+;; @0063                               v36 = iadd v33, v35  ; v35 = 4
+;; @0063                               v37 = icmp ult v36, v33
+;; @0063                               v38 = icmp ugt v36, v34
+;; @0063                               v39 = bor v37, v38
+;; @0063                               v40 = imul_imm v31, 4
+;; @0063                               v41 = icmp_imm ugt v31, 0x3fff_ffff_ffff_ffff
+;; @0063                               v42 = bor v39, v41
+;; @0063                               v43 = iadd v36, v40
+;; @0063                               v44 = icmp ult v43, v36
+;; @0063                               v45 = icmp ugt v43, v34
+;; @0063                               v46 = bor v42, v44
+;; @0063                               v47 = bor v46, v45
+;; @0063                               trapnz v47, heap_oob
+;; @0057                               jump block2(v5, v4)  ; v5 = 0, v4 = 0
+;;
+;;                                 block2(v6: i32, v8: i64):
+;; @005f                               v9 = iconst.i64 2
+;; @0061                               v10 = ishl v8, v9  ; v9 = 2
+;; @0062                               v11 = iadd.i64 v7, v10
+;; @0063                               v12 = iconst.i64 4
+;; @0063                               v13 = iadd v11, v12  ; v12 = 4
+;; @0063                               v14 = global_value.i64 gv1
+;; @0063                               v15 = icmp ugt v13, v14
+;; @0063                               v16 = global_value.i64 gv2
+;; @0063                               v17 = iadd v16, v11
+;; @0063                               v18 = load.i32 little heap v17
+;; @0066                               v19 = iadd v6, v18
+;; @006b                               v20 = iconst.i64 1
+;; @006d                               v21 = iadd v8, v20  ; v20 = 1
+;; @0072                               v23 = icmp ult v21, v22
+;; @0072                               v24 = uextend.i32 v23
+;; @0073                               brif v24, block2(v19, v21), block4
+;;
+;;                                 block4:
+;; @0075                               jump block3
+;;
+;;                                 block3:
+;; @0078                               jump block1(v19)
+;;
+;;                                 block1(v3: i32):
+;; @0078                               return v3
+;; }
@@ -0,0 +1,204 @@
+;;! target = "x86_64"
+;;!
+;;! settings = [
+;;!   "enable_heap_access_spectre_mitigation=true",
+;;!   "enable_heap_bounds_check_elimination=true",
+;;! ]
+;;!
+;;! compile = false
+;;!
+;;! [globals.vmctx]
+;;! type = "i64"
+;;! vmctx = true
+;;!
+;;! [globals.heap_base]
+;;! type = "i64"
+;;! load = { base = "vmctx", offset = 0 }
+;;!
+;;! [globals.heap_bound]
+;;! type = "i64"
+;;! load = { base = "vmctx", offset = 8 }
+;;!
+;;! [[heaps]]
+;;! base = "heap_base"
+;;! min_size = 0
+;;! offset_guard_size = 0
+;;! index_type = "i64"
+;;! style = { kind = "dynamic", bound = "heap_bound" }
+
+(module
+  (memory i64 0)
+
+  (func (export "loads") (param i64) (result i32)
+    ;; Checks that `index + 12 <= bound`.
+    local.get 0
+    i32.load offset=8
+    ;; Covered by the first check.
+    local.get 0
+    i32.load offset=4
+    i32.add
+    ;; Also covered: the index is 4 bytes further along, but the access ends
+    ;; at the same byte.
+    local.get 0
+    i64.const 4
+    i64.add
+    i32.load offset=4
+    i32.add
+    ;; Ends past the range of the first check, needs its own check.
+    local.get 0
+    i32.load offset=16
+    i32.add
+  )
+
+  (func (export "sum") (param i64 i64) (result i32)
+    (local i64 i32)
+    ;; Sums the 32-bit integers at `p + 4 * i` for the parameters `p` and `n`,
+    ;; and `i` counting up from zero while `i + 1 < n`. The range of indices is
+    ;; known before the loop, so the check is hoisted out of it.
+    loop
+      local.get 3
+      local.get 0
+      local.get 2
+      i64.const 2
+      i64.shl
+      i64.add
+      i32.load
+      i32.add
+      local.set 3
+      local.get 2
+      i64.const 1
+      i64.add
+      local.tee 2
+      local.get 1
+      i64.lt_u
+      br_if 0
+    end
+    local.get 3
+  )
+)
+
+;; function u0:0(i64, i64 vmctx) -> i32 fast {
+;;     gv0 = vmctx
+;;     gv1 = load.i64 notrap aligned gv0+8
+;;     gv2 = load.i64 notrap aligned gv0
+;;
+;;                                 block0(v0: i64, v1: i64):
+;; @0038                               v3 = iconst.i64 12
+;; @0038                               v4 = uadd_overflow_trap v0, v3, heap_oob  ; v3 = 12
+;; @0038                               v5 = global_value.i64 gv1
+;; @0038                               v6 = icmp ugt v4, v5
+;; @0038                               v7 = global_value.i64 gv2
+;; @0038                               v8 = iadd v7, v0
+;; @0038                               v9 = iconst.i64 8
+;; @0038                               v10 = iadd v8, v9  ; v9 = 8
+;; @0038                               v11 = iconst.i64 0
+;; @0038                               v12 = select_spectre_guard v6, v11, v10  ; v11 = 0
+;; @0038                               v13 = load.i32 little heap v12
+;; @003d                               v14 = iconst.i64 8
+;; @003d                               v15 = iadd v0, v14  ; v14 = 8
+;; @003d                               v16 = global_value.i64 gv1
+;; @003d                               v17 = icmp ugt v15, v16
+;; @003d                               v18 = global_value.i64 gv2
+;; @003d                               v19 = iadd v18, v0
+;; @003d                               v20 = iconst.i64 4
+;; @003d                               v21 = iadd v19, v20  ; v20 = 4
+;; @003d                               v22 = iconst.i64 0
+;; @003d                               v23 = select_spectre_guard v6, v22, v21  ; v22 = 0
+;; @003d                               v24 = load.i32 little heap v23
+;; @0040                               v25 = iadd v13, v24
+;; @0043                               v26 = iconst.i64 4
+;; @0045                               v27 = iadd v0, v26  ; v26 = 4
+;; @0046                               v28 = iconst.i64 8
+;; @0046                               v29 = iadd v27, v28  ; v28 = 8
+;; @0046                               v30 = global_value.i64 gv1
+;; @0046                               v31 = icmp ugt v29, v30
+;; @0046                               v32 = global_value.i64 gv2
+;; @0046                               v33 = iadd v32, v27
+;; @0046                               v34 = iconst.i64 4
+;; @0046                               v35 = iadd v33, v34  ; v34 = 4
+;; @0046                               v36 = iconst.i64 0
+;; @0046                               v37 = select_spectre_guard v6, v36, v35  ; v36 = 0
+;; @0046                               v38 = load.i32 little heap v37
+;; @0049                               v39 = iadd v25, v38
+;; @004c                               v40 = iconst.i64 20
+;; @004c                               v41 = uadd_overflow_trap v0, v40, heap_oob  ; v40 = 20
+;; @004c                               v42 = global_value.i64 gv1
+;; @004c                               v43 = icmp ugt v41, v42
+;; @004c                               v44 = global_value.i64 gv2
+;; @004c                               v45 = iadd v44, v0
+;; @004c                               v46 = iconst.i64 16
+;; @004c                               v47 = iadd v45, v46  ; v46 = 16
+;; @004c                               v48 = iconst.i64 0
+;; @004c                               v49 = select_spectre_guard v43, v48, v47  ; v48 = 0
+;; @004c                               v50 = load.i32 little heap v49
+;; @004f                               v51 = iadd v39, v50
+;; @0050                               jump block1(v51)
+;;
+;;                                 block1(v2: i32):
+;; @0050                               return v2
+;; }
+;;
+;; function u0:1(i64, i64, i64 vmctx) -> i32 fast {
+;;     gv0 = vmctx
+;;     gv1 = load.i64 notrap aligned gv0+8
+;;     gv2 = load.i64 notrap aligned gv0
+;;
+;;                                 block0(v0: i64, v1: i64, v2: i64):
+;;                                     v7 -> v0
+;;                                     v24 -> v1
+;; @0053                               v4 = iconst.i64 0
+;; @0055                               v5 = iconst.i32 0
+;; @0063                               v27 = isub v1, v4  ; v4 = 0
+;; @0063                               v28 = iadd_imm v27, -1
+;; @0063                               v29 = icmp ult v4, v1  ; v4 = 0
+;; @0063                               v30 = icmp_imm eq v4, -1  ; v4 = 0
+;; @0063                               v31 = iconst.i64 0
+;; @0063                               v32 = select v30, v1, v31  ; v31 = 0
+;; @0063                               v33 = select v29, v28, v32
+;; @0063                               v34 = imul_imm v4, 4  ; v4 = 0
+;; @0063                               v35 = iadd v0, v34
+;; @0063                               v36 = global_value.i64 gv1
+;; @0063                               v37 = iconst.i64 4
+;; @0063                               v38 = iadd v35, v37  ; v37 = 4
+;; @0063                               v39 = icmp ult v38, v35
+;; @0063                               v40 = icmp ugt v38, v36
+;; @0063                               v41 = bor v39, v40
+;; @0063                               v42 = imul_imm v33, 4
+;; @0063                               v43 = icmp_imm ugt v33, 0x3fff_ffff_ffff_ffff
+;; @0063                               v44 = bor v41, v43
+;; @0063                               v45 = iadd v38, v42
+;; @0063                               v46 = icmp ult v45, v38
+;; @0063                               v47 = icmp ugt v45, v36
+;; @0063                               v48 = bor v44, v46
+;; @0063                               v49 = bor v48, v47
+;; @0057                               jump block2(v5, v4)  ; v5 = 0, v4 = 0
+;;
+;;                                 block2(v6: i32, v8: i64):
+;; @005f                               v9 = iconst.i64 2
+;; @0061                               v10 = ishl v8, v9  ; v9 = 2
+;; @0062                               v11 = iadd.i64 v7, v10
+;; @0063                               v12 = iconst.i64 4
+;; @0063                               v13 = iadd v11, v12  ; v12 = 4
+;; @0063                               v14 = global_value.i64 gv1
+;; @0063                               v15 = icmp ugt v13, v14
+;; @0063                               v16 = global_value.i64 gv2
+;; @0063                               v17 = iadd v16, v11
+;; @0063                               v18 = iconst.i64 0
+;; @0063                               v19 = select_spectre_guard v49, v18, v17  ; v18 = 0
+;; @0063                               v20 = load.i32 little heap v19
+;; @0066                               v21 = iadd v6, v20
+;; @006b                               v22 = iconst.i64 1
+;; @006d                               v23 = iadd v8, v22  ; v22 = 1
+;; @0072                               v25 = icmp ult v23, v24
+;; @0072                               v26 = uextend.i32 v25
+;; @0073                               brif v26, block2(v21, v23), block4
+;;
+;;                                 block4:
+;; @0075                               jump block3
+;;
+;;                                 block3:
+;; @0078                               jump block1(v21)
+;;
+;;                                 block1(v3: i32):
+;; @0078                               return v3
+;; }
@@ -38,6 +38,12 @@ struct DefinedFunction {
 
     /// JIT [FuncId]
     func_id: FuncId,
+
+    /// The size of the function's code, once it has been defined.
+    code_size: usize,
+
+    /// The offsets and codes of the traps in the function's code.
+    traps: Vec<(usize, ir::TrapCode)>,
 }
 
 /// Compile a test case.
@@ -183,6 +189,8 @@ impl TestFileCompiler {
                     new_name: UserExternalName::new(TESTFILE_NAMESPACE, next_id),
                     signature: func.signature.clone(),
                     func_id,
+                    code_size: 0,
+                    traps: Vec::new(),
                 });
             }
         };
@@ -243,6 +251,7 @@ impl TestFileCompiler {
             .defined_functions
             .get(&func.name)
             .ok_or(anyhow!("Undeclared function {} found!", &func.name))?;
+        let name = func.name.clone();
 
         self.ctx.func = self.apply_func_rename(func, defined_func)?;
         self.module.define_function_with_control_plane(
@@ -250,6 +259,18 @@ impl TestFileCompiler {
             &mut self.ctx,
             ctrl_plane,
         )?;
+
+        // Keep the trap sites so that traps can be attributed to their trap codes.
+        let compiled_code = self.ctx.compiled_code().unwrap();
+        let defined_func = self.defined_functions.get_mut(&name).unwrap();
+        defined_func.code_size = compiled_code.code_info().total_size as usize;
+        defined_func.traps = compiled_code
+            .buffer
+            .traps()
+            .iter()
+            .map(|trap| (trap.offset as usize, trap.code))
+            .collect();
+
         self.module.clear_context(&mut self.ctx);
         Ok(())
     }
@@ -329,6 +350,24 @@ impl CompiledTestFile {
             trampoline_id,
         })
     }
+
+    /// Return the trap code of the trap site at `pc`, if `pc` is one in the code of this
+    /// [JITModule].
+    pub fn trap_code(&self, pc: *const u8) -> Option<ir::TrapCode> {
+        let module = self.module.as_ref()?;
+        self.defined_functions.values().find_map(|defined_func| {
+            let start = module.get_finalized_function(defined_func.func_id);
+            let offset = (pc as usize).checked_sub(start as usize)?;
+            if offset >= defined_func.code_size {
+                return None;
+            }
+            defined_func
+                .traps
+                .iter()
+                .find(|(trap_offset, _)| *trap_offset == offset)
+                .map(|&(_, code)| code)
+        })
+    }
 }
 
 impl Drop for CompiledTestFile {
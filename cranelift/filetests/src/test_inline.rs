@@ -0,0 +1,115 @@
+//! Test command for testing the inlining pass.
+//!
+//! The `inline` test command runs each function through the inlining pass,
+//! using the other functions in the same file as the bodies of its callees.
+//!
+//! The resulting function is sent to `filecheck`.
+
+use crate::runone::FileUpdate;
+use crate::subtest::{run_filecheck, Context, SubTest};
+use anyhow::Context as _;
+use cranelift_codegen::inline::{InlineCallees, InlineOptions};
+use cranelift_codegen::ir::{ExternalName, Function, UserFuncName};
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::settings::Flags;
+use cranelift_reader::{Details, TestCommand, TestFile};
+use log::info;
+use std::borrow::Cow;
+
+struct TestInline;
+
+pub fn subtest(parsed: &TestCommand) -> anyhow::Result<Box<dyn SubTest>> {
+    assert_eq!(parsed.command, "inline");
+    if !parsed.options.is_empty() {
+        anyhow::bail!("No options allowed on {}", parsed);
+    }
+    Ok(Box::new(TestInline))
+}
+
+/// Callees looked up by name among the functions of a test file.
+struct FileCallees<'a> {
+    functions: &'a [(Function, Details<'a>)],
+}
+
+impl InlineCallees for FileCallees<'_> {
+    fn callee(&mut self, caller: &Function, name: &ExternalName) -> Option<&Function> {
+        self.functions
+            .iter()
+            .map(|(func, _)| func)
+            .find(|func| match (&func.name, name) {
+                (UserFuncName::Testcase(a), ExternalName::TestCase(b)) => a == b,
+                (UserFuncName::User(a), ExternalName::User(b)) => {
+                    *a == caller.params.user_named_funcs()[*b]
+                }
+                _ => false,
+            })
+    }
+}
+
+impl TestInline {
+    fn inline(
+        &self,
+        func: Cow<Function>,
+        callees: &mut FileCallees,
+        context: &Context,
+    ) -> anyhow::Result<()> {
+        let mut comp_ctx = cranelift_codegen::Context::for_function(func.into_owned());
+
+        comp_ctx
+            .inline(callees, &InlineOptions::default(), context.flags_or_isa())
+            .map_err(|e| crate::pretty_anyhow_error(&comp_ctx.func, Into::into(e)))?;
+
+        let text = comp_ctx.func.display().to_string();
+        run_filecheck(&text, context)
+    }
+}
+
+impl SubTest for TestInline {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn run_target<'a>(
+        &self,
+        testfile: &TestFile,
+        file_update: &mut FileUpdate,
+        file_path: &'a str,
+        flags: &'a Flags,
+        isa: Option<&'a dyn TargetIsa>,
+    ) -> anyhow::Result<()> {
+        let mut callees = FileCallees {
+            functions: &testfile.functions,
+        };
+        for (func, details) in &testfile.functions {
+            info!(
+                "Test: {}({}) {}",
+                self.name(),
+                func.name,
+                isa.map_or("-", TargetIsa::name)
+            );
+
+            let context = Context {
+                preamble_comments: &testfile.preamble_comments,
+                details,
+                flags,
+                isa,
+                file_path: file_path.as_ref(),
+                file_update,
+            };
+
+            self.inline(Cow::Borrowed(func), &mut callees, &context)
+                .context(self.name())?;
+        }
+
+        Ok(())
+    }
+
+    fn run(&self, func: Cow<Function>, context: &Context) -> anyhow::Result<()> {
+        let mut callees = FileCallees { functions: &[] };
+        self.inline(func, &mut callees, context)
+    }
+}
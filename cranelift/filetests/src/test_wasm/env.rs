@@ -20,6 +20,7 @@ pub struct ModuleEnv {
     pub inner: DummyEnvironment,
     pub config: TestConfig,
     pub heap_access_spectre_mitigation: bool,
+    pub heap_bounds_check_elimination: bool,
     pub proof_carrying_code: bool,
 }
 
@@ -32,6 +33,9 @@ impl ModuleEnv {
             heap_access_spectre_mitigation: target_isa
                 .flags()
                 .enable_heap_access_spectre_mitigation(),
+            heap_bounds_check_elimination: target_isa
+                .flags()
+                .enable_heap_bounds_check_elimination(),
             proof_carrying_code: target_isa.flags().enable_pcc(),
         }
     }
@@ -53,6 +57,7 @@ impl<'data> ModuleEnvironment<'data> for ModuleEnv {
                 self.inner.expected_reachability.clone(),
                 self.config.clone(),
                 self.heap_access_spectre_mitigation,
+                self.heap_bounds_check_elimination,
                 self.proof_carrying_code,
             );
             let func_index = FuncIndex::new(
@@ -247,6 +252,7 @@ pub struct FuncEnv<'a> {
     pub name_to_ir_global: BTreeMap<String, ir::GlobalValue>,
     pub next_heap: usize,
     pub heap_access_spectre_mitigation: bool,
+    pub heap_bounds_check_elimination: bool,
     pub proof_carrying_code: bool,
 }
 
@@ -256,6 +262,7 @@ impl<'a> FuncEnv<'a> {
         expected_reachability: Option<cranelift_wasm::ExpectedReachability>,
         config: TestConfig,
         heap_access_spectre_mitigation: bool,
+        heap_bounds_check_elimination: bool,
         proof_carrying_code: bool,
     ) -> Self {
         let inner = cranelift_wasm::DummyFuncEnvironment::new(mod_info, expected_reachability);
@@ -265,6 +272,7 @@ impl<'a> FuncEnv<'a> {
             name_to_ir_global: Default::default(),
             next_heap: 0,
             heap_access_spectre_mitigation,
+            heap_bounds_check_elimination,
             proof_carrying_code,
         }
     }
@@ -285,6 +293,10 @@ impl<'a> TargetEnvironment for FuncEnv<'a> {
         self.heap_access_spectre_mitigation
     }
 
+    fn heap_bounds_check_elimination(&self) -> bool {
+        self.heap_bounds_check_elimination
+    }
+
     fn proof_carrying_code(&self) -> bool {
         self.proof_carrying_code
     }
@@ -9,12 +9,13 @@ use cranelift::codegen::ir::instructions::{InstructionFormat, ResolvedConstraint
 use cranelift::codegen::ir::stackslot::StackSize;
 
 use cranelift::codegen::ir::{
-    types::*, AtomicRmwOp, Block, ConstantData, Endianness, ExternalName, FuncRef, Function,
-    LibCall, Opcode, SigRef, Signature, StackSlot, Type, UserExternalName, UserFuncName, Value,
+    types::*, AtomicRmwOp, Block, ConstantData, DynamicStackSlot, DynamicStackSlotData,
+    DynamicTypeData, Endianness, ExternalName, FuncRef, Function, GlobalValueData, LibCall, Opcode,
+    SigRef, Signature, StackSlot, Type, UserExternalName, UserFuncName, Value,
 };
 use cranelift::codegen::isa::CallConv;
 use cranelift::frontend::{FunctionBuilder, FunctionBuilderContext, Switch, Variable};
-use cranelift::prelude::isa::OwnedTargetIsa;
+use cranelift::prelude::isa::{OwnedTargetIsa, TargetIsa};
 use cranelift::prelude::{
     EntityRef, ExtFuncData, FloatCC, InstBuilder, IntCC, JumpTableData, MemFlags, StackSlotData,
     StackSlotKind,
@@ -23,7 +24,7 @@ use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
-use target_lexicon::{Architecture, Triple};
+use target_lexicon::Architecture;
 
 type BlockSignature = Vec<Type>;
 
@@ -176,6 +177,81 @@ fn insert_stack_store(
     Ok(())
 }
 
+fn insert_dynamic_stack_access(
+    fgen: &mut FunctionGenerator,
+    builder: &mut FunctionBuilder,
+    opcode: Opcode,
+    _args: &[Type],
+    rets: &[Type],
+) -> Result<()> {
+    // Variables only hold fixed-size vectors, so the type of the access is the base vector type
+    // of a random dynamic slot rather than part of the opcode signature.
+    let (slot, ty) = *fgen.u.choose(&fgen.resources.dynamic_stack_slots)?;
+    let dyn_ty = ty.vector_to_dynamic().unwrap();
+
+    match opcode {
+        Opcode::DynamicStackLoad => {
+            let val = builder.ins().dynamic_stack_load(dyn_ty, slot);
+            let val = builder.ins().extract_vector(val, 0);
+            let var = fgen.get_variable_of_type(ty)?;
+            builder.def_var(var, val);
+        }
+        Opcode::DynamicStackStore => {
+            // There's no conversion from fixed-size to dynamic vectors, so store a splat of a
+            // variable of the lane type instead.
+            let var = fgen.get_variable_of_type(ty.lane_type())?;
+            let val = builder.use_var(var);
+            let val = builder.ins().splat(dyn_ty, val);
+            builder.ins().dynamic_stack_store(val, slot);
+        }
+        Opcode::DynamicStackAddr => {
+            // Like other addresses, this one isn't stored in a variable. Load the contents of the
+            // slot through it instead.
+            let addr = builder.ins().dynamic_stack_addr(rets[0], slot);
+            let mut flags = MemFlags::new();
+            flags.set_notrap();
+            let val = builder.ins().load(ty, flags, addr, 0);
+            let var = fgen.get_variable_of_type(ty)?;
+            builder.def_var(var, val);
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn insert_frame_address(
+    fgen: &mut FunctionGenerator,
+    builder: &mut FunctionBuilder,
+    opcode: Opcode,
+    _args: &[Type],
+    rets: &[Type],
+) -> Result<()> {
+    let addr_ty = rets[0];
+
+    // These addresses are different in the interpreter, so they can't be stored in variables.
+    // Instead we store the result of comparisons which hold in both: the frame pointer is above
+    // the stack pointer, and the return address is never null.
+    let res = match opcode {
+        Opcode::GetFramePointer | Opcode::GetStackPointer => {
+            let fp = builder.ins().get_frame_pointer(addr_ty);
+            let sp = builder.ins().get_stack_pointer(addr_ty);
+            builder
+                .ins()
+                .icmp(IntCC::UnsignedGreaterThanOrEqual, fp, sp)
+        }
+        Opcode::GetReturnAddress => {
+            let ra = builder.ins().get_return_address(addr_ty);
+            builder.ins().icmp_imm(IntCC::NotEqual, ra, 0)
+        }
+        _ => unreachable!(),
+    };
+
+    let var = fgen.get_variable_of_type(I8)?;
+    builder.def_var(var, res);
+    Ok(())
+}
+
 fn insert_cmp(
     fgen: &mut FunctionGenerator,
     builder: &mut FunctionBuilder,
@@ -468,7 +544,7 @@ macro_rules! exceptions {
 /// with specific lowerings on specific backends, and we don't want to get
 /// fuzz bug reports for those. Over time our goal is to eliminate all of these
 /// exceptions.
-fn valid_for_target(triple: &Triple, op: Opcode, args: &[Type], rets: &[Type]) -> bool {
+fn valid_for_target(isa: &dyn TargetIsa, op: Opcode, args: &[Type], rets: &[Type]) -> bool {
     // Rule out invalid combinations that we don't yet have a good way of rejecting with the
     // instruction DSL type constraints.
     match op {
@@ -512,10 +588,52 @@ fn valid_for_target(triple: &Triple, op: Opcode, args: &[Type], rets: &[Type]) -
             }
         }
 
+        // These produce native addresses, which are pointer sized. Reading the frame pointer and
+        // the return address requires frame pointers to be preserved, and the stack pointer is
+        // checked against the frame pointer, see `insert_frame_address`.
+        Opcode::GetFramePointer | Opcode::GetStackPointer | Opcode::GetReturnAddress => {
+            if rets != [isa.pointer_type()] || !isa.flags().preserve_frame_pointers() {
+                return false;
+            }
+        }
+
+        // Dynamic vectors are only supported on AArch64.
+        Opcode::DynamicStackLoad | Opcode::DynamicStackStore | Opcode::DynamicStackAddr => {
+            if !matches!(isa.triple().architecture, Architecture::Aarch64(_)) {
+                return false;
+            }
+            if op == Opcode::DynamicStackAddr && rets != [isa.pointer_type()] {
+                return false;
+            }
+        }
+
+        // The x86 lookalikes are only lowered on x86, for the types of the instructions that they
+        // mirror and only when the CPU has those instructions.
+        Opcode::X86Pshufb
+        | Opcode::X86Blendv
+        | Opcode::X86Pmulhrsw
+        | Opcode::X86Pmaddubsw
+        | Opcode::X86Cvtt2dq => {
+            if isa.triple().architecture != Architecture::X86_64 {
+                return false;
+            }
+
+            let (supported_types, required_flag) = match op {
+                Opcode::X86Pshufb | Opcode::X86Pmaddubsw => (true, Some("has_ssse3")),
+                Opcode::X86Pmulhrsw => (rets == [I16X8], Some("has_ssse3")),
+                Opcode::X86Blendv => (matches!(rets, [I8X16 | I32X4 | I64X2]), Some("has_sse41")),
+                Opcode::X86Cvtt2dq => (args == [F32X4] && rets == [I32X4], None),
+                _ => unreachable!(),
+            };
+            if !supported_types || required_flag.map_or(false, |flag| !isa.has_isa_flag(flag)) {
+                return false;
+            }
+        }
+
         _ => {}
     }
 
-    match triple.architecture {
+    match isa.triple().architecture {
         Architecture::X86_64 => {
             exceptions!(
                 op,
@@ -822,8 +940,8 @@ static OPCODE_SIGNATURES: Lazy<Vec<OpcodeSignature>> = Lazy::new(|| {
                 // Constants are generated outside of `generate_instructions`
                 Opcode::Iconst => false,
 
-                // TODO: extract_vector raises exceptions during return type generation becuase it
-                // uses dynamic vectors.
+                // `extract_vector` operates on dynamic vectors, which variables can't hold. It's
+                // generated along with `dynamic_stack_load`, see `insert_dynamic_stack_access`.
                 Opcode::ExtractVector => false,
 
                 _ => true,
@@ -882,6 +1000,12 @@ static OPCODE_SIGNATURES: Lazy<Vec<OpcodeSignature>> = Lazy::new(|| {
                 argss.into_iter().map(move |args| (*op, args, rets.clone()))
             })
         })
+        // Dynamic vectors can't be held in variables, so the dynamic stack accesses don't have
+        // a type in their signature, see `insert_dynamic_stack_access`.
+        .chain([
+            (Opcode::DynamicStackLoad, vec![], vec![]),
+            (Opcode::DynamicStackStore, vec![], vec![]),
+        ])
         .filter(|(op, args, rets)| {
             // These op/signature combinations need to be vetted
             exceptions!(
@@ -896,31 +1020,15 @@ static OPCODE_SIGNATURES: Lazy<Vec<OpcodeSignature>> = Lazy::new(|| {
                 (Opcode::ResumableTrapnz),
                 (Opcode::CallIndirect, &[I32]),
                 (Opcode::FuncAddr),
-                (Opcode::X86Pshufb),
                 (Opcode::AvgRound),
-                (Opcode::Uload8x8),
-                (Opcode::Sload8x8),
-                (Opcode::Uload16x4),
-                (Opcode::Sload16x4),
-                (Opcode::Uload32x2),
-                (Opcode::Sload32x2),
                 (Opcode::StackAddr),
-                (Opcode::DynamicStackLoad),
-                (Opcode::DynamicStackStore),
-                (Opcode::DynamicStackAddr),
                 (Opcode::GlobalValue),
                 (Opcode::SymbolValue),
                 (Opcode::TlsValue),
                 (Opcode::GetPinnedReg),
                 (Opcode::SetPinnedReg),
-                (Opcode::GetFramePointer),
-                (Opcode::GetStackPointer),
-                (Opcode::GetReturnAddress),
                 (Opcode::TableAddr),
-                (Opcode::Null),
-                (Opcode::X86Blendv),
                 (Opcode::IcmpImm),
-                (Opcode::X86Pmulhrsw),
                 (Opcode::IaddImm),
                 (Opcode::ImulImm),
                 (Opcode::UdivImm),
@@ -941,11 +1049,7 @@ static OPCODE_SIGNATURES: Lazy<Vec<OpcodeSignature>> = Lazy::new(|| {
                 (Opcode::IshlImm),
                 (Opcode::UshrImm),
                 (Opcode::SshrImm),
-                (Opcode::IsNull),
-                (Opcode::IsInvalid),
                 (Opcode::ScalarToVector),
-                (Opcode::X86Pmaddubsw),
-                (Opcode::X86Cvtt2dq),
                 (Opcode::Umulhi, &[I128, I128], &[I128]),
                 (Opcode::Smulhi, &[I128, I128], &[I128]),
                 // https://github.com/bytecodealliance/wasmtime/issues/6073
@@ -1076,8 +1180,8 @@ fn inserter_for_format(fmt: InstructionFormat) -> OpcodeInserter {
         InstructionFormat::Call => insert_call,
         InstructionFormat::CallIndirect => insert_call,
         InstructionFormat::CondTrap => todo!(),
-        InstructionFormat::DynamicStackLoad => todo!(),
-        InstructionFormat::DynamicStackStore => todo!(),
+        InstructionFormat::DynamicStackLoad => insert_dynamic_stack_access,
+        InstructionFormat::DynamicStackStore => insert_dynamic_stack_access,
         InstructionFormat::FloatCompare => insert_cmp,
         InstructionFormat::FuncAddr => todo!(),
         InstructionFormat::IntAddTrap => todo!(),
@@ -1198,6 +1302,8 @@ struct Resources {
     /// We use this invariant when searching for stack slots with a given size.
     /// See [FunctionGenerator::stack_slot_with_size]
     stack_slots: Vec<(StackSlot, StackSize, AACategory)>,
+    /// Dynamic stack slots along with the fixed-size base type of their dynamic vector type.
+    dynamic_stack_slots: Vec<(DynamicStackSlot, Type)>,
     usercalls: Vec<(UserExternalName, Signature)>,
     libcalls: Vec<LibCall>,
 }
@@ -1549,11 +1655,16 @@ where
             // We filter out instructions that aren't supported by the target at this point instead
             // of building a single vector of valid instructions at the beginning of function
             // generation, to avoid invalidating the corpus when instructions are enabled/disabled.
-            if !valid_for_target(&self.isa.triple(), *op, &args, &rets) {
+            if !valid_for_target(&*self.isa, *op, &args, &rets) {
                 return Err(arbitrary::Error::IncorrectFormat.into());
             }
 
-            let inserter = inserter_for_format(op.format());
+            let inserter = match op {
+                Opcode::GetFramePointer | Opcode::GetStackPointer | Opcode::GetReturnAddress => {
+                    insert_frame_address
+                }
+                _ => inserter_for_format(op.format()),
+            };
             inserter(self, builder, *op, &args, &rets)?;
         }
 
@@ -1620,6 +1731,24 @@ where
             .stack_slots
             .sort_unstable_by_key(|&(_slot, bytes, _category)| bytes);
 
+        // Dynamic vectors are only supported on AArch64.
+        if matches!(self.isa.triple().architecture, Architecture::Aarch64(_)) {
+            for _ in 0..self.param(&self.config.dynamic_stack_slots_per_function)? {
+                let ty = *self.u.choose(&[I8X16, I16X8, I32X4, I64X2, F32X4, F64X2])?;
+                let scale = builder
+                    .create_global_value(GlobalValueData::DynScaleTargetConst { vector_type: ty });
+                let dyn_ty = builder
+                    .func
+                    .dfg
+                    .make_dynamic_ty(DynamicTypeData::new(ty, scale));
+                let slot = builder.create_dynamic_stack_slot(DynamicStackSlotData::new(
+                    StackSlotKind::ExplicitDynamicSlot,
+                    dyn_ty,
+                ));
+                self.resources.dynamic_stack_slots.push((slot, ty));
+            }
+        }
+
         Ok(())
     }
 
@@ -1657,6 +1786,22 @@ where
                 size -= filled;
             }
         }
+
+        for &(slot, ty) in self.resources.dynamic_stack_slots.iter() {
+            let lane_zero = match ty.lane_type() {
+                I8 => i8_zero,
+                I16 => i16_zero,
+                I32 => i32_zero,
+                I64 => i64_zero,
+                F32 => builder.ins().f32const(0.0),
+                F64 => builder.ins().f64const(0.0),
+                _ => unreachable!(),
+            };
+            let zero = builder
+                .ins()
+                .splat(ty.vector_to_dynamic().unwrap(), lane_zero);
+            builder.ins().dynamic_stack_store(zero, slot);
+        }
         Ok(())
     }
 
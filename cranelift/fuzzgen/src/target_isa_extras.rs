@@ -3,19 +3,24 @@ use target_lexicon::Architecture;
 
 pub trait TargetIsaExtras {
     fn supports_simd(&self) -> bool;
+
+    fn has_isa_flag(&self, name: &str) -> bool;
 }
 
 impl TargetIsaExtras for &dyn TargetIsa {
     fn supports_simd(&self) -> bool {
         match self.triple().architecture {
             // RISC-V only supports SIMD with the V extension.
-            Architecture::Riscv64(_) => self
-                .isa_flags()
-                .iter()
-                .find(|f| f.name == "has_v")
-                .and_then(|f| f.as_bool())
-                .unwrap_or(false),
+            Architecture::Riscv64(_) => self.has_isa_flag("has_v"),
             _ => true,
         }
     }
+
+    fn has_isa_flag(&self, name: &str) -> bool {
+        self.isa_flags()
+            .iter()
+            .find(|f| f.name == name)
+            .and_then(|f| f.as_bool())
+            .unwrap_or(false)
+    }
 }
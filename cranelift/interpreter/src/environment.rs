@@ -32,6 +32,16 @@ impl<'a> FunctionStore<'a> {
         self.function_names.insert(name, index);
     }
 
+    /// The number of functions in the function store.
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Whether the function store has no functions.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
     /// Retrieve the index of a function in the function store by its `name`.
     pub fn index_of(&self, name: &str) -> Option<FuncIndex> {
         self.function_names.get(name).cloned()
@@ -35,30 +35,27 @@ impl<'a> Frame<'a> {
     pub fn get(&self, name: ValueRef) -> &DataValue {
         assert!(name.index() < self.registers.len());
         trace!("Get {}", name);
-        &self
-            .registers
-            .get(name.index())
-            .unwrap_or_else(|| panic!("unknown value: {}", name))
-            .as_ref()
-            .or_else(|| {
-                // We couldn't find the `name` value directly in `registers`, but it is still
-                // possible that it is aliased to another value.
-
-                // If we are looking up an undefined value it will have an invalid type, return
-                // before trying to resolve it.
-                if self.function.dfg.value_type(name) == types::INVALID {
-                    return None;
-                }
-
-                let alias = self.function.dfg.resolve_aliases(name);
-                self.registers
-                    .get(alias.index())
-                    .unwrap_or_else(|| panic!("unknown value: {}", alias))
-                    .as_ref()
-            })
+        self.try_get(name)
             .unwrap_or_else(|| panic!("empty slot: {}", name))
     }
 
+    /// Retrieve the value associated with an SSA reference, if it has been assigned yet.
+    pub fn try_get(&self, name: ValueRef) -> Option<&DataValue> {
+        self.registers.get(name.index())?.as_ref().or_else(|| {
+            // We couldn't find the `name` value directly in `registers`, but it is still
+            // possible that it is aliased to another value.
+
+            // If we are looking up an undefined value it will have an invalid type, return
+            // before trying to resolve it.
+            if self.function.dfg.value_type(name) == types::INVALID {
+                return None;
+            }
+
+            let alias = self.function.dfg.resolve_aliases(name);
+            self.registers.get(alias.index())?.as_ref()
+        })
+    }
+
     /// Retrieve multiple SSA references; see `get`.
     pub fn get_all(&self, names: &[ValueRef]) -> Vec<DataValue> {
         names.iter().map(|r| self.get(*r)).cloned().collect()
@@ -7,12 +7,12 @@ use crate::environment::{FuncIndex, FunctionStore};
 use crate::frame::Frame;
 use crate::instruction::DfgInstructionContext;
 use crate::state::{InterpreterFunctionRef, MemoryError, State};
-use crate::step::{step, ControlFlow, StepError};
+use crate::step::{step, ControlFlow, CraneliftTrap, StepError};
 use crate::value::{DataValueExt, ValueError};
 use cranelift_codegen::data_value::DataValue;
 use cranelift_codegen::ir::{
-    ArgumentPurpose, Block, Endianness, ExternalName, FuncRef, Function, GlobalValue,
-    GlobalValueData, LibCall, MemFlags, StackSlot, TrapCode, Type,
+    dynamic_to_fixed, ArgumentPurpose, Block, DynamicStackSlot, Endianness, ExternalName, FuncRef,
+    Function, GlobalValue, GlobalValueData, Inst, LibCall, MemFlags, StackSlot, TrapCode, Type,
 };
 use log::trace;
 use smallvec::SmallVec;
@@ -27,11 +27,16 @@ use thiserror::Error;
 pub struct Interpreter<'a> {
     state: InterpreterState<'a>,
     fuel: Option<u64>,
+    inspector: Option<Box<dyn Inspector<'a> + 'a>>,
 }
 
 impl<'a> Interpreter<'a> {
     pub fn new(state: InterpreterState<'a>) -> Self {
-        Self { state, fuel: None }
+        Self {
+            state,
+            fuel: None,
+            inspector: None,
+        }
     }
 
     /// The `fuel` mechanism sets a number of instructions that
@@ -41,6 +46,15 @@ impl<'a> Interpreter<'a> {
         Self { fuel, ..self }
     }
 
+    /// Attach an [Inspector] which is notified before and after each instruction is interpreted,
+    /// e.g. to implement a debugger.
+    pub fn with_inspector(self, inspector: impl Inspector<'a> + 'a) -> Self {
+        Self {
+            inspector: Some(Box::new(inspector)),
+            ..self
+        }
+    }
+
     /// Call a function by name; this is a helpful proxy for [Interpreter::call_by_index].
     pub fn call_by_name(
         &mut self,
@@ -101,6 +115,12 @@ impl<'a> Interpreter<'a> {
                 return Err(InterpreterError::FuelExhausted);
             }
 
+            if let Some(inspector) = &mut self.inspector {
+                if inspector.before_step(&self.state, inst) == InspectorAction::Stop {
+                    return Err(InterpreterError::Stopped);
+                }
+            }
+
             let inst_context = DfgInstructionContext::new(inst, &function.dfg);
             match step(&mut self.state, inst_context)? {
                 ControlFlow::Assign(values) => {
@@ -146,7 +166,18 @@ impl<'a> Interpreter<'a> {
                     self.state.pop_frame();
                     return Ok(ControlFlow::Return(returned_values));
                 }
-                ControlFlow::Trap(trap) => return Ok(ControlFlow::Trap(trap)),
+                ControlFlow::Trap(trap) => {
+                    if let Some(inspector) = &mut self.inspector {
+                        inspector.trapped(&self.state, inst, &trap);
+                    }
+                    return Ok(ControlFlow::Trap(trap));
+                }
+            }
+
+            if let Some(inspector) = &mut self.inspector {
+                if inspector.after_step(&self.state, inst) == InspectorAction::Stop {
+                    return Err(InterpreterError::Stopped);
+                }
             }
         }
         Err(InterpreterError::Unreachable)
@@ -175,6 +206,36 @@ pub enum FuelResult {
     Stop,
 }
 
+/// Observes the execution of an [Interpreter]; see [Interpreter::with_inspector].
+pub trait Inspector<'a> {
+    /// Called before `inst`, an instruction of the function in the current frame of `state`, is
+    /// interpreted.
+    fn before_step(&mut self, _state: &InterpreterState<'a>, _inst: Inst) -> InspectorAction {
+        InspectorAction::Continue
+    }
+
+    /// Called after `inst` has been interpreted and its results have been assigned in the current
+    /// frame of `state`. This is not called for instructions which leave the current function,
+    /// i.e. returns, tail calls and traps.
+    fn after_step(&mut self, _state: &InterpreterState<'a>, _inst: Inst) -> InspectorAction {
+        InspectorAction::Continue
+    }
+
+    /// Called when `inst`, an instruction of the function in the current frame of `state`,
+    /// traps. Interpretation ends with the trap afterwards, so traps in callees are only reported
+    /// here and not for the calls which they propagate through.
+    fn trapped(&mut self, _state: &InterpreterState<'a>, _inst: Inst, _trap: &CraneliftTrap) {}
+}
+
+/// Signals if the [Interpreter] should continue after notifying an [Inspector].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InspectorAction {
+    /// Continue interpreting.
+    Continue,
+    /// Stop interpreting with [InterpreterError::Stopped].
+    Stop,
+}
+
 /// The ways interpretation can fail.
 #[derive(Error, Debug)]
 pub enum InterpreterError {
@@ -190,6 +251,8 @@ pub enum InterpreterError {
     ValueError(#[from] ValueError),
     #[error("fuel exhausted")]
     FuelExhausted,
+    #[error("stopped by the inspector")]
+    Stopped,
 }
 
 pub type LibCallValues = SmallVec<[DataValue; 1]>;
@@ -253,12 +316,12 @@ impl<'a> State<'a> for InterpreterState<'a> {
 
     fn push_frame(&mut self, function: &'a Function) {
         if let Some(frame) = self.frame_stack.iter().last() {
-            self.frame_offset += frame.function().fixed_stack_size() as usize;
+            self.frame_offset += frame_size(frame.function());
         }
 
         // Grow the stack by the space necessary for this frame
         self.stack
-            .extend(iter::repeat(0).take(function.fixed_stack_size() as usize));
+            .extend(iter::repeat(0).take(frame_size(function)));
 
         self.frame_stack.push(Frame::new(function));
     }
@@ -266,11 +329,11 @@ impl<'a> State<'a> for InterpreterState<'a> {
         if let Some(frame) = self.frame_stack.pop() {
             // Shorten the stack after exiting the frame
             self.stack
-                .truncate(self.stack.len() - frame.function().fixed_stack_size() as usize);
+                .truncate(self.stack.len() - frame_size(frame.function()));
 
             // Reset frame_offset to the start of this function
             if let Some(frame) = self.frame_stack.iter().last() {
-                self.frame_offset -= frame.function().fixed_stack_size() as usize;
+                self.frame_offset -= frame_size(frame.function());
             }
         }
     }
@@ -319,6 +382,56 @@ impl<'a> State<'a> for InterpreterState<'a> {
         Address::from_parts(size, AddressRegion::Stack, 0, final_offset)
     }
 
+    fn dynamic_stack_address(
+        &self,
+        size: AddressSize,
+        slot: DynamicStackSlot,
+    ) -> Result<Address, MemoryError> {
+        let function = self.get_current_function();
+        let stack_slots = &function.dynamic_stack_slots;
+
+        // Dynamic stack slots are placed after all of the sized stack slots
+        let slot_offset: u64 = stack_slots
+            .keys()
+            .filter(|k| k < &slot)
+            .map(|k| dynamic_stack_slot_size(function, k) as u64)
+            .sum();
+
+        let final_offset =
+            self.frame_offset as u64 + function.fixed_stack_size() as u64 + slot_offset;
+        Address::from_parts(size, AddressRegion::Stack, 0, final_offset)
+    }
+
+    fn stack_pointer(&self, size: AddressSize) -> Result<Address, MemoryError> {
+        Address::from_parts(size, AddressRegion::Stack, 0, self.frame_offset as u64)
+    }
+
+    fn frame_pointer(&self, size: AddressSize) -> Result<Address, MemoryError> {
+        let frame_end = self.frame_offset + frame_size(self.get_current_function());
+        Address::from_parts(size, AddressRegion::Stack, 0, frame_end as u64)
+    }
+
+    fn return_address(&self, size: AddressSize) -> Result<Address, MemoryError> {
+        // We don't have addresses for individual instructions, so the return address is the
+        // address of the calling function.
+        let index = match self.frame_stack.iter().rev().nth(1) {
+            Some(frame) => self
+                .functions
+                .index_of(&frame.function().name.to_string())
+                .expect("calling function to be in the function store")
+                .as_u32(),
+            // The outermost function returns to the host, which isn't in the function store. Like
+            // a native return address its address isn't null, but it can't be called either.
+            None => self.functions.len() as u32,
+        };
+        Address::from_parts(
+            size,
+            AddressRegion::Function,
+            AddressFunctionEntry::UserFunction as u64,
+            u64::from(index),
+        )
+    }
+
     fn checked_load(
         &self,
         addr: Address,
@@ -518,7 +631,11 @@ impl<'a> State<'a> for InterpreterState<'a> {
                         action_stack.push(ResolveAction::Resolve(base));
                     }
                     GlobalValueData::Symbol { .. } => unimplemented!(),
-                    GlobalValueData::DynScaleTargetConst { .. } => unimplemented!(),
+                    GlobalValueData::DynScaleTargetConst { .. } => {
+                        // Dynamic vectors are interpreted as their fixed-size base vectors, so
+                        // they're always scaled by one.
+                        current_val = DataValue::I64(1);
+                    }
                 },
                 Some(ResolveAction::Add(dv)) => {
                     current_val = current_val
@@ -551,10 +668,30 @@ impl<'a> State<'a> for InterpreterState<'a> {
     }
 }
 
+/// The number of bytes of stack space used by a frame of `function`: its sized stack slots,
+/// followed by its dynamic stack slots.
+fn frame_size(function: &Function) -> usize {
+    let dynamic_size: u32 = function
+        .dynamic_stack_slots
+        .keys()
+        .map(|k| dynamic_stack_slot_size(function, k))
+        .sum();
+    (function.fixed_stack_size() + dynamic_size) as usize
+}
+
+/// The size of a dynamic stack slot, given that dynamic vectors are interpreted as their fixed-size
+/// base vectors.
+fn dynamic_stack_slot_size(function: &Function, slot: DynamicStackSlot) -> u32 {
+    let dyn_ty = function.dynamic_stack_slots[slot].dyn_ty;
+    let ty = function
+        .get_concrete_dynamic_ty(dyn_ty)
+        .expect("dynamic stack slot to have a concrete type");
+    dynamic_to_fixed(ty).bytes()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::step::CraneliftTrap;
     use cranelift_codegen::ir::immediates::Ieee32;
     use cranelift_codegen::ir::TrapCode;
     use cranelift_reader::parse_functions;
@@ -699,6 +836,143 @@ mod tests {
         assert_eq!(result, ControlFlow::Return(smallvec![DataValue::I32(2)]));
     }
 
+    #[test]
+    fn inspector() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let code = "
+        function %add1(i32) -> i32 {
+        block0(v0: i32):
+            v1 = iadd_imm v0, 1
+            return v1
+        }
+
+        function %test() -> i32 {
+            fn0 = %add1(i32) -> i32
+        block0:
+            v0 = iconst.i32 1
+            v1 = call fn0(v0)
+            v2 = iadd_imm v1, 1
+            return v2
+        }";
+
+        // Records the depth and results of each instruction in `after_step`, stopping once
+        // `limit` instructions have been interpreted.
+        struct Recorder {
+            limit: usize,
+            steps: Rc<RefCell<Vec<(usize, Vec<DataValue>)>>>,
+        }
+        impl<'a> Inspector<'a> for Recorder {
+            fn before_step(&mut self, _: &InterpreterState<'a>, _: Inst) -> InspectorAction {
+                if self.steps.borrow().len() == self.limit {
+                    InspectorAction::Stop
+                } else {
+                    InspectorAction::Continue
+                }
+            }
+
+            fn after_step(&mut self, state: &InterpreterState<'a>, inst: Inst) -> InspectorAction {
+                let frame = state.current_frame();
+                let results = frame.function().dfg.inst_results(inst);
+                let results = results.iter().map(|&v| frame.get(v).clone()).collect();
+                self.steps
+                    .borrow_mut()
+                    .push((state.frame_stack.len(), results));
+                InspectorAction::Continue
+            }
+        }
+
+        let mut env = FunctionStore::default();
+        let funcs = parse_functions(code).unwrap();
+        for func in &funcs {
+            env.add(func.name.to_string(), func);
+        }
+
+        // Returns in the callee aren't observed by `after_step`, but the call is.
+        let steps = Rc::new(RefCell::new(vec![]));
+        let state = InterpreterState::default().with_function_store(env.clone());
+        let result = Interpreter::new(state)
+            .with_inspector(Recorder {
+                limit: usize::MAX,
+                steps: steps.clone(),
+            })
+            .call_by_name("%test", &[])
+            .unwrap();
+        assert_eq!(result, ControlFlow::Return(smallvec![DataValue::I32(3)]));
+        assert_eq!(
+            *steps.borrow(),
+            [
+                (1, vec![DataValue::I32(1)]),
+                (2, vec![DataValue::I32(2)]),
+                (1, vec![DataValue::I32(2)]),
+                (1, vec![DataValue::I32(3)]),
+            ]
+        );
+
+        let steps = Rc::new(RefCell::new(vec![]));
+        let state = InterpreterState::default().with_function_store(env);
+        let result = Interpreter::new(state)
+            .with_inspector(Recorder {
+                limit: 2,
+                steps: steps.clone(),
+            })
+            .call_by_name("%test", &[]);
+        assert!(matches!(result, Err(InterpreterError::Stopped)));
+        assert_eq!(steps.borrow().len(), 2);
+    }
+
+    #[test]
+    fn inspector_trapped() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let code = "
+        function %div(i32) -> i32 {
+        block0(v0: i32):
+            v1 = iconst.i32 0
+            v2 = udiv v0, v1
+            return v2
+        }
+
+        function %test() -> i32 {
+            fn0 = %div(i32) -> i32
+        block0:
+            v0 = iconst.i32 1
+            v1 = call fn0(v0)
+            return v1
+        }";
+
+        // Records the function, instruction and trap of each trap.
+        struct Recorder(Rc<RefCell<Vec<(String, Inst, CraneliftTrap)>>>);
+        impl<'a> Inspector<'a> for Recorder {
+            fn trapped(&mut self, state: &InterpreterState<'a>, inst: Inst, trap: &CraneliftTrap) {
+                let name = state.get_current_function().name.to_string();
+                self.0.borrow_mut().push((name, inst, trap.clone()));
+            }
+        }
+
+        let mut env = FunctionStore::default();
+        let funcs = parse_functions(code).unwrap();
+        for func in &funcs {
+            env.add(func.name.to_string(), func);
+        }
+
+        // The trap is only reported where it happens, and not for the call in `%test`.
+        let traps = Rc::new(RefCell::new(vec![]));
+        let state = InterpreterState::default().with_function_store(env);
+        let result = Interpreter::new(state)
+            .with_inspector(Recorder(traps.clone()))
+            .call_by_name("%test", &[])
+            .unwrap();
+        let trap = CraneliftTrap::User(TrapCode::IntegerDivisionByZero);
+        assert_eq!(result, ControlFlow::Trap(trap.clone()));
+        assert_eq!(
+            *traps.borrow(),
+            [("%div".to_string(), Inst::from_u32(1), trap)]
+        );
+    }
+
     // Verifies that writing to the stack on a called function does not overwrite the parents
     // stack slots.
     #[test]
@@ -1061,4 +1335,96 @@ mod tests {
             ControlFlow::Trap(CraneliftTrap::User(TrapCode::HeapMisaligned))
         );
     }
+
+    #[test]
+    fn stack_and_frame_pointers() {
+        let code = "
+        function %callee() -> i8, i64, i64 {
+            ss0 = explicit_slot 8
+            ss1 = explicit_slot 16
+
+        block0:
+            v0 = get_stack_pointer.i64
+            v1 = stack_addr.i64 ss0
+            v2 = icmp eq v0, v1
+            v3 = get_frame_pointer.i64
+            v4 = isub v3, v0
+            return v2, v4, v0
+        }
+
+        function %caller() -> i8, i64, i8 {
+            fn0 = %callee() -> i8, i64, i64
+            ss0 = explicit_slot 8
+
+        block0:
+            v0 = get_frame_pointer.i64
+            v1, v2, v3 = call fn0()
+            v4 = icmp eq v0, v3
+            return v1, v2, v4
+        }";
+
+        let mut env = FunctionStore::default();
+        let funcs = parse_functions(code).unwrap().to_vec();
+        funcs.iter().for_each(|f| env.add(f.name.to_string(), f));
+
+        let state = InterpreterState::default().with_function_store(env);
+        let result = Interpreter::new(state)
+            .call_by_name("%caller", &[])
+            .unwrap();
+
+        // The callee's frame is placed right after the caller's, so its stack
+        // pointer is the caller's frame pointer.
+        assert_eq!(
+            result,
+            ControlFlow::Return(smallvec![
+                DataValue::I8(1),
+                DataValue::I64(24),
+                DataValue::I8(1)
+            ])
+        );
+    }
+
+    #[test]
+    fn return_address() {
+        let code = "
+        function %callee() -> i64 {
+        block0:
+            v0 = get_return_address.i64
+            return v0
+        }
+
+        function %caller() -> i8, i8, i8 {
+            fn0 = %callee() -> i64
+            fn1 = %caller() -> i8, i8, i8
+
+        block0:
+            v0 = call fn0()
+            v1 = func_addr.i64 fn1
+            v2 = icmp eq v0, v1
+            v3 = get_return_address.i64
+            v4 = icmp_imm ne v3, 0
+            v5 = icmp ne v3, v1
+            return v2, v4, v5
+        }";
+
+        let mut env = FunctionStore::default();
+        let funcs = parse_functions(code).unwrap().to_vec();
+        funcs.iter().for_each(|f| env.add(f.name.to_string(), f));
+
+        let state = InterpreterState::default().with_function_store(env);
+        let result = Interpreter::new(state)
+            .call_by_name("%caller", &[])
+            .unwrap();
+
+        // The callee returns into the caller, while the caller returns to the
+        // host, which has a non-null address of its own.
+        assert_eq!(
+            result,
+            ControlFlow::Return(smallvec![
+                DataValue::I8(1),
+                DataValue::I8(1),
+                DataValue::I8(1)
+            ])
+        );
+    }
 }
@@ -5,8 +5,8 @@ use crate::frame::Frame;
 use crate::interpreter::LibCallHandler;
 use cranelift_codegen::data_value::DataValue;
 use cranelift_codegen::ir::{
-    types, ExternalName, FuncRef, Function, GlobalValue, LibCall, MemFlags, Signature, StackSlot,
-    Type, Value,
+    types, DynamicStackSlot, ExternalName, FuncRef, Function, GlobalValue, LibCall, MemFlags,
+    Signature, StackSlot, Type, Value,
 };
 use cranelift_codegen::isa::CallConv;
 use smallvec::SmallVec;
@@ -49,6 +49,21 @@ pub trait State<'a> {
         slot: StackSlot,
         offset: u64,
     ) -> Result<Address, MemoryError>;
+    /// Computes the stack address of this dynamic stack slot.
+    fn dynamic_stack_address(
+        &self,
+        size: AddressSize,
+        slot: DynamicStackSlot,
+    ) -> Result<Address, MemoryError>;
+    /// Computes the address of the lowest byte of the current frame's stack space, which is what
+    /// `get_stack_pointer` returns.
+    fn stack_pointer(&self, size: AddressSize) -> Result<Address, MemoryError>;
+    /// Computes the address just past the end of the current frame's stack space, which is what
+    /// `get_frame_pointer` returns.
+    fn frame_pointer(&self, size: AddressSize) -> Result<Address, MemoryError>;
+    /// Computes the address that the current function returns to, which is what
+    /// `get_return_address` returns.
+    fn return_address(&self, size: AddressSize) -> Result<Address, MemoryError>;
     /// Retrieve a value `V` from memory at the given `address`, checking if it belongs either to the
     /// stack or to one of the heaps; the number of bytes loaded corresponds to the specified [Type].
     fn checked_load(
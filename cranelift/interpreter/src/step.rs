@@ -7,8 +7,8 @@ use crate::value::{DataValueExt, ValueConversionKind, ValueError, ValueResult};
 use cranelift_codegen::data_value::DataValue;
 use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
 use cranelift_codegen::ir::{
-    types, AbiParam, AtomicRmwOp, Block, BlockCall, Endianness, ExternalName, FuncRef, Function,
-    InstructionData, MemFlags, Opcode, TrapCode, Type, Value as ValueRef,
+    dynamic_to_fixed, types, AbiParam, AtomicRmwOp, Block, BlockCall, Endianness, ExternalName,
+    FuncRef, Function, InstructionData, MemFlags, Opcode, TrapCode, Type, Value as ValueRef,
 };
 use log::trace;
 use smallvec::{smallvec, SmallVec};
@@ -21,7 +21,7 @@ use thiserror::Error;
 fn validate_signature_params(sig: &[AbiParam], args: &[DataValue]) -> bool {
     args.iter()
         .map(|r| r.ty())
-        .zip(sig.iter().map(|r| r.value_type))
+        .zip(sig.iter().map(|r| interpreted_type(r.value_type)))
         .all(|(a, b)| match (a, b) {
             // For these two cases we don't have precise type information for `a`.
             // We don't distinguish between different bool types, or different vector types
@@ -52,7 +52,7 @@ where
     I: InstructionContext,
 {
     let inst = inst_context.data();
-    let ctrl_ty = inst_context.controlling_type().unwrap();
+    let ctrl_ty = interpreted_type(inst_context.controlling_type().unwrap());
     trace!(
         "Step: {}{}",
         inst.opcode(),
@@ -195,7 +195,6 @@ where
     // instruction's results.
     let unary =
         |op: fn(DataValue) -> ValueResult<DataValue>, arg: DataValue| -> ValueResult<ControlFlow> {
-            let ctrl_ty = inst_context.controlling_type().unwrap();
             let res = unary_arith(arg, ctrl_ty, op)?;
             Ok(assign(res))
         };
@@ -206,7 +205,6 @@ where
                   left: DataValue,
                   right: DataValue|
      -> ValueResult<ControlFlow> {
-        let ctrl_ty = inst_context.controlling_type().unwrap();
         let res = binary_arith(left, right, ctrl_ty, op)?;
         Ok(assign(res))
     };
@@ -216,7 +214,6 @@ where
                            left: DataValue,
                            right: DataValue|
      -> ValueResult<ControlFlow> {
-        let ctrl_ty = inst_context.controlling_type().unwrap();
         let res = binary_arith(left, right, ctrl_ty, op);
         assign_or_trap(res)
     };
@@ -424,7 +421,7 @@ where
                 .get(func_ref)
                 .ok_or(StepError::UnknownFunction(func_ref))?;
 
-            let addr_ty = inst_context.controlling_type().unwrap();
+            let addr_ty = ctrl_ty;
             assign_or_memtrap({
                 AddressSize::try_from(addr_ty).and_then(|addr_size| {
                     let addr = state.function_address(addr_size, &ext_data.name)?;
@@ -446,7 +443,14 @@ where
         | Opcode::Sload16x4
         | Opcode::Uload32x2
         | Opcode::Sload32x2 => {
-            let ctrl_ty = inst_context.controlling_type().unwrap();
+            // The vector extending loads are only polymorphic over the address type, so their
+            // result type is fixed by the opcode rather than by `ctrl_ty`.
+            let result_ty = match inst.opcode() {
+                Opcode::Uload8x8 | Opcode::Sload8x8 => types::I16X8,
+                Opcode::Uload16x4 | Opcode::Sload16x4 => types::I32X4,
+                Opcode::Uload32x2 | Opcode::Sload32x2 => types::I64X2,
+                _ => ctrl_ty,
+            };
             let (load_ty, kind) = match inst.opcode() {
                 Opcode::Load => (ctrl_ty, None),
                 Opcode::Uload8 => (types::I8, Some(ValueConversionKind::ZeroExtend(ctrl_ty))),
@@ -455,12 +459,14 @@ where
                 Opcode::Sload16 => (types::I16, Some(ValueConversionKind::SignExtend(ctrl_ty))),
                 Opcode::Uload32 => (types::I32, Some(ValueConversionKind::ZeroExtend(ctrl_ty))),
                 Opcode::Sload32 => (types::I32, Some(ValueConversionKind::SignExtend(ctrl_ty))),
-                Opcode::Uload8x8
-                | Opcode::Sload8x8
-                | Opcode::Uload16x4
-                | Opcode::Sload16x4
-                | Opcode::Uload32x2
-                | Opcode::Sload32x2 => unimplemented!(),
+                Opcode::Uload8x8 | Opcode::Uload16x4 | Opcode::Uload32x2 => (
+                    result_ty.half_width().unwrap(),
+                    Some(ValueConversionKind::ZeroExtend(result_ty.lane_type())),
+                ),
+                Opcode::Sload8x8 | Opcode::Sload16x4 | Opcode::Sload32x2 => (
+                    result_ty.half_width().unwrap(),
+                    Some(ValueConversionKind::SignExtend(result_ty.lane_type())),
+                ),
                 _ => unreachable!(),
             };
 
@@ -471,10 +477,19 @@ where
                     .and_then(|addr| state.checked_load(addr, load_ty, mem_flags)),
             );
 
+            // Extend each of the loaded lanes, since the vector loads extend every lane.
+            let extend = |loaded: DataValue, c: ValueConversionKind| -> ValueResult<DataValue> {
+                let lanes = extractlanes(&loaded, load_ty)?
+                    .into_iter()
+                    .map(|lane| lane.convert(c.clone()))
+                    .collect::<ValueResult<SimdVec<DataValue>>>()?;
+                vectorizelanes(&lanes, result_ty)
+            };
+
             match (loaded, kind) {
                 (ControlFlow::Assign(ret), Some(c)) => ControlFlow::Assign(
                     ret.into_iter()
-                        .map(|loaded| loaded.convert(c.clone()))
+                        .map(|loaded| extend(loaded, c.clone()))
                         .collect::<ValueResult<SmallVec<[DataValue; 1]>>>()?,
                 ),
                 (cf, _) => cf,
@@ -502,7 +517,7 @@ where
             )
         }
         Opcode::StackLoad => {
-            let load_ty = inst_context.controlling_type().unwrap();
+            let load_ty = ctrl_ty;
             let slot = inst.stack_slot().unwrap();
             let offset = sum_unsigned(imm(), args())? as u64;
             let mem_flags = MemFlags::new();
@@ -524,7 +539,7 @@ where
             })
         }
         Opcode::StackAddr => {
-            let load_ty = inst_context.controlling_type().unwrap();
+            let load_ty = ctrl_ty;
             let slot = inst.stack_slot().unwrap();
             let offset = sum_unsigned(imm(), args())? as u64;
             assign_or_memtrap({
@@ -535,9 +550,35 @@ where
                 })
             })
         }
-        Opcode::DynamicStackAddr => unimplemented!("DynamicStackSlot"),
-        Opcode::DynamicStackLoad => unimplemented!("DynamicStackLoad"),
-        Opcode::DynamicStackStore => unimplemented!("DynamicStackStore"),
+        Opcode::DynamicStackLoad => {
+            let slot = inst.dynamic_stack_slot().unwrap();
+            let mem_flags = MemFlags::new();
+            assign_or_memtrap({
+                state
+                    .dynamic_stack_address(AddressSize::_64, slot)
+                    .and_then(|addr| state.checked_load(addr, ctrl_ty, mem_flags))
+            })
+        }
+        Opcode::DynamicStackStore => {
+            let arg = arg(0);
+            let slot = inst.dynamic_stack_slot().unwrap();
+            let mem_flags = MemFlags::new();
+            continue_or_memtrap({
+                state
+                    .dynamic_stack_address(AddressSize::_64, slot)
+                    .and_then(|addr| state.checked_store(addr, arg, mem_flags))
+            })
+        }
+        Opcode::DynamicStackAddr => {
+            let slot = inst.dynamic_stack_slot().unwrap();
+            assign_or_memtrap({
+                AddressSize::try_from(ctrl_ty).and_then(|addr_size| {
+                    let addr = state.dynamic_stack_address(addr_size, slot)?;
+                    let dv = DataValue::try_from(addr)?;
+                    Ok(dv.into())
+                })
+            })
+        }
         Opcode::GlobalValue | Opcode::SymbolValue | Opcode::TlsValue => {
             if let InstructionData::UnaryGlobalValue { global_value, .. } = inst {
                 assign_or_memtrap(state.resolve_global_value(global_value))
@@ -577,7 +618,7 @@ where
         Opcode::F32const => assign(imm()),
         Opcode::F64const => assign(imm()),
         Opcode::Vconst => assign(imm()),
-        Opcode::Null => unimplemented!("Null"),
+        Opcode::Null => assign(DataValueExt::int(0, ctrl_ty)?),
         Opcode::Nop => ControlFlow::Continue,
         Opcode::Select | Opcode::SelectSpectreGuard => choose(arg(0).into_bool()?, arg(1), arg(2)),
         Opcode::Bitselect => assign(bitselect(arg(0), arg(1), arg(2))?),
@@ -890,10 +931,14 @@ where
         Opcode::Floor => unary(DataValueExt::floor, arg(0))?,
         Opcode::Trunc => unary(DataValueExt::trunc, arg(0))?,
         Opcode::Nearest => unary(DataValueExt::nearest, arg(0))?,
-        Opcode::IsNull => unimplemented!("IsNull"),
-        Opcode::IsInvalid => unimplemented!("IsInvalid"),
+        Opcode::IsNull => assign(DataValueExt::bool(arg(0).is_zero()?, false, types::I8)?),
+        Opcode::IsInvalid => {
+            // Invalid references have all of their bits set.
+            let invalid = DataValueExt::int(-1, ctrl_ty)?;
+            assign(DataValueExt::bool(arg(0) == invalid, false, types::I8)?)
+        }
         Opcode::Bitcast | Opcode::ScalarToVector => {
-            let input_ty = inst_context.type_of(inst_context.args()[0]).unwrap();
+            let input_ty = interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap());
             let lanes = &if input_ty.is_vector() {
                 assert_eq!(
                     inst.memflags()
@@ -1009,10 +1054,8 @@ where
         Opcode::VhighBits => {
             // `ctrl_ty` controls the return type for this, so the input type
             // must be retrieved via `inst_context`.
-            let vector_type = inst_context
-                .type_of(inst_context.args()[0])
-                .unwrap()
-                .as_int();
+            let vector_type =
+                interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap()).as_int();
             let a = extractlanes(&arg(0), vector_type)?;
             let mut result: u128 = 0;
             for (i, val) in a.into_iter().enumerate() {
@@ -1087,7 +1130,7 @@ where
             assign(DataValueExt::int(x, ctrl_ty)?)
         }
         Opcode::FcvtToUintSat | Opcode::FcvtToSintSat => {
-            let in_ty = inst_context.type_of(inst_context.args()[0]).unwrap();
+            let in_ty = interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap());
             let cvt = |x: DataValue| -> ValueResult<DataValue> {
                 // NaN check
                 if x.is_nan()? {
@@ -1122,7 +1165,7 @@ where
         Opcode::FcvtFromUint | Opcode::FcvtFromSint => {
             let x = extractlanes(
                 &arg(0),
-                inst_context.type_of(inst_context.args()[0]).unwrap(),
+                interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap()),
             )?;
             let bits = |x: DataValue| -> ValueResult<u64> {
                 Ok(match ctrl_ty.lane_type() {
@@ -1149,7 +1192,7 @@ where
             )?)
         }
         Opcode::FvpromoteLow => {
-            let in_ty = inst_context.type_of(inst_context.args()[0]).unwrap();
+            let in_ty = interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap());
             assert_eq!(in_ty, types::F32X4);
             let out_ty = types::F64X2;
             let x = extractlanes(&arg(0), in_ty)?;
@@ -1167,7 +1210,7 @@ where
             )?)
         }
         Opcode::Fvdemote => {
-            let in_ty = inst_context.type_of(inst_context.args()[0]).unwrap();
+            let in_ty = interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap());
             assert_eq!(in_ty, types::F64X2);
             let out_ty = types::F32X4;
             let x = extractlanes(&arg(0), in_ty)?;
@@ -1238,7 +1281,7 @@ where
             assign_or_memtrap(val_to_assign)
         }
         Opcode::AtomicLoad => {
-            let load_ty = inst_context.controlling_type().unwrap();
+            let load_ty = ctrl_ty;
             let addr = arg(0).into_int_unsigned()? as u64;
             let mem_flags = inst.memflags().expect("instruction to have memory flags");
             // We are doing a regular load here, this isn't actually thread safe.
@@ -1293,19 +1336,133 @@ where
             assign(binary_pairwise(arg(0), arg(1), ctrl_ty, DataValueExt::add)?)
         }
         Opcode::ExtractVector => {
-            unimplemented!("ExtractVector not supported");
-        }
-        Opcode::GetFramePointer => unimplemented!("GetFramePointer"),
-        Opcode::GetStackPointer => unimplemented!("GetStackPointer"),
-        Opcode::GetReturnAddress => unimplemented!("GetReturnAddress"),
-        Opcode::X86Pshufb => unimplemented!("X86Pshufb"),
-        Opcode::X86Blendv => unimplemented!("X86Blendv"),
-        Opcode::X86Pmulhrsw => unimplemented!("X86Pmulhrsw"),
-        Opcode::X86Pmaddubsw => unimplemented!("X86Pmaddubsw"),
-        Opcode::X86Cvtt2dq => unimplemented!("X86Cvtt2dq"),
+            // Dynamic vectors are always scaled by one, so they only contain a single fixed-size
+            // vector.
+            let idx = imm().into_int_unsigned()?;
+            if idx != 0 {
+                return Err(StepError::ValueError(ValueError::InvalidValue(ctrl_ty)));
+            }
+            assign(arg(0))
+        }
+        Opcode::GetFramePointer | Opcode::GetStackPointer | Opcode::GetReturnAddress => {
+            assign_or_memtrap({
+                AddressSize::try_from(ctrl_ty).and_then(|addr_size| {
+                    let addr = match inst.opcode() {
+                        Opcode::GetFramePointer => state.frame_pointer(addr_size)?,
+                        Opcode::GetStackPointer => state.stack_pointer(addr_size)?,
+                        Opcode::GetReturnAddress => state.return_address(addr_size)?,
+                        _ => unreachable!(),
+                    };
+                    let dv = DataValue::try_from(addr)?;
+                    Ok(dv.into())
+                })
+            })
+        }
+        Opcode::X86Pshufb => {
+            let x = extractlanes(&arg(0), types::I8X16)?;
+            let y = extractlanes(&arg(1), types::I8X16)?;
+            let new_vec = y
+                .into_iter()
+                .map(|idx| {
+                    let idx = idx.into_int_unsigned()? as usize;
+                    // Indices with the top bit set select zero, otherwise only the bottom
+                    // four bits are used.
+                    if idx & 0x80 != 0 {
+                        DataValueExt::int(0, types::I8)
+                    } else {
+                        Ok(x[idx & 0xf].clone())
+                    }
+                })
+                .collect::<ValueResult<SimdVec<_>>>()?;
+            assign(vectorizelanes(&new_vec, types::I8X16)?)
+        }
+        Opcode::X86Blendv => {
+            let c = extractlanes(&arg(0), ctrl_ty)?;
+            let x = extractlanes(&arg(1), ctrl_ty)?;
+            let y = extractlanes(&arg(2), ctrl_ty)?;
+            let new_vec = c
+                .into_iter()
+                .zip(x.into_iter().zip(y.into_iter()))
+                .map(|(c, (x, y))| Ok(if c.into_int_signed()? < 0 { x } else { y }))
+                .collect::<ValueResult<SimdVec<_>>>()?;
+            assign(vectorizelanes(&new_vec, ctrl_ty)?)
+        }
+        Opcode::X86Pmulhrsw => {
+            let lane_type = ctrl_ty.lane_type();
+            let arg0 = extractlanes(&arg(0), ctrl_ty)?;
+            let arg1 = extractlanes(&arg(1), ctrl_ty)?;
+            let new_vec = arg0
+                .into_iter()
+                .zip(arg1.into_iter())
+                .map(|(x, y)| {
+                    let x = x.into_int_signed()?;
+                    let y = y.into_int_signed()?;
+                    // Unlike `sqmul_round_sat` this doesn't saturate, so `MIN * MIN` wraps
+                    // around to `MIN`.
+                    let z = (x * y + (1 << (lane_type.bits() - 2))) >> (lane_type.bits() - 1);
+                    DataValue::int(z, lane_type.double_width().unwrap())?
+                        .convert(ValueConversionKind::Truncate(lane_type))
+                })
+                .collect::<ValueResult<SimdVec<_>>>()?;
+            assign(vectorizelanes(&new_vec, ctrl_ty)?)
+        }
+        Opcode::X86Pmaddubsw => {
+            let x = extractlanes(&arg(0), types::I8X16)?;
+            let y = extractlanes(&arg(1), types::I8X16)?;
+            let (min, max) = types::I16.bounds(true);
+            let products = x
+                .into_iter()
+                .zip(y.into_iter())
+                .map(|(x, y)| {
+                    // The lanes of `x` are signed and the lanes of `y` are unsigned.
+                    Ok(x.into_int_signed()? * y.into_int_unsigned()? as i128)
+                })
+                .collect::<ValueResult<SimdVec<_>>>()?;
+            let new_vec = products
+                .chunks(2)
+                .map(|pair| {
+                    let sum = (pair[0] + pair[1]).clamp(min as i128, max as i128);
+                    DataValueExt::int(sum, types::I16)
+                })
+                .collect::<ValueResult<SimdVec<_>>>()?;
+            assign(vectorizelanes(&new_vec, types::I16X8)?)
+        }
+        Opcode::X86Cvtt2dq => {
+            let in_ty = interpreted_type(inst_context.type_of(inst_context.args()[0]).unwrap());
+            let cvt = |x: DataValue| -> ValueResult<DataValue> {
+                // The conversion always produces a 32-bit integer, which is `i32::MIN` for NaN or
+                // out-of-bounds lanes. Wider lanes are sign-extended from it.
+                let x = x.into_float()?.trunc();
+                let x = if x >= i32::MIN as f64 && x <= i32::MAX as f64 {
+                    x as i32
+                } else {
+                    i32::MIN
+                };
+                DataValueExt::int(x as i128, ctrl_ty.lane_type())
+            };
+            let new_vec = extractlanes(&arg(0), in_ty)?
+                .into_iter()
+                .map(cvt)
+                .collect::<ValueResult<SimdVec<_>>>()?;
+            assign(vectorizelanes(&new_vec, ctrl_ty)?)
+        }
     })
 }
 
+/// The type that the interpreter uses for values of type `ty`.
+///
+/// References are interpreted as integers of the same width. Dynamic vectors are interpreted as
+/// their fixed-size base vectors, which is what they are on targets whose dynamic vectors are 128
+/// bits wide; this means that `dyn_scale_target_const` always resolves to one.
+fn interpreted_type(ty: Type) -> Type {
+    match ty {
+        types::R32 => types::I32,
+        types::R64 => types::I64,
+        ty if ty.is_dynamic_vector() => dynamic_to_fixed(ty),
+        ty => ty,
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum StepError {
     #[error("unable to retrieve value from SSA reference: {0}")]
@@ -1344,7 +1501,7 @@ pub enum ControlFlow<'a> {
     Trap(CraneliftTrap),
 }
 
-#[derive(Error, Debug, PartialEq, Eq, Hash)]
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CraneliftTrap {
     #[error("user code: {0}")]
     User(TrapCode),
@@ -0,0 +1,34 @@
+mod rules;
+
+use std::sync::atomic::Ordering;
+
+struct Context;
+impl rules::Context for Context {}
+
+fn main() {
+    let mut ctx = Context;
+
+    assert_eq!(rules::constructor_Y(&mut ctx, 0), 1);
+    assert_eq!(rules::constructor_Y(&mut ctx, 0), 1);
+    assert_eq!(rules::constructor_Y(&mut ctx, 2), 3);
+    assert_eq!(rules::constructor_Y(&mut ctx, 7), 0);
+
+    let counts = rules::ISLE_RULES
+        .iter()
+        .zip(&rules::ISLE_RULE_COUNTS)
+        .map(|(&(term, pos), count)| {
+            let line = pos.rsplit(' ').next().unwrap();
+            (term, line, count.load(Ordering::Relaxed))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(counts.len(), 5);
+    for expected in [
+        ("X", "4", 2),
+        ("X", "5", 0),
+        ("X", "6", 1),
+        ("Y", "9", 3),
+        ("Y", "10", 1),
+    ] {
+        assert!(counts.contains(&expected), "{expected:?} not in {counts:?}");
+    }
+}
@@ -12,6 +12,11 @@ pub struct CodegenOptions {
     /// Do not include the `#![allow(...)]` pragmas in the generated
     /// source. Useful if it must be include!()'d elsewhere.
     pub exclude_global_allow_pragmas: bool,
+
+    /// Count how many times each rule is applied. The generated source then
+    /// contains a `ISLE_RULES` table with the term and source position of each
+    /// rule, and a `ISLE_RULE_COUNTS` table with the count of each rule.
+    pub rule_coverage: bool,
 }
 
 /// Emit Rust source code for the given type and term environments.
@@ -34,16 +39,20 @@ struct Codegen<'a> {
 struct BodyContext<'a, W> {
     out: &'a mut W,
     ruleset: &'a RuleSet,
+    /// With rule coverage, the index in `ISLE_RULE_COUNTS` of the first rule
+    /// in `ruleset`.
+    first_rule: Option<usize>,
     indent: String,
     is_ref: StableSet<BindingId>,
     is_bound: StableSet<BindingId>,
 }
 
 impl<'a, W: Write> BodyContext<'a, W> {
-    fn new(out: &'a mut W, ruleset: &'a RuleSet) -> Self {
+    fn new(out: &'a mut W, ruleset: &'a RuleSet, first_rule: Option<usize>) -> Self {
         Self {
             out,
             ruleset,
+            first_rule,
             indent: Default::default(),
             is_ref: Default::default(),
             is_bound: Default::default(),
@@ -99,7 +108,11 @@ impl<'a> Codegen<'a> {
         self.generate_header(&mut code, options);
         self.generate_ctx_trait(&mut code);
         self.generate_internal_types(&mut code);
-        self.generate_internal_term_constructors(&mut code).unwrap();
+        self.generate_internal_term_constructors(&mut code, options)
+            .unwrap();
+        if options.rule_coverage {
+            self.generate_rule_coverage(&mut code).unwrap();
+        }
 
         code
     }
@@ -136,6 +149,9 @@ impl<'a> Codegen<'a> {
 
         writeln!(code, "\nuse super::*;  // Pulls in all external types.").unwrap();
         writeln!(code, "use std::marker::PhantomData;").unwrap();
+        if options.rule_coverage {
+            writeln!(code, "use std::sync::atomic;").unwrap();
+        }
     }
 
     fn generate_trait_sig(&self, code: &mut String, indent: &str, sig: &ExternalSig) {
@@ -376,10 +392,17 @@ impl<L: Length, C> Length for ContextIterWrapper<L, C> {{
         }
     }
 
-    fn generate_internal_term_constructors(&self, code: &mut String) -> std::fmt::Result {
+    fn generate_internal_term_constructors(
+        &self,
+        code: &mut String,
+        options: &CodegenOptions,
+    ) -> std::fmt::Result {
+        let mut first_rule = 0;
         for &(termid, ref ruleset) in self.terms.iter() {
             let root = crate::serialize::serialize(ruleset);
-            let mut ctx = BodyContext::new(code, ruleset);
+            let mut ctx =
+                BodyContext::new(code, ruleset, options.rule_coverage.then_some(first_rule));
+            first_rule += ruleset.rules.len();
 
             let termdata = &self.termenv.terms[termid.index()];
             let term_name = &self.typeenv.syms[termdata.name.index()];
@@ -468,6 +491,51 @@ impl<L: Length, C> Length for ContextIterWrapper<L, C> {{
         Ok(())
     }
 
+    fn generate_rule_coverage(&self, code: &mut String) -> std::fmt::Result {
+        let num_rules: usize = self
+            .terms
+            .iter()
+            .map(|(_, ruleset)| ruleset.rules.len())
+            .sum();
+
+        writeln!(code)?;
+        writeln!(
+            code,
+            "/// The term and source position of each rule, in the order of `ISLE_RULE_COUNTS`."
+        )?;
+        writeln!(
+            code,
+            "pub static ISLE_RULES: [(&str, &str); {num_rules}] = ["
+        )?;
+        for &(termid, ref ruleset) in self.terms.iter() {
+            let termdata = &self.termenv.terms[termid.index()];
+            let term_name = &self.typeenv.syms[termdata.name.index()];
+            for rule in &ruleset.rules {
+                writeln!(
+                    code,
+                    "    ({:?}, {:?}),",
+                    term_name,
+                    rule.pos.pretty_print_line(&self.typeenv.filenames)
+                )?;
+            }
+        }
+        writeln!(code, "];")?;
+
+        writeln!(code)?;
+        writeln!(code, "/// How many times each rule was applied.")?;
+        writeln!(
+            code,
+            "pub static ISLE_RULE_COUNTS: [atomic::AtomicU64; {num_rules}] = {{"
+        )?;
+        writeln!(code, "    #[allow(clippy::declare_interior_mutable_const)]")?;
+        writeln!(
+            code,
+            "    const ZERO: atomic::AtomicU64 = atomic::AtomicU64::new(0);"
+        )?;
+        writeln!(code, "    [ZERO; {num_rules}]")?;
+        writeln!(code, "}};")
+    }
+
     fn ty(&self, typeid: TypeId) -> (bool, Sym) {
         match &self.typeenv.types[typeid.index()] {
             &Type::Primitive(_, sym, _) => (false, sym),
@@ -630,13 +698,21 @@ impl<L: Length, C> Length for ContextIterWrapper<L, C> {{
                     ctx.end_block(scope)?;
                 }
 
-                &ControlFlow::Return { pos, result } => {
+                &ControlFlow::Return { pos, rule, result } => {
                     writeln!(
                         ctx.out,
                         "{}// Rule at {}.",
                         &ctx.indent,
                         pos.pretty_print_line(&self.typeenv.filenames)
                     )?;
+                    if let Some(first_rule) = ctx.first_rule {
+                        writeln!(
+                            ctx.out,
+                            "{}ISLE_RULE_COUNTS[{}].fetch_add(1, atomic::Ordering::Relaxed);",
+                            &ctx.indent,
+                            first_rule + rule
+                        )?;
+                    }
                     write!(ctx.out, "{}", &ctx.indent)?;
                     match ret_kind {
                         ReturnKind::Plain => write!(ctx.out, "return ")?,
@@ -104,6 +104,8 @@ pub enum ControlFlow {
     Return {
         /// Where was the rule defined that had this right-hand side?
         pos: Pos,
+        /// The index of the rule in its term's [RuleSet].
+        rule: usize,
         /// What is the result expression which should be returned if this
         /// rule matched?
         result: BindingId,
@@ -492,7 +494,11 @@ impl<'a> Decomposition<'a> {
             }
             self.use_expr(result);
 
-            let check = ControlFlow::Return { pos, result };
+            let check = ControlFlow::Return {
+                pos,
+                rule: idx,
+                result,
+            };
             let bind_order = std::mem::take(&mut self.bind_order);
             self.block.steps.push(EvalStep { bind_order, check });
         }
@@ -1,5 +1,6 @@
 //! Helper for autogenerated unit tests.
 
+use cranelift_isle::codegen::CodegenOptions;
 use cranelift_isle::compile;
 use cranelift_isle::error::Errors;
 use std::default::Default;
@@ -8,6 +9,14 @@ fn build(filename: &str) -> Result<String, Errors> {
     compile::from_files(&[filename], &Default::default())
 }
 
+fn build_with_rule_coverage(filename: &str) -> Result<String, Errors> {
+    let options = CodegenOptions {
+        rule_coverage: true,
+        ..Default::default()
+    };
+    compile::from_files(&[filename], &options)
+}
+
 pub fn run_pass(filename: &str) {
     if let Err(err) = build(filename) {
         panic!("pass test failed:\n{:?}", err);
@@ -24,7 +33,10 @@ pub fn run_fail(filename: &str) {
     }
 }
 
-fn build_and_link_isle(isle_filename: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+fn build_and_link_isle(
+    isle_filename: &str,
+    build: fn(&str) -> Result<String, Errors>,
+) -> (tempfile::TempDir, std::path::PathBuf) {
     let tempdir = tempfile::tempdir().unwrap();
     let code = build(isle_filename).unwrap();
 
@@ -60,12 +72,18 @@ fn build_and_link_isle(isle_filename: &str) -> (tempfile::TempDir, std::path::Pa
 }
 
 pub fn run_link(isle_filename: &str) {
-    build_and_link_isle(isle_filename);
+    build_and_link_isle(isle_filename, build);
 }
 
 pub fn run_run(isle_filename: &str) {
-    let (_tempdir, exe) = build_and_link_isle(isle_filename);
+    run_exe(build_and_link_isle(isle_filename, build));
+}
+
+pub fn run_coverage(isle_filename: &str) {
+    run_exe(build_and_link_isle(isle_filename, build_with_rule_coverage));
+}
 
+fn run_exe((_tempdir, exe): (tempfile::TempDir, std::path::PathBuf)) {
     assert!(std::process::Command::new(exe)
         .spawn()
         .unwrap()
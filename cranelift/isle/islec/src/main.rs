@@ -1,8 +1,8 @@
 use clap::Parser;
+use cranelift_isle::codegen::CodegenOptions;
 use cranelift_isle::compile;
 use cranelift_isle::error::Errors;
 use std::{
-    default::Default,
     fs,
     io::{self, Write},
     path::PathBuf,
@@ -15,6 +15,10 @@ struct Opts {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Count how many times each rule is applied in the generated Rust code.
+    #[arg(long)]
+    rule_coverage: bool,
+
     /// The input ISLE DSL source files.
     #[arg(required = true)]
     inputs: Vec<PathBuf>,
@@ -24,7 +28,11 @@ fn main() -> Result<(), Errors> {
     let _ = env_logger::try_init();
 
     let opts = Opts::parse();
-    let code = compile::from_files(opts.inputs, &Default::default())?;
+    let options = CodegenOptions {
+        rule_coverage: opts.rule_coverage,
+        ..CodegenOptions::default()
+    };
+    let code = compile::from_files(opts.inputs, &options)?;
 
     let stdout = io::stdout();
     let (mut output, output_name): (Box<dyn Write>, _) = match &opts.output {
@@ -1,9 +1,12 @@
 //! Defines `JITModule`.
 
+use crate::tls::{self, TlsDescriptor};
 use crate::{compiled_blob::CompiledBlob, memory::BranchProtection, memory::Memory};
 use cranelift_codegen::binemit::Reloc;
-use cranelift_codegen::isa::{OwnedTargetIsa, TargetIsa};
-use cranelift_codegen::settings::Configurable;
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::ir::InstBuilder;
+use cranelift_codegen::isa::{CallConv, OwnedTargetIsa, TargetIsa};
+use cranelift_codegen::settings::{Configurable, TlsModel};
 use cranelift_codegen::{self, ir, settings, FinalizedMachReloc};
 use cranelift_control::ControlPlane;
 use cranelift_entity::SecondaryMap;
@@ -11,7 +14,7 @@ use cranelift_module::{
     DataDescription, DataId, FuncId, Init, Linkage, Module, ModuleDeclarations, ModuleError,
     ModuleReloc, ModuleRelocTarget, ModuleResult,
 };
-use log::info;
+use log::{error, info};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
@@ -25,6 +28,10 @@ use target_lexicon::PointerWidth;
 const WRITABLE_DATA_ALIGNMENT: u64 = 0x8;
 const READONLY_DATA_ALIGNMENT: u64 = 0x1;
 
+/// The trap code of the trap raised by the stub of a lazily compiled function when generating or
+/// compiling the function fails; see [`JITModule::define_function_lazily`].
+pub const LAZY_FUNCTION_TRAP_CODE: ir::TrapCode = ir::TrapCode::User(u16::MAX);
+
 /// A builder for `JITModule`.
 pub struct JITBuilder {
     isa: OwnedTargetIsa,
@@ -157,6 +164,20 @@ impl JITBuilder {
     }
 }
 
+/// Generates the body of a lazily compiled function; see [`JITModule::define_function_lazily`].
+type LazyFunctionGenerator =
+    Box<dyn FnOnce(&mut JITModule, &mut cranelift_codegen::Context) -> ModuleResult<()>>;
+
+/// A function that is compiled when it's first called.
+struct LazyFunction {
+    /// The code which compiles the function before calling it. This is the address of the
+    /// function until it has been compiled.
+    stub: *const u8,
+
+    /// Generates the function's body, until it has been compiled.
+    generator: Option<LazyFunctionGenerator>,
+}
+
 /// A pending update to the GOT.
 struct GotUpdate {
     /// The entry that is to be updated.
@@ -185,9 +206,24 @@ pub struct JITModule {
     libcall_plt_entries: HashMap<ir::LibCall, NonNull<[u8; 16]>>,
     compiled_functions: SecondaryMap<FuncId, Option<CompiledBlob>>,
     compiled_data_objects: SecondaryMap<DataId, Option<CompiledBlob>>,
+    /// The descriptors of defined thread-local data objects, whose compiled data is their
+    /// initial contents.
+    tls_descriptors: SecondaryMap<DataId, Option<NonNull<TlsDescriptor>>>,
     functions_to_finalize: Vec<FuncId>,
     data_objects_to_finalize: Vec<DataId>,
 
+    /// The code of definitions replaced through `prepare_for_function_redefine`, which may still
+    /// be executing and is only freed by `free_replaced_functions`.
+    replaced_functions: Vec<*const u8>,
+
+    /// Functions defined through `define_function_lazily`.
+    lazy_functions: HashMap<FuncId, LazyFunction>,
+    /// The address of this module, which the stubs of lazy functions pass to the resolver.
+    ///
+    /// This is boxed so that the stubs can refer to it even if the module moves, and updated
+    /// whenever definitions are finalized.
+    lazy_resolver_module: Box<*mut JITModule>,
+
     /// Updates to the GOT awaiting relocations to be made and region protections to be set
     pending_got_updates: Vec<GotUpdate>,
 }
@@ -195,10 +231,24 @@ pub struct JITModule {
 /// A handle to allow freeing memory allocated by the `Module`.
 struct MemoryHandle {
     code: Memory,
+    /// The code of lazily compiled functions, which is finalized on its own when the functions
+    /// are compiled.
+    lazy_code: Memory,
     readonly: Memory,
     writable: Memory,
 }
 
+impl MemoryHandle {
+    /// The memory for the code of a function, depending on whether it is lazily compiled.
+    fn code_memory(&mut self, lazy: bool) -> &mut Memory {
+        if lazy {
+            &mut self.lazy_code
+        } else {
+            &mut self.code
+        }
+    }
+}
+
 impl JITModule {
     /// Free memory allocated for code and data segments of compiled functions.
     ///
@@ -210,10 +260,126 @@ impl JITModule {
     /// are called afterwards.
     pub unsafe fn free_memory(mut self) {
         self.memory.code.free_memory();
+        self.memory.lazy_code.free_memory();
         self.memory.readonly.free_memory();
         self.memory.writable.free_memory();
     }
 
+    /// Free the code of a defined function, after which it may be defined again.
+    ///
+    /// The function's GOT entry, if any, is reset to null, so that calls through its PLT entry
+    /// fault rather than executing freed memory. The memory of the function is only returned to
+    /// the system once all other functions sharing its pages have been freed too, while any of
+    /// its pages not shared with other functions are reused by later definitions.
+    ///
+    /// # Safety
+    ///
+    /// The function must not be currently executing, and none of the pointers to it may be used
+    /// afterwards. This includes pointers from [`JITModule::get_finalized_function`] as well as
+    /// direct calls from other functions and references from data objects, which only go through
+    /// the GOT and PLT for PIC code when hotswapping is enabled.
+    pub unsafe fn free_function(&mut self, func_id: FuncId) -> ModuleResult<()> {
+        let compiled = self.compiled_functions[func_id].take();
+        let lazy = self.lazy_functions.remove(&func_id);
+        if compiled.is_none() && lazy.is_none() {
+            let decl = self.declarations.get_function_decl(func_id);
+            return Err(ModuleError::Backend(anyhow::anyhow!(
+                "Tried to free not yet defined function {}",
+                decl.linkage_name(func_id),
+            )));
+        }
+
+        self.functions_to_finalize.retain(|&id| id != func_id);
+        if let Some(got_entry) = self.function_got_entries[func_id] {
+            self.reset_got_entry(got_entry);
+        }
+        if let Some(compiled) = compiled {
+            self.free_code(compiled.ptr);
+        }
+        // The stub of a lazily compiled function is freed along with it.
+        if let Some(lazy) = lazy {
+            self.memory.code.free(lazy.stub);
+        }
+        Ok(())
+    }
+
+    /// Free the memory of a defined data object, after which it may be defined again.
+    ///
+    /// The data object's GOT entry, if any, is reset to null. For thread-local data objects, only
+    /// the calling thread's copy is freed right away, while the copies of other threads are freed
+    /// when those threads exit or access a new thread-local data object which reuses the freed
+    /// object's key.
+    ///
+    /// # Safety
+    ///
+    /// None of the pointers to the data object may be used afterwards. This includes pointers
+    /// from [`JITModule::get_finalized_data`] and [`JITModule::get_finalized_tls_data`] as well as
+    /// references from functions and other data objects.
+    pub unsafe fn free_data(&mut self, data_id: DataId) -> ModuleResult<()> {
+        let decl = self.declarations.get_data_decl(data_id);
+        let writable = decl.writable;
+        let Some(compiled) = self.compiled_data_objects[data_id].take() else {
+            return Err(ModuleError::Backend(anyhow::anyhow!(
+                "Tried to free not yet defined data object {}",
+                decl.linkage_name(data_id),
+            )));
+        };
+
+        self.data_objects_to_finalize.retain(|&id| id != data_id);
+        if let Some(got_entry) = self.data_object_got_entries[data_id] {
+            self.reset_got_entry(got_entry);
+        }
+        if let Some(descriptor) = self.tls_descriptors[data_id].take() {
+            descriptor.as_ref().release();
+            self.memory.writable.free(descriptor.as_ptr().cast());
+        }
+        // Empty data objects don't have any memory allocated.
+        if compiled.size != 0 {
+            if writable {
+                self.memory.writable.free(compiled.ptr);
+            } else {
+                self.memory.readonly.free(compiled.ptr);
+            }
+        }
+        Ok(())
+    }
+
+    /// Free the code of all function definitions that were replaced after
+    /// [`JITModule::prepare_for_function_redefine`].
+    ///
+    /// This must be called after the replacing definitions have been finalized, which updates
+    /// the GOT entries that still point to the replaced code until then.
+    ///
+    /// # Safety
+    ///
+    /// None of the replaced definitions may be currently executing, and none of the pointers to
+    /// them, e.g. from [`JITModule::get_finalized_function`], may be used afterwards.
+    pub unsafe fn free_replaced_functions(&mut self) {
+        assert!(
+            self.pending_got_updates.is_empty(),
+            "replacing definitions not yet finalized"
+        );
+        for ptr in std::mem::take(&mut self.replaced_functions) {
+            self.free_code(ptr);
+        }
+    }
+
+    /// Free the code of a function, which was allocated by either of the code memories.
+    unsafe fn free_code(&mut self, ptr: *const u8) {
+        if self.memory.lazy_code.contains(ptr) {
+            self.memory.lazy_code.free(ptr);
+        } else {
+            self.memory.code.free(ptr);
+        }
+    }
+
+    /// Reset a GOT entry to null, dropping any pending update of it.
+    fn reset_got_entry(&mut self, entry: NonNull<AtomicPtr<u8>>) {
+        self.pending_got_updates
+            .retain(|update| update.entry != entry);
+        unsafe { entry.as_ref() }.store(ptr::null_mut(), Ordering::SeqCst);
+    }
+
     fn lookup_symbol(&self, name: &str) -> Option<*const u8> {
         match self.symbols.borrow_mut().entry(name.to_owned()) {
             std::collections::hash_map::Entry::Occupied(occ) => Some(*occ.get()),
@@ -311,6 +477,9 @@ impl JITModule {
                         match &self.compiled_functions[func_id] {
                             Some(compiled) => return compiled.ptr,
                             None => {
+                                if let Some(lazy) = self.lazy_functions.get(&func_id) {
+                                    return lazy.stub;
+                                }
                                 let decl = self.declarations.get_function_decl(func_id);
                                 (&decl.name, decl.linkage)
                             }
@@ -318,6 +487,9 @@ impl JITModule {
                     }
                 } else {
                     let data_id = DataId::from_name(name);
+                    if let Some(descriptor) = self.tls_descriptors[data_id] {
+                        return descriptor.as_ptr().cast();
+                    }
                     match &self.compiled_data_objects[data_id] {
                         Some(compiled) => return compiled.ptr,
                         None => {
@@ -338,6 +510,9 @@ impl JITModule {
                 }
             }
             ModuleRelocTarget::LibCall(ref libcall) => {
+                if let (ir::LibCall::ElfTlsGetAddr, Some(addr)) = (libcall, tls::tls_get_addr()) {
+                    return addr;
+                }
                 let sym = (self.libcall_names)(*libcall);
                 self.lookup_symbol(&sym)
                     .unwrap_or_else(|| panic!("can't resolve libcall {}", sym))
@@ -398,24 +573,39 @@ impl JITModule {
 
     /// Returns the address of a finalized function.
     ///
-    /// The pointer remains valid until either [`JITModule::free_memory`] is called or in the future
-    /// some way of deallocating this individual function is used.
+    /// The pointer remains valid until either [`JITModule::free_memory`] is called or this
+    /// individual function is freed, either directly by [`JITModule::free_function`] or after
+    /// being redefined by [`JITModule::free_replaced_functions`].
+    ///
+    /// For a function defined by [`JITModule::define_function_lazily`] which hasn't been compiled
+    /// yet, this is the address of the stub compiling it.
     pub fn get_finalized_function(&self, func_id: FuncId) -> *const u8 {
         let info = &self.compiled_functions[func_id];
         assert!(
             !self.functions_to_finalize.iter().any(|x| *x == func_id),
             "function not yet finalized"
         );
-        info.as_ref()
-            .expect("function must be compiled before it can be finalized")
-            .ptr
+        match (info, self.lazy_functions.get(&func_id)) {
+            (None, Some(lazy)) => lazy.stub,
+            _ => {
+                info.as_ref()
+                    .expect("function must be compiled before it can be finalized")
+                    .ptr
+            }
+        }
     }
 
     /// Returns the address and size of a finalized data object.
     ///
-    /// The pointer remains valid until either [`JITModule::free_memory`] is called or in the future
-    /// some way of deallocating this individual data object is used.
+    /// The pointer remains valid until either [`JITModule::free_memory`] or
+    /// [`JITModule::free_data`] is called.
+    ///
+    /// Panics for thread-local data objects, see [`JITModule::get_finalized_tls_data`] instead.
     pub fn get_finalized_data(&self, data_id: DataId) -> (*const u8, usize) {
+        assert!(
+            self.tls_descriptors[data_id].is_none(),
+            "data object is thread-local"
+        );
         let info = &self.compiled_data_objects[data_id];
         assert!(
             !self.data_objects_to_finalize.iter().any(|x| *x == data_id),
@@ -428,6 +618,38 @@ impl JITModule {
         (compiled.ptr, compiled.size)
     }
 
+    /// Returns the address and size of the calling thread's copy of a finalized thread-local data
+    /// object.
+    ///
+    /// The pointer remains valid until the thread exits, or until either
+    /// [`JITModule::free_memory`] or [`JITModule::free_data`] is called.
+    pub fn get_finalized_tls_data(&self, data_id: DataId) -> (*mut u8, usize) {
+        assert!(
+            !self.data_objects_to_finalize.iter().any(|x| *x == data_id),
+            "data object not yet finalized"
+        );
+        let descriptor = self.tls_descriptors[data_id]
+            .expect("thread-local data object must be compiled before it can be finalized");
+        let size = self.compiled_data_objects[data_id].as_ref().unwrap().size;
+
+        (unsafe { descriptor.as_ref() }.address(), size)
+    }
+
+    /// Check that thread-local data objects can be accessed by compiled code.
+    fn check_tls_support(&self) -> ModuleResult<()> {
+        if !tls::SUPPORTED {
+            return Err(ModuleError::Backend(anyhow::anyhow!(
+                "JIT only supports TLS on x86_64 and aarch64 Linux"
+            )));
+        }
+        if self.isa.flags().tls_model() != TlsModel::ElfGd {
+            return Err(ModuleError::Backend(anyhow::anyhow!(
+                "JIT only supports TLS with `tls_model=elf_gd`"
+            )));
+        }
+        Ok(())
+    }
+
     fn record_function_for_perf(&self, ptr: *mut u8, size: usize, name: &str) {
         // The Linux perf tool supports JIT code via a /tmp/perf-$PID.map file,
         // which contains memory regions and their associated names.  If we
@@ -452,19 +674,11 @@ impl JITModule {
     /// Use `get_finalized_function` and `get_finalized_data` to obtain the final
     /// artifacts.
     ///
-    /// Returns ModuleError in case of allocation or syscall failure
+    /// Returns ModuleError in case of allocation or syscall failure, or if a relocation can't
+    /// reach its target
     pub fn finalize_definitions(&mut self) -> ModuleResult<()> {
         for func in std::mem::take(&mut self.functions_to_finalize) {
-            let decl = self.declarations.get_function_decl(func);
-            assert!(decl.linkage.is_definable());
-            let func = self.compiled_functions[func]
-                .as_ref()
-                .expect("function must be compiled before it can be finalized");
-            func.perform_relocations(
-                |name| self.get_address(name),
-                |name| self.get_got_address(name).as_ptr().cast(),
-                |name| self.get_plt_address(name),
-            );
+            self.perform_function_relocations(func)?;
         }
 
         for data in std::mem::take(&mut self.data_objects_to_finalize) {
@@ -477,16 +691,61 @@ impl JITModule {
                 |name| self.get_address(name),
                 |name| self.get_got_address(name).as_ptr().cast(),
                 |name| self.get_plt_address(name),
-            );
+            )?;
         }
 
         // Now that we're done patching, prepare the memory for execution!
         self.memory.readonly.set_readonly()?;
         self.memory.code.set_readable_and_executable()?;
+        self.memory.lazy_code.set_readable_and_executable()?;
 
         for update in self.pending_got_updates.drain(..) {
             unsafe { update.entry.as_ref() }.store(update.ptr as *mut _, Ordering::SeqCst);
         }
+
+        *self.lazy_resolver_module = self;
+        Ok(())
+    }
+
+    /// Perform the relocations of the defined function `func`.
+    fn perform_function_relocations(&self, func: FuncId) -> ModuleResult<()> {
+        let decl = self.declarations.get_function_decl(func);
+        assert!(decl.linkage.is_definable());
+        let func = self.compiled_functions[func]
+            .as_ref()
+            .expect("function must be compiled before it can be finalized");
+        func.perform_relocations(
+            |name| self.get_address(name),
+            |name| self.get_got_address(name).as_ptr().cast(),
+            |name| self.get_plt_address(name),
+        )
+    }
+
+    /// Finalize the lazily compiled function `func_id` alone, leaving any other pending
+    /// definitions to `finalize_definitions`.
+    fn finalize_lazy_function(&mut self, func_id: FuncId) -> ModuleResult<()> {
+        // With hotswapping, the relocations are performed when the function is defined.
+        if let Some(index) = self
+            .functions_to_finalize
+            .iter()
+            .position(|&id| id == func_id)
+        {
+            self.functions_to_finalize.remove(index);
+            self.perform_function_relocations(func_id)?;
+        }
+        self.memory.lazy_code.set_readable_and_executable()?;
+
+        if let Some(entry) = self.function_got_entries[func_id] {
+            let mut pending = Vec::new();
+            for update in self.pending_got_updates.drain(..) {
+                if update.entry == entry {
+                    unsafe { entry.as_ref() }.store(update.ptr as *mut _, Ordering::SeqCst);
+                } else {
+                    pending.push(update);
+                }
+            }
+            self.pending_got_updates = pending;
+        }
         Ok(())
     }
 
@@ -512,7 +771,8 @@ impl JITModule {
             lookup_symbols: builder.lookup_symbols,
             libcall_names: builder.libcall_names,
             memory: MemoryHandle {
-                code: Memory::new(branch_protection),
+                code: Memory::new(branch_protection.clone()),
+                lazy_code: Memory::new(branch_protection),
                 // Branch protection is not applicable to non-executable memory.
                 readonly: Memory::new(BranchProtection::None),
                 writable: Memory::new(BranchProtection::None),
@@ -525,8 +785,12 @@ impl JITModule {
             libcall_plt_entries: HashMap::new(),
             compiled_functions: SecondaryMap::new(),
             compiled_data_objects: SecondaryMap::new(),
+            tls_descriptors: SecondaryMap::new(),
             functions_to_finalize: Vec::new(),
             data_objects_to_finalize: Vec::new(),
+            replaced_functions: Vec::new(),
+            lazy_functions: HashMap::new(),
+            lazy_resolver_module: Box::new(ptr::null_mut()),
             pending_got_updates: Vec::new(),
         };
 
@@ -549,12 +813,191 @@ impl JITModule {
             module.libcall_plt_entries.insert(libcall, plt_entry);
         }
 
+        // Accesses to thread-local data objects always call the `ElfTlsGetAddr` libcall through
+        // the PLT, which must go to the JIT's own TLS support rather than to the system's.
+        if let Some(addr) = tls::tls_get_addr() {
+            let got_entry = module.new_got_entry(addr);
+            module
+                .libcall_got_entries
+                .insert(ir::LibCall::ElfTlsGetAddr, got_entry);
+            let plt_entry = module.new_plt_entry(got_entry);
+            module
+                .libcall_plt_entries
+                .insert(ir::LibCall::ElfTlsGetAddr, plt_entry);
+        }
+
         module
     }
 
+    /// Define a function whose body is only generated and compiled when it's first called.
+    ///
+    /// Until then, the address of the function is that of a stub which calls `generator` to
+    /// generate the function's body, compiles and finalizes it, and then continues into the
+    /// compiled function. Like other definitions, this requires a call to
+    /// [`JITModule::finalize_definitions`] before the function can be called.
+    ///
+    /// Calls to the function through the GOT and PLT, i.e. from PIC code, go directly to the
+    /// compiled function once it has been compiled, while other references keep calling the stub
+    /// which then continues into the compiled function.
+    ///
+    /// The generator is given the module, e.g. to declare the functions and data objects the
+    /// function refers to, and a context whose function already has the declared signature. Only
+    /// the function itself is finalized when it is compiled, so any definitions the generator
+    /// makes are left to the next call to `finalize_definitions`.
+    ///
+    /// If generating or compiling the function fails, the error is logged and the stub traps with
+    /// [`LAZY_FUNCTION_TRAP_CODE`].
+    ///
+    /// # Safety
+    ///
+    /// The stub refers to the module, so while the function may be called, the module must
+    /// neither be borrowed nor moved since the last call to `finalize_definitions`, and must not
+    /// be dropped. The function must only be called from the thread which owns the module, and
+    /// none of the definitions referenced by the function may be pending when it is compiled.
+    pub unsafe fn define_function_lazily(
+        &mut self,
+        func_id: FuncId,
+        generator: impl FnOnce(&mut JITModule, &mut cranelift_codegen::Context) -> ModuleResult<()>
+            + 'static,
+    ) -> ModuleResult<()> {
+        info!("defining function {} lazily", func_id);
+        let decl = self.declarations.get_function_decl(func_id);
+        if !decl.linkage.is_definable() {
+            return Err(ModuleError::InvalidImportDefinition(
+                decl.linkage_name(func_id).into_owned(),
+            ));
+        }
+
+        if self.compiled_functions[func_id].is_some() || self.lazy_functions.contains_key(&func_id)
+        {
+            return Err(ModuleError::DuplicateDefinition(
+                decl.linkage_name(func_id).into_owned(),
+            ));
+        }
+
+        let name = format!("{}@lazy", decl.linkage_name(func_id));
+        let (stub, size) = self.compile_lazy_stub(func_id)?;
+        self.record_function_for_perf(stub, size, &name);
+        if self.isa.flags().is_pic() {
+            self.pending_got_updates.push(GotUpdate {
+                entry: self.function_got_entries[func_id].unwrap(),
+                ptr: stub,
+            })
+        }
+        self.lazy_functions.insert(
+            func_id,
+            LazyFunction {
+                stub,
+                generator: Some(Box::new(generator)),
+            },
+        );
+        Ok(())
+    }
+
+    /// Compile the stub of a lazily compiled function, which has the same signature as the
+    /// function. It calls `resolve_lazy_function` to compile the function and then calls it with
+    /// its own arguments.
+    fn compile_lazy_stub(&mut self, func_id: FuncId) -> ModuleResult<(*mut u8, usize)> {
+        let signature = self
+            .declarations
+            .get_function_decl(func_id)
+            .signature
+            .clone();
+        let pointer_type = self.isa.pointer_type();
+        let mut resolver_signature =
+            ir::Signature::new(CallConv::triple_default(self.isa.triple()));
+        resolver_signature.params.extend([
+            ir::AbiParam::new(pointer_type),
+            ir::AbiParam::new(ir::types::I32),
+        ]);
+        resolver_signature
+            .returns
+            .push(ir::AbiParam::new(pointer_type));
+
+        let mut func = ir::Function::with_name_signature(Default::default(), signature.clone());
+        let resolver_sig_ref = func.import_signature(resolver_signature);
+        let sig_ref = func.import_signature(signature);
+        let block = func.dfg.make_block();
+        func.layout.append_block(block);
+        let params = func.signature.params.clone();
+        let args = params
+            .iter()
+            .map(|param| func.dfg.append_block_param(block, param.value_type))
+            .collect::<Vec<_>>();
+
+        let mut pos = FuncCursor::new(&mut func).at_bottom(block);
+        let resolver = pos
+            .ins()
+            .iconst(pointer_type, resolve_lazy_function as *const u8 as i64);
+        let module = pos
+            .ins()
+            .iconst(pointer_type, &*self.lazy_resolver_module as *const _ as i64);
+        let id = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(func_id.as_u32()));
+        let call = pos
+            .ins()
+            .call_indirect(resolver_sig_ref, resolver, &[module, id]);
+        let callee = pos.func.dfg.first_result(call);
+        pos.ins().trapz(callee, LAZY_FUNCTION_TRAP_CODE);
+        let call = pos.ins().call_indirect(sig_ref, callee, &args);
+        let results = pos.func.dfg.inst_results(call).to_vec();
+        pos.ins().return_(&results);
+
+        let mut ctx = cranelift_codegen::Context::for_function(func);
+        let compiled_code = ctx.compile(&*self.isa, &mut ControlPlane::default())?;
+        let size = compiled_code.code_info().total_size as usize;
+        let align = u64::from(compiled_code.buffer.alignment)
+            .max(self.isa.function_alignment().minimum as u64)
+            .max(self.isa.symbol_alignment());
+        let ptr = self
+            .memory
+            .code
+            .allocate(size, align)
+            .map_err(|e| ModuleError::Allocation {
+                message: "unable to alloc lazy function stub",
+                err: e,
+            })?;
+        unsafe {
+            ptr::copy_nonoverlapping(compiled_code.code_buffer().as_ptr(), ptr, size);
+        }
+        Ok((ptr, size))
+    }
+
+    /// Compile a function defined by `define_function_lazily` if it hasn't been yet, returning
+    /// its address.
+    fn resolve_lazy_function(&mut self, func_id: FuncId) -> ModuleResult<*const u8> {
+        if self.compiled_functions[func_id].is_some() {
+            return Ok(self.get_finalized_function(func_id));
+        }
+
+        let decl = self.declarations.get_function_decl(func_id);
+        let generator = self
+            .lazy_functions
+            .get_mut(&func_id)
+            .and_then(|lazy| lazy.generator.take())
+            .ok_or_else(|| {
+                ModuleError::Backend(anyhow::anyhow!(
+                    "Lazily compiled function {} is not defined anymore",
+                    decl.linkage_name(func_id),
+                ))
+            })?;
+
+        let mut ctx = self.make_context();
+        ctx.func.signature = decl.signature.clone();
+        ctx.func.name = ir::UserFuncName::user(0, func_id.as_u32());
+        generator(self, &mut ctx)?;
+        self.define_function(func_id, &mut ctx)?;
+        self.finalize_lazy_function(func_id)?;
+        Ok(self.get_finalized_function(func_id))
+    }
+
     /// Allow a single future `define_function` on a previously defined function. This allows for
     /// hot code swapping and lazy compilation of functions.
     ///
+    /// The code of the previous definition is kept until [`JITModule::free_replaced_functions`]
+    /// is called.
+    ///
     /// This requires hotswap support to be enabled first using [`JITBuilder::hotswap`].
     pub fn prepare_for_function_redefine(&mut self, func_id: FuncId) -> ModuleResult<()> {
         assert!(self.hotswap_enabled, "Hotswap support is not enabled");
@@ -572,9 +1015,10 @@ impl JITModule {
             )));
         }
 
-        self.compiled_functions[func_id] = None;
-
-        // FIXME return some kind of handle that allows for deallocating the function
+        // The old definition may still be executing, so it can only be freed once the caller
+        // knows that it isn't anymore.
+        let replaced = self.compiled_functions[func_id].take().unwrap();
+        self.replaced_functions.push(replaced.ptr);
 
         Ok(())
     }
@@ -625,7 +1069,9 @@ impl Module for JITModule {
         writable: bool,
         tls: bool,
     ) -> ModuleResult<DataId> {
-        assert!(!tls, "JIT doesn't yet support TLS");
+        if tls {
+            self.check_tls_support()?;
+        }
         let (id, linkage) = self
             .declarations
             .declare_data(name, linkage, writable, tls)?;
@@ -642,7 +1088,9 @@ impl Module for JITModule {
     }
 
     fn declare_anonymous_data(&mut self, writable: bool, tls: bool) -> ModuleResult<DataId> {
-        assert!(!tls, "JIT doesn't yet support TLS");
+        if tls {
+            self.check_tls_support()?;
+        }
         let id = self.declarations.declare_anonymous_data(writable, tls)?;
         if self.isa.flags().is_pic() {
             self.new_data_got_entry(id, std::ptr::null());
@@ -696,7 +1144,7 @@ impl Module for JITModule {
             .max(self.isa.symbol_alignment());
         let ptr = self
             .memory
-            .code
+            .code_memory(self.lazy_functions.contains_key(&id))
             .allocate(size, align)
             .map_err(|e| ModuleError::Allocation {
                 message: "unable to alloc function",
@@ -744,7 +1192,7 @@ impl Module for JITModule {
                     },
                     |name| self.get_got_address(name).as_ptr().cast(),
                     |name| self.get_plt_address(name),
-                );
+                )?;
         } else {
             self.functions_to_finalize.push(id);
         }
@@ -780,7 +1228,7 @@ impl Module for JITModule {
             .max(self.isa.symbol_alignment());
         let ptr = self
             .memory
-            .code
+            .code_memory(self.lazy_functions.contains_key(&id))
             .allocate(size, align)
             .map_err(|e| ModuleError::Allocation {
                 message: "unable to alloc function bytes",
@@ -816,7 +1264,7 @@ impl Module for JITModule {
                     |name| unreachable!("non GOT or PLT relocation in function {} to {}", id, name),
                     |name| self.get_got_address(name).as_ptr().cast(),
                     |name| self.get_plt_address(name),
-                );
+                )?;
         } else {
             self.functions_to_finalize.push(id);
         }
@@ -838,8 +1286,6 @@ impl Module for JITModule {
             ));
         }
 
-        assert!(!decl.tls, "JIT doesn't yet support TLS");
-
         let &DataDescription {
             ref init,
             function_decls: _,
@@ -904,9 +1350,28 @@ impl Module for JITModule {
         };
         let relocs = data.all_relocs(pointer_reloc).collect::<Vec<_>>();
 
+        if decl.tls {
+            // The data is the initial contents of each thread's copy of the object.
+            let align = align.unwrap_or(WRITABLE_DATA_ALIGNMENT);
+            let descriptor = self
+                .memory
+                .writable
+                .allocate(
+                    std::mem::size_of::<TlsDescriptor>(),
+                    std::mem::align_of::<TlsDescriptor>().try_into().unwrap(),
+                )
+                .map_err(|e| ModuleError::Allocation {
+                    message: "unable to alloc TLS descriptor",
+                    err: e,
+                })?
+                .cast::<TlsDescriptor>();
+            unsafe { ptr::write(descriptor, TlsDescriptor::new(ptr, size, align)) };
+            self.tls_descriptors[id] = NonNull::new(descriptor);
+        }
+
         self.compiled_data_objects[id] = Some(CompiledBlob { ptr, size, relocs });
         self.data_objects_to_finalize.push(id);
-        if self.isa.flags().is_pic() {
+        if self.isa.flags().is_pic() && !decl.tls {
             self.pending_got_updates.push(GotUpdate {
                 entry: self.data_object_got_entries[id].unwrap(),
                 ptr,
@@ -993,3 +1458,30 @@ fn use_bti(isa_flags: &Vec<settings::Value>) -> bool {
         .find(|&f| f.name == "use_bti")
         .map_or(false, |f| f.as_bool().unwrap_or(false))
 }
+
+/// Called by the stubs of lazily compiled functions to compile them, returning the address of
+/// the compiled function, or null if compiling it failed.
+///
+/// # Safety
+///
+/// `module` must point to a pointer to a module which isn't borrowed anywhere else, as required
+/// by [`JITModule::define_function_lazily`], and `func_id` must be a lazily defined function of
+/// the module.
+unsafe extern "C" fn resolve_lazy_function(
+    module: *const *mut JITModule,
+    func_id: u32,
+) -> *const u8 {
+    let module = &mut **module;
+    let func_id = FuncId::from_u32(func_id);
+    match module.resolve_lazy_function(func_id) {
+        Ok(ptr) => ptr,
+        Err(e) => {
+            // The stub traps, so the error can only be reported here.
+            error!(
+                "failed to compile lazily defined function {}: {}",
+                func_id, e
+            );
+            ptr::null()
+        }
+    }
+}
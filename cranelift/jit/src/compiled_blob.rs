@@ -1,6 +1,7 @@
 use cranelift_codegen::binemit::Reloc;
 use cranelift_module::ModuleReloc;
 use cranelift_module::ModuleRelocTarget;
+use cranelift_module::{ModuleError, ModuleResult};
 use std::convert::TryFrom;
 
 /// Reads a 32bit instruction at `iptr`, and writes it again after
@@ -11,6 +12,22 @@ unsafe fn modify_inst32(iptr: *mut u32, modifier: impl FnOnce(u32) -> u32) {
     iptr.write_unaligned(new_inst);
 }
 
+/// The error for a relocation at `at` which can't reach `name`.
+fn out_of_range(name: &ModuleRelocTarget, at: *const u8, reach: &str) -> ModuleError {
+    ModuleError::Backend(anyhow::anyhow!(
+        "can't reach {} from {:p} with {}",
+        name,
+        at,
+        reach
+    ))
+}
+
+/// The 32-bit PC-relative offset of `what` from `at`, relocated for `name`.
+fn pcrel32(name: &ModuleRelocTarget, what: *const u8, at: *const u8) -> ModuleResult<i32> {
+    i32::try_from((what as isize) - (at as isize))
+        .map_err(|_| out_of_range(name, at, "a ±2GB PC-relative offset"))
+}
+
 #[derive(Clone)]
 pub(crate) struct CompiledBlob {
     pub(crate) ptr: *mut u8,
@@ -19,12 +36,13 @@ pub(crate) struct CompiledBlob {
 }
 
 impl CompiledBlob {
+    /// Perform the relocations of this blob, failing if a relocation can't reach its target.
     pub(crate) fn perform_relocations(
         &self,
         get_address: impl Fn(&ModuleRelocTarget) -> *const u8,
         get_got_entry: impl Fn(&ModuleRelocTarget) -> *const u8,
         get_plt_entry: impl Fn(&ModuleRelocTarget) -> *const u8,
-    ) {
+    ) -> ModuleResult<()> {
         use std::ptr::write_unaligned;
 
         for &ModuleReloc {
@@ -54,25 +72,26 @@ impl CompiledBlob {
                 Reloc::X86PCRel4 | Reloc::X86CallPCRel4 => {
                     let base = get_address(name);
                     let what = unsafe { base.offset(isize::try_from(addend).unwrap()) };
-                    let pcrel = i32::try_from((what as isize) - (at as isize)).unwrap();
+                    let pcrel = pcrel32(name, what, at)?;
                     unsafe { write_unaligned(at as *mut i32, pcrel) };
                 }
                 Reloc::X86GOTPCRel4 => {
                     let base = get_got_entry(name);
                     let what = unsafe { base.offset(isize::try_from(addend).unwrap()) };
-                    let pcrel = i32::try_from((what as isize) - (at as isize)).unwrap();
+                    let pcrel = pcrel32(name, what, at)?;
                     unsafe { write_unaligned(at as *mut i32, pcrel) };
                 }
                 Reloc::X86CallPLTRel4 => {
                     let base = get_plt_entry(name);
                     let what = unsafe { base.offset(isize::try_from(addend).unwrap()) };
-                    let pcrel = i32::try_from((what as isize) - (at as isize)).unwrap();
+                    let pcrel = pcrel32(name, what, at)?;
                     unsafe { write_unaligned(at as *mut i32, pcrel) };
                 }
                 Reloc::S390xPCRel32Dbl | Reloc::S390xPLTRel32Dbl => {
                     let base = get_address(name);
                     let what = unsafe { base.offset(isize::try_from(addend).unwrap()) };
-                    let pcrel = i32::try_from(((what as isize) - (at as isize)) >> 1).unwrap();
+                    let pcrel = i32::try_from(((what as isize) - (at as isize)) >> 1)
+                        .map_err(|_| out_of_range(name, at, "a ±4GB PC-relative offset"))?;
                     unsafe { write_unaligned(at as *mut i32, pcrel) };
                 }
                 Reloc::Arm64Call => {
@@ -86,7 +105,9 @@ impl CompiledBlob {
                     // included bits, so the result is expected to be
                     // either all sign bits or 0, depending on if the original
                     // value was negative or positive.
-                    assert!((diff >> 26 == -1) || (diff >> 26 == 0));
+                    if diff >> 26 != -1 && diff >> 26 != 0 {
+                        return Err(out_of_range(name, at, "a ±128MB `bl` instruction"));
+                    }
                     // The lower 26 bits of the `bl` instruction form the
                     // immediate offset argument.
                     let chop = 32 - 26;
@@ -100,10 +121,9 @@ impl CompiledBlob {
                     let what_page = (what as usize) & !0xfff;
                     let at_page = (at as usize) & !0xfff;
                     let pcrel = (what_page as isize).checked_sub(at_page as isize).unwrap();
-                    assert!(
-                        (-1 << 32) <= pcrel && pcrel < (1 << 32),
-                        "can't reach GOT page with ±4GB `adrp` instruction"
-                    );
+                    if !((-1 << 32) <= pcrel && pcrel < (1 << 32)) {
+                        return Err(out_of_range(name, at, "a ±4GB `adrp` instruction"));
+                    }
                     let val = pcrel >> 12;
 
                     let immlo = ((val as u32) & 0b11) << 29;
@@ -122,6 +142,53 @@ impl CompiledBlob {
                     let mask = !(0x1ff << 10);
                     unsafe { modify_inst32(at as *mut u32, |ldr| (ldr & mask) | imm9) };
                 }
+                Reloc::ElfX86_64TlsGd => {
+                    // The address of the TLS descriptor, which is passed to the `ElfTlsGetAddr`
+                    // libcall.
+                    let base = get_address(name);
+                    let what = unsafe { base.offset(isize::try_from(addend).unwrap()) };
+                    let pcrel = pcrel32(name, what, at)?;
+                    unsafe { write_unaligned(at as *mut i32, pcrel) };
+                }
+                Reloc::Aarch64TlsDescAdrPage21 => {
+                    // Set the immediate value of an ADRP to bits [32:12] of the TLS descriptor's
+                    // page offset.
+                    assert_eq!(addend, 0);
+                    let what = get_address(name);
+                    let what_page = (what as usize) & !0xfff;
+                    let at_page = (at as usize) & !0xfff;
+                    let pcrel = (what_page as isize).checked_sub(at_page as isize).unwrap();
+                    if !((-1 << 32) <= pcrel && pcrel < (1 << 32)) {
+                        return Err(out_of_range(name, at, "a ±4GB `adrp` instruction"));
+                    }
+                    let val = pcrel >> 12;
+
+                    let immlo = ((val as u32) & 0b11) << 29;
+                    let immhi = (((val as u32) >> 2) & &0x7ffff) << 5;
+                    let mask = !((0x7ffff << 5) | (0b11 << 29));
+                    unsafe { modify_inst32(at as *mut u32, |adrp| (adrp & mask) | immlo | immhi) };
+                }
+                Reloc::Aarch64TlsDescLd64Lo12 => {
+                    // Set the LD/ST immediate field to bits 11:3 of the TLS descriptor's address.
+                    assert_eq!(addend, 0);
+                    let what = get_address(name) as u32;
+                    assert_eq!(what & 0b111, 0);
+                    let val = (what & 0xfff) >> 3;
+                    let imm12 = val << 10;
+                    let mask = !(0xfff << 10);
+                    unsafe { modify_inst32(at as *mut u32, |ldr| (ldr & mask) | imm12) };
+                }
+                Reloc::Aarch64TlsDescAddLo12 => {
+                    // Set the ADD immediate field to bits 11:0 of the TLS descriptor's address.
+                    assert_eq!(addend, 0);
+                    let what = get_address(name) as u32;
+                    let imm12 = (what & 0xfff) << 10;
+                    let mask = !(0xfff << 10);
+                    unsafe { modify_inst32(at as *mut u32, |add| (add & mask) | imm12) };
+                }
+                Reloc::Aarch64TlsDescCall => {
+                    // Only marks the call to the descriptor's resolver, for linker relaxation.
+                }
                 Reloc::RiscvCallPlt => {
                     // A R_RISCV_CALL_PLT relocation expects auipc+jalr instruction pair.
                     // It is the equivalent of two relocations:
@@ -130,7 +197,7 @@ impl CompiledBlob {
 
                     let base = get_address(name);
                     let what = unsafe { base.offset(isize::try_from(addend).unwrap()) };
-                    let pcrel = i32::try_from((what as isize) - (at as isize)).unwrap() as u32;
+                    let pcrel = pcrel32(name, what, at)? as u32;
 
                     // See https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-elf.adoc#pc-relative-symbol-addresses
                     // for a better explanation of the following code.
@@ -161,5 +228,6 @@ impl CompiledBlob {
                 _ => unimplemented!(),
             }
         }
+        Ok(())
     }
 }
@@ -8,8 +8,9 @@
 mod backend;
 mod compiled_blob;
 mod memory;
+mod tls;
 
-pub use crate::backend::{JITBuilder, JITModule};
+pub use crate::backend::{JITBuilder, JITModule, LAZY_FUNCTION_TRAP_CODE};
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -5,10 +5,12 @@ use memmap2::MmapMut;
 
 #[cfg(not(any(feature = "selinux-fix", windows)))]
 use std::alloc;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::ffi::c_void;
 use std::io;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use wasmtime_jit_icache_coherence as icache_coherence;
 
@@ -19,6 +21,10 @@ struct PtrLen {
 
     ptr: *mut u8,
     len: usize,
+
+    /// The allocations in this region which haven't been freed, as the end offset of each
+    /// allocation keyed by its start offset.
+    live: BTreeMap<usize, usize>,
 }
 
 impl PtrLen {
@@ -30,9 +36,43 @@ impl PtrLen {
 
             ptr: ptr::null_mut(),
             len: 0,
+            live: BTreeMap::new(),
         }
     }
 
+    /// Whether `ptr` points into this region.
+    fn contains(&self, ptr: *const u8) -> bool {
+        let start = self.ptr as usize;
+        (start..start + self.len).contains(&(ptr as usize))
+    }
+
+    /// The address ranges of the whole pages within `range`, a range of offsets in this region,
+    /// which don't overlap any live allocation.
+    fn unused_pages(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let page_size = region::page::size();
+        let mut pages = Vec::new();
+        let mut page = range.start - range.start % page_size;
+        while page < range.end.min(self.len) {
+            let end = page + page_size;
+            // Allocations start in order, so only the last one starting before the end of the
+            // page may overlap it.
+            let used = self
+                .live
+                .range(..end)
+                .next_back()
+                .is_some_and(|(_, &alloc_end)| alloc_end > page);
+            if !used {
+                let start = self.ptr as usize + page;
+                match pages.last_mut() {
+                    Some(Range { end, .. }) if *end == start => *end += page_size,
+                    _ => pages.push(start..start + page_size),
+                }
+            }
+            page = end;
+        }
+        pages
+    }
+
     /// Create a new `PtrLen` pointing to at least `size` bytes of memory,
     /// suitably sized and aligned for memory protection.
     #[cfg(all(not(target_os = "windows"), feature = "selinux-fix"))]
@@ -45,6 +85,7 @@ impl PtrLen {
                 ptr: mmap.as_mut_ptr(),
                 map: Some(mmap),
                 len: alloc_size,
+                live: BTreeMap::new(),
             }
         })
     }
@@ -62,6 +103,7 @@ impl PtrLen {
             Ok(Self {
                 ptr,
                 len: alloc_size,
+                live: BTreeMap::new(),
             })
         } else {
             Err(io::Error::from(io::ErrorKind::OutOfMemory))
@@ -87,6 +129,7 @@ impl PtrLen {
             Ok(Self {
                 ptr: ptr as *mut u8,
                 len: region::page::ceil(size),
+                live: BTreeMap::new(),
             })
         } else {
             Err(io::Error::last_os_error())
@@ -110,7 +153,18 @@ impl Drop for PtrLen {
     }
 }
 
-// TODO: add a `Drop` impl for `cfg(target_os = "windows")`
+#[cfg(target_os = "windows")]
+impl Drop for PtrLen {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            use windows_sys::Win32::System::Memory::{VirtualFree, MEM_RELEASE};
+
+            unsafe {
+                VirtualFree(self.ptr.cast(), 0, MEM_RELEASE);
+            }
+        }
+    }
+}
 
 /// Type of branch protection to apply to executable memory.
 #[derive(Clone, Debug, PartialEq)]
@@ -124,12 +178,18 @@ pub(crate) enum BranchProtection {
 /// JIT memory manager. This manages pages of suitably aligned and
 /// accessible memory. Memory will be leaked by default to have
 /// function pointers remain valid for the remainder of the
-/// program's life.
+/// program's life, unless it is explicitly freed.
 pub(crate) struct Memory {
     allocations: Vec<PtrLen>,
     already_protected: usize,
     current: PtrLen,
     position: usize,
+    /// Whole pages of `allocations` which don't hold any live allocation, as sorted and disjoint
+    /// address ranges. These are reused before allocating new regions.
+    free_pages: Vec<Range<usize>>,
+    /// Reused pages of already protected regions, which are protected again along with the new
+    /// regions.
+    reused_pages: Vec<Range<usize>>,
     branch_protection: BranchProtection,
 }
 
@@ -140,16 +200,73 @@ impl Memory {
             already_protected: 0,
             current: PtrLen::new(),
             position: 0,
+            free_pages: Vec::new(),
+            reused_pages: Vec::new(),
             branch_protection,
         }
     }
 
     fn finish_current(&mut self) {
-        self.allocations
-            .push(mem::replace(&mut self.current, PtrLen::new()));
+        let current = mem::replace(&mut self.current, PtrLen::new());
+        // Regions whose allocations were all freed already are released right away.
+        if !current.live.is_empty() {
+            for pages in current.unused_pages(0..current.len) {
+                self.add_free_pages(pages);
+            }
+            self.allocations.push(current);
+        }
         self.position = 0;
     }
 
+    /// Add `pages` to the free pages, merging it with adjacent ranges.
+    fn add_free_pages(&mut self, pages: Range<usize>) {
+        let index = self
+            .free_pages
+            .partition_point(|free| free.start < pages.start);
+        let merge_prev = index > 0 && self.free_pages[index - 1].end == pages.start;
+        let merge_next = index < self.free_pages.len() && self.free_pages[index].start == pages.end;
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                self.free_pages[index - 1].end = self.free_pages[index].end;
+                self.free_pages.remove(index);
+            }
+            (true, false) => self.free_pages[index - 1].end = pages.end,
+            (false, true) => self.free_pages[index].start = pages.start,
+            (false, false) => self.free_pages.insert(index, pages),
+        }
+    }
+
+    /// Allocate `size` bytes from the first free pages that are large enough, if any.
+    fn allocate_free_pages(&mut self, size: usize) -> io::Result<Option<*mut u8>> {
+        let len = region::page::ceil(size);
+        let Some(index) = self.free_pages.iter().position(|free| free.len() >= len) else {
+            return Ok(None);
+        };
+        let start = self.free_pages[index].start;
+        if self.free_pages[index].len() == len {
+            self.free_pages.remove(index);
+        } else {
+            self.free_pages[index].start += len;
+        }
+
+        let index = self
+            .allocations
+            .iter()
+            .position(|region| region.contains(start as *const u8))
+            .unwrap();
+        let region = &mut self.allocations[index];
+        let offset = start - region.ptr as usize;
+        region.live.insert(offset, offset + size);
+        if index < self.already_protected {
+            unsafe {
+                region::protect(start as *const u8, len, region::Protection::READ_WRITE)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            }
+            self.reused_pages.push(start..start + len);
+        }
+        Ok(Some(start as *mut u8))
+    }
+
     pub(crate) fn allocate(&mut self, size: usize, align: u64) -> io::Result<*mut u8> {
         let align = usize::try_from(align).expect("alignment too big");
         if self.position % align != 0 {
@@ -160,19 +277,84 @@ impl Memory {
         if size <= self.current.len - self.position {
             // TODO: Ensure overflow is not possible.
             let ptr = unsafe { self.current.ptr.add(self.position) };
+            self.current
+                .live
+                .insert(self.position, self.position + size);
             self.position += size;
             return Ok(ptr);
         }
 
+        // Free pages are page aligned.
+        if align <= region::page::size() {
+            if let Some(ptr) = self.allocate_free_pages(size)? {
+                return Ok(ptr);
+            }
+        }
+
         self.finish_current();
 
         // TODO: Allocate more at a time.
         self.current = PtrLen::with_size(size)?;
         self.position = size;
+        self.current.live.insert(0, size);
 
         Ok(self.current.ptr)
     }
 
+    /// Whether `ptr` points into memory allocated by this `Memory`.
+    pub(crate) fn contains(&self, ptr: *const u8) -> bool {
+        self.current.contains(ptr) || self.allocations.iter().any(|region| region.contains(ptr))
+    }
+
+    /// Free an allocation made by `allocate`.
+    ///
+    /// Memory is managed in regions of whole pages which may hold several allocations, so a
+    /// region is only released once all of its allocations have been freed. Until then, the
+    /// pages of the region which no longer hold any allocation are reused by later allocations,
+    /// which makes them writable again until the memory is protected the next time. Pages shared
+    /// with other allocations aren't reused, since those may be executing.
+    ///
+    /// # Safety
+    ///
+    /// The allocation must not be accessed anymore.
+    pub(crate) unsafe fn free(&mut self, ptr: *const u8) {
+        if self.current.contains(ptr) {
+            // The free pages of the current region are only known once it is finished, and it
+            // is released then if it is unused.
+            let offset = ptr as usize - self.current.ptr as usize;
+            self.current.live.remove(&offset);
+            return;
+        }
+
+        let index = self
+            .allocations
+            .iter()
+            .position(|region| region.contains(ptr))
+            .expect("pointer was not allocated by this memory");
+        let region = &mut self.allocations[index];
+        let offset = ptr as usize - region.ptr as usize;
+        let end = region
+            .live
+            .remove(&offset)
+            .expect("pointer was already freed");
+        if region.live.is_empty() {
+            let region = self.allocations.remove(index);
+            if index < self.already_protected {
+                self.already_protected -= 1;
+            }
+            let start = region.ptr as usize;
+            let end = start + region.len;
+            self.free_pages
+                .retain(|free| free.end <= start || free.start >= end);
+            self.reused_pages
+                .retain(|reused| reused.end <= start || reused.start >= end);
+        } else {
+            for pages in region.unused_pages(offset..end) {
+                self.add_free_pages(pages);
+            }
+        }
+    }
+
     /// Set all memory allocated in this `Memory` up to now as readable and executable.
     pub(crate) fn set_readable_and_executable(&mut self) -> ModuleResult<()> {
         self.finish_current();
@@ -181,7 +363,7 @@ impl Memory {
         //
         // Do this before marking the memory as R+X, technically we should be able to do it after
         // but there are some CPU's that have had errata about doing this with read only memory.
-        for &PtrLen { ptr, len, .. } in self.non_protected_allocations_iter() {
+        for (ptr, len) in self.non_protected_ranges() {
             unsafe {
                 icache_coherence::clear_cache(ptr as *const c_void, len)
                     .expect("Failed cache clear")
@@ -217,7 +399,7 @@ impl Memory {
             Ok(())
         };
 
-        for &PtrLen { ptr, len, .. } in self.non_protected_allocations_iter() {
+        for (ptr, len) in self.non_protected_ranges() {
             set_region_readable_and_executable(ptr, len)?;
         }
 
@@ -225,6 +407,7 @@ impl Memory {
         icache_coherence::pipeline_flush_mt().expect("Failed pipeline flush");
 
         self.already_protected = self.allocations.len();
+        self.reused_pages.clear();
         Ok(())
     }
 
@@ -232,7 +415,7 @@ impl Memory {
     pub(crate) fn set_readonly(&mut self) -> ModuleResult<()> {
         self.finish_current();
 
-        for &PtrLen { ptr, len, .. } in self.non_protected_allocations_iter() {
+        for (ptr, len) in self.non_protected_ranges() {
             unsafe {
                 region::protect(ptr, len, region::Protection::READ).map_err(|e| {
                     ModuleError::Backend(
@@ -243,6 +426,7 @@ impl Memory {
         }
 
         self.already_protected = self.allocations.len();
+        self.reused_pages.clear();
         Ok(())
     }
 
@@ -257,11 +441,24 @@ impl Memory {
         return iter.filter(|&PtrLen { len, .. }| *len != 0);
     }
 
+    /// Iterates the address ranges of non protected memory allocations and reused pages.
+    fn non_protected_ranges(&self) -> impl Iterator<Item = (*mut u8, usize)> + '_ {
+        self.non_protected_allocations_iter()
+            .map(|&PtrLen { ptr, len, .. }| (ptr, len))
+            .chain(
+                self.reused_pages
+                    .iter()
+                    .map(|pages| (pages.start as *mut u8, pages.len())),
+            )
+    }
+
     /// Frees all allocated memory regions that would be leaked otherwise.
     /// Likely to invalidate existing function pointers, causing unsafety.
     pub(crate) unsafe fn free_memory(&mut self) {
         self.allocations.clear();
         self.already_protected = 0;
+        self.free_pages.clear();
+        self.reused_pages.clear();
     }
 }
 
@@ -273,3 +470,75 @@ impl Drop for Memory {
             .for_each(mem::forget);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_releases_regions() {
+        let mut memory = Memory::new(BranchProtection::None);
+        let a = memory.allocate(16, 8).unwrap();
+        let b = memory.allocate(16, 8).unwrap();
+        let c = memory.allocate(region::page::size() + 1, 8).unwrap();
+        memory.set_readable_and_executable().unwrap();
+        assert_eq!(memory.allocations.len(), 2);
+
+        unsafe {
+            // `a` and `b` share a region, which is released with the last of them.
+            memory.free(a);
+            assert_eq!(memory.allocations.len(), 2);
+            memory.free(b);
+            assert_eq!(memory.allocations.len(), 1);
+            memory.free(c);
+        }
+        assert!(memory.allocations.is_empty());
+        assert_eq!(memory.already_protected, 0);
+
+        // A region whose allocations are all freed before it is finished is never kept.
+        let d = memory.allocate(16, 8).unwrap();
+        unsafe { memory.free(d) };
+        memory.set_readonly().unwrap();
+        assert!(memory.allocations.is_empty());
+    }
+
+    #[test]
+    fn free_pages_are_reused() {
+        let page_size = region::page::size();
+        let mut memory = Memory::new(BranchProtection::None);
+        // `a` covers the first page of a region of two pages, whose second page `b` shares.
+        let a = memory.allocate(page_size + 1, 8).unwrap();
+        let b = memory.allocate(16, 8).unwrap();
+        memory.set_readonly().unwrap();
+        assert!(memory.free_pages.is_empty());
+
+        unsafe { memory.free(a) };
+        assert_eq!(memory.free_pages, [a as usize..a as usize + page_size]);
+
+        // The free page is made writable again, and protected along with the new regions.
+        let c = memory.allocate(16, 8).unwrap();
+        assert_eq!(c, a);
+        assert!(memory.free_pages.is_empty());
+        unsafe { c.write(1) };
+        memory.set_readonly().unwrap();
+        assert!(memory.reused_pages.is_empty());
+        assert_eq!(memory.allocations.len(), 1);
+
+        // Pages freed before their region is finished are only reused afterwards.
+        unsafe {
+            memory.free(b);
+            memory.free(c);
+        }
+        let d = memory.allocate(page_size + 1, 8).unwrap();
+        let e = memory.allocate(16, 8).unwrap();
+        unsafe { memory.free(d) };
+        assert!(memory.free_pages.is_empty());
+        memory.set_readonly().unwrap();
+        assert_eq!(memory.free_pages, [d as usize..d as usize + page_size]);
+
+        // Releasing a region drops its free pages.
+        unsafe { memory.free(e) };
+        assert!(memory.allocations.is_empty());
+        assert!(memory.free_pages.is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! Runtime support for thread-local data objects.
+//!
+//! Compiled code refers to a thread-local data object through a [`TlsDescriptor`], which is
+//! allocated close to the code so that PC-relative relocations can reach it. The descriptor
+//! identifies the object by a key, which is reused by new objects once the object is freed, and
+//! points to the object's initial contents. Each thread lazily allocates its own copy of the
+//! object the first time the thread accesses it, and frees it when the thread exits.
+//!
+//! Only the general dynamic TLS model of ELF (`tls_model=elf_gd`) is supported:
+//!
+//! * On x86_64 compiled code calls the `ElfTlsGetAddr` libcall with the address of the
+//!   descriptor, which returns the address of the calling thread's copy of the object.
+//! * On aarch64 compiled code calls the resolver at the start of the descriptor (TLSDESC), which
+//!   returns the offset of the calling thread's copy from the thread pointer.
+
+use std::alloc::Layout;
+use std::cell::RefCell;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Whether thread-local data objects are supported on the host.
+pub(crate) const SUPPORTED: bool = cfg!(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+));
+
+/// Describes a thread-local data object to the compiled code accessing it.
+///
+/// The layout of the first two fields is that of a TLSDESC descriptor on aarch64.
+#[repr(C)]
+pub(crate) struct TlsDescriptor {
+    /// The function returning the offset of the object from the thread pointer, on aarch64.
+    resolver: *const u8,
+    /// The key of the object in the blocks of each thread.
+    key: usize,
+    /// The number of objects which used `key` before this one.
+    generation: usize,
+    /// The initial contents of the object.
+    init: *const u8,
+    /// The size of the object.
+    size: usize,
+    /// The layout of each thread's copy of the object, which isn't empty.
+    layout: Layout,
+}
+
+impl TlsDescriptor {
+    /// Create a descriptor for a new thread-local data object with the initial contents at
+    /// `init`, which must stay valid as long as the object may be accessed.
+    pub(crate) fn new(init: *const u8, size: usize, align: u64) -> Self {
+        static NEXT_KEY: AtomicUsize = AtomicUsize::new(0);
+        // Threads may still have copies of the objects which used a reused key before, which
+        // are told apart by their generation.
+        let (key, generation) = match FREE_KEYS.lock().unwrap().pop() {
+            Some((key, generation)) => (key, generation + 1),
+            None => (NEXT_KEY.fetch_add(1, Ordering::Relaxed), 0),
+        };
+        Self {
+            resolver: resolver(),
+            key,
+            generation,
+            init,
+            size,
+            layout: Layout::from_size_align(size.max(1), usize::try_from(align).unwrap()).unwrap(),
+        }
+    }
+
+    /// The address of the calling thread's copy of the object, which is allocated and
+    /// initialized if the thread didn't access the object before.
+    pub(crate) fn address(&self) -> *mut u8 {
+        BLOCKS.with(|blocks| {
+            let mut blocks = blocks.borrow_mut();
+            if blocks.len() <= self.key {
+                blocks.resize_with(self.key + 1, || None);
+            }
+            let block = &mut blocks[self.key];
+            match block {
+                Some(block) if block.generation == self.generation => block.ptr.as_ptr(),
+                // The thread may have a copy of a freed object which used the same key.
+                _ => block.insert(TlsBlock::new(self)).ptr.as_ptr(),
+            }
+        })
+    }
+
+    /// Free the calling thread's copy of the object, if any, and allow new objects to reuse the
+    /// object's key.
+    ///
+    /// The copies of other threads are only freed when those threads exit, or access a new object
+    /// reusing the key.
+    pub(crate) fn release(&self) {
+        BLOCKS.with(|blocks| {
+            if let Some(block) = blocks.borrow_mut().get_mut(self.key) {
+                if block
+                    .as_ref()
+                    .map_or(false, |block| block.generation == self.generation)
+                {
+                    *block = None;
+                }
+            }
+        });
+        FREE_KEYS.lock().unwrap().push((self.key, self.generation));
+    }
+}
+
+/// A thread's copy of a thread-local data object.
+struct TlsBlock {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    /// The generation of the object's key.
+    generation: usize,
+}
+
+impl TlsBlock {
+    fn new(descriptor: &TlsDescriptor) -> Self {
+        let layout = descriptor.layout;
+        let ptr = NonNull::new(unsafe { std::alloc::alloc(layout) })
+            .unwrap_or_else(|| std::alloc::handle_alloc_error(layout));
+        unsafe { std::ptr::copy_nonoverlapping(descriptor.init, ptr.as_ptr(), descriptor.size) };
+        Self {
+            ptr,
+            layout,
+            generation: descriptor.generation,
+        }
+    }
+}
+
+impl Drop for TlsBlock {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+thread_local! {
+    /// The calling thread's copies of thread-local data objects, by their key.
+    static BLOCKS: RefCell<Vec<Option<TlsBlock>>> = RefCell::new(Vec::new());
+}
+
+/// The keys of released objects along with their generation, which are reused by new objects.
+static FREE_KEYS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+/// The function to use for the `ElfTlsGetAddr` libcall, if compiled code calls one.
+pub(crate) fn tls_get_addr() -> Option<*const u8> {
+    if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some(get_addr as *const u8)
+    } else {
+        None
+    }
+}
+
+extern "C" fn get_addr(descriptor: &TlsDescriptor) -> *mut u8 {
+    descriptor.address()
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn resolver() -> *const u8 {
+    extern "C" {
+        fn cranelift_jit_tlsdesc_resolver();
+    }
+    cranelift_jit_tlsdesc_resolver as *const u8
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+fn resolver() -> *const u8 {
+    std::ptr::null()
+}
+
+/// Returns the offset of the calling thread's copy of the object from the thread pointer.
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+extern "C" fn tlsdesc_offset(descriptor: &TlsDescriptor) -> isize {
+    let thread_pointer: usize;
+    unsafe { std::arch::asm!("mrs {}, tpidr_el0", out(reg) thread_pointer) };
+    (descriptor.address() as usize).wrapping_sub(thread_pointer) as isize
+}
+
+// TLSDESC resolvers must preserve all registers except for x0 and the link register, so this
+// saves all caller-saved registers around the call to `tlsdesc_offset`. The SIMD registers are
+// saved in full, as only the lower halves of v8-v15 are callee-saved.
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+std::arch::global_asm!(
+    "
+        .p2align 4
+        .hidden cranelift_jit_tlsdesc_resolver
+        .global cranelift_jit_tlsdesc_resolver
+        .type cranelift_jit_tlsdesc_resolver,%function
+    cranelift_jit_tlsdesc_resolver:
+        hint #34 // bti c
+        stp x29, x30, [sp, #-16]!
+        mov x29, sp
+        stp x1, x2, [sp, #-16]!
+        stp x3, x4, [sp, #-16]!
+        stp x5, x6, [sp, #-16]!
+        stp x7, x8, [sp, #-16]!
+        stp x9, x10, [sp, #-16]!
+        stp x11, x12, [sp, #-16]!
+        stp x13, x14, [sp, #-16]!
+        stp x15, x16, [sp, #-16]!
+        stp x17, x18, [sp, #-16]!
+        stp q0, q1, [sp, #-32]!
+        stp q2, q3, [sp, #-32]!
+        stp q4, q5, [sp, #-32]!
+        stp q6, q7, [sp, #-32]!
+        stp q8, q9, [sp, #-32]!
+        stp q10, q11, [sp, #-32]!
+        stp q12, q13, [sp, #-32]!
+        stp q14, q15, [sp, #-32]!
+        stp q16, q17, [sp, #-32]!
+        stp q18, q19, [sp, #-32]!
+        stp q20, q21, [sp, #-32]!
+        stp q22, q23, [sp, #-32]!
+        stp q24, q25, [sp, #-32]!
+        stp q26, q27, [sp, #-32]!
+        stp q28, q29, [sp, #-32]!
+        stp q30, q31, [sp, #-32]!
+        bl {tlsdesc_offset}
+        ldp q30, q31, [sp], #32
+        ldp q28, q29, [sp], #32
+        ldp q26, q27, [sp], #32
+        ldp q24, q25, [sp], #32
+        ldp q22, q23, [sp], #32
+        ldp q20, q21, [sp], #32
+        ldp q18, q19, [sp], #32
+        ldp q16, q17, [sp], #32
+        ldp q14, q15, [sp], #32
+        ldp q12, q13, [sp], #32
+        ldp q10, q11, [sp], #32
+        ldp q8, q9, [sp], #32
+        ldp q6, q7, [sp], #32
+        ldp q4, q5, [sp], #32
+        ldp q2, q3, [sp], #32
+        ldp q0, q1, [sp], #32
+        ldp x17, x18, [sp], #16
+        ldp x15, x16, [sp], #16
+        ldp x13, x14, [sp], #16
+        ldp x11, x12, [sp], #16
+        ldp x9, x10, [sp], #16
+        ldp x7, x8, [sp], #16
+        ldp x5, x6, [sp], #16
+        ldp x3, x4, [sp], #16
+        ldp x1, x2, [sp], #16
+        ldp x29, x30, [sp], #16
+        ret
+        .size cranelift_jit_tlsdesc_resolver,.-cranelift_jit_tlsdesc_resolver
+    ",
+    tlsdesc_offset = sym tlsdesc_offset,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freed_keys_are_reused() {
+        let first_init = 1u64;
+        let first = TlsDescriptor::new(std::ptr::addr_of!(first_init).cast(), 8, 8);
+        unsafe { *first.address().cast::<u64>() = 2 };
+        first.release();
+
+        // A new object reuses the key, but not the released object's contents.
+        let second_init = 3u64;
+        let second = TlsDescriptor::new(std::ptr::addr_of!(second_init).cast(), 8, 8);
+        assert_eq!(second.key, first.key);
+        assert_eq!(second.generation, first.generation + 1);
+        assert_eq!(unsafe { *second.address().cast::<u64>() }, 3);
+
+        // The calling thread's copy of an object released by another thread is replaced when the
+        // calling thread accesses a new object reusing the key.
+        unsafe { *second.address().cast::<u64>() = 4 };
+        FREE_KEYS
+            .lock()
+            .unwrap()
+            .push((second.key, second.generation));
+        let third_init = 5u64;
+        let third = TlsDescriptor::new(std::ptr::addr_of!(third_init).cast(), 8, 8);
+        assert_eq!(third.key, second.key);
+        assert_eq!(unsafe { *third.address().cast::<u64>() }, 5);
+        third.release();
+    }
+}
@@ -237,3 +237,364 @@ fn empty_data_object() {
     data.define(Box::new([]));
     module.define_data(data_id, &data).unwrap();
 }
+
+fn define_const_function(module: &mut JITModule, func_id: FuncId, value: i64) {
+    let mut ctx = module.make_context();
+    ctx.func.signature.returns.push(AbiParam::new(types::I32));
+    ctx.func.name = UserFuncName::user(0, func_id.as_u32());
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let block = bcx.create_block();
+        bcx.switch_to_block(block);
+        let value = bcx.ins().iconst(types::I32, value);
+        bcx.ins().return_(&[value]);
+        bcx.seal_all_blocks();
+    }
+    module.define_function(func_id, &mut ctx).unwrap();
+}
+
+#[test]
+fn free_and_redefine() {
+    let mut flag_builder = settings::builder();
+    flag_builder.set("use_colocated_libcalls", "false").unwrap();
+    // FIXME set back to true once the x64 backend supports it.
+    flag_builder.set("is_pic", "false").unwrap();
+    let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
+        panic!("host machine is not supported: {}", msg);
+    });
+    let isa = isa_builder
+        .finish(settings::Flags::new(flag_builder))
+        .unwrap();
+    let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I32));
+    let func_id = module
+        .declare_function("constant", Linkage::Local, &sig)
+        .unwrap();
+    let data_id = module
+        .declare_data("data", Linkage::Local, true, false)
+        .unwrap();
+
+    for value in 1..=3 {
+        define_const_function(&mut module, func_id, value);
+        let mut data = DataDescription::new();
+        data.define(Box::new([value as u8; 4]));
+        module.define_data(data_id, &data).unwrap();
+        module.finalize_definitions().unwrap();
+
+        let code = module.get_finalized_function(func_id);
+        let code = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(code) };
+        assert_eq!(code(), value as i32);
+        let (data, size) = module.get_finalized_data(data_id);
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(data, size) },
+            [value as u8; 4]
+        );
+
+        unsafe {
+            module.free_function(func_id).unwrap();
+            module.free_data(data_id).unwrap();
+        }
+    }
+
+    // Only defined functions and data objects can be freed.
+    unsafe {
+        assert!(module.free_function(func_id).is_err());
+        assert!(module.free_data(data_id).is_err());
+    }
+}
+
+/// Lazily define `lazy_id` as a function returning 42, and define `caller_id` as a function
+/// calling it and adding 1, returning the number of times the function has been generated.
+fn define_lazy_function(
+    module: &mut JITModule,
+    lazy_id: FuncId,
+    caller_id: FuncId,
+) -> std::rc::Rc<std::cell::Cell<i32>> {
+    let generated = std::rc::Rc::new(std::cell::Cell::new(0));
+    let generator_calls = generated.clone();
+    let define = unsafe {
+        module.define_function_lazily(lazy_id, move |_module, ctx| {
+            generator_calls.set(generator_calls.get() + 1);
+            let mut func_ctx = FunctionBuilderContext::new();
+            let mut bcx = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+            let block = bcx.create_block();
+            bcx.switch_to_block(block);
+            let value = bcx.ins().iconst(types::I32, 42);
+            bcx.ins().return_(&[value]);
+            bcx.seal_all_blocks();
+            bcx.finalize();
+            Ok(())
+        })
+    };
+    define.unwrap();
+
+    let mut ctx = module.make_context();
+    ctx.func.signature = module
+        .declarations()
+        .get_function_decl(caller_id)
+        .signature
+        .clone();
+    ctx.func.name = UserFuncName::user(0, caller_id.as_u32());
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let lazy = module.declare_func_in_func(lazy_id, bcx.func);
+        let block = bcx.create_block();
+        bcx.switch_to_block(block);
+        let call = bcx.ins().call(lazy, &[]);
+        let value = bcx.inst_results(call)[0];
+        let value = bcx.ins().iadd_imm(value, 1);
+        bcx.ins().return_(&[value]);
+        bcx.seal_all_blocks();
+        bcx.finalize();
+    }
+    module.define_function(caller_id, &mut ctx).unwrap();
+    generated
+}
+
+#[test]
+fn lazy_function() {
+    let mut flag_builder = settings::builder();
+    flag_builder.set("use_colocated_libcalls", "false").unwrap();
+    // FIXME set back to true once the x64 backend supports it.
+    flag_builder.set("is_pic", "false").unwrap();
+    let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
+        panic!("host machine is not supported: {}", msg);
+    });
+    let isa = isa_builder
+        .finish(settings::Flags::new(flag_builder))
+        .unwrap();
+    let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I32));
+    let lazy_id = module
+        .declare_function("lazy", Linkage::Local, &sig)
+        .unwrap();
+    let caller_id = module
+        .declare_function("caller", Linkage::Local, &sig)
+        .unwrap();
+
+    let generated = define_lazy_function(&mut module, lazy_id, caller_id);
+    assert!(unsafe { module.define_function_lazily(lazy_id, |_, _| Ok(())) }.is_err());
+    module.finalize_definitions().unwrap();
+    assert_eq!(generated.get(), 0);
+
+    // The first call compiles the lazy function, later calls reuse it.
+    let caller = module.get_finalized_function(caller_id);
+    let caller = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(caller) };
+    assert_eq!(caller(), 43);
+    assert_eq!(caller(), 43);
+    assert_eq!(generated.get(), 1);
+
+    let lazy = module.get_finalized_function(lazy_id);
+    let lazy = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(lazy) };
+    assert_eq!(lazy(), 42);
+    assert_eq!(generated.get(), 1);
+}
+
+#[test]
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn tls_data() {
+    let mut flag_builder = settings::builder();
+    flag_builder.set("use_colocated_libcalls", "false").unwrap();
+    // FIXME set back to true once the x64 backend supports it.
+    flag_builder.set("is_pic", "false").unwrap();
+    flag_builder.set("tls_model", "elf_gd").unwrap();
+    let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
+        panic!("host machine is not supported: {}", msg);
+    });
+    let isa = isa_builder
+        .finish(settings::Flags::new(flag_builder))
+        .unwrap();
+    let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+    let data_id = module
+        .declare_data("counter", Linkage::Local, true, true)
+        .unwrap();
+    let mut data = DataDescription::new();
+    data.define(Box::new(5i64.to_ne_bytes()));
+    module.define_data(data_id, &data).unwrap();
+
+    // Increments the calling thread's counter and returns its new value.
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I64));
+    let func_id = module
+        .declare_function("increment", Linkage::Local, &sig)
+        .unwrap();
+    let mut ctx = module.make_context();
+    ctx.func.signature = sig;
+    ctx.func.name = UserFuncName::user(0, func_id.as_u32());
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let counter = module.declare_data_in_func(data_id, bcx.func);
+        let block = bcx.create_block();
+        bcx.switch_to_block(block);
+        let addr = bcx.ins().tls_value(types::I64, counter);
+        let value = bcx.ins().load(types::I64, MemFlags::trusted(), addr, 0);
+        let value = bcx.ins().iadd_imm(value, 1);
+        bcx.ins().store(MemFlags::trusted(), value, addr, 0);
+        bcx.ins().return_(&[value]);
+        bcx.seal_all_blocks();
+        bcx.finalize();
+    }
+    module.define_function(func_id, &mut ctx).unwrap();
+    module.finalize_definitions().unwrap();
+
+    let code = module.get_finalized_function(func_id);
+    let increment = unsafe { std::mem::transmute::<_, extern "C" fn() -> i64>(code) };
+    assert_eq!(increment(), 6);
+    assert_eq!(increment(), 7);
+
+    // Other threads start with their own copy of the initial value.
+    let other = std::thread::spawn(move || (increment(), increment()));
+    assert_eq!(other.join().unwrap(), (6, 7));
+
+    assert_eq!(increment(), 8);
+    let (counter, size) = module.get_finalized_tls_data(data_id);
+    assert_eq!(size, 8);
+    assert_eq!(unsafe { *counter.cast::<i64>() }, 8);
+}
+
+/// Create a JIT module for PIC code, optionally with hotswap support enabled.
+#[cfg(target_arch = "x86_64")]
+fn pic_module(hotswap: bool) -> JITModule {
+    let mut flag_builder = settings::builder();
+    flag_builder.set("use_colocated_libcalls", "false").unwrap();
+    flag_builder.set("is_pic", "true").unwrap();
+    let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
+        panic!("host machine is not supported: {}", msg);
+    });
+    let isa = isa_builder
+        .finish(settings::Flags::new(flag_builder))
+        .unwrap();
+    let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+    builder.hotswap(hotswap);
+    JITModule::new(builder)
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn free_function_resets_got_entry() {
+    let mut module = pic_module(true);
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I32));
+    let func_id = module
+        .declare_function("constant", Linkage::Local, &sig)
+        .unwrap();
+
+    for value in 1..=3 {
+        define_const_function(&mut module, func_id, value);
+        module.finalize_definitions().unwrap();
+        let code = module.read_got_entry(func_id);
+        assert_eq!(code, module.get_finalized_function(func_id));
+        let code = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(code) };
+        assert_eq!(code(), value as i32);
+
+        unsafe { module.free_function(func_id).unwrap() };
+        assert!(module.read_got_entry(func_id).is_null());
+    }
+
+    // A definition which is freed before it is finalized never updates the GOT entry.
+    define_const_function(&mut module, func_id, 4);
+    unsafe { module.free_function(func_id).unwrap() };
+    module.finalize_definitions().unwrap();
+    assert!(module.read_got_entry(func_id).is_null());
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn free_replaced_functions() {
+    let mut module = pic_module(true);
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I32));
+    let func_id = module
+        .declare_function("constant", Linkage::Local, &sig)
+        .unwrap();
+    define_const_function(&mut module, func_id, 0);
+    module.finalize_definitions().unwrap();
+
+    for value in 1..=3 {
+        let replaced = module.read_got_entry(func_id);
+        module.prepare_for_function_redefine(func_id).unwrap();
+        define_const_function(&mut module, func_id, value);
+        module.finalize_definitions().unwrap();
+        assert_ne!(module.read_got_entry(func_id), replaced);
+
+        // The replaced code is only freed once nothing refers to it anymore, and its memory
+        // may be reused by the next definition.
+        unsafe { module.free_replaced_functions() };
+        let code = module.read_got_entry(func_id);
+        let code = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(code) };
+        assert_eq!(code(), value as i32);
+    }
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+#[should_panic(expected = "replacing definitions not yet finalized")]
+fn free_replaced_functions_before_finalize() {
+    let mut module = pic_module(true);
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I32));
+    let func_id = module
+        .declare_function("constant", Linkage::Local, &sig)
+        .unwrap();
+    define_const_function(&mut module, func_id, 0);
+    module.finalize_definitions().unwrap();
+
+    module.prepare_for_function_redefine(func_id).unwrap();
+    define_const_function(&mut module, func_id, 1);
+    unsafe { module.free_replaced_functions() };
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn lazy_function_pic() {
+    let mut module = pic_module(false);
+    let mut sig = module.make_signature();
+    sig.returns.push(AbiParam::new(types::I32));
+    let lazy_id = module
+        .declare_function("lazy", Linkage::Local, &sig)
+        .unwrap();
+    let caller_id = module
+        .declare_function("caller", Linkage::Local, &sig)
+        .unwrap();
+    let other_id = module
+        .declare_function("other", Linkage::Local, &sig)
+        .unwrap();
+    let generated = define_lazy_function(&mut module, lazy_id, caller_id);
+    module.finalize_definitions().unwrap();
+    let stub = module.read_got_entry(lazy_id);
+    assert_eq!(stub, module.get_finalized_function(lazy_id));
+
+    // Compiling the lazy function only finalizes the function itself, not other pending
+    // definitions.
+    define_const_function(&mut module, other_id, 7);
+    let caller = module.get_finalized_function(caller_id);
+    let caller = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(caller) };
+    assert_eq!(caller(), 43);
+    assert_eq!(generated.get(), 1);
+    assert!(module.read_got_entry(other_id).is_null());
+
+    // Calls through the GOT now go directly to the compiled function.
+    let lazy = module.read_got_entry(lazy_id);
+    assert_ne!(lazy, stub);
+    assert_eq!(lazy, module.get_finalized_function(lazy_id));
+    let lazy = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(lazy) };
+    assert_eq!(lazy(), 42);
+    assert_eq!(caller(), 43);
+    assert_eq!(generated.get(), 1);
+
+    module.finalize_definitions().unwrap();
+    let other = module.read_got_entry(other_id);
+    let other = unsafe { std::mem::transmute::<_, extern "C" fn() -> i32>(other) };
+    assert_eq!(other(), 7);
+}
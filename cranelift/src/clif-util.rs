@@ -13,6 +13,9 @@ mod utils;
 #[cfg(feature = "souper-harvest")]
 mod souper_harvest;
 
+#[cfg(feature = "isle-coverage")]
+mod isle_coverage;
+
 #[cfg(feature = "wasm")]
 mod wasm;
 
@@ -37,6 +40,11 @@ enum Commands {
     SouperHarvest(souper_harvest::Options),
     #[cfg(not(feature = "souper-harvest"))]
     SouperHarvest(CompiledWithoutSupportOptions),
+
+    #[cfg(feature = "isle-coverage")]
+    IsleCoverage(isle_coverage::Options),
+    #[cfg(not(feature = "isle-coverage"))]
+    IsleCoverage(CompiledWithoutSupportOptions),
 }
 
 /// Run Cranelift tests
@@ -105,6 +113,14 @@ fn main() -> anyhow::Result<()> {
              subcommand",
         ),
 
+        #[cfg(feature = "isle-coverage")]
+        Commands::IsleCoverage(c) => isle_coverage::run(&c)?,
+        #[cfg(not(feature = "isle-coverage"))]
+        Commands::IsleCoverage(_) => anyhow::bail!(
+            "Error: clif-util was compiled without support for the `isle-coverage` \
+             subcommand",
+        ),
+
         Commands::Test(t) => {
             cranelift_filetests::run(
                 t.verbose,
@@ -1,5 +1,6 @@
 //! CLI tool to interpret Cranelift IR files.
 
+use crate::run::create_target_isa;
 use crate::utils::iterate_files;
 use clap::Parser;
 use cranelift_interpreter::environment::FunctionStore;
@@ -10,6 +11,12 @@ use std::path::PathBuf;
 use std::{fs, io};
 use thiserror::Error;
 
+mod debugger;
+mod native;
+
+use debugger::{Breakpoint, DebugOptions, Debugger};
+use native::NativeTracer;
+
 /// Interpret clif code
 #[derive(Parser)]
 pub struct Options {
@@ -20,15 +27,38 @@ pub struct Options {
     /// Be more verbose
     #[arg(short, long)]
     verbose: bool,
+
+    /// Step through the interpreted functions with an interactive debugger
+    #[arg(short, long)]
+    debug: bool,
+
+    /// Break before interpreting an instruction (`inst7`), the definition of a value (`v5`) or
+    /// the first instruction of a block (`block3`), optionally in a specific function
+    /// (`%func:block3`); implies `--debug`
+    #[arg(short, long = "break", value_name = "LOCATION")]
+    breakpoints: Vec<Breakpoint>,
+
+    /// Compare the results of each instruction against a native execution of the function on the
+    /// host, and stop at the first instruction whose results differ
+    #[arg(long)]
+    compare_native: bool,
 }
 
 /// Run files through the Cranelift interpreter, interpreting any functions with annotations.
 pub fn run(options: &Options) -> anyhow::Result<()> {
     let mut total = 0;
     let mut errors = 0;
+    let debug = DebugOptions {
+        interactive: options.debug || !options.breakpoints.is_empty(),
+        compare_native: options.compare_native,
+        breakpoints: options.breakpoints.clone(),
+    };
     for file in iterate_files(&options.files) {
         total += 1;
-        let runner = FileInterpreter::from_path(file)?;
+        let mut runner = FileInterpreter::from_path(file)?;
+        if debug.interactive || debug.compare_native {
+            runner = runner.with_debug(debug.clone());
+        }
         match runner.run() {
             Ok(_) => {
                 if options.verbose {
@@ -63,6 +93,7 @@ pub fn run(options: &Options) -> anyhow::Result<()> {
 pub struct FileInterpreter {
     path: Option<PathBuf>,
     contents: String,
+    debug: Option<DebugOptions>,
 }
 
 impl FileInterpreter {
@@ -74,6 +105,7 @@ impl FileInterpreter {
         Ok(Self {
             path: Some(path),
             contents,
+            debug: None,
         })
     }
 
@@ -84,6 +116,15 @@ impl FileInterpreter {
         Self {
             path: None,
             contents,
+            debug: None,
+        }
+    }
+
+    /// Debug the interpreted functions, reading debugger commands from stdin.
+    pub fn with_debug(self, debug: DebugOptions) -> Self {
+        Self {
+            debug: Some(debug),
+            ..self
         }
     }
 
@@ -105,18 +146,38 @@ impl FileInterpreter {
         // collect functions
         let mut env = FunctionStore::default();
         let mut commands = vec![];
+        let mut invoked = vec![];
         for (func, details) in test.functions.iter() {
             for comment in &details.comments {
                 if let Some(command) = parse_run_command(comment.text, &func.signature)
                     .map_err(|e| FileInterpreterFailure::ParsingClif(self.path(), e))?
                 {
                     commands.push(command);
+                    invoked.push(func);
                 }
             }
             // Note: func.name may truncate the function name
             env.add(func.name.to_string(), func);
         }
 
+        // compile the invoked functions natively if their results will be compared
+        let tracer = match &self.debug {
+            Some(debug) if debug.compare_native => {
+                let isa = create_target_isa(&test.isa_spec)
+                    .map_err(FileInterpreterFailure::NativeCompilation)?;
+                let functions = test
+                    .functions
+                    .iter()
+                    .map(|(func, _)| func.clone())
+                    .collect::<Vec<_>>();
+                invoked.dedup_by_key(|func| func.name.clone());
+                let tracer = NativeTracer::new(isa, &functions, &invoked)
+                    .map_err(FileInterpreterFailure::NativeCompilation)?;
+                Some(tracer)
+            }
+            _ => None,
+        };
+
         // Run assertion commands
         for command in commands {
             command
@@ -124,8 +185,21 @@ impl FileInterpreter {
                     // Because we have stored function names with a leading %, we need to re-add it.
                     let func_name = &format!("%{}", func_name);
                     let state = InterpreterState::default().with_function_store(env.clone());
-                    match Interpreter::new(state).call_by_name(func_name, args) {
+                    let mut interpreter = Interpreter::new(state);
+                    if let Some(debug) = &self.debug {
+                        let native = match &tracer {
+                            Some(tracer) => {
+                                Some(tracer.trace(func_name, args).map_err(|e| e.to_string())?)
+                            }
+                            None => None,
+                        };
+                        let debugger =
+                            Debugger::new(debug, native, io::stdin().lock(), io::stdout());
+                        interpreter = interpreter.with_inspector(debugger);
+                    }
+                    match interpreter.call_by_name(func_name, args) {
                         Ok(ControlFlow::Return(results)) => Ok(results.to_vec()),
+                        Ok(ControlFlow::Trap(trap)) => Err(format!("trapped: {trap}")),
                         Ok(_) => panic!("Unexpected returned control flow--this is likely a bug."),
                         Err(t) => Err(t.to_string()),
                     }
@@ -146,6 +220,8 @@ pub enum FileInterpreterFailure {
     ParsingClif(String, ParseError),
     #[error("failed to run function: {0}")]
     FailedExecution(String),
+    #[error("failed to compile functions natively: {0}")]
+    NativeCompilation(anyhow::Error),
 }
 
 #[cfg(test)]
@@ -174,6 +250,9 @@ mod test {
         run(&Options {
             files: vec![PathBuf::from("../filetests/filetests/interpreter")],
             verbose: true,
+            debug: false,
+            breakpoints: vec![],
+            compare_native: false,
         })
         .unwrap()
     }
@@ -0,0 +1,788 @@
+//! An interactive debugger for interpreted CLIF functions.
+//!
+//! The debugger is an [Inspector] of the interpreter: it stops before instructions, either when
+//! stepping or at breakpoints, and reads commands to inspect the interpreter's state. It can also
+//! compare the results of each instruction and the trap ending the call against a [NativeTrace] of
+//! the same call, stopping at the first difference.
+
+use super::native::{is_traced_type, NativeTrace, TraceEntry};
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::{Block, Function, Inst, Opcode, StackSlot, Value};
+use cranelift_interpreter::address::AddressSize;
+use cranelift_interpreter::interpreter::{Inspector, InspectorAction, InterpreterState};
+use cranelift_interpreter::state::State;
+use cranelift_interpreter::step::CraneliftTrap;
+use cranelift_interpreter::value::DataValueExt;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+const HELP: &str = "\
+commands:
+  s, step               execute the next instruction, stepping into calls (also an empty line)
+  n, next               execute the next instruction, stepping over calls
+  c, continue           run until the next breakpoint
+  b, break [LOCATION]   add a breakpoint, or list the breakpoints without a location
+  d, delete N           delete breakpoint N
+  p, print [VALUE...]   print SSA values, or all assigned values of the current frame
+  x, slots [SLOT...]    print the contents of stack slots, or of all stack slots
+  l, list               print the current block
+  bt, backtrace         print the call stack
+  q, quit               stop interpreting
+  h, help               print this message
+
+A LOCATION is a block (`block3`), an instruction (`inst7`) or the instruction defining a value
+(`v5`), optionally restricted to a function (`%func:block3`).";
+
+/// Configures the debugging of interpreted functions.
+#[derive(Debug, Clone, Default)]
+pub struct DebugOptions {
+    /// Read commands whenever execution stops.
+    pub interactive: bool,
+    /// Compare the interpreted results against native execution.
+    pub compare_native: bool,
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+/// A place where the debugger stops before interpreting an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    /// The name of the function to stop in, or `None` to stop in any function.
+    function: Option<String>,
+    location: Location,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Location {
+    /// The first instruction of a block.
+    Block(Block),
+    Inst(Inst),
+    /// The instruction defining a value.
+    Value(Value),
+}
+
+impl Breakpoint {
+    fn matches(&self, function: &Function, inst: Inst) -> bool {
+        let location = match self.location {
+            Location::Block(block) => {
+                function.layout.is_block_inserted(block)
+                    && function.layout.first_inst(block) == Some(inst)
+            }
+            Location::Inst(i) => i == inst,
+            Location::Value(v) => {
+                function.dfg.value_is_valid(v) && function.dfg.value_def(v).inst() == Some(inst)
+            }
+        };
+        location
+            && match &self.function {
+                Some(name) => function.name.to_string() == *name,
+                None => true,
+            }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (function, location) = match s.rsplit_once(':') {
+            Some((function, location)) => (Some(function.to_string()), location),
+            None => (None, s),
+        };
+        let number = |prefix: &str| location.strip_prefix(prefix)?.parse::<u32>().ok();
+        let location = if let Some(n) = number("block") {
+            Block::with_number(n).map(Location::Block)
+        } else if let Some(n) = number("inst") {
+            Some(Location::Inst(Inst::from_u32(n)))
+        } else if let Some(n) = number("v") {
+            Value::with_number(n).map(Location::Value)
+        } else {
+            None
+        };
+        match location {
+            Some(location) => Ok(Self { function, location }),
+            None => Err(format!("invalid breakpoint location: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(function) = &self.function {
+            write!(f, "{function}:")?;
+        }
+        match self.location {
+            Location::Block(block) => write!(f, "{block}"),
+            Location::Inst(inst) => write!(f, "{inst}"),
+            Location::Value(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// When the debugger stops next, apart from breakpoints.
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// Before the next instruction.
+    Step,
+    /// Before the next instruction in a frame at most this deep.
+    Next(usize),
+    /// Only at breakpoints.
+    Continue,
+}
+
+/// Debugs a single call of an interpreted function, reading commands from `input` and printing
+/// to `output`.
+pub struct Debugger<R, W> {
+    input: R,
+    output: W,
+    interactive: bool,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+    /// The native results which haven't been compared yet. Comparisons end at the first
+    /// divergence.
+    native: Option<NativeTrace>,
+    /// The index of the next entry of `native` to compare.
+    next_entry: usize,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(options: &DebugOptions, native: Option<NativeTrace>, input: R, output: W) -> Self {
+        // Without any breakpoints, an interactive session starts at the first instruction.
+        let mode = if options.interactive && options.breakpoints.is_empty() {
+            Mode::Step
+        } else {
+            Mode::Continue
+        };
+        Self {
+            input,
+            output,
+            interactive: options.interactive,
+            breakpoints: options.breakpoints.clone(),
+            mode,
+            native,
+            next_entry: 0,
+        }
+    }
+
+    /// Print where `inst` is and read commands until execution should resume.
+    fn stop(&mut self, state: &InterpreterState, inst: Inst) -> io::Result<InspectorAction> {
+        let function = state.get_current_function();
+        writeln!(self.output, "{}", Position(function, inst))?;
+        loop {
+            write!(self.output, "(clif) ")?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(InspectorAction::Stop);
+            }
+
+            let mut words = line.split_whitespace();
+            let command = words.next();
+            let args = words.collect::<Vec<_>>();
+            match command {
+                None | Some("s" | "step") => {
+                    self.mode = Mode::Step;
+                    return Ok(InspectorAction::Continue);
+                }
+                Some("n" | "next") => {
+                    self.mode = Mode::Next(state.frame_stack.len());
+                    return Ok(InspectorAction::Continue);
+                }
+                Some("c" | "continue") => {
+                    self.mode = Mode::Continue;
+                    return Ok(InspectorAction::Continue);
+                }
+                Some("b" | "break") => self.add_breakpoints(&args)?,
+                Some("d" | "delete") => self.delete_breakpoints(&args)?,
+                Some("p" | "print") => self.print_values(state, &args)?,
+                Some("x" | "slots") => self.print_slots(state, &args)?,
+                Some("l" | "list") => self.list(function, inst)?,
+                Some("bt" | "backtrace") => {
+                    for (i, frame) in state.frame_stack.iter().rev().enumerate() {
+                        writeln!(self.output, "#{i} {}", frame.function().name)?;
+                    }
+                }
+                Some("q" | "quit") => return Ok(InspectorAction::Stop),
+                Some("h" | "help") => writeln!(self.output, "{HELP}")?,
+                Some(command) => writeln!(self.output, "unknown command `{command}`; try `help`")?,
+            }
+        }
+    }
+
+    fn add_breakpoints(&mut self, args: &[&str]) -> io::Result<()> {
+        if args.is_empty() {
+            for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+                writeln!(self.output, "breakpoint {i}: {breakpoint}")?;
+            }
+        }
+        for arg in args {
+            match arg.parse::<Breakpoint>() {
+                Ok(breakpoint) => {
+                    writeln!(
+                        self.output,
+                        "breakpoint {}: {breakpoint}",
+                        self.breakpoints.len()
+                    )?;
+                    self.breakpoints.push(breakpoint);
+                }
+                Err(e) => writeln!(self.output, "{e}")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn delete_breakpoints(&mut self, args: &[&str]) -> io::Result<()> {
+        let mut indices = Vec::with_capacity(args.len());
+        for arg in args {
+            match arg.parse::<usize>() {
+                Ok(i) if i < self.breakpoints.len() => indices.push(i),
+                _ => writeln!(self.output, "no breakpoint {arg}")?,
+            }
+        }
+        // Remove from the back so that the remaining indices stay valid.
+        indices.sort_unstable();
+        indices.dedup();
+        for i in indices.into_iter().rev() {
+            self.breakpoints.remove(i);
+        }
+        Ok(())
+    }
+
+    fn print_values(&mut self, state: &InterpreterState, args: &[&str]) -> io::Result<()> {
+        let frame = state.current_frame();
+        let function = frame.function();
+        let values = if args.is_empty() {
+            // All values assigned so far, in layout order.
+            let mut values = Vec::new();
+            for block in function.layout.blocks() {
+                values.extend_from_slice(function.dfg.block_params(block));
+                for inst in function.layout.block_insts(block) {
+                    values.extend_from_slice(function.dfg.inst_results(inst));
+                }
+            }
+            values.retain(|&v| frame.try_get(v).is_some());
+            values
+        } else {
+            let mut values = Vec::with_capacity(args.len());
+            for arg in args {
+                match parse_entity(arg, "v", Value::with_number) {
+                    Some(v) if function.dfg.value_is_valid(v) => values.push(v),
+                    _ => writeln!(self.output, "no value {arg}")?,
+                }
+            }
+            values
+        };
+
+        for v in values {
+            let ty = function.dfg.value_type(v);
+            match frame.try_get(v) {
+                Some(value) => writeln!(self.output, "{v}: {ty} = {value}")?,
+                None => writeln!(self.output, "{v}: {ty} is not assigned")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn print_slots(&mut self, state: &InterpreterState, args: &[&str]) -> io::Result<()> {
+        let function = state.get_current_function();
+        let slots = if args.is_empty() {
+            function.sized_stack_slots.keys().collect()
+        } else {
+            let mut slots = Vec::with_capacity(args.len());
+            for arg in args {
+                match parse_entity(arg, "ss", StackSlot::with_number) {
+                    Some(slot) if function.sized_stack_slots.is_valid(slot) => slots.push(slot),
+                    _ => writeln!(self.output, "no stack slot {arg}")?,
+                }
+            }
+            slots
+        };
+
+        for slot in slots {
+            let data = &function.sized_stack_slots[slot];
+            write!(self.output, "{slot}: {data} =")?;
+            // Zero-sized slots don't have an address.
+            if let Ok(addr) = state.stack_address(AddressSize::_64, slot, 0) {
+                let start = addr.offset as usize;
+                for byte in &state.stack[start..start + data.size as usize] {
+                    write!(self.output, " {byte:02x}")?;
+                }
+            }
+            writeln!(self.output)?;
+        }
+        Ok(())
+    }
+
+    fn list(&mut self, function: &Function, inst: Inst) -> io::Result<()> {
+        let block = function.layout.inst_block(inst).unwrap();
+        writeln!(self.output, "{block}:")?;
+        for i in function.layout.block_insts(block) {
+            let marker = if i == inst { "=>" } else { "  " };
+            writeln!(
+                self.output,
+                "{marker} {i}: {}",
+                function.dfg.display_inst(i)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Compare the results of `inst` against the next entries of the native trace, returning a
+    /// description of the first difference.
+    fn compare(&mut self, state: &InterpreterState, inst: Inst) -> Option<String> {
+        let native = self.native.as_ref()?;
+        let frame = state.current_frame();
+        let function = frame.function();
+        // Only the outermost call of the traced function is traced.
+        if state.frame_stack.len() != 1 || function.name != native.function {
+            return None;
+        }
+
+        for (i, &v) in function.dfg.inst_results(inst).iter().enumerate() {
+            if !is_traced_type(function.dfg.value_type(v)) {
+                continue;
+            }
+            let interpreted = frame.get(v);
+            let difference = match native.entries.get(self.next_entry) {
+                None if native.truncated => {
+                    let _ = writeln!(self.output, "native trace is full; stopping comparisons");
+                    self.native = None;
+                    return None;
+                }
+                None => format!("{v} = {interpreted}, but {}", native_end(native)),
+                Some(TraceEntry {
+                    inst: native_inst,
+                    result,
+                    ..
+                }) if (*native_inst, *result) != (inst, i) => {
+                    let native_value = function.dfg.inst_results(*native_inst)[*result];
+                    format!("{v} = {interpreted}, but native execution computed {native_value}")
+                }
+                Some(TraceEntry { value, .. }) if !same_value(interpreted, value) => {
+                    format!("{v} = {interpreted}, but native execution computed {value}")
+                }
+                Some(_) => {
+                    self.next_entry += 1;
+                    continue;
+                }
+            };
+            self.native = None;
+            return Some(difference);
+        }
+        None
+    }
+
+    /// Compare the end of the call, which either returns or traps with `trap`, against the native
+    /// trace, returning a description of the difference.
+    fn compare_end(
+        &mut self,
+        state: &InterpreterState,
+        trap: Option<&CraneliftTrap>,
+    ) -> Option<String> {
+        let native = self.native.as_ref()?;
+        // Traps in callees end the outermost call as well.
+        let function = state.frame_stack.first()?.function();
+        if function.name != native.function {
+            return None;
+        }
+
+        let interpreted = match trap {
+            Some(trap) => format!("trapped: {trap}"),
+            None => "returned".to_string(),
+        };
+        let difference = match native.entries.get(self.next_entry) {
+            Some(TraceEntry {
+                inst: native_inst,
+                result,
+                ..
+            }) => {
+                let native_value = function.dfg.inst_results(*native_inst)[*result];
+                format!("{interpreted}, but native execution computed {native_value}")
+            }
+            None if native.truncated => return None,
+            None => match (trap, native.trap) {
+                (Some(CraneliftTrap::User(code)), Some(native_code)) if *code == native_code => {
+                    return None
+                }
+                (None, None) => return None,
+                _ => format!("{interpreted}, but {}", native_end(native)),
+            },
+        };
+        self.native = None;
+        Some(difference)
+    }
+
+    /// Report `difference`, found at `inst`, and stop unless the session is interactive.
+    fn report(
+        &mut self,
+        state: &InterpreterState,
+        inst: Inst,
+        difference: &str,
+    ) -> InspectorAction {
+        let function = state.get_current_function();
+        let reported = writeln!(
+            self.output,
+            "first divergence from native execution at {}\n  {difference}",
+            Position(function, inst)
+        );
+        if reported.is_err() || !self.interactive {
+            return InspectorAction::Stop;
+        }
+        self.stop(state, inst).unwrap_or(InspectorAction::Stop)
+    }
+}
+
+impl<'a, R: BufRead, W: Write> Inspector<'a> for Debugger<R, W> {
+    fn before_step(&mut self, state: &InterpreterState<'a>, inst: Inst) -> InspectorAction {
+        let function = state.get_current_function();
+        // `after_step` isn't called for the return which ends the call, so compare it here.
+        if state.frame_stack.len() == 1 && function.dfg.insts[inst].opcode() == Opcode::Return {
+            if let Some(difference) = self.compare_end(state, None) {
+                return self.report(state, inst, &difference);
+            }
+        }
+        let stop = match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => state.frame_stack.len() <= depth,
+            Mode::Continue => false,
+        } || self.breakpoints.iter().any(|b| b.matches(function, inst));
+        if !stop || !self.interactive {
+            return InspectorAction::Continue;
+        }
+        self.stop(state, inst).unwrap_or(InspectorAction::Stop)
+    }
+
+    fn after_step(&mut self, state: &InterpreterState<'a>, inst: Inst) -> InspectorAction {
+        match self.compare(state, inst) {
+            Some(difference) => self.report(state, inst, &difference),
+            None => InspectorAction::Continue,
+        }
+    }
+
+    fn trapped(&mut self, state: &InterpreterState<'a>, inst: Inst, trap: &CraneliftTrap) {
+        if let Some(difference) = self.compare_end(state, Some(trap)) {
+            // Interpretation ends with the trap either way.
+            self.report(state, inst, &difference);
+        }
+    }
+}
+
+/// Displays an instruction along with its function and block.
+struct Position<'a>(&'a Function, Inst);
+
+impl fmt::Display for Position<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self(function, inst) = *self;
+        let block = function.layout.inst_block(inst).unwrap();
+        write!(
+            f,
+            "{} {block}: {}",
+            function.name,
+            function.dfg.display_inst(inst)
+        )
+    }
+}
+
+/// Parse an entity reference like `v3`, given its prefix and constructor.
+fn parse_entity<T>(s: &str, prefix: &str, with_number: fn(u32) -> Option<T>) -> Option<T> {
+    with_number(s.strip_prefix(prefix)?.parse().ok()?)
+}
+
+/// Describe how the native call of `native` ended.
+fn native_end(native: &NativeTrace) -> String {
+    match native.trap {
+        Some(code) => format!("native execution trapped: user code: {code}"),
+        None => "native execution returned".to_string(),
+    }
+}
+
+/// Whether an interpreted and a native value are the same. NaNs are equal regardless of their
+/// payload, which is not consistent across architectures.
+fn same_value(interpreted: &DataValue, native: &DataValue) -> bool {
+    interpreted.bitwise_eq(native)
+        || matches!(
+            (interpreted.is_nan(), native.is_nan()),
+            (Ok(true), Ok(true))
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpret::native::NativeTracer;
+    use cranelift_codegen::ir::{TrapCode, UserFuncName};
+    use cranelift_codegen::settings;
+    use cranelift_interpreter::environment::FunctionStore;
+    use cranelift_interpreter::interpreter::{Interpreter, InterpreterError};
+    use cranelift_interpreter::step::ControlFlow;
+    use cranelift_reader::parse_functions;
+
+    const CODE: &str = "
+        function %add1(i32) -> i32 {
+        block0(v0: i32):
+            v1 = iadd_imm v0, 1
+            return v1
+        }
+
+        function %test(i32) -> i32 {
+            ss0 = explicit_slot 4
+            fn0 = %add1(i32) -> i32
+        block0(v0: i32):
+            stack_store v0, ss0
+            v1 = call fn0(v0)
+            jump block1(v1)
+
+        block1(v2: i32):
+            v3 = imul_imm v2, 3
+            return v3
+        }";
+
+    const TRAP_CODE: &str = "
+        function %div(i32, i32) -> i32 {
+        block0(v0: i32, v1: i32):
+            v2 = udiv v0, v1
+            return v2
+        }";
+
+    /// Interpret `%test(5)` from `CODE` with a debugger reading `input`, returning the results and
+    /// the debugger's output.
+    fn debug(
+        options: DebugOptions,
+        native: Option<NativeTrace>,
+        input: &str,
+    ) -> (Result<Vec<DataValue>, InterpreterError>, String) {
+        let (result, output) =
+            debug_call(CODE, "%test", &[DataValue::I32(5)], options, native, input);
+        let result = result.map(|result| result.unwrap());
+        (result, output)
+    }
+
+    /// Interpret `func_name(args)` from `code` with a debugger reading `input`, returning the
+    /// results or trap and the debugger's output.
+    fn debug_call(
+        code: &str,
+        func_name: &str,
+        args: &[DataValue],
+        options: DebugOptions,
+        native: Option<NativeTrace>,
+        input: &str,
+    ) -> (
+        Result<Result<Vec<DataValue>, CraneliftTrap>, InterpreterError>,
+        String,
+    ) {
+        let functions = parse_functions(code).unwrap();
+        let mut env = FunctionStore::default();
+        for func in &functions {
+            env.add(func.name.to_string(), func);
+        }
+
+        let mut output = Vec::new();
+        let debugger = Debugger::new(&options, native, input.as_bytes(), &mut output);
+        let state = InterpreterState::default().with_function_store(env);
+        let result = Interpreter::new(state)
+            .with_inspector(debugger)
+            .call_by_name(func_name, args)
+            .map(|control_flow| match control_flow {
+                ControlFlow::Return(results) => Ok(results.to_vec()),
+                ControlFlow::Trap(trap) => Err(trap),
+                control_flow => panic!("unexpected control flow: {control_flow:?}"),
+            });
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    /// Create a native tracer of all functions in `functions` for the host.
+    fn host_tracer(functions: &[Function]) -> NativeTracer {
+        let isa = crate::run::create_target_isa(&cranelift_reader::IsaSpec::None(
+            settings::Flags::new(settings::builder()),
+        ))
+        .unwrap();
+        let traced: Vec<_> = functions.iter().collect();
+        NativeTracer::new(isa, functions, &traced).unwrap()
+    }
+
+    #[test]
+    fn breakpoints() {
+        assert_eq!(
+            "%f:block3".parse::<Breakpoint>().unwrap().to_string(),
+            "%f:block3"
+        );
+        assert_eq!("inst7".parse::<Breakpoint>().unwrap().to_string(), "inst7");
+        assert_eq!("v5".parse::<Breakpoint>().unwrap().to_string(), "v5");
+        assert!("ss0".parse::<Breakpoint>().is_err());
+        assert!("%f:".parse::<Breakpoint>().is_err());
+    }
+
+    #[test]
+    fn step_and_print() {
+        let options = DebugOptions {
+            interactive: true,
+            ..Default::default()
+        };
+        let (result, output) = debug(options, None, "p\nl\ns\nx ss0\nn\np v1 v3\nbt\nq\n");
+        assert!(matches!(result, Err(InterpreterError::Stopped)));
+        assert_eq!(
+            output,
+            "\
+%test block0: stack_store.i32 v0, ss0
+(clif) v0: i32 = 5
+(clif) block0:
+=> inst0: stack_store.i32 v0, ss0
+   inst1: v1 = call fn0(v0)
+   inst2: jump block1(v1)
+(clif) %test block0: v1 = call fn0(v0)
+(clif) ss0: explicit_slot 4 = 05 00 00 00
+(clif) %test block0: jump block1(v1)
+(clif) v1: i32 = 6
+v3: i32 is not assigned
+(clif) #0 %test
+(clif) "
+        );
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let options = DebugOptions {
+            interactive: true,
+            breakpoints: vec!["%add1:v1".parse().unwrap(), "block1".parse().unwrap()],
+            ..Default::default()
+        };
+        let (result, output) = debug(options, None, "bt\nc\np\nc\n");
+        assert_eq!(result.unwrap(), [DataValue::I32(18)]);
+        assert_eq!(
+            output,
+            "\
+%add1 block0: v1 = iadd_imm.i32 v0, 1
+(clif) #0 %add1
+#1 %test
+(clif) %test block1: v3 = imul_imm.i32 v2, 3
+(clif) v0: i32 = 5
+v1: i32 = 6
+v2: i32 = 6
+(clif) "
+        );
+    }
+
+    #[test]
+    fn native_divergence() {
+        // A trace in which the call returned 7 rather than 6.
+        let func = parse_functions(CODE).unwrap().pop().unwrap();
+        let entry = |inst, value| TraceEntry {
+            inst: Inst::from_u32(inst),
+            result: 0,
+            value: DataValue::I32(value),
+        };
+        let native = NativeTrace {
+            function: func.name.clone(),
+            entries: vec![entry(1, 7), entry(3, 21)],
+            truncated: false,
+            trap: None,
+        };
+        let options = DebugOptions {
+            compare_native: true,
+            ..Default::default()
+        };
+        let (result, output) = debug(options, Some(native), "");
+        assert!(matches!(result, Err(InterpreterError::Stopped)));
+        assert_eq!(
+            output,
+            "\
+first divergence from native execution at %test block0: v1 = call fn0(v0)
+  v1 = 6, but native execution computed 7
+"
+        );
+    }
+
+    #[test]
+    fn native_trace() {
+        let functions = parse_functions(CODE).unwrap();
+        let tracer = host_tracer(&functions);
+        let native = tracer.trace("%test", &[DataValue::I32(5)]).unwrap();
+        assert_eq!(native.function, UserFuncName::testcase("test"));
+        assert!(!native.truncated);
+        assert_eq!(native.trap, None);
+        assert_eq!(
+            native.entries,
+            [
+                TraceEntry {
+                    inst: Inst::from_u32(1),
+                    result: 0,
+                    value: DataValue::I32(6),
+                },
+                TraceEntry {
+                    inst: Inst::from_u32(3),
+                    result: 0,
+                    value: DataValue::I32(18),
+                },
+            ]
+        );
+
+        let options = DebugOptions {
+            compare_native: true,
+            ..Default::default()
+        };
+        let (result, output) = debug(options, Some(native), "");
+        assert_eq!(result.unwrap(), [DataValue::I32(18)]);
+        assert_eq!(output, "");
+    }
+
+    #[test]
+    fn native_trap() {
+        let functions = parse_functions(TRAP_CODE).unwrap();
+        let tracer = host_tracer(&functions);
+        let args = [DataValue::I32(1), DataValue::I32(0)];
+        let native = tracer.trace("%div", &args).unwrap();
+        assert_eq!(native.entries, []);
+        assert_eq!(native.trap, Some(TrapCode::IntegerDivisionByZero));
+
+        let options = DebugOptions {
+            compare_native: true,
+            ..Default::default()
+        };
+        let trap = CraneliftTrap::User(TrapCode::IntegerDivisionByZero);
+        let (result, output) =
+            debug_call(TRAP_CODE, "%div", &args, options.clone(), Some(native), "");
+        assert_eq!(result.unwrap(), Err(trap.clone()));
+        assert_eq!(output, "");
+
+        // A trace in which the division trapped with another code.
+        let native = NativeTrace {
+            function: functions[0].name.clone(),
+            entries: vec![],
+            truncated: false,
+            trap: Some(TrapCode::IntegerOverflow),
+        };
+        let (result, output) =
+            debug_call(TRAP_CODE, "%div", &args, options.clone(), Some(native), "");
+        assert_eq!(result.unwrap(), Err(trap));
+        assert_eq!(
+            output,
+            "\
+first divergence from native execution at %div block0: v2 = udiv.i32 v0, v1
+  trapped: user code: int_divz, but native execution trapped: user code: int_ovf
+"
+        );
+
+        // A trace in which the call trapped after the multiplication.
+        let func = parse_functions(CODE).unwrap().pop().unwrap();
+        let entry = |inst, value| TraceEntry {
+            inst: Inst::from_u32(inst),
+            result: 0,
+            value: DataValue::I32(value),
+        };
+        let native = NativeTrace {
+            function: func.name.clone(),
+            entries: vec![entry(1, 6), entry(3, 18)],
+            truncated: false,
+            trap: Some(TrapCode::StackOverflow),
+        };
+        let (result, output) = debug(options, Some(native), "");
+        assert!(matches!(result, Err(InterpreterError::Stopped)));
+        assert_eq!(
+            output,
+            "\
+first divergence from native execution at %test block1: return v3
+  returned, but native execution trapped: user code: stk_ovf
+"
+        );
+    }
+}
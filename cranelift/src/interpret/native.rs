@@ -0,0 +1,420 @@
+//! Native execution traces for comparing the interpreter against compiled code.
+//!
+//! To find the first instruction where the interpreter and the compiled code disagree, each
+//! traced function is compiled a second time with instrumentation: an extra pointer parameter
+//! points to a trace buffer, and every result of every instruction is appended to that buffer,
+//! together with the instruction that produced it, in the order that the instructions execute.
+//! Since both executions take the same path through the function as long as they agree on all
+//! values, the first entry that doesn't match the interpreter is the first divergence.
+//!
+//! Only the instructions of the traced function itself are recorded; callees run uninstrumented
+//! and are compared through the results of the calls.
+//!
+//! On Unix hosts, the native call runs in a child process so that traps can be caught: the
+//! child's signal handler reports the faulting address, which is looked up in the trap sites of
+//! the compiled functions to find the trap code. The trace buffer is shared with the child.
+
+use anyhow::{bail, Result};
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::{
+    types, AbiParam, Function, Inst, InstBuilder, MemFlags, StackSlotData, StackSlotKind, TrapCode,
+    Type, UserFuncName,
+};
+use cranelift_codegen::isa::OwnedTargetIsa;
+use cranelift_entity::EntityRef;
+use cranelift_filetests::function_runner::CompiledTestFile;
+use cranelift_filetests::TestFileCompiler;
+use std::collections::HashMap;
+use std::ptr;
+#[cfg(unix)]
+use std::{io, mem};
+
+/// The maximum number of results recorded for a single call of a traced function.
+const MAX_ENTRIES: usize = 1 << 16;
+
+/// The size of an entry in the trace buffer: an 8 byte tag identifying the instruction result,
+/// followed by up to 16 bytes of the value.
+const ENTRY_SIZE: usize = 32;
+
+/// The offset of the value in an entry of the trace buffer.
+const VALUE_OFFSET: usize = 16;
+
+/// The tag of entries that weren't written.
+const EMPTY_TAG: u64 = u64::MAX;
+
+/// A result of an instruction of a traced function, as computed by native code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub inst: Inst,
+    /// The index of the value in the instruction's results.
+    pub result: usize,
+    pub value: DataValue,
+}
+
+/// The results computed by a native call of a traced function, in execution order.
+#[derive(Debug)]
+pub struct NativeTrace {
+    /// The name of the traced function.
+    pub function: UserFuncName,
+    pub entries: Vec<TraceEntry>,
+    /// Whether more than `MAX_ENTRIES` results were computed, of which only the first ones were
+    /// recorded.
+    pub truncated: bool,
+    /// The code of the trap which ended the call, or `None` if it returned.
+    pub trap: Option<TrapCode>,
+}
+
+/// Whether results of type `ty` are recorded in native traces.
+pub fn is_traced_type(ty: Type) -> bool {
+    (ty.is_int() || ty.is_float() || ty.is_vector())
+        && ty.bytes() as usize <= ENTRY_SIZE - VALUE_OFFSET
+}
+
+/// Compiles the functions of a file for the host, along with instrumented copies of the
+/// functions to trace.
+pub struct NativeTracer {
+    compiled: CompiledTestFile,
+    /// The original names and instrumented copies of the traced functions, by the name of the
+    /// original function.
+    traced: HashMap<String, (UserFuncName, Function)>,
+}
+
+impl NativeTracer {
+    /// Compile `functions`, which must include any function called by the `traced` functions.
+    pub fn new(isa: OwnedTargetIsa, functions: &[Function], traced: &[&Function]) -> Result<Self> {
+        if isa.pointer_type() != types::I64 {
+            bail!("native traces are only supported on 64-bit hosts");
+        }
+
+        let mut instrumented = HashMap::new();
+        for (i, func) in traced.iter().enumerate() {
+            let name = UserFuncName::testcase(format!("__native_trace{i}"));
+            instrumented.insert(
+                func.name.to_string(),
+                (func.name.clone(), instrument(func, name)),
+            );
+        }
+
+        let mut compiler = TestFileCompiler::new(isa);
+        let functions = functions
+            .iter()
+            .chain(instrumented.values().map(|(_, func)| func))
+            .cloned()
+            .collect::<Vec<_>>();
+        compiler.add_functions(&functions, Vec::new())?;
+        Ok(Self {
+            compiled: compiler.compile()?,
+            traced: instrumented,
+        })
+    }
+
+    /// Natively call the traced function named `func_name` with `arguments`, recording its
+    /// results and the trap that ends the call, if any.
+    ///
+    /// Traps are only caught on Unix hosts; elsewhere they abort the process.
+    pub fn trace(&self, func_name: &str, arguments: &[DataValue]) -> Result<NativeTrace> {
+        let Some((name, func)) = self.traced.get(func_name) else {
+            bail!("function {func_name} is not traced");
+        };
+        let trampoline = self.compiled.get_trampoline(func).unwrap();
+
+        // One extra entry at the end of the buffer is overwritten by any results past
+        // `MAX_ENTRIES`.
+        let mut buffer = TraceBuffer::new((MAX_ENTRIES + 1) * ENTRY_SIZE)?;
+        let mut arguments = arguments.to_vec();
+        arguments.push(DataValue::I64(buffer.as_mut_ptr() as i64));
+        let trap = call_catching_traps(&self.compiled, || {
+            trampoline.call(&arguments);
+        })?;
+
+        let mut trace = NativeTrace {
+            function: name.clone(),
+            entries: Vec::new(),
+            truncated: false,
+            trap,
+        };
+        for (i, entry) in buffer.as_slice().chunks_exact(ENTRY_SIZE).enumerate() {
+            let tag = u64::from_ne_bytes(entry[..8].try_into().unwrap());
+            if tag == EMPTY_TAG {
+                break;
+            }
+            if i == MAX_ENTRIES {
+                trace.truncated = true;
+                break;
+            }
+
+            let inst = Inst::new((tag >> 32) as usize);
+            let result = tag as u32 as usize;
+            let ty = func.dfg.value_type(func.dfg.inst_results(inst)[result]);
+            let value = &entry[VALUE_OFFSET..][..ty.bytes() as usize];
+            trace.entries.push(TraceEntry {
+                inst,
+                result,
+                value: DataValue::read_from_slice_ne(value, ty),
+            });
+        }
+        Ok(trace)
+    }
+}
+
+/// Run `call`, returning the code of the trap which ends it, if any.
+#[cfg(unix)]
+fn call_catching_traps(
+    compiled: &CompiledTestFile,
+    call: impl FnOnce(),
+) -> Result<Option<TrapCode>> {
+    let mut pc_buffer = TraceBuffer::new(mem::size_of::<usize>())?;
+    let pc = pc_buffer.as_mut_ptr().cast::<usize>();
+
+    match unsafe { libc::fork() } {
+        -1 => bail!("failed to fork: {}", io::Error::last_os_error()),
+        0 => {
+            // The child only runs the call, and reports the address of any trap through `pc`.
+            unsafe {
+                signals::catch_traps(pc);
+                call();
+                libc::_exit(0)
+            }
+        }
+        child => {
+            let mut status = 0;
+            if unsafe { libc::waitpid(child, &mut status, 0) } == -1 {
+                bail!(
+                    "failed to wait for native execution: {}",
+                    io::Error::last_os_error()
+                );
+            }
+            if libc::WIFSIGNALED(status) {
+                bail!(
+                    "native execution was killed by signal {}",
+                    libc::WTERMSIG(status)
+                );
+            }
+            match libc::WEXITSTATUS(status) {
+                0 => Ok(None),
+                signals::TRAPPED => {
+                    let pc = unsafe { *pc } as *const u8;
+                    match compiled.trap_code(pc) {
+                        Some(code) => Ok(Some(code)),
+                        None => {
+                            bail!("native execution crashed at {pc:p}, which isn't a trap site")
+                        }
+                    }
+                }
+                status => bail!("native execution exited with status {status}"),
+            }
+        }
+    }
+}
+
+/// Run `call`, which isn't expected to trap.
+#[cfg(not(unix))]
+fn call_catching_traps(
+    _compiled: &CompiledTestFile,
+    call: impl FnOnce(),
+) -> Result<Option<TrapCode>> {
+    call();
+    Ok(None)
+}
+
+/// A buffer which is shared with child processes on Unix hosts.
+struct TraceBuffer {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl TraceBuffer {
+    /// Allocate a buffer of `len` bytes, with all bytes set to `0xff`.
+    fn new(len: usize) -> Result<Self> {
+        #[cfg(unix)]
+        let ptr = unsafe {
+            let ptr = libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_ANON,
+                -1,
+                0,
+            );
+            if ptr == libc::MAP_FAILED {
+                bail!("failed to map trace buffer: {}", io::Error::last_os_error());
+            }
+            ptr.cast::<u8>()
+        };
+        #[cfg(not(unix))]
+        let ptr = Box::into_raw(vec![0u8; len].into_boxed_slice()).cast::<u8>();
+
+        unsafe { ptr::write_bytes(ptr, 0xff, len) };
+        Ok(Self { ptr, len })
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for TraceBuffer {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+        #[cfg(not(unix))]
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                self.ptr, self.len,
+            )));
+        }
+    }
+}
+
+/// Signal handling in the child process which runs a native call.
+#[cfg(unix)]
+mod signals {
+    use std::ptr;
+    use std::sync::atomic::{AtomicPtr, Ordering};
+
+    /// The exit status of a child process which trapped.
+    pub const TRAPPED: i32 = 101;
+
+    /// Where to report the address of a trap.
+    static TRAP_PC: AtomicPtr<usize> = AtomicPtr::new(ptr::null_mut());
+
+    /// The size of the stack that the signal handler runs on, which also handles stack
+    /// overflows.
+    const SIGNAL_STACK_SIZE: usize = 64 * 1024;
+
+    /// Report the address of any trap in `pc` and exit with [TRAPPED].
+    ///
+    /// # Safety
+    ///
+    /// This replaces the process's signal handlers, so it must only be called in a child
+    /// process which exits after running the native code.
+    pub unsafe fn catch_traps(pc: *mut usize) {
+        TRAP_PC.store(pc, Ordering::Relaxed);
+
+        let stack = Box::leak(vec![0u8; SIGNAL_STACK_SIZE].into_boxed_slice());
+        let mut signal_stack: libc::stack_t = std::mem::zeroed();
+        signal_stack.ss_sp = stack.as_mut_ptr().cast();
+        signal_stack.ss_size = stack.len();
+        libc::sigaltstack(&signal_stack, ptr::null_mut());
+
+        for signal in [libc::SIGILL, libc::SIGFPE, libc::SIGSEGV, libc::SIGBUS] {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+            action.sa_sigaction = trap_handler as *const () as usize;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, ptr::null_mut());
+        }
+    }
+
+    extern "C" fn trap_handler(
+        signal: libc::c_int,
+        _info: *mut libc::siginfo_t,
+        context: *mut libc::c_void,
+    ) {
+        unsafe {
+            *TRAP_PC.load(Ordering::Relaxed) = get_pc(context, signal) as usize;
+            libc::_exit(TRAPPED);
+        }
+    }
+
+    /// The address of the instruction which raised `signal`, or null if it isn't known on this
+    /// host.
+    unsafe fn get_pc(context: *mut libc::c_void, signal: libc::c_int) -> *const u8 {
+        let _ = signal;
+        cfg_if::cfg_if! {
+            if #[cfg(all(any(target_os = "linux", target_os = "android"), target_arch = "x86_64"))] {
+                let context = &*(context as *const libc::ucontext_t);
+                context.uc_mcontext.gregs[libc::REG_RIP as usize] as *const u8
+            } else if #[cfg(all(any(target_os = "linux", target_os = "android"), target_arch = "aarch64"))] {
+                let context = &*(context as *const libc::ucontext_t);
+                context.uc_mcontext.pc as *const u8
+            } else if #[cfg(all(target_os = "linux", target_arch = "s390x"))] {
+                // SIGILL and SIGFPE are delivered with the address *after* the faulting
+                // instruction, and the code generator registers those traps on its last byte.
+                let offset = match signal {
+                    libc::SIGILL | libc::SIGFPE => 1,
+                    _ => 0,
+                };
+                let context = &*(context as *const libc::ucontext_t);
+                (context.uc_mcontext.psw.addr - offset) as *const u8
+            } else if #[cfg(all(target_os = "linux", target_arch = "riscv64"))] {
+                let context = &*(context as *const libc::ucontext_t);
+                context.uc_mcontext.__gregs[libc::REG_PC] as *const u8
+            } else if #[cfg(all(target_os = "macos", target_arch = "x86_64"))] {
+                let context = &*(context as *const libc::ucontext_t);
+                (*context.uc_mcontext).__ss.__rip as *const u8
+            } else if #[cfg(all(target_os = "macos", target_arch = "aarch64"))] {
+                let context = &*(context as *const libc::ucontext_t);
+                (*context.uc_mcontext).__ss.__pc as *const u8
+            } else {
+                let _ = context;
+                ptr::null()
+            }
+        }
+    }
+}
+
+/// Create a copy of `func` named `name` which records its results in a trace buffer.
+///
+/// The address of the trace buffer is passed in an additional `i64` parameter. The address of the
+/// next entry and of the end of the buffer are kept in a stack slot, so that no block parameters
+/// need to be threaded through the function.
+fn instrument(func: &Function, name: UserFuncName) -> Function {
+    let mut func = func.clone();
+    func.name = name;
+
+    let traced = func
+        .layout
+        .blocks()
+        .flat_map(|block| func.layout.block_insts(block))
+        .filter(|&inst| {
+            func.dfg
+                .inst_results(inst)
+                .iter()
+                .any(|&v| is_traced_type(func.dfg.value_type(v)))
+        })
+        .collect::<Vec<_>>();
+
+    let entry = func.layout.entry_block().expect("to have an entry block");
+    let buffer = func.dfg.append_block_param(entry, types::I64);
+    func.signature.params.push(AbiParam::new(types::I64));
+    let slot = func.create_sized_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, 16));
+
+    let mut pos = FuncCursor::new(&mut func);
+    pos.goto_first_insertion_point(entry);
+    let end = pos
+        .ins()
+        .iadd_imm(buffer, (MAX_ENTRIES * ENTRY_SIZE) as i64);
+    pos.ins().stack_store(buffer, slot, 0);
+    pos.ins().stack_store(end, slot, 8);
+
+    let flags = MemFlags::new();
+    for inst in traced {
+        pos.goto_after_inst(inst);
+        let results = pos.func.dfg.inst_results(inst).to_vec();
+        for (i, v) in results.into_iter().enumerate() {
+            if !is_traced_type(pos.func.dfg.value_type(v)) {
+                continue;
+            }
+            // Once the buffer is full, all further results go to the extra entry at its end.
+            let next = pos.ins().stack_load(types::I64, slot, 0);
+            let end = pos.ins().stack_load(types::I64, slot, 8);
+            let addr = pos.ins().umin(next, end);
+            let tag = ((inst.index() as u64) << 32) | i as u64;
+            let tag = pos.ins().iconst(types::I64, tag as i64);
+            pos.ins().store(flags, tag, addr, 0);
+            pos.ins().store(flags, v, addr, VALUE_OFFSET as i32);
+            let next = pos.ins().iadd_imm(addr, ENTRY_SIZE as i64);
+            pos.ins().stack_store(next, slot, 0);
+        }
+    }
+
+    func
+}
@@ -0,0 +1,137 @@
+//! Reports the ISLE rules which are never applied while compiling a set of
+//! inputs, using the rule counters of the `isle-coverage` feature of
+//! cranelift-codegen.
+
+use crate::utils::iterate_files;
+use anyhow::Result;
+use clap::Parser;
+use cranelift_codegen::isle_coverage::rule_coverage;
+use std::path::PathBuf;
+
+/// Report ISLE rules which are never applied by filetests or Wasm modules
+#[derive(Parser)]
+pub struct Options {
+    /// Print how many times each rule was applied, instead of only the rules
+    /// which were never applied
+    #[arg(short, long)]
+    all: bool,
+
+    /// Only report the rules of these ISLE compilations, e.g. `opt` or `x64`
+    #[arg(long = "compilation")]
+    compilations: Vec<String>,
+
+    /// Configure Cranelift settings for Wasm modules
+    #[arg(long = "set")]
+    settings: Vec<String>,
+
+    /// Specify the Cranelift target for Wasm modules
+    #[arg(long = "target")]
+    target: Option<String>,
+
+    /// Filetests (`.clif`) and Wasm modules (`.wasm` or `.wat`) to compile, or
+    /// directories containing them
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+pub fn run(options: &Options) -> Result<()> {
+    let mut filetests = Vec::new();
+    let mut modules = Vec::new();
+    for path in iterate_files(&options.files) {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("clif") => filetests.push(path.display().to_string()),
+            Some("wasm" | "wat") => modules.push(path),
+            _ => {}
+        }
+    }
+
+    if !modules.is_empty() {
+        compile_modules(options, &modules)?;
+    }
+    // Failing filetests still applied rules, so report them either way.
+    let result = if filetests.is_empty() {
+        Ok(())
+    } else {
+        cranelift_filetests::run(false, false, &filetests)
+    };
+
+    report(options);
+    result
+}
+
+fn report(options: &Options) {
+    let mut compilation = "";
+    let (mut total, mut applied) = (0, 0);
+    for rule in rule_coverage() {
+        if !options.compilations.is_empty()
+            && !options.compilations.iter().any(|c| c == rule.compilation)
+        {
+            continue;
+        }
+        if rule.compilation != compilation {
+            summary(compilation, total, applied);
+            compilation = rule.compilation;
+            (total, applied) = (0, 0);
+        }
+
+        total += 1;
+        if rule.count > 0 {
+            applied += 1;
+        }
+        if options.all {
+            println!(
+                "{}: {} ({}): {}",
+                compilation, rule.pos, rule.term, rule.count
+            );
+        } else if rule.count == 0 {
+            println!(
+                "{}: {} ({}): never applied",
+                compilation, rule.pos, rule.term
+            );
+        }
+    }
+    summary(compilation, total, applied);
+}
+
+fn summary(compilation: &str, total: usize, applied: usize) {
+    if !compilation.is_empty() {
+        println!("{compilation}: {applied} of {total} rules applied");
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn compile_modules(options: &Options, modules: &[PathBuf]) -> Result<()> {
+    use anyhow::Context as _;
+    use cranelift_codegen::print_errors::pretty_error;
+    use cranelift_codegen::Context;
+    use cranelift_reader::parse_sets_and_triple;
+    use cranelift_wasm::{translate_module, DummyEnvironment};
+
+    let Some(target) = &options.target else {
+        anyhow::bail!("compiling Wasm modules requires a `--target`");
+    };
+    let parsed = parse_sets_and_triple(&options.settings, target)?;
+    let Some(isa) = parsed.as_fisa().isa else {
+        anyhow::bail!("compiling Wasm modules requires a target isa");
+    };
+
+    for path in modules {
+        let module_binary = wat::parse_file(path)?;
+        let mut dummy_environ = DummyEnvironment::new(isa.frontend_config());
+        translate_module(&module_binary, &mut dummy_environ)
+            .with_context(|| format!("failed to translate {}", path.display()))?;
+
+        for (_, func) in dummy_environ.info.function_bodies.iter() {
+            let mut context = Context::for_function(func.clone());
+            context
+                .compile(isa, &mut Default::default())
+                .map_err(|err| anyhow::anyhow!("{}", pretty_error(&err.func, err.inner)))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "wasm"))]
+fn compile_modules(_options: &Options, _modules: &[PathBuf]) -> Result<()> {
+    anyhow::bail!("Error: clif-util was compiled without wasm support.")
+}
@@ -71,6 +71,7 @@
 //!   <https://github.com/bytecodealliance/cranelift/pull/1236>
 //!     ("Relax verification to allow I8X16 to act as a default vector type")
 
+pub(crate) mod bounds_check_elim;
 mod bounds_checks;
 
 use super::{hash_map, HashMap};
@@ -2780,6 +2781,16 @@ where
     // `heap_addr` instruction plus a hardcoded i32-offset in memory-related
     // instructions.
     let heap = environ.heaps()[heap].clone();
+
+    // Record explicit checks so that redundant ones can be removed once the
+    // whole function is translated. Removed checks would leave behind facts
+    // that no longer hold, so this is skipped with proof-carrying code.
+    let checks = if environ.heap_bounds_check_elimination() && !environ.proof_carrying_code() {
+        Some(&mut state.bounds_checks)
+    } else {
+        None
+    };
+
     let addr = match u32::try_from(memarg.offset) {
         // If our offset fits within a u32, then we can place the it into the
         // offset immediate of the `heap_addr` instruction.
@@ -2790,6 +2801,7 @@ where
             index,
             offset,
             access_size,
+            checks,
         )?,
 
         // If the offset doesn't fit within a u32, then we can't pass it
@@ -2831,6 +2843,7 @@ where
                 adjusted_index,
                 0,
                 access_size,
+                checks,
             )?
         }
     };
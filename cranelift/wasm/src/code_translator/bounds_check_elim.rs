@@ -0,0 +1,734 @@
+//! Elimination of redundant bounds checks on dynamic heaps.
+//!
+//! Every access to a dynamic heap is preceded by an explicit comparison of the
+//! accessed index against the heap's current bound (see `bounds_checks.rs`).
+//! The egraph merges checks which are exactly identical, but it can't know
+//! that
+//!
+//! * a check that `index + 8 <= bound` makes a later check that
+//!   `index + 4 <= bound` redundant,
+//! * a check on `index` makes a later check on `index + 4` redundant, as
+//!   long as the first check covered those extra bytes, and
+//! * while the bound is reloaded for every check because the heap may grow,
+//!   heaps never shrink, so a check still holds after calls or `memory.grow`.
+//!
+//! This pass uses all three facts. Each explicit check is recorded with the
+//! range of the index that it establishes, symbolically as `base + addend +
+//! checked <= bound`, much like the `Fact::Compare` facts of proof-carrying
+//! code. Once the function is translated, a check that is dominated by a
+//! check establishing a larger range is removed.
+//!
+//! Indices are only related to each other through constant additions. A check
+//! inside a loop is therefore covered by a check of the very same SSA index,
+//! for example one made before the loop on a loop-invariant index, but not by
+//! the check of the previous iteration.
+//!
+//! Checks of an index that changes every iteration are instead hoisted out of
+//! counted loops. When an innermost loop
+//!
+//! * is only left by the branch ending every iteration, which compares a
+//!   counter stepping by a constant to a loop-invariant limit with `<` when
+//!   counting up by one, or with `!=` when stepping by a power of two,
+//! * can't trap or have side effects, besides its loads and its checks, and
+//! * has a single check left, on an index `invariant + scale * iv + addend`
+//!   for a parameter `iv` of the loop header that also steps by a constant,
+//!
+//! then the number of iterations is known when entering the loop, and the
+//! checked indices form an arithmetic sequence whose first and last elements
+//! bound all of the others. A check of both is emitted before the loop and the
+//! check in the loop is removed. When the hoisted check fails, the check of
+//! some iteration would have failed, and as the iterations before it have no
+//! effects, trapping before the loop can't be told apart from trapping in
+//! that iteration. The sequence of indices may wrap around, but the index
+//! changes by less than 64 KiB per iteration while a heap, made of 64 KiB
+//! pages, ends at least that far below 2^64: an index that wraps around first
+//! steps onto an out-of-bounds index, which the hoisted check accounts for.
+//!
+//! Without Spectre mitigations a check is a `trapnz`, which is simply
+//! removed. With Spectre mitigations the check is the condition of a
+//! `select_spectre_guard`, which must stay so that a misspeculated access
+//! can't use an out-of-bounds address; instead the guard reuses the dominating
+//! check's condition, and the comparison feeding the original condition along
+//! with its bound load become dead. A hoisted check is only a condition as
+//! well, which the guards in the loop use, so that the first access in the
+//! loop traps when it fails.
+//!
+//! Other checks are never hoisted above the code that precedes them since Wasm
+//! traps must happen exactly at the out-of-bounds access.
+//!
+//! The pass doesn't run when proof-carrying code is enabled: the facts on the
+//! address computed behind a removed check are only proven by that check, and
+//! the PCC checker doesn't relate an index to the differently offset index of
+//! the covering check.
+
+use crate::HashMap;
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::dominator_tree::DominatorTree;
+use cranelift_codegen::flowgraph::ControlFlowGraph;
+use cranelift_codegen::ir::condcodes::{CondCode, IntCC};
+use cranelift_codegen::ir::{self, InstBuilder, InstructionData, Opcode};
+use cranelift_codegen::loop_analysis::{Loop, LoopAnalysis};
+use std::vec::Vec;
+
+/// The maximum number of checks of the same base index considered for
+/// covering each other. Comparing all pairs is quadratic, and checks of one
+/// pointer are rarely this numerous outside of generated code.
+const MAX_CHECKS_PER_INDEX: usize = 64;
+
+/// The index of a hoisted check must change by less than this in every
+/// iteration. Heaps are made of 64 KiB pages, so a heap's bound is at most
+/// `2^64 - 2^16` and an index that wraps around steps over the bound.
+const MAX_HOISTED_DELTA: u64 = 1 << 16;
+
+/// An explicit bounds check emitted for an access to a dynamic heap.
+pub(crate) struct DynamicBoundsCheck {
+    /// The global value of the heap bound that the index was checked against.
+    pub bound_gv: ir::GlobalValue,
+    /// The pointer-sized index that was checked.
+    pub index: ir::Value,
+    /// When the check succeeds, `index + checked <= bound` holds.
+    ///
+    /// This is zero for checks that rely on guard pages to catch the access's
+    /// offset and size.
+    pub checked: u64,
+    /// The `i8` condition which is non-zero when the check fails.
+    pub oob: ir::Value,
+    /// The `trapnz` or `select_spectre_guard` that consumes `oob`.
+    pub guard: ir::Inst,
+    /// The `uadd_overflow_trap` computing `index + checked`, if any.
+    pub overflow_check: Option<ir::Inst>,
+}
+
+/// The explicit bounds checks emitted while translating a function.
+#[derive(Default)]
+pub(crate) struct DynamicBoundsChecks {
+    checks: Vec<DynamicBoundsCheck>,
+}
+
+impl DynamicBoundsChecks {
+    pub(crate) fn clear(&mut self) {
+        self.checks.clear();
+    }
+
+    pub(crate) fn push(&mut self, check: DynamicBoundsCheck) {
+        self.checks.push(check);
+    }
+
+    /// Remove the recorded checks in `func` which are covered by a dominating
+    /// check, hoist the checks of counted loops, then forget all recorded
+    /// checks.
+    pub(crate) fn eliminate_redundant(&mut self, func: &mut ir::Function) {
+        let checks = core::mem::take(&mut self.checks);
+        if checks.is_empty() {
+            return;
+        }
+
+        // Group the checks by the bound they compare against and the base of
+        // their index, since only checks within a group can cover each other.
+        let mut addends = Vec::with_capacity(checks.len());
+        let mut groups: HashMap<(ir::GlobalValue, ir::Value), Vec<usize>> = HashMap::new();
+        for (i, check) in checks.iter().enumerate() {
+            let (base, addend) = split_index(&func.dfg, check.index);
+            addends.push(addend);
+            if func.layout.inst_block(check.guard).is_some() {
+                groups.entry((check.bound_gv, base)).or_default().push(i);
+            }
+        }
+
+        let cfg = ControlFlowGraph::with_function(func);
+        let domtree = DominatorTree::with_function(func, &cfg);
+
+        // `a` covers `b` when `a` succeeding implies that `b` succeeds:
+        //
+        //     base + addend_a + checked_a <= bound_a <= bound_b
+        //
+        // where `bound_a <= bound_b` because the bound never decreases. If
+        // `addend_a <= addend_b`, then `index_b` is `index_a` plus a
+        // non-negative delta that doesn't wrap, since `index_a + checked_a`
+        // doesn't. It's then enough that `addend_b + checked_b` is at most
+        // `addend_a + checked_a`.
+        let covers = |a: usize, b: usize| {
+            let end = |i: usize| addends[i].checked_add(checks[i].checked);
+            addends[a] <= addends[b]
+                && matches!((end(a), end(b)), (Some(end_a), Some(end_b)) if end_b <= end_a)
+                && domtree.dominates(checks[a].guard, checks[b].guard, &func.layout)
+        };
+
+        // Every covered check is covered by a strictly dominating check, so
+        // following `covered_by` always terminates.
+        let mut covered_by = vec![None; checks.len()];
+        for group in groups.values() {
+            let group = &group[..group.len().min(MAX_CHECKS_PER_INDEX)];
+            for &b in group {
+                covered_by[b] = group.iter().copied().find(|&a| a != b && covers(a, b));
+            }
+        }
+
+        // The check that each covered check is ultimately covered by.
+        let mut roots = vec![None; checks.len()];
+        for (i, check) in checks.iter().enumerate() {
+            let Some(mut root) = covered_by[i] else {
+                continue;
+            };
+            while let Some(next) = covered_by[root] {
+                root = next;
+            }
+            roots[i] = Some(root);
+            log::trace!(
+                "bounds check on {} at {} is covered by the check at {}",
+                check.index,
+                check.guard,
+                checks[root].guard
+            );
+            remove_check(func, check, checks[root].oob);
+        }
+
+        hoist_from_loops(func, &cfg, &domtree, &checks, &roots);
+    }
+}
+
+/// Remove the check `check`, whose success is implied by `oob` being zero.
+fn remove_check(func: &mut ir::Function, check: &DynamicBoundsCheck, oob: ir::Value) {
+    match func.dfg.insts[check.guard].opcode() {
+        Opcode::Trapnz => func.layout.remove_inst(check.guard),
+        Opcode::SelectSpectreGuard => {
+            func.dfg.inst_args_mut(check.guard)[0] = oob;
+        }
+        opcode => unreachable!("unexpected bounds check guard: {opcode}"),
+    }
+
+    // Whatever implies the check also proves that the addition can't
+    // overflow.
+    if let Some(inst) = check.overflow_check {
+        let args = func.dfg.inst_args(inst);
+        let (x, y) = (args[0], args[1]);
+        func.dfg.replace(inst).iadd(x, y);
+    }
+}
+
+/// Hoist the only remaining check of each counted loop without side effects
+/// to the loop's preheader, see the module documentation.
+fn hoist_from_loops(
+    func: &mut ir::Function,
+    cfg: &ControlFlowGraph,
+    domtree: &DominatorTree,
+    checks: &[DynamicBoundsCheck],
+    roots: &[Option<usize>],
+) {
+    let mut loop_analysis = LoopAnalysis::new();
+    loop_analysis.compute(func, cfg, domtree);
+
+    for lp in loop_analysis.loops() {
+        let blocks = func
+            .layout
+            .blocks()
+            .filter(|&block| loop_analysis.is_in_loop(block, lp))
+            .collect::<Vec<_>>();
+        // The number of iterations of an inner loop isn't known before the
+        // outer loop is entered.
+        if blocks
+            .iter()
+            .any(|&block| loop_analysis.innermost_loop(block) != Some(lp))
+        {
+            continue;
+        }
+
+        // The check must be the only one left in the loop, so that hoisting
+        // it doesn't change which access traps first.
+        let mut remaining = checks.iter().enumerate().filter(|&(i, check)| {
+            roots[i].is_none()
+                && func
+                    .layout
+                    .inst_block(check.guard)
+                    .is_some_and(|block| loop_analysis.is_in_loop(block, lp))
+        });
+        let (Some((i, check)), None) = (remaining.next(), remaining.next()) else {
+            continue;
+        };
+
+        let Some(counted) = CountedLoop::new(func, cfg, &loop_analysis, lp, &blocks) else {
+            continue;
+        };
+        let ty = func.dfg.value_type(check.index);
+        let guard_block = func.layout.inst_block(check.guard).unwrap();
+        if func.dfg.value_type(counted.limit) != ty
+            || !domtree.dominates(guard_block, counted.latch, &func.layout)
+            || !has_no_side_effects(func, &blocks, check)
+        {
+            continue;
+        }
+        let Some(index) = counted.affine_index(func, &loop_analysis, lp, check.index) else {
+            continue;
+        };
+
+        log::trace!(
+            "bounds check on {} at {} is hoisted out of the loop at {}",
+            check.index,
+            check.guard,
+            counted.header
+        );
+        let oob = counted.emit_check(func, check, &index);
+        if func.dfg.insts[check.guard].opcode() == Opcode::Trapnz {
+            let srcloc = func.srcloc(check.guard);
+            let mut pos = FuncCursor::new(func)
+                .at_inst(counted.preheader_jump)
+                .with_srcloc(srcloc);
+            pos.ins().trapnz(oob, ir::TrapCode::HeapOutOfBounds);
+        } else {
+            // The checks covered by the hoisted one use its condition.
+            for (j, covered) in checks.iter().enumerate() {
+                if roots[j] == Some(i) {
+                    func.dfg.inst_args_mut(covered.guard)[0] = oob;
+                }
+            }
+        }
+        remove_check(func, check, oob);
+    }
+}
+
+/// Whether the instructions in `blocks` neither have side effects nor may trap,
+/// besides the bounds check `check` and loads.
+///
+/// Loads may only trap on out-of-bounds heap accesses, with the same trap as
+/// the check.
+fn has_no_side_effects(
+    func: &ir::Function,
+    blocks: &[ir::Block],
+    check: &DynamicBoundsCheck,
+) -> bool {
+    blocks.iter().all(|&block| {
+        func.layout.block_insts(block).all(|inst| {
+            if inst == check.guard || Some(inst) == check.overflow_check {
+                return true;
+            }
+            let opcode = func.dfg.insts[inst].opcode();
+            match opcode {
+                Opcode::Jump | Opcode::Brif | Opcode::BrTable | Opcode::SelectSpectreGuard => true,
+                _ => {
+                    !opcode.is_terminator()
+                        && !opcode.is_call()
+                        && !opcode.can_trap()
+                        && !opcode.can_store()
+                        && !opcode.other_side_effects()
+                }
+            }
+        })
+    })
+}
+
+/// A loop whose number of iterations is known when it's entered.
+///
+/// The loop's header has a single predecessor outside of the loop, and a
+/// single one inside of it: the latch, which ends every iteration and is the
+/// only block that branches out of the loop. Its branch compares a counter,
+/// a parameter of the header that changes by a constant step in every
+/// iteration, to a loop-invariant limit.
+struct CountedLoop {
+    header: ir::Block,
+    /// The `jump` that enters the loop.
+    preheader_jump: ir::Inst,
+    latch: ir::Block,
+    /// The arguments of the header in the first iteration.
+    initial: Vec<ir::Value>,
+    /// The arguments of the header in the following iterations.
+    next: Vec<ir::Value>,
+    /// The index of the counter among the parameters of the header.
+    counter: usize,
+    /// The loop continues while `counter + step` compares to `limit` with
+    /// `cond`.
+    cond: IntCC,
+    limit: ir::Value,
+}
+
+impl CountedLoop {
+    fn new(
+        func: &ir::Function,
+        cfg: &ControlFlowGraph,
+        loop_analysis: &LoopAnalysis,
+        lp: Loop,
+        blocks: &[ir::Block],
+    ) -> Option<CountedLoop> {
+        let dfg = &func.dfg;
+        let header = loop_analysis.loop_header(lp);
+        let mut preheader_jump = None;
+        let mut latch = None;
+        for pred in cfg.pred_iter(header) {
+            let pred_slot = if loop_analysis.is_in_loop(pred.block, lp) {
+                &mut latch
+            } else {
+                &mut preheader_jump
+            };
+            if pred_slot.replace((pred.block, pred.inst)).is_some() {
+                return None;
+            }
+        }
+        let ((_, preheader_jump), (latch, backedge)) = (preheader_jump?, latch?);
+
+        let InstructionData::Jump { destination, .. } = dfg.insts[preheader_jump] else {
+            return None;
+        };
+        let InstructionData::Brif {
+            arg, blocks: dests, ..
+        } = dfg.insts[backedge]
+        else {
+            return None;
+        };
+        let (continues, backedge_dest, exit) = if dests[0].block(&dfg.value_lists) == header {
+            (true, dests[0], dests[1])
+        } else {
+            (false, dests[1], dests[0])
+        };
+        if loop_analysis.is_in_loop(exit.block(&dfg.value_lists), lp) {
+            return None;
+        }
+        for &block in blocks {
+            if block != latch
+                && cfg
+                    .succ_iter(block)
+                    .any(|succ| !loop_analysis.is_in_loop(succ, lp))
+            {
+                return None;
+            }
+        }
+
+        let mut cond_value = dfg.resolve_aliases(arg);
+        if let Some(InstructionData::Unary {
+            opcode: Opcode::Uextend,
+            arg,
+        }) = value_inst(dfg, cond_value)
+        {
+            cond_value = dfg.resolve_aliases(arg);
+        }
+        let Some(InstructionData::IntCompare {
+            opcode: Opcode::Icmp,
+            args,
+            cond,
+        }) = value_inst(dfg, cond_value)
+        else {
+            return None;
+        };
+        let cond = if continues { cond } else { cond.complement() };
+        let is_invariant = |value| {
+            defined_outside(func, loop_analysis, lp, value) || iconst_value(dfg, value).is_some()
+        };
+        let (next, limit, cond) = if is_invariant(args[1]) {
+            (args[0], args[1], cond)
+        } else if is_invariant(args[0]) {
+            (args[1], args[0], cond.swap_args())
+        } else {
+            return None;
+        };
+
+        let resolve = |values: &[ir::Value]| {
+            values
+                .iter()
+                .map(|&value| dfg.resolve_aliases(value))
+                .collect::<Vec<_>>()
+        };
+        let counted = CountedLoop {
+            header,
+            preheader_jump,
+            latch,
+            initial: resolve(destination.args_slice(&dfg.value_lists)),
+            next: resolve(backedge_dest.args_slice(&dfg.value_lists)),
+            counter: 0,
+            cond,
+            limit: dfg.resolve_aliases(limit),
+        };
+        let next = dfg.resolve_aliases(next);
+        let counter = counted.next.iter().position(|&value| value == next)?;
+        let step = counted.step(func, counter)?;
+        let step_size = step.min(step.wrapping_neg());
+        match cond {
+            IntCC::UnsignedLessThan if step == 1 => {}
+            IntCC::NotEqual if step_size.is_power_of_two() => {}
+            _ => return None,
+        }
+        Some(CountedLoop { counter, ..counted })
+    }
+
+    /// The constant by which the parameter `param` of the header changes in
+    /// every iteration, if any.
+    fn step(&self, func: &ir::Function, param: usize) -> Option<u64> {
+        let dfg = &func.dfg;
+        let value = dfg.block_params(self.header)[param];
+        let is_value = |x| dfg.resolve_aliases(x) == value;
+        match value_inst(dfg, *self.next.get(param)?)? {
+            InstructionData::Binary {
+                opcode: Opcode::Iadd,
+                args,
+            } => [(args[0], args[1]), (args[1], args[0])]
+                .into_iter()
+                .find(|&(x, _)| is_value(x))
+                .and_then(|(_, step)| iconst_value(dfg, step)),
+            InstructionData::Binary {
+                opcode: Opcode::Isub,
+                args,
+            } if is_value(args[0]) => iconst_value(dfg, args[1]).map(u64::wrapping_neg),
+            _ => None,
+        }
+    }
+
+    /// Split `index` into an affine function of a parameter of the header,
+    /// whose index changes by less than 64 KiB in every iteration.
+    fn affine_index(
+        &self,
+        func: &ir::Function,
+        loop_analysis: &LoopAnalysis,
+        lp: Loop,
+        index: ir::Value,
+    ) -> Option<AffineIndex> {
+        let dfg = &func.dfg;
+        let (base, addend) = split_index(dfg, index);
+        if dfg.value_type(base) != dfg.value_type(index) {
+            return None;
+        }
+
+        // The base is `scaled` or `invariant + scaled`.
+        let mut invariant = None;
+        let mut scaled = base;
+        if let Some(InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            args,
+        }) = value_inst(dfg, base)
+        {
+            let (x, y) = (dfg.resolve_aliases(args[0]), dfg.resolve_aliases(args[1]));
+            if defined_outside(func, loop_analysis, lp, x) {
+                (invariant, scaled) = (Some(x), y);
+            } else if defined_outside(func, loop_analysis, lp, y) {
+                (invariant, scaled) = (Some(y), x);
+            }
+        }
+
+        // The scaled part is `param`, `param << shift` or `param * scale`.
+        let mut param = scaled;
+        let mut scale = 1;
+        if let Some(InstructionData::Binary { opcode, args }) = value_inst(dfg, scaled) {
+            let amount = iconst_value(dfg, args[1]);
+            (param, scale) = match (opcode, amount) {
+                (Opcode::Ishl, Some(shift)) => (args[0], 1u64 << (shift & 63)),
+                (Opcode::Imul, Some(factor)) => (args[0], factor),
+                (Opcode::Imul, None) => (args[1], iconst_value(dfg, args[0])?),
+                _ => return None,
+            };
+        }
+        let param = match dfg.value_def(dfg.resolve_aliases(param)) {
+            ir::ValueDef::Param(block, num) if block == self.header => num,
+            _ => return None,
+        };
+
+        // The index changes by `scale * step` modulo 2^64 in every iteration.
+        let delta = scale.wrapping_mul(self.step(func, param)?);
+        if delta == 0 || delta.min(delta.wrapping_neg()) >= MAX_HOISTED_DELTA {
+            return None;
+        }
+        Some(AffineIndex {
+            invariant,
+            param,
+            scale,
+            addend,
+            delta,
+        })
+    }
+
+    /// Emit code before the loop that computes whether the check of `index`
+    /// fails in some iteration, and return that condition.
+    fn emit_check(
+        &self,
+        func: &mut ir::Function,
+        check: &DynamicBoundsCheck,
+        index: &AffineIndex,
+    ) -> ir::Value {
+        let ty = func.dfg.value_type(check.index);
+        let counter_step = self.step(func, self.counter).unwrap();
+        let srcloc = func.srcloc(check.guard);
+        let mut pos = FuncCursor::new(func)
+            .at_inst(self.preheader_jump)
+            .with_srcloc(srcloc);
+
+        // The number of iterations after the first one.
+        let initial = self.initial[self.counter];
+        let limit = match iconst_value(&pos.func.dfg, self.limit) {
+            Some(limit) => pos.ins().iconst(ty, limit as i64),
+            None => self.limit,
+        };
+        let (count, misaligned) = match self.cond {
+            // The counter goes up to `limit - 1`, unless it starts at or above
+            // the limit: then there's just one iteration, or it wraps around
+            // to zero if it starts at the maximum.
+            IntCC::UnsignedLessThan => {
+                let distance = pos.ins().isub(limit, initial);
+                let remaining = pos.ins().iadd_imm(distance, -1);
+                let below = pos.ins().icmp(IntCC::UnsignedLessThan, initial, limit);
+                let wraps = pos.ins().icmp_imm(IntCC::Equal, initial, -1);
+                let zero = pos.ins().iconst(ty, 0);
+                let wrapped = pos.ins().select(wraps, limit, zero);
+                (pos.ins().select(below, remaining, wrapped), None)
+            }
+            // The counter steps towards the limit, modulo 2^64, and never
+            // reaches it if the distance isn't a multiple of the step.
+            IntCC::NotEqual => {
+                let (distance, step_size) = if (counter_step as i64) > 0 {
+                    (pos.ins().isub(limit, initial), counter_step)
+                } else {
+                    (pos.ins().isub(initial, limit), counter_step.wrapping_neg())
+                };
+                let mut count = pos
+                    .ins()
+                    .iadd_imm(distance, step_size.wrapping_neg() as i64);
+                if step_size > 1 {
+                    count = pos
+                        .ins()
+                        .ushr_imm(count, i64::from(step_size.trailing_zeros()));
+                }
+                let misaligned = (step_size > 1).then(|| {
+                    let rest = pos.ins().band_imm(distance, (step_size - 1) as i64);
+                    pos.ins().icmp_imm(IntCC::NotEqual, rest, 0)
+                });
+                (count, misaligned)
+            }
+            cond => unreachable!("unexpected loop condition: {cond}"),
+        };
+
+        // The index checked in the first iteration.
+        let initial = self.initial[index.param];
+        let mut first = if index.scale == 1 {
+            initial
+        } else {
+            pos.ins().imul_imm(initial, index.scale as i64)
+        };
+        if let Some(invariant) = index.invariant {
+            first = pos.ins().iadd(invariant, first);
+        }
+        if index.addend != 0 {
+            first = pos.ins().iadd_imm(first, index.addend as i64);
+        }
+
+        // The check in the first iteration, without wrapping.
+        let bound = pos.ins().global_value(ty, check.bound_gv);
+        let checked = pos.ins().iconst(ty, check.checked as i64);
+        let (end, overflows) = add_overflow(&mut pos, first, checked);
+        let past_bound = pos.ins().icmp(IntCC::UnsignedGreaterThan, end, bound);
+        let mut oob = pos.ins().bor(overflows, past_bound);
+
+        // The distance to the last index, without wrapping.
+        let delta_size = index.delta.min(index.delta.wrapping_neg());
+        let delta = pos.ins().imul_imm(count, delta_size as i64);
+        let overflows = pos.ins().icmp_imm(
+            IntCC::UnsignedGreaterThan,
+            count,
+            (u64::MAX / delta_size) as i64,
+        );
+        oob = pos.ins().bor(oob, overflows);
+
+        if index.delta == delta_size {
+            // The last index is the largest.
+            let (last_end, overflows) = add_overflow(&mut pos, end, delta);
+            let past_bound = pos.ins().icmp(IntCC::UnsignedGreaterThan, last_end, bound);
+            oob = pos.ins().bor(oob, overflows);
+            oob = pos.ins().bor(oob, past_bound);
+        } else {
+            // The first index is the largest, and the last one mustn't go
+            // below zero.
+            let below_zero = pos.ins().icmp(IntCC::UnsignedLessThan, first, delta);
+            oob = pos.ins().bor(oob, below_zero);
+        }
+
+        // A counter that never reaches its limit wraps around, and so does the
+        // index.
+        if let Some(misaligned) = misaligned {
+            oob = pos.ins().bor(oob, misaligned);
+        }
+        oob
+    }
+}
+
+/// An index of the form `invariant + scale * param + addend`, where `param` is
+/// a parameter of a loop's header and `invariant` is defined outside of the
+/// loop.
+struct AffineIndex {
+    invariant: Option<ir::Value>,
+    param: usize,
+    scale: u64,
+    addend: u64,
+    /// How much the index changes in every iteration, modulo 2^64.
+    delta: u64,
+}
+
+/// Add `x` and `y`, and return the sum along with whether it overflows.
+fn add_overflow(pos: &mut FuncCursor, x: ir::Value, y: ir::Value) -> (ir::Value, ir::Value) {
+    let sum = pos.ins().iadd(x, y);
+    let overflows = pos.ins().icmp(IntCC::UnsignedLessThan, sum, x);
+    (sum, overflows)
+}
+
+/// Whether `value` is defined outside of the loop `lp`.
+fn defined_outside(
+    func: &ir::Function,
+    loop_analysis: &LoopAnalysis,
+    lp: Loop,
+    value: ir::Value,
+) -> bool {
+    let block = match func.dfg.value_def(func.dfg.resolve_aliases(value)) {
+        ir::ValueDef::Result(inst, _) => func.layout.inst_block(inst),
+        ir::ValueDef::Param(block, _) => Some(block),
+        ir::ValueDef::Union(..) => None,
+    };
+    block.is_some_and(|block| !loop_analysis.is_in_loop(block, lp))
+}
+
+fn value_inst(dfg: &ir::DataFlowGraph, value: ir::Value) -> Option<InstructionData> {
+    match dfg.value_def(dfg.resolve_aliases(value)) {
+        ir::ValueDef::Result(inst, _) => Some(dfg.insts[inst]),
+        _ => None,
+    }
+}
+
+/// Split a bounds-checked index into a base value and a constant addend.
+///
+/// Different `uextend`s of the same 32-bit index are the same index. For
+/// pointer-sized indices, additions of a constant are looked through; the
+/// resulting addend is only relied upon by `covers` when the addition is known
+/// not to wrap.
+fn split_index(dfg: &ir::DataFlowGraph, index: ir::Value) -> (ir::Value, u64) {
+    let index = dfg.resolve_aliases(index);
+    let ir::ValueDef::Result(inst, _) = dfg.value_def(index) else {
+        return (index, 0);
+    };
+    match dfg.insts[inst] {
+        InstructionData::Unary {
+            opcode: Opcode::Uextend,
+            arg,
+        } => (dfg.resolve_aliases(arg), 0),
+        InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            args,
+        } => {
+            for (base, addend) in [(args[0], args[1]), (args[1], args[0])] {
+                if let Some(addend) = iconst_value(dfg, addend) {
+                    return (dfg.resolve_aliases(base), addend);
+                }
+            }
+            (index, 0)
+        }
+        _ => (index, 0),
+    }
+}
+
+fn iconst_value(dfg: &ir::DataFlowGraph, value: ir::Value) -> Option<u64> {
+    let value = dfg.resolve_aliases(value);
+    let ir::ValueDef::Result(inst, _) = dfg.value_def(value) else {
+        return None;
+    };
+    match dfg.insts[inst] {
+        InstructionData::UnaryImm {
+            opcode: Opcode::Iconst,
+            imm,
+        } => {
+            let bits = dfg.value_type(value).bits();
+            let mask = u64::MAX >> (64 - bits);
+            Some(imm.bits() as u64 & mask)
+        }
+        _ => None,
+    }
+}
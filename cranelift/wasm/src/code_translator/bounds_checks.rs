@@ -19,6 +19,7 @@
 //! !!!                                                                      !!!
 //! !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
 
+use super::bounds_check_elim::{DynamicBoundsCheck, DynamicBoundsChecks};
 use super::Reachability;
 use crate::{FuncEnvironment, HeapData, HeapStyle};
 use cranelift_codegen::{
@@ -35,6 +36,9 @@ use Reachability::*;
 ///
 /// Returns the `ir::Value` holding the native address of the heap access, or
 /// `None` if the heap access will unconditionally trap.
+///
+/// Explicit checks of dynamic heaps are recorded in `checks`, if given, so that
+/// redundant ones can be removed once the whole function is translated.
 pub fn bounds_check_and_compute_addr<Env>(
     builder: &mut FunctionBuilder,
     env: &mut Env,
@@ -45,6 +49,7 @@ pub fn bounds_check_and_compute_addr<Env>(
     offset: u32,
     // Static size of the heap access.
     access_size: u8,
+    checks: Option<&mut DynamicBoundsChecks>,
 ) -> WasmResult<Reachability<ir::Value>>
 where
     Env: FuncEnvironment + ?Sized,
@@ -150,7 +155,7 @@ where
                 bound,
                 Some(0),
             );
-            Reachable(explicit_check_oob_condition_and_compute_addr(
+            let (addr, guard) = explicit_check_oob_condition_and_compute_addr(
                 &mut builder.cursor(),
                 heap,
                 env.pointer_type(),
@@ -160,7 +165,18 @@ where
                 spectre_mitigations_enabled,
                 AddrPcc::dynamic(heap.memory_type, bound_gv),
                 oob,
-            ))
+            );
+            if let Some(checks) = checks {
+                checks.push(DynamicBoundsCheck {
+                    bound_gv,
+                    index,
+                    checked: 1,
+                    oob,
+                    guard,
+                    overflow_check: None,
+                });
+            }
+            Reachable(addr)
         }
 
         // 2. Second special case for when we know that there are enough guard
@@ -198,7 +214,7 @@ where
                 bound,
                 Some(0),
             );
-            Reachable(explicit_check_oob_condition_and_compute_addr(
+            let (addr, guard) = explicit_check_oob_condition_and_compute_addr(
                 &mut builder.cursor(),
                 heap,
                 env.pointer_type(),
@@ -208,7 +224,18 @@ where
                 spectre_mitigations_enabled,
                 AddrPcc::dynamic(heap.memory_type, bound_gv),
                 oob,
-            ))
+            );
+            if let Some(checks) = checks {
+                checks.push(DynamicBoundsCheck {
+                    bound_gv,
+                    index,
+                    checked: 0,
+                    oob,
+                    guard,
+                    overflow_check: None,
+                });
+            }
+            Reachable(addr)
         }
 
         // 3. Third special case for when `offset + access_size <= min_size`.
@@ -242,7 +269,7 @@ where
                 adjusted_bound,
                 Some(adjustment),
             );
-            Reachable(explicit_check_oob_condition_and_compute_addr(
+            let (addr, guard) = explicit_check_oob_condition_and_compute_addr(
                 &mut builder.cursor(),
                 heap,
                 env.pointer_type(),
@@ -252,7 +279,18 @@ where
                 spectre_mitigations_enabled,
                 AddrPcc::dynamic(heap.memory_type, bound_gv),
                 oob,
-            ))
+            );
+            if let Some(checks) = checks {
+                checks.push(DynamicBoundsCheck {
+                    bound_gv,
+                    index,
+                    checked: offset_and_size,
+                    oob,
+                    guard,
+                    overflow_check: None,
+                });
+            }
+            Reachable(addr)
         }
 
         // 4. General case for dynamic memories:
@@ -291,7 +329,7 @@ where
                 bound,
                 Some(0),
             );
-            Reachable(explicit_check_oob_condition_and_compute_addr(
+            let (addr, guard) = explicit_check_oob_condition_and_compute_addr(
                 &mut builder.cursor(),
                 heap,
                 env.pointer_type(),
@@ -301,7 +339,18 @@ where
                 spectre_mitigations_enabled,
                 AddrPcc::dynamic(heap.memory_type, bound_gv),
                 oob,
-            ))
+            );
+            if let Some(checks) = checks {
+                checks.push(DynamicBoundsCheck {
+                    bound_gv,
+                    index,
+                    checked: offset_and_size,
+                    oob,
+                    guard,
+                    overflow_check: Some(builder.func.dfg.value_def(adjusted_index).unwrap_inst()),
+                });
+            }
+            Reachable(addr)
         }
 
         // ====== Static Memories ======
@@ -404,7 +453,7 @@ where
                 adjusted_bound_value,
                 Some(0),
             );
-            Reachable(explicit_check_oob_condition_and_compute_addr(
+            let (addr, _guard) = explicit_check_oob_condition_and_compute_addr(
                 &mut builder.cursor(),
                 heap,
                 env.pointer_type(),
@@ -414,7 +463,8 @@ where
                 spectre_mitigations_enabled,
                 AddrPcc::static32(heap.memory_type, u64::from(bound)),
                 oob,
-            ))
+            );
+            Reachable(addr)
         }
     })
 }
@@ -528,7 +578,8 @@ impl AddrPcc {
 }
 
 /// Emit explicit checks on the given out-of-bounds condition for the Wasm
-/// address and return the native address.
+/// address and return the native address, along with the instruction that
+/// consumes the condition: either a `trapnz` or a `select_spectre_guard`.
 ///
 /// This function deduplicates explicit bounds checks and Spectre mitigations
 /// that inherently also implement bounds checking.
@@ -547,10 +598,13 @@ fn explicit_check_oob_condition_and_compute_addr(
     // bounds (and therefore we should trap) and is zero when the heap access is
     // in bounds (and therefore we can proceed).
     oob_condition: ir::Value,
-) -> ir::Value {
+) -> (ir::Value, ir::Inst) {
+    let mut guard = None;
     if !spectre_mitigations_enabled {
-        pos.ins()
-            .trapnz(oob_condition, ir::TrapCode::HeapOutOfBounds);
+        guard = Some(
+            pos.ins()
+                .trapnz(oob_condition, ir::TrapCode::HeapOutOfBounds),
+        );
     }
 
     let mut addr = compute_addr(pos, heap, addr_ty, index, offset, pcc);
@@ -558,6 +612,7 @@ fn explicit_check_oob_condition_and_compute_addr(
     if spectre_mitigations_enabled {
         let null = pos.ins().iconst(addr_ty, 0);
         addr = pos.ins().select_spectre_guard(oob_condition, null, addr);
+        guard = Some(pos.func.dfg.value_def(addr).unwrap_inst());
 
         match pcc {
             None => {}
@@ -591,7 +646,7 @@ fn explicit_check_oob_condition_and_compute_addr(
         }
     }
 
-    addr
+    (addr, guard.unwrap())
 }
 
 /// Emit code for the native address computation of a Wasm address,
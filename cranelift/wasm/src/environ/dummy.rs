@@ -262,6 +262,10 @@ impl<'dummy_environment> TargetEnvironment for DummyFuncEnvironment<'dummy_envir
         false
     }
 
+    fn heap_bounds_check_elimination(&self) -> bool {
+        false
+    }
+
     fn proof_carrying_code(&self) -> bool {
         false
     }
@@ -718,6 +722,10 @@ impl TargetEnvironment for DummyEnvironment {
         false
     }
 
+    fn heap_bounds_check_elimination(&self) -> bool {
+        false
+    }
+
     fn proof_carrying_code(&self) -> bool {
         false
     }
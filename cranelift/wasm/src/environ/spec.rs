@@ -52,6 +52,10 @@ pub trait TargetEnvironment: TypeConvert {
     /// Whether to enable Spectre mitigations for heap accesses.
     fn heap_access_spectre_mitigation(&self) -> bool;
 
+    /// Whether to remove heap bounds checks which are made redundant by a
+    /// dominating bounds check.
+    fn heap_bounds_check_elimination(&self) -> bool;
+
     /// Whether to add proof-carrying-code facts to verify memory accesses.
     fn proof_carrying_code(&self) -> bool;
 
@@ -116,6 +116,7 @@ impl FuncTranslator {
         parse_function_body(validator, reader, &mut builder, &mut self.state, environ)?;
 
         builder.finalize();
+        self.state.bounds_checks.eliminate_redundant(func);
         log::trace!("translated Wasm to CLIF:\n{}", func.display());
         Ok(())
     }
@@ -3,6 +3,7 @@
 //! The `FuncTranslationState` struct defined in this module is used to keep track of the WebAssembly
 //! value and control stacks during the translation of a single function.
 
+use crate::code_translator::bounds_check_elim::DynamicBoundsChecks;
 use crate::environ::{FuncEnvironment, GlobalVariable};
 use crate::{FuncIndex, GlobalIndex, Heap, MemoryIndex, TableIndex, TypeIndex, WasmResult};
 use crate::{HashMap, Occupied, Vacant};
@@ -241,6 +242,10 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FuncIndex, (ir::FuncRef, usize)>,
+
+    // Explicit bounds checks of dynamic heaps, recorded when
+    // `FuncEnvironment::heap_bounds_check_elimination()` is enabled.
+    pub(crate) bounds_checks: DynamicBoundsChecks,
 }
 
 // Public methods that are exposed to non-`cranelift_wasm` API consumers.
@@ -264,6 +269,7 @@ impl FuncTranslationState {
             tables: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            bounds_checks: DynamicBoundsChecks::default(),
         }
     }
 
@@ -276,6 +282,7 @@ impl FuncTranslationState {
         self.tables.clear();
         self.signatures.clear();
         self.functions.clear();
+        self.bounds_checks.clear();
     }
 
     /// Initialize the state for compiling a function with the given signature.
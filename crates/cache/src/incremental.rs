@@ -0,0 +1,73 @@
+//! An on-disk store for Cranelift's incremental compilation cache.
+
+use crate::{compiler_dir, read_cache_file, write_cache_file, CacheConfig};
+use anyhow::{bail, Result};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use wasmtime_environ::CacheStore;
+
+/// A [`CacheStore`] which keeps Cranelift's incrementally-compiled functions
+/// in the file system.
+///
+/// Entries live in a `functions` directory next to the `modules` directory
+/// used by whole-module caching, and are compressed, recompressed, and
+/// cleaned up in the same way according to the provided [`CacheConfig`]. This
+/// means that functions compiled in one process can be reused by another when
+/// a slightly changed module is recompiled.
+#[derive(Debug)]
+pub struct FileCacheStore {
+    root_path: PathBuf,
+    cache_config: CacheConfig,
+}
+
+impl FileCacheStore {
+    /// Creates a new store for the cache described by `cache_config`.
+    ///
+    /// Returns an error if caching is disabled in `cache_config`.
+    pub fn new(cache_config: CacheConfig) -> Result<Self> {
+        if !cache_config.enabled() {
+            bail!("the incremental compilation cache requires caching to be enabled");
+        }
+        let root_path = cache_config
+            .directory()
+            .join("functions")
+            .join(compiler_dir("cranelift"));
+        Ok(Self {
+            root_path,
+            cache_config,
+        })
+    }
+
+    fn entry_path(&self, key: &[u8]) -> PathBuf {
+        let hash: [u8; 32] = Sha256::digest(key).into();
+        // standard encoding uses '/' which can't be used for filename
+        let hash = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&hash);
+        self.root_path.join(hash)
+    }
+}
+
+impl CacheStore for FileCacheStore {
+    fn get(&self, key: &[u8]) -> Option<Cow<[u8]>> {
+        let path = self.entry_path(key);
+        let bytes = read_cache_file(&path)?;
+        self.cache_config.on_cache_get_async(&path);
+        Some(Cow::Owned(bytes))
+    }
+
+    fn insert(&self, key: &[u8], value: Vec<u8>) -> bool {
+        let path = self.entry_path(key);
+        if write_cache_file(
+            &path,
+            &value,
+            self.cache_config.baseline_compression_level(),
+        )
+        .is_none()
+        {
+            return false;
+        }
+        self.cache_config.on_cache_update_async(&path);
+        true
+    }
+}
@@ -10,9 +10,11 @@ use std::{fs, io};
 
 #[macro_use] // for tests
 mod config;
+mod incremental;
 mod worker;
 
 pub use config::{create_new_config, CacheConfig};
+pub use incremental::FileCacheStore;
 use worker::Worker;
 
 /// Module level cache entry.
@@ -109,40 +111,10 @@ impl<'config> ModuleCacheEntry<'config> {
 
 impl<'config> ModuleCacheEntryInner<'config> {
     fn new<'data>(compiler_name: &str, cache_config: &'config CacheConfig) -> Self {
-        // If debug assertions are enabled then assume that we're some sort of
-        // local build. We don't want local builds to stomp over caches between
-        // builds, so just use a separate cache directory based on the mtime of
-        // our executable, which should roughly correlate with "you changed the
-        // source code so you get a different directory".
-        //
-        // Otherwise if this is a release build we use the `GIT_REV` env var
-        // which is either the git rev if installed from git or the crate
-        // version if installed from crates.io.
-        let compiler_dir = if cfg!(debug_assertions) {
-            fn self_mtime() -> Option<String> {
-                let path = std::env::current_exe().ok()?;
-                let metadata = path.metadata().ok()?;
-                let mtime = metadata.modified().ok()?;
-                Some(match mtime.duration_since(std::time::UNIX_EPOCH) {
-                    Ok(dur) => format!("{}", dur.as_millis()),
-                    Err(err) => format!("m{}", err.duration().as_millis()),
-                })
-            }
-            let self_mtime = self_mtime().unwrap_or("no-mtime".to_string());
-            format!(
-                "{comp_name}-{comp_ver}-{comp_mtime}",
-                comp_name = compiler_name,
-                comp_ver = env!("GIT_REV"),
-                comp_mtime = self_mtime,
-            )
-        } else {
-            format!(
-                "{comp_name}-{comp_ver}",
-                comp_name = compiler_name,
-                comp_ver = env!("GIT_REV"),
-            )
-        };
-        let root_path = cache_config.directory().join("modules").join(compiler_dir);
+        let root_path = cache_config
+            .directory()
+            .join("modules")
+            .join(compiler_dir(compiler_name));
 
         Self {
             root_path,
@@ -151,58 +123,106 @@ impl<'config> ModuleCacheEntryInner<'config> {
     }
 
     fn get_data(&self, hash: &str) -> Option<Vec<u8>> {
-        let mod_cache_path = self.root_path.join(hash);
-        trace!("get_data() for path: {}", mod_cache_path.display());
-        let compressed_cache_bytes = fs::read(&mod_cache_path).ok()?;
-        let cache_bytes = zstd::decode_all(&compressed_cache_bytes[..])
-            .map_err(|err| warn!("Failed to decompress cached code: {}", err))
-            .ok()?;
-        Some(cache_bytes)
+        read_cache_file(&self.root_path.join(hash))
     }
 
     fn update_data(&self, hash: &str, serialized_data: &[u8]) -> Option<()> {
-        let mod_cache_path = self.root_path.join(hash);
-        trace!("update_data() for path: {}", mod_cache_path.display());
-        let compressed_data = zstd::encode_all(
-            &serialized_data[..],
+        write_cache_file(
+            &self.root_path.join(hash),
+            serialized_data,
             self.cache_config.baseline_compression_level(),
         )
+    }
+}
+
+/// Returns the name of the directory, within a cache kind's directory, that
+/// holds entries produced by this build of `compiler_name`.
+fn compiler_dir(compiler_name: &str) -> String {
+    // If debug assertions are enabled then assume that we're some sort of
+    // local build. We don't want local builds to stomp over caches between
+    // builds, so just use a separate cache directory based on the mtime of
+    // our executable, which should roughly correlate with "you changed the
+    // source code so you get a different directory".
+    //
+    // Otherwise if this is a release build we use the `GIT_REV` env var
+    // which is either the git rev if installed from git or the crate
+    // version if installed from crates.io.
+    if cfg!(debug_assertions) {
+        fn self_mtime() -> Option<String> {
+            let path = std::env::current_exe().ok()?;
+            let metadata = path.metadata().ok()?;
+            let mtime = metadata.modified().ok()?;
+            Some(match mtime.duration_since(std::time::UNIX_EPOCH) {
+                Ok(dur) => format!("{}", dur.as_millis()),
+                Err(err) => format!("m{}", err.duration().as_millis()),
+            })
+        }
+        let self_mtime = self_mtime().unwrap_or("no-mtime".to_string());
+        format!(
+            "{comp_name}-{comp_ver}-{comp_mtime}",
+            comp_name = compiler_name,
+            comp_ver = env!("GIT_REV"),
+            comp_mtime = self_mtime,
+        )
+    } else {
+        format!(
+            "{comp_name}-{comp_ver}",
+            comp_name = compiler_name,
+            comp_ver = env!("GIT_REV"),
+        )
+    }
+}
+
+/// Reads and decompresses the cache entry at `path`.
+fn read_cache_file(path: &Path) -> Option<Vec<u8>> {
+    trace!("read_cache_file() for path: {}", path.display());
+    let compressed_cache_bytes = fs::read(path).ok()?;
+    let cache_bytes = zstd::decode_all(&compressed_cache_bytes[..])
+        .map_err(|err| warn!("Failed to decompress cached code: {}", err))
+        .ok()?;
+    Some(cache_bytes)
+}
+
+/// Compresses `serialized_data` and atomically writes it to `path`, creating
+/// the cache directory if necessary.
+fn write_cache_file(path: &Path, serialized_data: &[u8], compression_level: i32) -> Option<()> {
+    trace!("write_cache_file() for path: {}", path.display());
+    let compressed_data = zstd::encode_all(&serialized_data[..], compression_level)
         .map_err(|err| warn!("Failed to compress cached code: {}", err))
         .ok()?;
 
-        // Optimize syscalls: first, try writing to disk. It should succeed in most cases.
-        // Otherwise, try creating the cache directory and retry writing to the file.
-        if fs_write_atomic(&mod_cache_path, "mod", &compressed_data).is_ok() {
-            return Some(());
-        }
+    // Optimize syscalls: first, try writing to disk. It should succeed in most cases.
+    // Otherwise, try creating the cache directory and retry writing to the file.
+    if fs_write_atomic(path, "mod", &compressed_data).is_ok() {
+        return Some(());
+    }
 
-        debug!(
-            "Attempting to create the cache directory, because \
-             failed to write cached code to disk, path: {}",
-            mod_cache_path.display(),
-        );
-
-        let cache_dir = mod_cache_path.parent().unwrap();
-        fs::create_dir_all(cache_dir)
-            .map_err(|err| {
-                warn!(
-                    "Failed to create cache directory, path: {}, message: {}",
-                    cache_dir.display(),
-                    err
-                )
-            })
-            .ok()?;
-
-        match fs_write_atomic(&mod_cache_path, "mod", &compressed_data) {
-            Ok(_) => Some(()),
-            Err(err) => {
-                warn!(
-                    "Failed to write file with rename, target path: {}, err: {}",
-                    mod_cache_path.display(),
-                    err
-                );
-                None
-            }
+    debug!(
+        "Attempting to create the cache directory, because \
+         failed to write cached code to disk, path: {}",
+        path.display(),
+    );
+
+    let cache_dir = path.parent().unwrap();
+    fs::create_dir_all(cache_dir)
+        .map_err(|err| {
+            warn!(
+                "Failed to create cache directory, path: {}, message: {}",
+                cache_dir.display(),
+                err
+            )
+        })
+        .ok()?;
+
+    match fs_write_atomic(path, "mod", &compressed_data) {
+        Ok(_) => Some(()),
+        Err(err) => {
+            warn!(
+                "Failed to write file with rename, target path: {}, err: {}",
+                path.display(),
+                err
+            );
+            None
         }
     }
 }
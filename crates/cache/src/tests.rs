@@ -90,3 +90,36 @@ fn test_write_read_cache() {
     entry1.get_data::<_, i32, i32>(4, |_| panic!()).unwrap();
     entry2.get_data::<_, i32, i32>(1, |_| panic!()).unwrap();
 }
+
+#[test]
+fn test_file_cache_store() {
+    use wasmtime_environ::CacheStore;
+
+    let (_tempdir, cache_dir, config_path) = test_prolog();
+    let cache_config = load_config!(
+        config_path,
+        "[cache]\n\
+         enabled = true\n\
+         directory = '{cache_dir}'\n\
+         baseline-compression-level = 3\n",
+        cache_dir
+    );
+
+    let store = FileCacheStore::new(cache_config.clone()).unwrap();
+    assert!(store.get(b"key1").is_none());
+    assert!(store.insert(b"key1", vec![1, 2, 3]));
+    assert!(store.insert(b"key2", vec![4, 5]));
+    assert_eq!(store.get(b"key1").as_deref(), Some(&[1, 2, 3][..]));
+    assert_eq!(store.get(b"key2").as_deref(), Some(&[4, 5][..]));
+
+    // A second store for the same directory, as another process would create,
+    // sees the same entries.
+    let store2 = FileCacheStore::new(cache_config.clone()).unwrap();
+    assert_eq!(store2.get(b"key1").as_deref(), Some(&[1, 2, 3][..]));
+
+    // Entries are kept apart from whole-module cache entries.
+    assert!(cache_config.directory().join("functions").is_dir());
+    assert!(!cache_config.directory().join("modules").exists());
+
+    assert!(FileCacheStore::new(CacheConfig::new_cache_disabled()).is_err());
+}
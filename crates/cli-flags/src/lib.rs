@@ -42,6 +42,22 @@ wasmtime_option_group! {
         /// Optimization level of generated code (0-2, s; default: 0)
         pub opt_level: Option<wasmtime::OptLevel>,
 
+        /// Inline small functions into their callers within a module
+        /// (default: no)
+        pub inlining: Option<bool>,
+
+        /// Remove bounds checks of memory accesses which are covered by an
+        /// earlier check, and hoist them out of counted loops (default: no)
+        pub bounds_check_elimination: Option<bool>,
+
+        /// Count how often blocks of compiled functions are executed, and add
+        /// the counts to the profile at this path on exit
+        pub pgo_instrument: Option<String>,
+
+        /// Optimize compiled functions with the profile at this path, as
+        /// written by `-O pgo-instrument`
+        pub pgo_profile: Option<String>,
+
         /// Byte size of the guard region after dynamic memories are allocated
         pub dynamic_memory_guard_size: Option<u64>,
 
@@ -77,6 +93,10 @@ wasmtime_option_group! {
         /// Configure attempting to initialize linear memory via a
         /// copy-on-write mapping (default: yes)
         pub memory_init_cow: Option<bool>,
+
+        /// Request that linear memories be backed by transparent huge pages
+        /// where supported (default: no)
+        pub memory_huge_pages: Option<bool>,
     }
 
     enum Optimize {
@@ -98,6 +118,9 @@ wasmtime_option_group! {
         pub cache: Option<bool>,
         /// Configuration for compiled module caching.
         pub cache_config: Option<String>,
+        /// Whether or not to cache individual functions compiled by Cranelift
+        /// so they can be reused when a changed module is recompiled.
+        pub incremental_cache: Option<bool>,
         /// Whether or not to enable parallel compilation of modules.
         pub parallel_compilation: Option<bool>,
         /// Whether to enable proof-carrying code (PCC)-based validation.
@@ -422,6 +445,26 @@ impl CommonOptions {
             level => config.cranelift_opt_level(level),
             _ => err,
         }
+        match_feature! {
+            ["cranelift" : self.opts.inlining]
+            enable => config.cranelift_inlining(enable),
+            true => err,
+        }
+        match_feature! {
+            ["cranelift" : self.opts.bounds_check_elimination]
+            enable => config.cranelift_bounds_check_elimination(enable),
+            true => err,
+        }
+        match_feature! {
+            ["cranelift" : &self.opts.pgo_instrument]
+            path => config.cranelift_pgo_instrument(path),
+            _ => err,
+        }
+        match_feature! {
+            ["cranelift" : &self.opts.pgo_profile]
+            path => config.cranelift_pgo_profile(path),
+            _ => err,
+        }
         match_feature! {
             ["cranelift" : self.wasm.nan_canonicalization]
             enable => config.cranelift_nan_canonicalization(enable),
@@ -470,6 +513,18 @@ impl CommonOptions {
             anyhow::bail!("support for caching disabled at compile time");
         }
 
+        #[cfg(feature = "incremental-cache")]
+        if self.codegen.incremental_cache == Some(true) {
+            // Share the configuration of the module cache loaded above, which
+            // also means that `-C cache=n` disables this cache too.
+            let store = wasmtime_cache::FileCacheStore::new(config.cache_config().clone())?;
+            config.enable_incremental_compilation(std::sync::Arc::new(store))?;
+        }
+        #[cfg(not(feature = "incremental-cache"))]
+        if self.codegen.incremental_cache == Some(true) {
+            anyhow::bail!("support for incremental caching disabled at compile time");
+        }
+
         match_feature! {
             ["parallel-compilation" : self.codegen.parallel_compilation]
             enable => config.parallel_compilation(enable),
@@ -509,6 +564,9 @@ impl CommonOptions {
         if let Some(enable) = self.opts.memory_init_cow {
             config.memory_init_cow(enable);
         }
+        if let Some(enable) = self.opts.memory_huge_pages {
+            config.memory_huge_pages(enable);
+        }
 
         match_feature! {
             ["pooling-allocator" : self.opts.pooling_allocator]
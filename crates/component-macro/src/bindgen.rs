@@ -1,3 +1,4 @@
+use anyhow::Context;
 use proc_macro2::{Span, TokenStream};
 use quote::ToTokens;
 use std::collections::HashMap;
@@ -8,6 +9,7 @@ use syn::parse::{Error, Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{braced, token, Token};
 use wasmtime_wit_bindgen::{AsyncConfig, Opts, Ownership, TrappableError};
+use wit_component::DecodedWasm;
 use wit_parser::{PackageId, Resolve, UnresolvedPackage, WorldId};
 
 pub struct Config {
@@ -52,13 +54,18 @@ pub fn expand(input: &Config) -> Result<TokenStream> {
     }
     let mut contents = src.parse::<TokenStream>().unwrap();
 
-    // Include a dummy `include_str!` for any files we read so rustc knows that
-    // we depend on the contents of those files.
+    // Include a dummy `include_bytes!` for any files we read so rustc knows
+    // that we depend on the contents of those files. Note that this is
+    // `include_bytes!` rather than `include_str!` since `path` may refer to a
+    // binary component.
     for file in input.files.iter() {
         contents.extend(
-            format!("const _: &str = include_str!(r#\"{}\"#);\n", file.display())
-                .parse::<TokenStream>()
-                .unwrap(),
+            format!(
+                "const _: &[u8] = include_bytes!(r#\"{}\"#);\n",
+                file.display()
+            )
+            .parse::<TokenStream>()
+            .unwrap(),
         );
     }
 
@@ -99,6 +106,7 @@ impl Parse for Config {
                         inline = Some(s.value());
                     }
                     Opt::Tracing(val) => opts.tracing = val,
+                    Opt::Interceptors(val) => opts.interceptors = val,
                     Opt::Async(val, span) => {
                         if async_configured {
                             return Err(Error::new(span, "cannot specify second async config"));
@@ -142,12 +150,23 @@ impl Parse for Config {
                 path = Some(input.parse::<syn::LitStr>()?.value());
             }
         }
-        let (resolve, pkg, files) = parse_source(&path, &inline)
+        let (resolve, pkg, component_world, files) = parse_source(&path, &inline)
             .map_err(|err| Error::new(call_site, format!("{err:?}")))?;
 
-        let world = resolve
-            .select_world(pkg, world.as_deref())
-            .map_err(|e| Error::new(call_site, format!("{e:?}")))?;
+        let world = match component_world {
+            Some(component_world) => {
+                if world.is_some() {
+                    return Err(Error::new(
+                        call_site,
+                        "cannot specify a world when `path` refers to a component",
+                    ));
+                }
+                component_world
+            }
+            None => resolve
+                .select_world(pkg, world.as_deref())
+                .map_err(|e| Error::new(call_site, format!("{e:?}")))?,
+        };
         Ok(Config {
             opts,
             resolve,
@@ -160,15 +179,40 @@ impl Parse for Config {
 fn parse_source(
     path: &Option<String>,
     inline: &Option<String>,
-) -> anyhow::Result<(Resolve, PackageId, Vec<PathBuf>)> {
+) -> anyhow::Result<(Resolve, PackageId, Option<WorldId>, Vec<PathBuf>)> {
     let mut resolve = Resolve::default();
     let mut files = Vec::new();
     let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
 
     let mut parse = |resolve: &mut Resolve, path: &Path| -> anyhow::Result<_> {
+        // Binary files, either a component or a wasm-encoded WIT package, are
+        // decoded here and merged into `resolve`. If it's a component then
+        // the world it was decoded to is returned as well.
+        if path.is_file() {
+            let contents = std::fs::read(path)?;
+            if contents.starts_with(b"\0asm") {
+                files.push(path.to_path_buf());
+                let decoded = wit_component::decode(&contents)
+                    .with_context(|| format!("failed to decode `{}`", path.display()))?;
+                return match decoded {
+                    DecodedWasm::WitPackage(r, pkg) => {
+                        let remap = resolve.merge(r)?;
+                        Ok((remap.packages[pkg.index()], None))
+                    }
+                    DecodedWasm::Component(r, world) => {
+                        let remap = resolve.merge(r)?;
+                        let world = remap.worlds[world.index()];
+                        let pkg = resolve.worlds[world]
+                            .package
+                            .context("world decoded from component has no package")?;
+                        Ok((pkg, Some(world)))
+                    }
+                };
+            }
+        }
         let (pkg, sources) = resolve.push_path(path)?;
         files.extend(sources);
-        Ok(pkg)
+        Ok((pkg, None))
     };
 
     let path_pkg = if let Some(path) = path {
@@ -183,17 +227,20 @@ fn parse_source(
         None
     };
 
-    let pkg = inline_pkg
-        .or(path_pkg)
-        .map_or_else(|| parse(&mut resolve, &root.join("wit")), Ok)?;
+    let (pkg, world) = match (inline_pkg, path_pkg) {
+        (Some(pkg), _) => (pkg, None),
+        (None, Some(path_pkg)) => path_pkg,
+        (None, None) => parse(&mut resolve, &root.join("wit"))?,
+    };
 
-    Ok((resolve, pkg, files))
+    Ok((resolve, pkg, world, files))
 }
 
 mod kw {
     syn::custom_keyword!(inline);
     syn::custom_keyword!(path);
     syn::custom_keyword!(tracing);
+    syn::custom_keyword!(interceptors);
     syn::custom_keyword!(trappable_error_type);
     syn::custom_keyword!(world);
     syn::custom_keyword!(ownership);
@@ -208,6 +255,7 @@ enum Opt {
     Path(syn::LitStr),
     Inline(syn::LitStr),
     Tracing(bool),
+    Interceptors(bool),
     Async(AsyncConfig, Span),
     TrappableErrorType(Vec<TrappableError>),
     Ownership(Ownership),
@@ -234,6 +282,10 @@ impl Parse for Opt {
             input.parse::<kw::tracing>()?;
             input.parse::<Token![:]>()?;
             Ok(Opt::Tracing(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::interceptors) {
+            input.parse::<kw::interceptors>()?;
+            input.parse::<Token![:]>()?;
+            Ok(Opt::Interceptors(input.parse::<syn::LitBool>()?.value))
         } else if l.peek(Token![async]) {
             let span = input.parse::<Token![async]>()?.span;
             input.parse::<Token![:]>()?;
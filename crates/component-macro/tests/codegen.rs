@@ -19,6 +19,19 @@ macro_rules! gentest {
                     }
                 });
             }
+            mod interceptors {
+                wasmtime::component::bindgen!({
+                    path: $path,
+                    interceptors: true,
+                });
+            }
+            mod interceptors_async {
+                wasmtime::component::bindgen!({
+                    path: $path,
+                    async: true,
+                    interceptors: true,
+                });
+            }
         }
     };
 }
@@ -149,6 +162,111 @@ mod trappable_errors {
     type MyX = u32;
 }
 
+mod trappable_errors_with_interceptors {
+    use self::demo::pkg::a;
+    use wasmtime::component::__internal::async_trait;
+    use wasmtime::component::Resource;
+
+    wasmtime::component::bindgen!({
+        inline: "
+            package demo:pkg;
+
+            interface a {
+                type b = u64;
+
+                resource r {
+                    get: func() -> result<u32, b>;
+                }
+
+                z: func(x: u32) -> result<_, b>;
+            }
+
+            world foo {
+                import a;
+            }
+        ",
+        async: true,
+        interceptors: true,
+        trappable_error_type: {
+            "demo:pkg/a/b" => MyX,
+        },
+    });
+
+    pub struct MyX;
+
+    struct MyHost;
+
+    #[async_trait]
+    impl a::Host for MyHost {
+        async fn z(&mut self, _: u32) -> Result<(), MyX> {
+            Ok(())
+        }
+
+        fn convert_b(&mut self, _: MyX) -> wasmtime::Result<a::B> {
+            Ok(0)
+        }
+    }
+
+    #[async_trait]
+    impl a::HostR for MyHost {
+        async fn get(&mut self, _: Resource<a::R>) -> Result<u32, MyX> {
+            Ok(0)
+        }
+
+        fn drop(&mut self, _: Resource<a::R>) -> wasmtime::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct MyHooks;
+
+    // Hooks of async functions are async, and fail with the function's
+    // trappable error type.
+    #[async_trait]
+    impl a::HostHooks for MyHooks {
+        async fn before_z(&mut self, _: &mut u32) -> Result<(), MyX> {
+            Err(MyX)
+        }
+
+        async fn after_method_r_get(&mut self, result: &mut Result<u32, MyX>) {
+            *result = Err(MyX);
+        }
+
+        fn before_drop_r(&mut self, _: &mut Resource<a::R>) -> wasmtime::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[allow(dead_code)]
+    fn assert_host() -> impl a::Host {
+        a::HostInterceptor {
+            host: MyHost,
+            hooks: MyHooks,
+        }
+    }
+}
+
+mod component_source {
+    // The WIT of this component is decoded from its binary.
+    wasmtime::component::bindgen!({
+        path: "tests/component/adder.wasm",
+    });
+
+    struct MyImports;
+
+    impl RootImports for MyImports {
+        fn log(&mut self, _: u32) -> wasmtime::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[allow(dead_code)]
+    fn add(engine: &wasmtime::Engine, root: &Root) -> wasmtime::Result<u32> {
+        let mut store = wasmtime::Store::new(engine, MyImports);
+        root.call_add(&mut store, 1, 2)
+    }
+}
+
 mod interface_name_with_rust_keyword {
     wasmtime::component::bindgen!({
         inline: "
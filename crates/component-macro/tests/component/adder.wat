@@ -0,0 +1,20 @@
+;; Source of `adder.wasm`, used as the source of WIT by the `bindgen!` tests.
+;; Regenerate with `wasm-tools parse adder.wat -o adder.wasm`.
+(component
+  (import "log" (func $log (param "x" u32)))
+  (core func $log (canon lower (func $log)))
+  (core module $m
+    (import "" "log" (func $log (param i32)))
+    (func (export "add") (param i32 i32) (result i32)
+      local.get 0
+      call $log
+      local.get 0
+      local.get 1
+      i32.add)
+  )
+  (core instance $i (instantiate $m
+    (with "" (instance (export "log" (func $log))))
+  ))
+  (func (export "add") (param "a" u32) (param "b" u32) (result u32)
+    (canon lift (core func $i "add")))
+)
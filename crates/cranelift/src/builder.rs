@@ -3,7 +3,8 @@
 //! This module contains the implementation of how Cranelift is configured, as
 //! well as providing a function to return the default configuration to build.
 
-use anyhow::Result;
+use crate::compiler::{Instrumentation, Profile};
+use anyhow::{bail, Result};
 use cranelift_codegen::{
     isa::{self, OwnedTargetIsa},
     CodegenResult,
@@ -21,7 +22,10 @@ struct Builder {
     linkopts: LinkOptions,
     cache_store: Option<Arc<dyn CacheStore>>,
     clif_dir: Option<path::PathBuf>,
+    clif_stages: bool,
     wmemcheck: bool,
+    pgo_instrument: Option<path::PathBuf>,
+    pgo_profile: Option<path::PathBuf>,
 }
 
 #[derive(Clone, Default)]
@@ -44,7 +48,10 @@ pub fn builder(triple: Option<Triple>) -> Result<Box<dyn CompilerBuilder>> {
         linkopts: LinkOptions::default(),
         cache_store: None,
         clif_dir: None,
+        clif_stages: false,
         wmemcheck: false,
+        pgo_instrument: None,
+        pgo_profile: None,
     }))
 }
 
@@ -58,6 +65,10 @@ impl CompilerBuilder for Builder {
         Ok(())
     }
 
+    fn clif_stages(&mut self, enable: bool) {
+        self.clif_stages = enable;
+    }
+
     fn target(&mut self, target: target_lexicon::Triple) -> Result<()> {
         self.inner.target(target)?;
         Ok(())
@@ -88,6 +99,11 @@ impl CompilerBuilder for Builder {
 
     fn build(&self) -> Result<Box<dyn wasmtime_environ::Compiler>> {
         let isa = self.inner.build()?;
+        if self.pgo_instrument.is_some() && self.pgo_profile.is_some() {
+            bail!("cannot both instrument functions and optimize them with a profile");
+        }
+        let instrumentation = self.pgo_instrument.clone().map(Instrumentation::new);
+        let profile = self.pgo_profile.as_deref().map(Profile::read).transpose()?;
         Ok(Box::new(crate::compiler::Compiler::new(
             self.tunables
                 .as_ref()
@@ -97,7 +113,10 @@ impl CompilerBuilder for Builder {
             self.cache_store.clone(),
             self.linkopts.clone(),
             self.clif_dir.clone(),
+            self.clif_stages,
             self.wmemcheck,
+            instrumentation,
+            profile,
         )))
     }
 
@@ -116,6 +135,16 @@ impl CompilerBuilder for Builder {
     fn wmemcheck(&mut self, enable: bool) {
         self.wmemcheck = enable;
     }
+
+    fn pgo_instrument(&mut self, path: &path::Path) -> Result<()> {
+        self.pgo_instrument = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    fn pgo_profile(&mut self, path: &path::Path) -> Result<()> {
+        self.pgo_profile = Some(path.to_path_buf());
+        Ok(())
+    }
 }
 
 impl fmt::Debug for Builder {
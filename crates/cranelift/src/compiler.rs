@@ -3,6 +3,7 @@ use crate::func_environ::FuncEnvironment;
 use crate::{array_call_signature, native_call_signature, DEBUG_ASSERT_TRAP_CODE};
 use crate::{builder::LinkOptions, value_type, wasm_call_signature};
 use anyhow::{Context as _, Result};
+use cranelift_codegen::inline::InlineOptions;
 use cranelift_codegen::ir::{
     self, InstBuilder, MemFlags, UserExternalName, UserExternalNameRef, UserFuncName, Value,
 };
@@ -38,6 +39,10 @@ use wasmtime_environ::{
 
 #[cfg(feature = "component-model")]
 mod component;
+mod inline;
+mod profile;
+
+pub(crate) use profile::{Instrumentation, Profile};
 
 struct IncrementalCacheContext {
     #[cfg(feature = "incremental-cache")]
@@ -73,7 +78,15 @@ pub(crate) struct Compiler {
     linkopts: LinkOptions,
     cache_store: Option<Arc<dyn CacheStore>>,
     clif_dir: Option<path::PathBuf>,
+    /// Whether to write out each stage of compilation, and not just the
+    /// initial CLIF, to `clif_dir`.
+    clif_stages: bool,
     wmemcheck: bool,
+    /// The block counters added to compiled functions, if instrumenting them
+    /// for profile-guided optimization.
+    instrumentation: Option<Instrumentation>,
+    /// The profile to optimize functions with.
+    profile: Option<Profile>,
 }
 
 impl Drop for Compiler {
@@ -111,7 +124,10 @@ impl Compiler {
         cache_store: Option<Arc<dyn CacheStore>>,
         linkopts: LinkOptions,
         clif_dir: Option<path::PathBuf>,
+        clif_stages: bool,
         wmemcheck: bool,
+        instrumentation: Option<Instrumentation>,
+        profile: Option<Profile>,
     ) -> Compiler {
         Compiler {
             contexts: Default::default(),
@@ -120,7 +136,10 @@ impl Compiler {
             linkopts,
             cache_store,
             clif_dir,
+            clif_stages,
             wmemcheck,
+            instrumentation,
+            profile,
         }
     }
 }
@@ -132,6 +151,7 @@ impl wasmtime_environ::Compiler for Compiler {
         func_index: DefinedFuncIndex,
         input: FunctionBodyData<'_>,
         types: &ModuleTypesBuilder,
+        symbol: &str,
     ) -> Result<(WasmFunctionInfo, Box<dyn Any + Send>), CompileError> {
         let isa = &*self.isa;
         let module = &translation.module;
@@ -217,18 +237,49 @@ impl wasmtime_environ::Compiler for Compiler {
             &mut func_env,
         )?;
 
-        if let Some(path) = &self.clif_dir {
-            use std::io::Write;
+        let def_func_index = module.defined_func_index(func_index).unwrap();
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.instrument(
+                translation.wasm_hash(),
+                def_func_index,
+                &mut context.func,
+                isa.pointer_type(),
+            );
+        }
+        if let Some(profile) = &self.profile {
+            profile.annotate(translation.wasm_hash(), def_func_index, &mut context.func);
+        }
+
+        if self.inlining_enabled() {
+            let mut callees =
+                inline::ModuleCallees::new(self, translation, types, validator.resources());
+            context
+                .inline(&mut callees, &InlineOptions::default(), isa)
+                .map_err(|error| CompileError::Codegen(pretty_error(&context.func, error)))?;
+        }
 
-            let mut path = path.to_path_buf();
-            path.push(format!("wasm_func_{}", func_index.as_u32()));
-            path.set_extension("clif");
+        let mut clif_name = None;
+        if self.clif_dir.is_some() {
+            self.write_clif(
+                &format!("wasm_func_{}", func_index.as_u32()),
+                "clif",
+                context.func.display(),
+            );
 
-            let mut output = std::fs::File::create(path).unwrap();
-            write!(output, "{}", context.func.display()).unwrap();
+            // Request the egraph's rewrites and the register-allocated VCode
+            // as well so that they can be written out once compiled. These
+            // are named after the function's symbol, which is unique even
+            // among the modules of a component, without characters that
+            // aren't allowed in file names everywhere.
+            if self.clif_stages {
+                context.set_egraph_rewrites(true);
+                context.set_disasm(true);
+                clif_name = Some(symbol.replace(':', "-"));
+            }
         }
 
-        let (info, func) = compiler.finish_with_info(Some((&body, &self.tunables)))?;
+        let (info, func) =
+            compiler.finish_with_info(Some((&body, &self.tunables)), clif_name.as_deref())?;
 
         let timing = cranelift_codegen::timing::take_current();
         log::debug!("{:?} translated in {:?}", func_index, timing.total());
@@ -696,6 +747,22 @@ fn compile_uncached<'a>(
 }
 
 impl Compiler {
+    /// Writes `contents` to the file `name.extension` within the directory
+    /// configured for CLIF output, if any.
+    fn write_clif(&self, name: &str, extension: &str, contents: impl std::fmt::Display) {
+        use std::io::Write;
+
+        let Some(path) = &self.clif_dir else {
+            return;
+        };
+        let mut path = path.to_path_buf();
+        path.push(name);
+        path.set_extension(extension);
+
+        let mut output = std::fs::File::create(path).unwrap();
+        write!(output, "{contents}").unwrap();
+    }
+
     /// Creates a trampoline for calling a host function callee defined with the
     /// "array" calling convention from a native calling convention caller.
     ///
@@ -1012,14 +1079,20 @@ impl FunctionCompiler<'_> {
     }
 
     fn finish(self) -> Result<CompiledFunction<CompiledFuncEnv>, CompileError> {
-        let (info, func) = self.finish_with_info(None)?;
+        let (info, func) = self.finish_with_info(None, None)?;
         assert!(info.stack_maps.is_empty());
         Ok(func)
     }
 
+    /// Compiles the function in this compiler's context.
+    ///
+    /// If `clif_name` is specified and CLIF output is enabled then the
+    /// optimized CLIF of the function is written out under that name, along
+    /// with any egraph rewrites and final VCode that were requested.
     fn finish_with_info(
         mut self,
         body_and_tunables: Option<(&FunctionBody<'_>, &Tunables)>,
+        clif_name: Option<&str>,
     ) -> Result<(WasmFunctionInfo, CompiledFunction<CompiledFuncEnv>), CompileError> {
         let context = &mut self.cx.codegen_context;
         let isa = &*self.compiler.isa;
@@ -1027,6 +1100,17 @@ impl FunctionCompiler<'_> {
             compile_maybe_cached(context, isa, self.cx.incremental_cache_ctx.as_mut())?;
         let compiled_code = context.compiled_code().unwrap();
 
+        if let Some(name) = clif_name {
+            self.compiler
+                .write_clif(name, "opt.clif", context.func.display());
+            if let Some(rewrites) = context.egraph_rewrites() {
+                self.compiler.write_clif(name, "rewrites", rewrites);
+            }
+            if let Some(vcode) = &compiled_code.vcode {
+                self.compiler.write_clif(name, "vcode", vcode);
+            }
+        }
+
         // Give wasm functions, user defined code, a "preferred" alignment
         // instead of the minimum alignment as this can help perf in niche
         // situations.
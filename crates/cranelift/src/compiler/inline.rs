@@ -0,0 +1,168 @@
+//! Inlining of direct calls between functions defined in the same module.
+
+use super::Compiler;
+use crate::func_environ::FuncEnvironment;
+use crate::wasm_call_signature;
+use cranelift_codegen::inline::InlineCallees;
+use cranelift_codegen::ir::{self, ExternalName, UserExternalName, UserFuncName};
+use cranelift_codegen::settings::OptLevel;
+use cranelift_wasm::{DefinedFuncIndex, FuncIndex, FuncTranslator};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+use wasmparser::{FuncToValidate, ValidatorResources};
+use wasmtime_environ::{FunctionBodies, ModuleTranslation, ModuleTypesBuilder};
+
+/// Functions whose wasm bodies are larger than this many bytes are never
+/// translated for inlining since they're well past Cranelift's callee size
+/// limit anyway.
+const MAX_INLINED_BODY_SIZE: usize = 128;
+
+impl Compiler {
+    /// Whether direct calls between functions of a module are inlined.
+    ///
+    /// Inlining only happens when optimizing for speed, as it generally
+    /// increases code size. It's also skipped when generating native debuginfo, which describes
+    /// one wasm function per native function, with wmemcheck, which
+    /// instruments calls to allocation functions, and when instrumenting
+    /// functions for profile-guided optimization, as inlined callees wouldn't
+    /// count their executions.
+    pub(super) fn inlining_enabled(&self) -> bool {
+        let flags = self.isa.flags();
+        flags.enable_inlining()
+            && flags.opt_level() == OptLevel::Speed
+            && !self.tunables.generate_native_debuginfo
+            && !self.wmemcheck
+            && self.instrumentation.is_none()
+    }
+}
+
+/// Provides the CLIF of functions defined in the module being compiled to
+/// Cranelift's inliner.
+///
+/// Callees are translated from wasm on demand. Translations are cached in the
+/// module's [`FunctionBodies`], so each callee is translated once for all of
+/// its callers.
+pub(super) struct ModuleCallees<'a, 'data> {
+    compiler: &'a Compiler,
+    translation: &'a ModuleTranslation<'data>,
+    bodies: &'a FunctionBodies<'data>,
+    types: &'a ModuleTypesBuilder,
+    resources: &'a ValidatorResources,
+    translator: FuncTranslator,
+    callees: HashMap<DefinedFuncIndex, Option<Arc<dyn Any + Send + Sync>>>,
+}
+
+impl<'a, 'data> ModuleCallees<'a, 'data> {
+    pub(super) fn new(
+        compiler: &'a Compiler,
+        translation: &'a ModuleTranslation<'data>,
+        types: &'a ModuleTypesBuilder,
+        resources: &'a ValidatorResources,
+    ) -> Self {
+        ModuleCallees {
+            compiler,
+            translation,
+            bodies: translation
+                .function_bodies
+                .as_ref()
+                .expect("function bodies are kept until the module is compiled"),
+            types,
+            resources,
+            translator: FuncTranslator::new(),
+            callees: HashMap::new(),
+        }
+    }
+
+    fn translate(&mut self, index: DefinedFuncIndex) -> Option<ir::Function> {
+        let (ty, body) = &self.bodies.bodies[index];
+        if body.range().len() > MAX_INLINED_BODY_SIZE {
+            return None;
+        }
+
+        let isa = &*self.compiler.isa;
+        let tunables = &self.compiler.tunables;
+        let func_index = self.translation.module.func_index(index);
+        let sig = self.translation.module.functions[func_index].signature;
+        let mut func = ir::Function::with_name_signature(
+            UserFuncName::User(UserExternalName {
+                namespace: 0,
+                index: func_index.as_u32(),
+            }),
+            wasm_call_signature(isa, &self.types[sig], tunables),
+        );
+        let mut func_env = FuncEnvironment::new(
+            isa,
+            self.translation,
+            self.types,
+            tunables,
+            self.compiler.wmemcheck,
+        );
+
+        // The body was already validated when the module was translated, so
+        // this validator only drives the translation.
+        let mut validator = FuncToValidate::new(
+            func_index.as_u32(),
+            ty.as_u32(),
+            self.resources,
+            &self.bodies.features,
+        )
+        .into_validator(Default::default());
+        self.translator
+            .translate_body(&mut validator, body.clone(), &mut func, &mut func_env)
+            .ok()?;
+        if let Some(profile) = &self.compiler.profile {
+            profile.annotate(self.translation.wasm_hash(), index, &mut func);
+        }
+        Some(func)
+    }
+}
+
+impl InlineCallees for ModuleCallees<'_, '_> {
+    fn callee(&mut self, caller: &ir::Function, name: &ExternalName) -> Option<&ir::Function> {
+        // Direct calls to wasm functions use namespace 0, see
+        // `FuncEnvironment::make_direct_func`.
+        let name = match name {
+            ExternalName::User(name) => &caller.params.user_named_funcs()[*name],
+            _ => return None,
+        };
+        if name.namespace != 0 {
+            return None;
+        }
+        let index = self
+            .translation
+            .module
+            .defined_func_index(FuncIndex::from_u32(name.index))?;
+        if !self.callees.contains_key(&index) {
+            // Other functions of the module may be compiled concurrently, so
+            // the lock isn't held while translating. If two of them translate
+            // the same callee, the first translation is kept.
+            let cached = self
+                .bodies
+                .translations
+                .lock()
+                .unwrap()
+                .get(&index)
+                .cloned();
+            let callee = match cached {
+                Some(callee) => callee,
+                None => {
+                    let func = self
+                        .translate(index)
+                        .map(|func| Arc::new(func) as Arc<dyn Any + Send + Sync>);
+                    self.bodies
+                        .translations
+                        .lock()
+                        .unwrap()
+                        .entry(index)
+                        .or_insert(func)
+                        .clone()
+                }
+            };
+            self.callees.insert(index, callee);
+        }
+        self.callees[&index]
+            .as_ref()
+            .map(|func| func.downcast_ref::<ir::Function>().unwrap())
+    }
+}
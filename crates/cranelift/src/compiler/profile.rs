@@ -0,0 +1,342 @@
+//! Profile-guided optimization.
+//!
+//! An instrumented compilation adds a counter to every block of every wasm
+//! function which counts how many times the block is executed. The counters are
+//! owned by the compiler, which outlives all code it compiled, and their values
+//! are written to a profile file when the compiler is dropped. Later
+//! compilations read the profile and attach the counts to the blocks as block
+//! frequencies, which Cranelift uses for block layout and inlining.
+//!
+//! The frequencies aren't given to the register allocator: regalloc2 has no way
+//! to take block weights, and its spill weights only depend on the loop depth
+//! it derives from the backedges in the block order. Spill weights thus only
+//! change indirectly, through the order that `frequency_guided_postorder`
+//! picks.
+//!
+//! Functions in a profile are identified by the hash of their module's wasm and
+//! their index, and blocks by their index in the CLIF right after translation.
+//! Block indices only depend on the wasm and the configuration of the compiler,
+//! so a profile is only used by compilations with the same configuration as the
+//! instrumented one; counts of functions whose number of blocks changed are
+//! ignored.
+//!
+//! The profile file is a text file with one line per function: the module's
+//! hash in hexadecimal, the index of the defined function, and the count of
+//! each block. Counts from several runs are summed up. Processes update the
+//! profile one at a time, by holding a lock file next to it.
+
+use anyhow::{bail, Context as _, Result};
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::ir::{self, types, InstBuilder, MemFlags};
+use cranelift_entity::EntityRef;
+use cranelift_wasm::DefinedFuncIndex;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another process to finish updating a profile.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The block counts of functions, by module hash and function index.
+#[derive(Default)]
+pub(crate) struct Profile {
+    functions: HashMap<(u64, DefinedFuncIndex), Vec<u64>>,
+}
+
+impl Profile {
+    /// Read the profile at `path`.
+    pub(crate) fn read(path: &Path) -> Result<Profile> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read profile {}", path.display()))?;
+        Profile::parse(&contents)
+            .with_context(|| format!("failed to parse profile {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Profile> {
+        let mut profile = Profile::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_ascii_whitespace();
+            let (Some(module), Some(func)) = (fields.next(), fields.next()) else {
+                bail!(
+                    "line {}: expected a module hash and a function index",
+                    i + 1
+                );
+            };
+            let module = u64::from_str_radix(module, 16)
+                .with_context(|| format!("line {}: invalid module hash", i + 1))?;
+            let func = func
+                .parse()
+                .map(DefinedFuncIndex::from_u32)
+                .with_context(|| format!("line {}: invalid function index", i + 1))?;
+            let counts = fields
+                .map(str::parse)
+                .collect::<Result<Vec<u64>, _>>()
+                .with_context(|| format!("line {}: invalid block count", i + 1))?;
+            profile.add(module, func, &counts);
+        }
+        Ok(profile)
+    }
+
+    /// Add the block counts of a function to the profile.
+    ///
+    /// If the profile has different number of blocks for the function, the
+    /// new counts replace the old ones.
+    fn add(&mut self, module: u64, func: DefinedFuncIndex, counts: &[u64]) {
+        let entry = self.functions.entry((module, func)).or_default();
+        if entry.len() == counts.len() {
+            for (total, count) in entry.iter_mut().zip(counts) {
+                *total = total.saturating_add(*count);
+            }
+        } else {
+            *entry = counts.to_vec();
+        }
+    }
+
+    /// Replace the locked profile with this one.
+    fn write(&self, lock: ProfileLock) -> Result<()> {
+        let mut functions = self.functions.iter().collect::<Vec<_>>();
+        functions.sort_unstable_by_key(|(key, _)| **key);
+
+        let mut contents = String::from("# wasmtime block counts\n");
+        for ((module, func), counts) in functions {
+            write!(contents, "{module:016x} {}", func.as_u32()).unwrap();
+            for count in counts {
+                write!(contents, " {count}").unwrap();
+            }
+            contents.push('\n');
+        }
+        // The lock file is renamed to the profile once it's written, so that
+        // the profile is replaced at once and unlocked at the same time.
+        fs::write(&lock.path, contents)
+            .and_then(|()| fs::rename(&lock.path, &lock.profile))
+            .with_context(|| format!("failed to write profile {}", lock.profile.display()))
+    }
+
+    /// Set the frequencies of the blocks of the function `func` of the module
+    /// with hash `module` from the profile.
+    ///
+    /// This must be called right after the function was translated.
+    pub(crate) fn annotate(&self, module: u64, func: DefinedFuncIndex, clif: &mut ir::Function) {
+        let Some(counts) = self.functions.get(&(module, func)) else {
+            return;
+        };
+        if counts.len() != clif.dfg.num_blocks() {
+            log::debug!("ignoring the profile of {func:?} whose blocks don't match");
+            return;
+        }
+        let blocks = clif.layout.blocks().collect::<Vec<_>>();
+        for block in blocks {
+            clif.layout.set_frequency(block, counts[block.index()]);
+        }
+    }
+}
+
+/// Exclusive access to a profile file among processes, which is held while
+/// the lock file next to the profile exists.
+struct ProfileLock {
+    profile: PathBuf,
+    path: PathBuf,
+}
+
+impl ProfileLock {
+    /// Lock the profile at `profile`, waiting for another process to unlock it
+    /// if necessary.
+    fn acquire(profile: &Path) -> Result<ProfileLock> {
+        let mut path = profile.as_os_str().to_owned();
+        path.push(".lock");
+        let path = PathBuf::from(path);
+
+        let start = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => {
+                    return Ok(ProfileLock {
+                        profile: profile.to_path_buf(),
+                        path,
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if start.elapsed() > LOCK_TIMEOUT {
+                        bail!(
+                            "timed out waiting for the lock {} of the profile, which can be \
+                             removed if no other process is updating the profile",
+                            path.display()
+                        );
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("failed to create lock {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ProfileLock {
+    fn drop(&mut self) {
+        // The lock file is already gone if the profile was written.
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The block counters of an instrumented compilation.
+pub(crate) struct Instrumentation {
+    /// The profile the counts are added to.
+    path: PathBuf,
+    functions: Mutex<Vec<FunctionCounters>>,
+}
+
+struct FunctionCounters {
+    module: u64,
+    func: DefinedFuncIndex,
+    /// The counter of each block, which compiled code increments without
+    /// synchronization.
+    counters: Box<[AtomicU64]>,
+}
+
+impl Instrumentation {
+    pub(crate) fn new(path: PathBuf) -> Instrumentation {
+        Instrumentation {
+            path,
+            functions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add block counters to the function `func` of the module with hash
+    /// `module`.
+    ///
+    /// This must be called right after the function was translated. The
+    /// counters are referred to by their address, so the compiled code can't
+    /// be used by other processes.
+    pub(crate) fn instrument(
+        &self,
+        module: u64,
+        func: DefinedFuncIndex,
+        clif: &mut ir::Function,
+        pointer_type: ir::Type,
+    ) {
+        let counters = (0..clif.dfg.num_blocks())
+            .map(|_| AtomicU64::new(0))
+            .collect::<Box<[_]>>();
+
+        let mut pos = FuncCursor::new(clif);
+        while let Some(block) = pos.next_block() {
+            pos.goto_first_insertion_point(block);
+            let counter = &counters[block.index()] as *const AtomicU64;
+            let addr = pos.ins().iconst(pointer_type, counter as i64);
+            let count = pos.ins().load(types::I64, MemFlags::trusted(), addr, 0);
+            let count = pos.ins().iadd_imm(count, 1);
+            pos.ins().store(MemFlags::trusted(), count, addr, 0);
+        }
+
+        self.functions.lock().unwrap().push(FunctionCounters {
+            module,
+            func,
+            counters,
+        });
+    }
+
+    /// Add the current counts to the profile file.
+    fn write(&self) -> Result<()> {
+        let lock = ProfileLock::acquire(&self.path)?;
+        let mut profile = if self.path.exists() {
+            Profile::read(&self.path)?
+        } else {
+            Profile::default()
+        };
+        for function in self.functions.lock().unwrap().iter() {
+            let counts = function
+                .counters
+                .iter()
+                .map(|counter| counter.load(Ordering::Relaxed))
+                .collect::<Vec<_>>();
+            profile.add(function.module, function.func, &counts);
+        }
+        profile.write(lock)
+    }
+}
+
+impl Drop for Instrumentation {
+    fn drop(&mut self) {
+        if let Err(e) = self.write() {
+            log::warn!("failed to save block counts: {e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_add() {
+        let mut profile = Profile::parse(
+            "# comment\n\
+             00000000000000ff 0 1 2 3\n\
+             \n\
+             00000000000000ff 1 4\n",
+        )
+        .unwrap();
+        let module = 0xff;
+        let func = DefinedFuncIndex::from_u32(0);
+        assert_eq!(profile.functions[&(module, func)], [1, 2, 3]);
+
+        profile.add(module, func, &[1, 1, 1]);
+        assert_eq!(profile.functions[&(module, func)], [2, 3, 4]);
+        // Counts with a different number of blocks replace the old ones.
+        profile.add(module, func, &[5]);
+        assert_eq!(profile.functions[&(module, func)], [5]);
+
+        assert!(Profile::parse("ff").is_err());
+        assert!(Profile::parse("ff 0 x").is_err());
+    }
+
+    #[test]
+    fn write_adds_counts_and_unlocks() {
+        let path = std::env::temp_dir().join(format!("wasmtime-profile-{}", std::process::id()));
+        fs::write(&path, "00000000000000ff 0 1 2\n").unwrap();
+
+        let module = 0xff;
+        let func = DefinedFuncIndex::from_u32(0);
+        let instrumentation = Instrumentation::new(path.clone());
+        instrumentation
+            .functions
+            .lock()
+            .unwrap()
+            .push(FunctionCounters {
+                module,
+                func,
+                counters: Box::new([AtomicU64::new(3), AtomicU64::new(4)]),
+            });
+        // The profile isn't updated while the lock is held, e.g. by another process.
+        let lock = ProfileLock::acquire(&path).unwrap();
+        let lock_path = lock.path.clone();
+        let writer = std::thread::spawn(move || drop(instrumentation));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!writer.is_finished());
+        drop(lock);
+        writer.join().unwrap();
+
+        assert!(!lock_path.exists());
+        assert_eq!(
+            Profile::read(&path).unwrap().functions[&(module, func)],
+            [4, 6]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
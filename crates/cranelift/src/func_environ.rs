@@ -1228,6 +1228,10 @@ impl<'module_environment> TargetEnvironment for FuncEnvironment<'module_environm
         self.isa.flags().enable_heap_access_spectre_mitigation()
     }
 
+    fn heap_bounds_check_elimination(&self) -> bool {
+        self.isa.flags().enable_heap_bounds_check_elimination()
+    }
+
     fn proof_carrying_code(&self) -> bool {
         self.isa.flags().enable_pcc()
     }
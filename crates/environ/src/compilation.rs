@@ -94,6 +94,11 @@ pub trait CompilerBuilder: Send + Sync + fmt::Debug {
         anyhow::bail!("clif output not supported");
     }
 
+    /// Enables output of each stage of compilation, in addition to the
+    /// initial clif, in the directory given to
+    /// [`CompilerBuilder::clif_dir`].
+    fn clif_stages(&mut self, _enable: bool) {}
+
     /// Returns the currently configured target triple that compilation will
     /// produce artifacts for.
     fn triple(&self) -> &target_lexicon::Triple;
@@ -129,6 +134,18 @@ pub trait CompilerBuilder: Send + Sync + fmt::Debug {
 
     /// Enables or disables wmemcheck during runtime according to the wmemcheck CLI flag.
     fn wmemcheck(&mut self, _enable: bool) {}
+
+    /// Instruments compiled functions to count how often their blocks are
+    /// executed, and adds the counts to the profile at `path` when the
+    /// compiler is dropped.
+    fn pgo_instrument(&mut self, _path: &path::Path) -> Result<()> {
+        anyhow::bail!("profile-guided optimization not supported");
+    }
+
+    /// Optimizes compiled functions with the profile at `path`.
+    fn pgo_profile(&mut self, _path: &path::Path) -> Result<()> {
+        anyhow::bail!("profile-guided optimization not supported");
+    }
 }
 
 /// Description of compiler settings returned by [`CompilerBuilder::settings`].
@@ -173,13 +190,17 @@ pub trait Compiler: Send + Sync {
     ///
     /// The body of the function is available in `data` and configuration
     /// values are also passed in via `tunables`. Type information in
-    /// `translation` is all relative to `types`.
+    /// `translation` is all relative to `types`. The `symbol` is the name of
+    /// the function in the final artifact, which is unique among all the
+    /// functions compiled together, for example when compiling all of the
+    /// modules of a component.
     fn compile_function(
         &self,
         translation: &ModuleTranslation<'_>,
         index: DefinedFuncIndex,
         data: FunctionBodyData<'_>,
         types: &ModuleTypesBuilder,
+        symbol: &str,
     ) -> Result<(WasmFunctionInfo, Box<dyn Any + Send>), CompileError>;
 
     /// Compile a trampoline for an array-call host function caller calling the
@@ -9,16 +9,17 @@ use crate::{
     WasmparserTypeConverter,
 };
 use cranelift_entity::packed_option::ReservedValue;
+use std::any::Any;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 use wasmparser::types::{CoreTypeId, Types};
 use wasmparser::{
     CompositeType, CustomSectionReader, DataKind, ElementItems, ElementKind, Encoding,
     ExternalKind, FuncToValidate, FunctionBody, NameSectionReader, Naming, Operator, Parser,
-    Payload, TypeRef, Validator, ValidatorResources,
+    Payload, TypeRef, Validator, ValidatorResources, WasmFeatures,
 };
 use wasmtime_types::ModuleInternedTypeIndex;
 
@@ -53,6 +54,13 @@ pub struct ModuleTranslation<'data> {
     /// References to the function bodies.
     pub function_body_inputs: PrimaryMap<DefinedFuncIndex, FunctionBodyData<'data>>,
 
+    /// The bodies of the defined functions, kept around while they're
+    /// compiled so functions can be translated more than once, for example to
+    /// inline them into their callers.
+    ///
+    /// This is cleared once the module's functions have been compiled.
+    pub function_bodies: Option<FunctionBodies<'data>>,
+
     /// A list of type signatures which are considered exported from this
     /// module, or those that can possibly be called. This list is sorted, and
     /// trampolines for each of these signatures are required.
@@ -94,9 +102,15 @@ pub struct ModuleTranslation<'data> {
     /// which function is currently being defined.
     code_index: u32,
 
+    /// The type indices of the functions declared in the function section.
+    func_type_indices: Vec<TypeIndex>,
+
     /// The type information of the current module made available at the end of the
     /// validation process.
     types: Option<Types>,
+
+    /// The hash of `wasm`, computed on demand.
+    wasm_hash: OnceLock<u64>,
 }
 
 impl<'data> ModuleTranslation<'data> {
@@ -106,6 +120,36 @@ impl<'data> ModuleTranslation<'data> {
             .as_ref()
             .expect("module type information to be available")
     }
+
+    /// Returns a hash of the input wasm binary.
+    ///
+    /// The hash only depends on the contents of the binary, so it can be used
+    /// to find information saved about the same module by other processes,
+    /// such as profiles.
+    pub fn wasm_hash(&self) -> u64 {
+        *self.wasm_hash.get_or_init(|| {
+            // 64-bit FNV-1a.
+            self.wasm.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+            })
+        })
+    }
+}
+
+/// The bodies of a module's defined functions along with what's needed to
+/// translate them again.
+#[derive(Default)]
+pub struct FunctionBodies<'data> {
+    /// The type index and body of each defined function.
+    pub bodies: PrimaryMap<DefinedFuncIndex, (TypeIndex, FunctionBody<'data>)>,
+
+    /// The WebAssembly features the bodies were validated with.
+    pub features: WasmFeatures,
+
+    /// Translations of the bodies cached by the compiler, shared by the
+    /// compilation of all of the module's functions so each body is only
+    /// translated once.
+    pub translations: Mutex<HashMap<DefinedFuncIndex, Option<Arc<dyn Any + Send + Sync>>>>,
 }
 
 /// Contains function data: byte code and its offset in the module.
@@ -293,6 +337,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                     let ty = TypeIndex::from_u32(sigindex);
                     let sig_index = self.result.module.types[ty].unwrap_function();
                     self.result.module.push_function(sig_index);
+                    self.result.func_type_indices.push(ty);
                 }
             }
 
@@ -533,6 +578,10 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                 self.validator.code_section_start(count, &range)?;
                 let cnt = usize::try_from(count).unwrap();
                 self.result.function_body_inputs.reserve_exact(cnt);
+                let mut bodies = FunctionBodies::default();
+                bodies.bodies.reserve_exact(cnt);
+                bodies.features = *self.validator.features();
+                self.result.function_bodies = Some(bodies);
                 self.result.debuginfo.wasm_file.code_section_offset = range.start as u64;
             }
 
@@ -561,6 +610,13 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                         });
                 }
                 body.allow_memarg64(self.validator.features().memory64);
+                let ty = self.result.func_type_indices[self.result.code_index as usize];
+                self.result
+                    .function_bodies
+                    .as_mut()
+                    .unwrap()
+                    .bodies
+                    .push((ty, body.clone()));
                 self.result
                     .function_body_inputs
                     .push(FunctionBodyData { validator, body });
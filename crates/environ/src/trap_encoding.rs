@@ -96,6 +96,9 @@ pub enum Trap {
     /// would have violated the reentrance rules of the component model,
     /// triggering a trap instead.
     CannotEnterComponent,
+
+    /// A component function call was cancelled through its `CancelToken`.
+    Cancelled,
     // if adding a variant here be sure to update the `check!` macro below
 }
 
@@ -120,6 +123,7 @@ impl fmt::Display for Trap {
             AtomicWaitNonSharedMemory => "atomic wait on non-shared memory",
             NullReference => "null reference",
             CannotEnterComponent => "cannot enter component instance",
+            Cancelled => "component function call cancelled",
         };
         write!(f, "wasm trap: {desc}")
     }
@@ -236,6 +240,7 @@ pub fn lookup_trap_code(section: &[u8], offset: usize) -> Option<Trap> {
         AtomicWaitNonSharedMemory
         NullReference
         CannotEnterComponent
+        Cancelled
     }
 
     if cfg!(debug_assertions) {
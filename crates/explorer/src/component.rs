@@ -0,0 +1,329 @@
+//! Exploration of components, which contain any number of core wasm modules in
+//! addition to the adapters and trampolines that Wasmtime generates for them.
+
+use crate::{
+    annotate_wat, disassemble, read_clif_stages, AnnotatedAsm, AnnotatedWat, AnnotatedWatChunk,
+    FunctionLocation,
+};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use wasmtime_environ::component::{
+    ComponentTranslation, ComponentTypesBuilder, CoreDef, Export, ExportItem, GlobalInitializer,
+    InstantiateModule, LoweredIndex, RuntimeImportIndex, StaticModuleIndex, Trampoline,
+    TrampolineIndex, Translator,
+};
+use wasmtime_environ::{
+    DefinedFuncIndex, EntityIndex, EntityRef, FuncIndex, ModuleTranslation, PrimaryMap, ScopeVec,
+};
+
+pub(crate) fn annotate(
+    config: &wasmtime::Config,
+    target: &target_lexicon::Triple,
+    clif_dir: Option<&Path>,
+    wasm: &[u8],
+) -> Result<(AnnotatedWat, AnnotatedAsm)> {
+    let mut config = config.clone();
+    config.wasm_component_model(true);
+    let engine = wasmtime::Engine::new(&config)?;
+    let component = wasmtime::component::Component::new(&engine, wasm)?;
+
+    // The compiled component only describes where its code lives, so translate
+    // the component again to learn what that code is: which core modules are
+    // adapters generated by Wasmtime, what each trampoline implements, and
+    // which core functions are lifted into component exports. Translation is
+    // deterministic and is done with the engine's own tunables and features,
+    // so the indices assigned here match those of the compiled component.
+    let scope = ScopeVec::new();
+    let mut validator = wasmparser::Validator::new_with_features(engine.wasm_features());
+    let mut types = ComponentTypesBuilder::default();
+    let (translation, modules) =
+        Translator::new(engine.tunables(), &mut validator, &mut types, &scope)
+            .translate(wasm)
+            .context("failed to translate component")?;
+    if modules.len() != component.static_modules().len()
+        || translation.trampolines.len() != component.trampoline_locations().len()
+    {
+        bail!("translation of the component does not match its compiled code");
+    }
+
+    // Core modules defined in the component are printed as part of the
+    // component itself, but adapter modules are generated during translation
+    // and are printed after it. Each adapter module's offsets are biased past
+    // the end of everything printed before it so that offsets remain unique.
+    let mut wat = annotate_wat(wasm, 0)?;
+    let mut offset_biases = PrimaryMap::<StaticModuleIndex, u32>::new();
+    let mut next_bias = u32::try_from(wasm.len()).unwrap();
+    for (index, module) in modules.iter() {
+        if is_adapter(wasm, module) {
+            wat.chunks.push(AnnotatedWatChunk {
+                wasm_offset: None,
+                wat: format!(
+                    "\n;; adapter module {} generated by Wasmtime\n",
+                    index.as_u32()
+                ),
+            });
+            wat.chunks
+                .extend(annotate_wat(module.wasm, next_bias)?.chunks);
+            offset_biases.push(next_bias);
+            next_bias += u32::try_from(module.wasm.len()).unwrap();
+        } else {
+            offset_biases.push(0);
+        }
+    }
+
+    let names = Names::new(wasm, &translation, &modules);
+    let mut functions = Vec::new();
+    for ((index, _), module) in modules.iter().zip(component.static_modules()) {
+        for (i, (start, len)) in module.function_locations().enumerate() {
+            let func = DefinedFuncIndex::new(i);
+            let stages = match clif_dir {
+                Some(dir) => {
+                    let func_index = modules[index].module.func_index(func);
+                    read_clif_stages(
+                        dir,
+                        index.as_u32(),
+                        func_index.as_u32(),
+                        offset_biases[index],
+                        modules.len() == 1,
+                    )?
+                }
+                None => Vec::new(),
+            };
+            functions.push(FunctionLocation {
+                name: names.function(index, func),
+                start,
+                len,
+                offset_bias: offset_biases[index],
+                stages,
+            });
+        }
+    }
+    for (index, (start, len)) in translation
+        .trampolines
+        .keys()
+        .zip(component.trampoline_locations())
+    {
+        functions.push(FunctionLocation {
+            name: names.trampoline(index),
+            start,
+            len,
+            offset_bias: 0,
+            stages: Vec::new(),
+        });
+    }
+
+    // All of the component's modules share its text section and therefore its
+    // address map as well, so it doesn't matter which module it's read from.
+    let address_map = match component.static_modules().next() {
+        Some(module) => module
+            .address_map()
+            .ok_or_else(|| anyhow::anyhow!("address maps must be enabled in the config"))?
+            .collect(),
+        None => Vec::new(),
+    };
+    let asm = disassemble(target, component.text(), address_map.into_iter(), functions)?;
+    Ok((wat, asm))
+}
+
+/// Returns whether `module` was generated by Wasmtime rather than found within
+/// the component binary `wasm`.
+fn is_adapter(wasm: &[u8], module: &ModuleTranslation<'_>) -> bool {
+    !wasm.as_ptr_range().contains(&module.wasm.as_ptr())
+}
+
+/// Descriptions of the compiled functions and trampolines of a component in
+/// terms of the component's own imports and exports.
+struct Names<'a> {
+    modules: &'a PrimaryMap<StaticModuleIndex, ModuleTranslation<'a>>,
+    adapters: Vec<bool>,
+    trampolines: Vec<String>,
+    notes: HashMap<Item, Vec<String>>,
+}
+
+/// A piece of compiled code within a component.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Item {
+    Function(StaticModuleIndex, DefinedFuncIndex),
+    Trampoline(TrampolineIndex),
+}
+
+impl<'a> Names<'a> {
+    fn new(
+        wasm: &[u8],
+        translation: &ComponentTranslation,
+        modules: &'a PrimaryMap<StaticModuleIndex, ModuleTranslation<'a>>,
+    ) -> Names<'a> {
+        let component = &translation.component;
+        let mut names = Names {
+            modules,
+            adapters: modules.values().map(|m| is_adapter(wasm, m)).collect(),
+            trampolines: Vec::new(),
+            notes: HashMap::new(),
+        };
+
+        // Runtime instances are numbered in the order of their instantiation,
+        // and lowered imports in the order of their lowering.
+        let mut instances = Vec::new();
+        let mut lowerings = HashMap::new();
+        for init in &component.initializers {
+            match init {
+                GlobalInitializer::InstantiateModule(InstantiateModule::Static(module, _)) => {
+                    instances.push(Some(*module));
+                }
+                GlobalInitializer::InstantiateModule(InstantiateModule::Import(..)) => {
+                    instances.push(None);
+                }
+                GlobalInitializer::LowerImport { index, import } => {
+                    lowerings.insert(*index, *import);
+                }
+                _ => {}
+            }
+        }
+
+        let import_name = |import: RuntimeImportIndex| {
+            let (index, path) = &component.imports[import];
+            let (name, _) = &component.import_types[*index];
+            std::iter::once(name.as_str())
+                .chain(path.iter().map(|s| s.as_str()))
+                .collect::<Vec<_>>()
+                .join("#")
+        };
+        let lowering_name = |index: &LoweredIndex| match lowerings.get(index) {
+            Some(import) => format!("lowering of import \"{}\"", import_name(*import)),
+            None => format!("lowering {}", index.as_u32()),
+        };
+        for trampoline in translation.trampolines.values() {
+            names.trampolines.push(match trampoline {
+                Trampoline::LowerImport { index, .. } => lowering_name(index),
+                other => other.symbol_name(),
+            });
+        }
+
+        let resolve = |def: &CoreDef| -> Option<Item> {
+            match def {
+                CoreDef::Trampoline(index) => Some(Item::Trampoline(*index)),
+                CoreDef::Export(export) => {
+                    let module = instances.get(export.instance.index()).copied().flatten()?;
+                    let env = &modules[module].module;
+                    let func = match &export.item {
+                        ExportItem::Index(EntityIndex::Function(func)) => *func,
+                        ExportItem::Name(name) => match env.exports.get(name)? {
+                            EntityIndex::Function(func) => *func,
+                            _ => return None,
+                        },
+                        _ => return None,
+                    };
+                    Some(Item::Function(module, env.defined_func_index(func)?))
+                }
+                CoreDef::InstanceFlags(_) => None,
+            }
+        };
+
+        for (index, module) in modules.iter() {
+            for (name, entity) in module.module.exports.iter() {
+                if let EntityIndex::Function(func) = entity {
+                    if let Some(def) = module.module.defined_func_index(*func) {
+                        names.note(
+                            Item::Function(index, def),
+                            format!("core export \"{name}\""),
+                        );
+                    }
+                }
+            }
+        }
+
+        for init in &component.initializers {
+            let GlobalInitializer::InstantiateModule(InstantiateModule::Static(module, args)) =
+                init
+            else {
+                continue;
+            };
+            let kind = if names.adapters[module.index()] {
+                "adapter module"
+            } else {
+                "core module"
+            };
+            for (arg, (import_module, import_field, _)) in
+                args.iter().zip(modules[*module].module.imports())
+            {
+                if let Some(item) = resolve(arg) {
+                    names.note(
+                        item,
+                        format!(
+                            "imported by {kind} {} as \"{import_module}\" \"{import_field}\"",
+                            module.as_u32()
+                        ),
+                    );
+                }
+            }
+        }
+
+        let mut lifts = Vec::new();
+        collect_lifts("", &component.exports, &mut lifts);
+        for (name, func) in lifts {
+            if let Some(item) = resolve(func) {
+                names.note(item, format!("lifted as component export \"{name}\""));
+            }
+        }
+
+        names
+    }
+
+    fn note(&mut self, item: Item, note: String) {
+        self.notes.entry(item).or_default().push(note);
+    }
+
+    fn with_notes(&self, item: Item, name: String) -> String {
+        match self.notes.get(&item) {
+            Some(notes) => format!("{name}: {}", notes.join(", ")),
+            None => name,
+        }
+    }
+
+    fn function(&self, module: StaticModuleIndex, func: DefinedFuncIndex) -> String {
+        let kind = if self.adapters[module.index()] {
+            "Adapter Module"
+        } else {
+            "Core Module"
+        };
+        let func_index =
+            FuncIndex::new(self.modules[module].module.num_imported_funcs + func.index());
+        let name = format!(
+            "{kind} {} Function {}",
+            module.as_u32(),
+            func_index.as_u32()
+        );
+        self.with_notes(Item::Function(module, func), name)
+    }
+
+    fn trampoline(&self, index: TrampolineIndex) -> String {
+        let name = format!(
+            "Trampoline {} ({})",
+            index.as_u32(),
+            self.trampolines[index.index()]
+        );
+        self.with_notes(Item::Trampoline(index), name)
+    }
+}
+
+/// Collects the core definitions of all functions lifted into `exports`, named
+/// by their path within the component's exports.
+fn collect_lifts<'a>(
+    prefix: &str,
+    exports: impl IntoIterator<Item = (&'a String, &'a Export)>,
+    lifts: &mut Vec<(String, &'a CoreDef)>,
+) {
+    for (name, export) in exports {
+        let name = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}#{name}")
+        };
+        match export {
+            Export::LiftedFunction { func, .. } => lifts.push((name, func)),
+            Export::Instance { exports, .. } => collect_lifts(&name, exports, lifts),
+            _ => {}
+        }
+    }
+}
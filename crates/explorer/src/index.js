@@ -64,10 +64,10 @@ const existingHueForOffset = offset => {
 // Get WAT chunk elements by Wasm offset.
 const watByOffset = new Map();
 
-// Get asm instruction elements by Wasm offset.
+// Get asm instruction and CLIF line elements by Wasm offset.
 const asmByOffset = new Map();
 
-// Get all (WAT chunk or asm instruction) elements by offset.
+// Get all (WAT chunk, asm instruction, or CLIF line) elements by offset.
 const anyByOffset = new Map();
 
 const addWatElem = (offset, elem) => {
@@ -191,12 +191,11 @@ const renderInst = (mnemonic, operands) => {
 
 // Render the ASM.
 
-let nthFunc = 0;
 for (const func of state.asm.functions) {
   const funcElem = document.createElement("div");
 
   const funcHeader = document.createElement("h3");
-  funcHeader.textContent = `Defined Function ${nthFunc}`;
+  funcHeader.textContent = func.name;
   funcElem.appendChild(funcHeader);
 
   const bodyElem = document.createElement("pre");
@@ -215,8 +214,33 @@ for (const func of state.asm.functions) {
   }
   funcElem.appendChild(bodyElem);
 
+  for (const stage of func.stages) {
+    const stageElem = document.createElement("details");
+
+    const stageHeader = document.createElement("summary");
+    stageHeader.textContent = stage.name;
+    stageElem.appendChild(stageHeader);
+
+    const stageBodyElem = document.createElement("pre");
+    for (const line of stage.lines) {
+      const lineElem = document.createElement("span");
+      lineElem.textContent = `${line.text}\n`;
+      if (line.wasm_offset != null) {
+        lineElem.setAttribute("data-wasm-offset", line.wasm_offset);
+        const hue = hueForOffset(line.wasm_offset);
+        lineElem.style.backgroundColor = `hsl(${hue} 50% 90%)`;
+        lineElem.addEventListener("mouseenter", onMouseEnter);
+        lineElem.addEventListener("mouseleave", onMouseLeave);
+        addAsmElem(line.wasm_offset, lineElem);
+      }
+      stageBodyElem.appendChild(lineElem);
+    }
+    stageElem.appendChild(stageBodyElem);
+
+    funcElem.appendChild(stageElem);
+  }
+
   asmElem.appendChild(funcElem);
-  nthFunc++;
 }
 
 // Render the WAT.
@@ -1,11 +1,19 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use capstone::arch::BuildsCapstone;
 use serde_derive::Serialize;
-use std::{io::Write, str::FromStr};
+use std::{io::Write, path::Path, str::FromStr};
 
+mod component;
+
+/// Writes an HTML exploration of the compilation of `wasm` to `dest`.
+///
+/// If `clif` is true then each function's Cranelift IR before and after
+/// optimization, the rewrites made by the optimizer, and its VCode after
+/// register allocation are included alongside its machine code.
 pub fn generate(
     config: &wasmtime::Config,
     target: Option<&str>,
+    clif: bool,
     wasm: &[u8],
     dest: &mut dyn Write,
 ) -> Result<()> {
@@ -14,9 +22,26 @@ pub fn generate(
         Some(target) => target_lexicon::Triple::from_str(target)?,
     };
 
-    let wat = annotate_wat(wasm)?;
+    let mut config = config.clone();
+    let clif_dir = if clif {
+        let dir = tempfile::tempdir().context("failed to create CLIF directory")?;
+        config.emit_clif(dir.path());
+        config.emit_clif_stages(true);
+        Some(dir)
+    } else {
+        None
+    };
+    let clif_dir = clif_dir.as_ref().map(|dir| dir.path());
+
+    let (wat, asm) = if wasmparser::Parser::is_component(wasm) {
+        component::annotate(&config, &target, clif_dir, wasm)?
+    } else {
+        (
+            annotate_wat(wasm, 0)?,
+            annotate_asm(&config, &target, clif_dir, wasm)?,
+        )
+    };
     let wat_json = serde_json::to_string(&wat)?;
-    let asm = annotate_asm(config, &target, wasm)?;
     let asm_json = serde_json::to_string(&asm)?;
 
     let index_css = include_str!("./index.css");
@@ -64,12 +89,17 @@ struct AnnotatedWatChunk {
     wat: String,
 }
 
-fn annotate_wat(wasm: &[u8]) -> Result<AnnotatedWat> {
+/// Prints `wasm` as WAT, annotating each chunk with its offset in `wasm` plus
+/// `offset_bias`.
+///
+/// The bias is used to give each wasm binary shown in a single exploration,
+/// such as a component's generated adapter modules, distinct offsets.
+fn annotate_wat(wasm: &[u8], offset_bias: u32) -> Result<AnnotatedWat> {
     let mut printer = wasmprinter::Printer::new();
     let chunks = printer
         .offsets_and_lines(wasm)?
         .map(|(offset, wat)| AnnotatedWatChunk {
-            wasm_offset: offset.map(|o| WasmOffset(u32::try_from(o).unwrap())),
+            wasm_offset: offset.map(|o| WasmOffset(u32::try_from(o).unwrap() + offset_bias)),
             wat: wat.to_string(),
         })
         .collect();
@@ -83,7 +113,9 @@ struct AnnotatedAsm {
 
 #[derive(Serialize, Debug)]
 struct AnnotatedFunction {
+    name: String,
     instructions: Vec<AnnotatedInstruction>,
+    stages: Vec<AnnotatedStage>,
 }
 
 #[derive(Serialize, Debug)]
@@ -95,21 +127,152 @@ struct AnnotatedInstruction {
     operands: Option<String>,
 }
 
+/// An intermediate representation of a function at some stage of its
+/// compilation.
+#[derive(Serialize, Debug)]
+struct AnnotatedStage {
+    name: &'static str,
+    lines: Vec<AnnotatedLine>,
+}
+
+#[derive(Serialize, Debug)]
+struct AnnotatedLine {
+    wasm_offset: Option<WasmOffset>,
+    text: String,
+}
+
+/// The stages of compilation after translation to CLIF that Cranelift writes
+/// out for each function when `Config::emit_clif_stages` is used, as pairs of
+/// file extension and display name.
+const CLIF_STAGES: &[(&str, &str)] = &[
+    ("rewrites", "Egraph Rewrites"),
+    ("opt.clif", "Optimized CLIF"),
+    ("vcode", "VCode After Register Allocation"),
+];
+
 fn annotate_asm(
     config: &wasmtime::Config,
     target: &target_lexicon::Triple,
+    clif_dir: Option<&Path>,
     wasm: &[u8],
 ) -> Result<AnnotatedAsm> {
     let engine = wasmtime::Engine::new(config)?;
     let module = wasmtime::Module::new(&engine, wasm)?;
 
-    let text = module.text();
-    let address_map: Vec<_> = module
+    let address_map = module
         .address_map()
-        .ok_or_else(|| anyhow::anyhow!("address maps must be enabled in the config"))?
-        .collect();
+        .ok_or_else(|| anyhow::anyhow!("address maps must be enabled in the config"))?;
+    let num_imported_funcs = module
+        .imports()
+        .filter(|import| matches!(import.ty(), wasmtime::ExternType::Func(_)))
+        .count();
+    let functions = module
+        .function_locations()
+        .enumerate()
+        .map(|(i, (start, len))| {
+            let stages = match clif_dir {
+                Some(dir) => {
+                    let func_index = u32::try_from(num_imported_funcs + i).unwrap();
+                    read_clif_stages(dir, 0, func_index, 0, true)?
+                }
+                None => Vec::new(),
+            };
+            Ok(FunctionLocation {
+                name: format!("Defined Function {i}"),
+                start,
+                len,
+                offset_bias: 0,
+                stages,
+            })
+        })
+        .collect::<Result<_>>()?;
+    disassemble(target, module.text(), address_map, functions)
+}
+
+/// Reads the stages of compilation written to `dir` for the function with
+/// index `func_index` in the module with index `module`, which is always 0 for
+/// core wasm modules. The wasm offsets of the stages are biased by
+/// `offset_bias`; see `annotate_wat`.
+///
+/// The unoptimized CLIF is named after the function index alone, so it's only
+/// read if `unique_func_index` says that no other compiled module has a
+/// function with the same index.
+fn read_clif_stages(
+    dir: &Path,
+    module: u32,
+    func_index: u32,
+    offset_bias: u32,
+    unique_func_index: bool,
+) -> Result<Vec<AnnotatedStage>> {
+    let clif =
+        unique_func_index.then(|| (dir.join(format!("wasm_func_{func_index}.clif")), "CLIF"));
+    // The later stages are named after the function's symbol, with `:`
+    // replaced.
+    let later = CLIF_STAGES.iter().map(|(extension, name)| {
+        (
+            dir.join(format!(
+                "wasm[{module}]--function[{func_index}].{extension}"
+            )),
+            *name,
+        )
+    });
+
+    let mut stages = Vec::new();
+    for (path, name) in clif.into_iter().chain(later) {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            // Nothing is written for modules which were loaded from the cache
+            // instead of being compiled.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        let lines = text
+            .lines()
+            .map(|line| AnnotatedLine {
+                wasm_offset: clif_wasm_offset(line).map(|o| WasmOffset(o.0 + offset_bias)),
+                text: line.to_string(),
+            })
+            .collect();
+        stages.push(AnnotatedStage { name, lines });
+    }
+    Ok(stages)
+}
+
+/// Parses the wasm offset of the `@xxxx` source location that CLIF prints
+/// before an instruction, if any.
+fn clif_wasm_offset(line: &str) -> Option<WasmOffset> {
+    let srcloc = line.trim_start().strip_prefix('@')?;
+    let hex = srcloc.split_whitespace().next()?;
+    u32::from_str_radix(hex, 16).ok().map(WasmOffset)
+}
 
-    let mut address_map_iter = address_map.into_iter().peekable();
+/// A compiled function within a `.text` section to disassemble.
+struct FunctionLocation {
+    /// The name to display for this function.
+    name: String,
+    /// The offset of this function within the `.text` section.
+    start: usize,
+    /// The length, in bytes, of this function.
+    len: usize,
+    /// The bias added to this function's wasm offsets; see `annotate_wat`.
+    offset_bias: u32,
+    /// The intermediate representations of this function to display.
+    stages: Vec<AnnotatedStage>,
+}
+
+/// Disassembles each of `functions` within `text`, annotating instructions
+/// with the wasm offsets found in `address_map`.
+fn disassemble(
+    target: &target_lexicon::Triple,
+    text: &[u8],
+    address_map: impl Iterator<Item = (usize, Option<u32>)>,
+    mut functions: Vec<FunctionLocation>,
+) -> Result<AnnotatedAsm> {
+    // The address map is consumed in order below, so the functions must be
+    // visited in the order they appear in the text section.
+    functions.sort_by_key(|f| f.start);
+
+    let mut address_map_iter = address_map.peekable();
     let mut current_entry = address_map_iter.next();
     let mut wasm_offset_for_address = |start: usize, address: u32| -> Option<WasmOffset> {
         // Consume any entries that happened before the current function for the
@@ -128,10 +291,16 @@ fn annotate_asm(
         current_entry.and_then(|entry| entry.1.map(WasmOffset))
     };
 
-    let functions = module
-        .function_locations()
+    let functions = functions
         .into_iter()
-        .map(|(start, len)| {
+        .map(|func| {
+            let FunctionLocation {
+                name,
+                start,
+                len,
+                offset_bias,
+                stages,
+            } = func;
             let body = &text[start..][..len];
 
             let mut cs = match target.architecture {
@@ -171,7 +340,8 @@ fn annotate_asm(
                 .iter()
                 .map(|inst| {
                     let address = u32::try_from(inst.address()).unwrap();
-                    let wasm_offset = wasm_offset_for_address(start, address);
+                    let wasm_offset = wasm_offset_for_address(start, address)
+                        .map(|offset| WasmOffset(offset.0 + offset_bias));
                     Ok(AnnotatedInstruction {
                         wasm_offset,
                         address,
@@ -181,7 +351,11 @@ fn annotate_asm(
                     })
                 })
                 .collect::<Result<Vec<_>>>()?;
-            Ok(AnnotatedFunction { instructions })
+            Ok(AnnotatedFunction {
+                name,
+                instructions,
+                stages,
+            })
         })
         .collect::<Result<Vec<_>>>()?;
 
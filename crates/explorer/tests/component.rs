@@ -0,0 +1,73 @@
+use anyhow::Result;
+
+#[test]
+fn explore_component() -> Result<()> {
+    let wasm = component()?;
+
+    // Explore with non-default settings to check that the component is
+    // translated the same way the engine compiled it.
+    let mut config = wasmtime::Config::new();
+    config.static_memory_maximum_size(0);
+    config.wasm_simd(false);
+    config.wasm_relaxed_simd(false);
+
+    let mut html = Vec::new();
+    wasmtime_explorer::generate(&config, None, false, &wasm, &mut html)?;
+    let html = String::from_utf8(html)?;
+
+    // Names are embedded in the page as JSON strings, hence the escaping.
+    assert!(
+        html.contains(
+            r#"Core Module 0 Function 1: core export \"run\", lifted as component export \"run\""#
+        ),
+        "{html}"
+    );
+    assert!(
+        html.contains(
+            r#"Trampoline 0 (lowering of import \"log\"): imported by core module 0 as \"host\" \"log\""#
+        ),
+        "{html}"
+    );
+    Ok(())
+}
+
+#[test]
+fn explore_component_clif() -> Result<()> {
+    let wasm = component()?;
+    let mut html = Vec::new();
+    wasmtime_explorer::generate(&wasmtime::Config::new(), None, true, &wasm, &mut html)?;
+    let html = String::from_utf8(html)?;
+
+    // Each stage of the core function's compilation is included.
+    for stage in [
+        "CLIF",
+        "Egraph Rewrites",
+        "Optimized CLIF",
+        "VCode After Register Allocation",
+    ] {
+        assert!(html.contains(&format!(r#""name":"{stage}""#)), "{html}");
+    }
+    Ok(())
+}
+
+fn component() -> Result<Vec<u8>> {
+    Ok(wat::parse_str(
+        r#"
+            (component
+                (import "log" (func $log (param "x" u32)))
+                (core func $log_lower (canon lower (func $log)))
+                (core module $m
+                    (import "host" "log" (func $log (param i32)))
+                    (func (export "run") (param i32)
+                        local.get 0
+                        call $log)
+                )
+                (core instance $i (instantiate $m
+                    (with "host" (instance (export "log" (func $log_lower))))
+                ))
+                (func (export "run") (param "x" u32)
+                    (canon lift (core func $i "run")))
+            )
+        "#,
+    )?)
+}
@@ -293,6 +293,14 @@ pub struct MemoryImageSlot {
     /// specific to this slot) in place when it is dropped. Default
     /// on, unless the caller knows what they are doing.
     clear_on_drop: bool,
+
+    /// Whether the memory in this slot should be backed by transparent huge
+    /// pages.
+    ///
+    /// Replacing a mapping with `mmap` discards any `madvise` hints that were
+    /// applied to it, so when this is set the hint is reapplied to each range
+    /// that this slot remaps.
+    huge_pages: bool,
 }
 
 impl MemoryImageSlot {
@@ -312,6 +320,7 @@ impl MemoryImageSlot {
             image: None,
             dirty: false,
             clear_on_drop: true,
+            huge_pages: false,
         }
     }
 
@@ -328,6 +337,7 @@ impl MemoryImageSlot {
             accessible: 0,
             dirty: false,
             clear_on_drop: false,
+            huge_pages: false,
         }
     }
 
@@ -339,6 +349,17 @@ impl MemoryImageSlot {
         self.clear_on_drop = false;
     }
 
+    /// Inform the MemoryImageSlot that the memory it manages should be backed
+    /// by transparent huge pages, if available.
+    ///
+    /// The caller is expected to have already applied the hint to any memory
+    /// that's accessible when the slot is created; this slot applies it to
+    /// memory it makes accessible and reapplies it whenever it replaces part
+    /// of the accessible mapping.
+    pub(crate) fn use_huge_pages(&mut self) {
+        self.huge_pages = true;
+    }
+
     pub(crate) fn set_heap_limit(&mut self, size_bytes: usize) -> Result<()> {
         assert!(size_bytes <= self.static_size);
 
@@ -354,6 +375,9 @@ impl MemoryImageSlot {
 
         // Otherwise use `mprotect` to make the new pages read/write.
         self.set_protection(self.accessible..size_bytes, true)?;
+        unsafe {
+            self.advise_huge_pages(self.accessible, size_bytes - self.accessible);
+        }
         self.accessible = size_bytes;
 
         Ok(())
@@ -404,6 +428,9 @@ impl MemoryImageSlot {
         // it's not large enough to accommodate `initial_size_bytes`.
         if self.accessible < initial_size_bytes {
             self.set_protection(self.accessible..initial_size_bytes, true)?;
+            unsafe {
+                self.advise_huge_pages(self.accessible, initial_size_bytes - self.accessible);
+            }
             self.accessible = initial_size_bytes;
         }
 
@@ -432,6 +459,7 @@ impl MemoryImageSlot {
                 if image.len > 0 {
                     unsafe {
                         image.map_at(self.base.as_ptr())?;
+                        self.advise_huge_pages(image.linear_memory_offset, image.len);
                     }
                 }
             }
@@ -449,6 +477,7 @@ impl MemoryImageSlot {
         if let Some(image) = &self.image {
             unsafe {
                 image.remap_as_zeros_at(self.base.as_ptr())?;
+                self.advise_huge_pages(image.linear_memory_offset, image.len);
             }
             self.image = None;
         }
@@ -609,6 +638,12 @@ impl MemoryImageSlot {
         Ok(())
     }
 
+    unsafe fn advise_huge_pages(&self, start: usize, len: usize) {
+        if self.huge_pages {
+            crate::mmap::advise_huge_pages(self.base.as_ptr().add(start), len);
+        }
+    }
+
     pub(crate) fn has_image(&self) -> bool {
         self.image.is_some()
     }
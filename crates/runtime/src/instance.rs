@@ -784,6 +784,53 @@ impl Instance {
         }
     }
 
+    /// Replaces the compiled module backing this instance with
+    /// `runtime_info`, keeping all of this instance's memories, tables and
+    /// globals intact.
+    ///
+    /// Funcref tables are fully initialized with the original module before
+    /// the replacement happens, and then every escaping function's
+    /// `VMFuncRef` in this instance's `VMContext` is rebuilt in place. Since
+    /// tables and exports only ever refer to these `VMFuncRef`s by pointer,
+    /// they start calling the new code afterwards. Other instances' imports
+    /// of these functions are copies of the old `VMFuncRef`s, however, and
+    /// keep calling the old code.
+    ///
+    /// # Unsafety
+    ///
+    /// The caller must ensure that `runtime_info` describes a module with the
+    /// same `VMContext` layout, the same function types at each index, and
+    /// the same set of escaping functions as the current module. The current
+    /// module's code must additionally be kept alive for as long as any of it
+    /// may still be executing.
+    unsafe fn replace_module(&mut self, runtime_info: Arc<dyn ModuleRuntimeInfo>) {
+        assert_eq!(
+            self.offsets().size_of_vmctx(),
+            runtime_info.offsets().size_of_vmctx()
+        );
+        assert_eq!(
+            self.offsets().num_escaped_funcs,
+            runtime_info.offsets().num_escaped_funcs
+        );
+
+        for i in 0..self.tables.len() {
+            let idx = DefinedTableIndex::new(i);
+            let size = self.tables[idx].1.size();
+            self.get_defined_table_with_lazy_init(idx, 0..size);
+        }
+
+        self.runtime_info = runtime_info;
+        let types = self.runtime_info.type_ids();
+        *self.vmctx_plus_offset_mut(self.offsets().vmctx_type_ids_array()) = types.as_ptr();
+
+        let module = self.module().clone();
+        for (index, func) in module.functions.iter() {
+            if func.is_escaping() {
+                self.get_func_ref(index);
+            }
+        }
+    }
+
     /// The `table.init` operation: initializes a portion of a table with a
     /// passive element.
     ///
@@ -1473,6 +1520,17 @@ impl InstanceHandle {
         allocator::initialize_instance(self.instance_mut(), module, is_bulk_memory)
     }
 
+    /// Replaces the compiled module backing this instance with
+    /// `runtime_info` while preserving its state.
+    ///
+    /// # Unsafety
+    ///
+    /// See the unsafety notes of `Instance::replace_module`; the replacement
+    /// module must be layout- and type-compatible with the current one.
+    pub unsafe fn replace_module(&mut self, runtime_info: Arc<dyn ModuleRuntimeInfo>) {
+        self.instance_mut().replace_module(runtime_info)
+    }
+
     /// Attempts to convert from the host `addr` specified to a WebAssembly
     /// based address recorded in `WasmFault`.
     ///
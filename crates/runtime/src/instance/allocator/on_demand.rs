@@ -25,6 +25,7 @@ use wasmtime_environ::{
 #[derive(Clone)]
 pub struct OnDemandInstanceAllocator {
     mem_creator: Option<Arc<dyn RuntimeMemoryCreator>>,
+    huge_pages: bool,
     #[cfg(feature = "async")]
     stack_creator: Option<Arc<dyn RuntimeFiberStackCreator>>,
     #[cfg(feature = "async")]
@@ -37,6 +38,7 @@ impl OnDemandInstanceAllocator {
         let _ = stack_size; // suppress warnings when async feature is disabled.
         Self {
             mem_creator,
+            huge_pages: false,
             #[cfg(feature = "async")]
             stack_creator: None,
             #[cfg(feature = "async")]
@@ -44,6 +46,12 @@ impl OnDemandInstanceAllocator {
         }
     }
 
+    /// Configures whether linear memories allocated without a custom memory
+    /// creator are backed by transparent huge pages.
+    pub fn set_huge_pages(&mut self, huge_pages: bool) {
+        self.huge_pages = huge_pages;
+    }
+
     /// Set the stack creator.
     #[cfg(feature = "async")]
     pub fn set_stack_creator(&mut self, stack_creator: Arc<dyn RuntimeFiberStackCreator>) {
@@ -55,6 +63,7 @@ impl Default for OnDemandInstanceAllocator {
     fn default() -> Self {
         Self {
             mem_creator: None,
+            huge_pages: false,
             #[cfg(feature = "async")]
             stack_creator: None,
             #[cfg(feature = "async")]
@@ -96,10 +105,8 @@ unsafe impl InstanceAllocatorImpl for OnDemandInstanceAllocator {
         memory_plan: &MemoryPlan,
         memory_index: DefinedMemoryIndex,
     ) -> Result<(MemoryAllocationIndex, Memory)> {
-        let creator = self
-            .mem_creator
-            .as_deref()
-            .unwrap_or_else(|| &DefaultMemoryCreator);
+        let default_creator = DefaultMemoryCreator::new(self.huge_pages);
+        let creator = self.mem_creator.as_deref().unwrap_or(&default_creator);
         let image = request.runtime_info.memory_image(memory_index)?;
         let allocation_index = MemoryAllocationIndex::default();
         let memory = Memory::new_dynamic(
@@ -171,6 +171,9 @@ pub struct PoolingInstanceAllocatorConfig {
     pub memory_protection_keys: MpkEnabled,
     /// How many memory protection keys to allocate.
     pub max_memory_protection_keys: usize,
+    /// Whether linear memories in the pool are backed by transparent huge
+    /// pages.
+    pub huge_pages: bool,
 }
 
 impl Default for PoolingInstanceAllocatorConfig {
@@ -185,6 +188,7 @@ impl Default for PoolingInstanceAllocatorConfig {
             table_keep_resident: 0,
             memory_protection_keys: MpkEnabled::Disable,
             max_memory_protection_keys: 16,
+            huge_pages: false,
         }
     }
 }
@@ -129,6 +129,9 @@ pub struct MemoryPool {
     //
     // Only applicable on Linux.
     keep_resident: usize,
+    // Whether linear memories in this pool are backed by transparent huge
+    // pages.
+    huge_pages: bool,
     // Keep track of protection keys handed out to initialized stores; this
     // allows us to round-robin the assignment of stores to stripes.
     next_available_pkey: AtomicUsize,
@@ -235,6 +238,7 @@ impl MemoryPool {
             layout,
             memories_per_instance: usize::try_from(config.limits.max_memories_per_module).unwrap(),
             keep_resident: config.linear_memory_keep_resident,
+            huge_pages: config.huge_pages,
             next_available_pkey: AtomicUsize::new(0),
         };
 
@@ -469,11 +473,15 @@ impl MemoryPool {
             .take();
 
         maybe_slot.unwrap_or_else(|| {
-            MemoryImageSlot::create(
+            let mut slot = MemoryImageSlot::create(
                 self.get_base(allocation_index) as *mut c_void,
                 0,
                 self.layout.max_memory_bytes,
-            )
+            );
+            if self.huge_pages {
+                slot.use_huge_pages();
+            }
+            slot
         })
     }
 
@@ -49,7 +49,8 @@ pub use crate::instance::{
     InstanceLimits, PoolingInstanceAllocator, PoolingInstanceAllocatorConfig,
 };
 pub use crate::memory::{
-    DefaultMemoryCreator, Memory, RuntimeLinearMemory, RuntimeMemoryCreator, SharedMemory,
+    DefaultMemoryCreator, FileMemory, FileMemoryCreator, Memory, RuntimeLinearMemory,
+    RuntimeMemoryCreator, SharedMemory,
 };
 pub use crate::mmap::Mmap;
 pub use crate::mmap_vec::MmapVec;
@@ -7,9 +7,11 @@ use crate::parking_spot::{ParkingSpot, Waiter};
 use crate::vmcontext::VMMemoryDefinition;
 use crate::{MemoryImage, MemoryImageSlot, SendSyncPtr, Store, WaitResult};
 use anyhow::Error;
-use anyhow::{bail, format_err, Result};
+use anyhow::{bail, format_err, Context, Result};
 use std::cell::RefCell;
 use std::convert::TryFrom;
+use std::fs::File;
+use std::mem;
 use std::ops::Range;
 use std::ptr::NonNull;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
@@ -34,7 +36,18 @@ pub trait RuntimeMemoryCreator: Send + Sync {
 }
 
 /// A default memory allocator used by Wasmtime
-pub struct DefaultMemoryCreator;
+#[derive(Default)]
+pub struct DefaultMemoryCreator {
+    huge_pages: bool,
+}
+
+impl DefaultMemoryCreator {
+    /// Creates a new memory allocator which, if `huge_pages` is true, requests
+    /// that linear memories be backed by transparent huge pages.
+    pub fn new(huge_pages: bool) -> DefaultMemoryCreator {
+        DefaultMemoryCreator { huge_pages }
+    }
+}
 
 impl RuntimeMemoryCreator for DefaultMemoryCreator {
     /// Create new MmapMemory
@@ -50,6 +63,7 @@ impl RuntimeMemoryCreator for DefaultMemoryCreator {
             minimum,
             maximum,
             memory_image,
+            self.huge_pages,
         )?))
     }
 }
@@ -193,16 +207,24 @@ pub struct MmapMemory {
     // An optional CoW mapping that provides the initial content of this
     // MmapMemory, if mapped.
     memory_image: Option<MemoryImageSlot>,
+
+    // Whether the linear memory should be backed by transparent huge pages,
+    // which is requested for each range as it's made accessible.
+    huge_pages: bool,
 }
 
 impl MmapMemory {
     /// Create a new linear memory instance with specified minimum and maximum
     /// number of wasm pages.
+    ///
+    /// If `huge_pages` is true then the memory is requested to be backed by
+    /// transparent huge pages where the host supports them.
     pub fn new(
         plan: &MemoryPlan,
         minimum: usize,
         mut maximum: Option<usize>,
         memory_image: Option<&Arc<MemoryImage>>,
+        huge_pages: bool,
     ) -> Result<Self> {
         // It's a programmer error for these two configuration values to exceed
         // the host available address space, so panic if such a configuration is
@@ -238,6 +260,9 @@ impl MmapMemory {
 
         if minimum > 0 {
             mmap.make_accessible(pre_guard_bytes, minimum)?;
+            if huge_pages {
+                mmap.advise_huge_pages(pre_guard_bytes, minimum);
+            }
         }
 
         // If a memory image was specified, try to create the MemoryImageSlot on
@@ -250,6 +275,9 @@ impl MmapMemory {
                     minimum,
                     alloc_bytes + extra_to_reserve_on_growth,
                 );
+                if huge_pages {
+                    slot.use_huge_pages();
+                }
                 slot.instantiate(minimum, Some(image), &plan)?;
                 // On drop, we will unmap our mmap'd range that this slot was
                 // mapped on top of, so there is no need for the slot to wipe
@@ -268,6 +296,7 @@ impl MmapMemory {
             offset_guard_size: offset_guard_bytes,
             extra_to_reserve_on_growth,
             memory_image,
+            huge_pages,
         })
     }
 }
@@ -296,6 +325,9 @@ impl RuntimeLinearMemory for MmapMemory {
 
             let mut new_mmap = Mmap::accessible_reserved(0, request_bytes)?;
             new_mmap.make_accessible(self.pre_guard_size, new_size)?;
+            if self.huge_pages {
+                new_mmap.advise_huge_pages(self.pre_guard_size, new_size);
+            }
 
             // This method has an exclusive reference to `self.mmap` and just
             // created `new_mmap` so it should be safe to acquire references
@@ -331,6 +363,12 @@ impl RuntimeLinearMemory for MmapMemory {
                 self.pre_guard_size + self.accessible,
                 new_size - self.accessible,
             )?;
+            if self.huge_pages {
+                self.mmap.advise_huge_pages(
+                    self.pre_guard_size + self.accessible,
+                    new_size - self.accessible,
+                );
+            }
         }
 
         self.accessible = new_size;
@@ -362,6 +400,249 @@ impl RuntimeLinearMemory for MmapMemory {
     }
 }
 
+/// A `RuntimeMemoryCreator` which creates memories backed by a host file.
+///
+/// See `FileMemory` for details on how the file is mapped.
+pub struct FileMemoryCreator {
+    file: Arc<File>,
+    shared: bool,
+}
+
+impl FileMemoryCreator {
+    /// Creates a new creator whose memories map `file`.
+    ///
+    /// If `shared` is true then modifications made to the memory are written
+    /// back to `file`, otherwise they are private to the memory.
+    pub fn new(file: File, shared: bool) -> FileMemoryCreator {
+        FileMemoryCreator {
+            file: Arc::new(file),
+            shared,
+        }
+    }
+}
+
+impl RuntimeMemoryCreator for FileMemoryCreator {
+    fn new_memory(
+        &self,
+        plan: &MemoryPlan,
+        minimum: usize,
+        maximum: Option<usize>,
+        _: Option<&Arc<MemoryImage>>,
+    ) -> Result<Box<dyn RuntimeLinearMemory>> {
+        Ok(Box::new(FileMemory::new(
+            plan,
+            minimum,
+            maximum,
+            self.file.clone(),
+            self.shared,
+        )?))
+    }
+}
+
+/// A linear memory whose contents are provided by a host file.
+///
+/// The memory is laid out just like `MmapMemory`, including its guard
+/// regions, except that the accessible part of linear memory is mapped from
+/// `file` rather than anonymous memory. The memory's minimum size must
+/// already cover the whole file, rounded up to the wasm page size.
+///
+/// With a shared mapping writes to the part of the memory covering the file
+/// go straight to the file, and with a private mapping they're copy-on-write
+/// and never reach the file. Either way the file's length is never changed,
+/// and any part of the memory beyond the end of the file is zero-filled
+/// anonymous memory.
+#[derive(Debug)]
+pub struct FileMemory {
+    // The underlying reservation, including guard regions.
+    mmap: Mmap,
+
+    // The file providing the contents of this memory.
+    file: Arc<File>,
+
+    // Whether `file` is mapped shared or private.
+    shared: bool,
+
+    // The number of bytes at the start of linear memory which are mapped from
+    // `file`, which is the file's length rounded up to the host page size.
+    file_limit: usize,
+
+    // The number of bytes that are accessible in `mmap`, starting at
+    // `pre_guard_size`.
+    accessible: usize,
+
+    // Same as the fields of the same name in `MmapMemory`.
+    maximum: Option<usize>,
+    extra_to_reserve_on_growth: usize,
+    pre_guard_size: usize,
+    offset_guard_size: usize,
+}
+
+impl FileMemory {
+    /// Create a new linear memory backed by `file` with the specified minimum
+    /// and maximum number of bytes.
+    ///
+    /// Returns an error if `file` doesn't fit within `minimum` bytes.
+    pub fn new(
+        plan: &MemoryPlan,
+        minimum: usize,
+        mut maximum: Option<usize>,
+        file: Arc<File>,
+        shared: bool,
+    ) -> Result<Self> {
+        let offset_guard_bytes = usize::try_from(plan.offset_guard_size).unwrap();
+        let pre_guard_bytes = usize::try_from(plan.pre_guard_size).unwrap();
+
+        let file_len = file
+            .metadata()
+            .context("failed to get file metadata")?
+            .len();
+        let file_len = usize::try_from(file_len)
+            .map_err(|_| format_err!("file of {file_len} bytes is too large to map"))?;
+        let too_large = || format_err!("file of {file_len} bytes is too large to map");
+        let file_limit = file_len
+            .checked_next_multiple_of(crate::page_size())
+            .ok_or_else(too_large)?;
+        let file_pages = file_limit
+            .checked_next_multiple_of(WASM_PAGE_SIZE)
+            .ok_or_else(too_large)?;
+        if file_pages > minimum {
+            bail!(
+                "file of {file_len} bytes does not fit in a linear memory \
+                 with a minimum size of {minimum} bytes"
+            );
+        }
+
+        let (alloc_bytes, extra_to_reserve_on_growth) = match plan.style {
+            MemoryStyle::Dynamic { reserve } => (minimum, usize::try_from(reserve).unwrap()),
+            MemoryStyle::Static { bound } => {
+                let bound_bytes =
+                    usize::try_from(bound.checked_mul(WASM_PAGE_SIZE_U64).unwrap()).unwrap();
+                maximum = Some(bound_bytes.min(maximum.unwrap_or(usize::MAX)));
+                (bound_bytes, 0)
+            }
+        };
+        if let Some(max) = maximum {
+            if minimum > max {
+                bail!(
+                    "file of {file_len} bytes does not fit in a linear memory \
+                     with a maximum size of {max} bytes"
+                );
+            }
+        }
+
+        let request_bytes = pre_guard_bytes
+            .checked_add(alloc_bytes)
+            .and_then(|i| i.checked_add(extra_to_reserve_on_growth))
+            .and_then(|i| i.checked_add(offset_guard_bytes))
+            .ok_or_else(|| format_err!("cannot allocate {} with guard regions", minimum))?;
+        let mmap = Mmap::accessible_reserved(0, request_bytes)?;
+
+        let mut memory = Self {
+            mmap,
+            file_limit,
+            file,
+            shared,
+            accessible: 0,
+            maximum,
+            pre_guard_size: pre_guard_bytes,
+            offset_guard_size: offset_guard_bytes,
+            extra_to_reserve_on_growth,
+        };
+        if minimum > 0 {
+            memory.map_range(0, minimum)?;
+        }
+        memory.accessible = minimum;
+        Ok(memory)
+    }
+
+    /// Makes the linear memory bytes in `start..end` accessible, mapping them
+    /// from the file as appropriate.
+    fn map_range(&mut self, start: usize, end: usize) -> Result<()> {
+        let file_end = end.min(self.file_limit).max(start);
+        if file_end > start {
+            self.mmap.map_file(
+                self.pre_guard_size + start,
+                file_end - start,
+                &self.file,
+                u64::try_from(start).unwrap(),
+                self.shared,
+            )?;
+        }
+        if end > file_end {
+            self.mmap
+                .make_accessible(self.pre_guard_size + file_end, end - file_end)?;
+        }
+        Ok(())
+    }
+}
+
+impl RuntimeLinearMemory for FileMemory {
+    fn byte_size(&self) -> usize {
+        self.accessible
+    }
+
+    fn maximum_byte_size(&self) -> Option<usize> {
+        self.maximum
+    }
+
+    fn grow_to(&mut self, new_size: usize) -> Result<()> {
+        if new_size > self.mmap.len() - self.offset_guard_size - self.pre_guard_size {
+            // Like `MmapMemory` this must be a dynamic memory which has run
+            // out of reserved space, so move it to a new, larger, reservation.
+            let request_bytes = self
+                .pre_guard_size
+                .checked_add(new_size)
+                .and_then(|s| s.checked_add(self.extra_to_reserve_on_growth))
+                .and_then(|s| s.checked_add(self.offset_guard_size))
+                .ok_or_else(|| format_err!("overflow calculating size of memory allocation"))?;
+            let old = mem::replace(&mut self.mmap, Mmap::accessible_reserved(0, request_bytes)?);
+            self.map_range(0, new_size)?;
+
+            // A shared mapping already sees all previous writes to the file
+            // through the new mapping, but everything else needs to be
+            // carried over.
+            let copy_start = if self.shared {
+                self.file_limit.min(self.accessible)
+            } else {
+                0
+            };
+            unsafe {
+                let range = self.pre_guard_size + copy_start..self.pre_guard_size + self.accessible;
+                let src = old.slice(range.clone());
+                let dst = self.mmap.slice_mut(range);
+                dst.copy_from_slice(src);
+            }
+        } else {
+            assert!(new_size > self.accessible);
+            self.map_range(self.accessible, new_size)?;
+        }
+
+        self.accessible = new_size;
+        Ok(())
+    }
+
+    fn vmmemory(&mut self) -> VMMemoryDefinition {
+        VMMemoryDefinition {
+            base: unsafe { self.mmap.as_mut_ptr().add(self.pre_guard_size) },
+            current_length: self.accessible.into(),
+        }
+    }
+
+    fn needs_init(&self) -> bool {
+        true
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn wasm_accessible(&self) -> Range<usize> {
+        let base = self.mmap.as_ptr() as usize + self.pre_guard_size;
+        let end = base + (self.mmap.len() - self.pre_guard_size);
+        base..end
+    }
+}
+
 /// A "static" memory where the lifetime of the backing memory is managed
 /// elsewhere. Currently used with the pooling allocator.
 struct StaticMemory {
@@ -481,9 +762,12 @@ struct SharedMemoryInner {
 
 impl SharedMemory {
     /// Construct a new [`SharedMemory`].
-    pub fn new(plan: MemoryPlan) -> Result<Self> {
+    ///
+    /// If `huge_pages` is true then the memory is requested to be backed by
+    /// transparent huge pages where the host supports them.
+    pub fn new(plan: MemoryPlan, huge_pages: bool) -> Result<Self> {
         let (minimum_bytes, maximum_bytes) = Memory::limit_new(&plan, None)?;
-        let mmap_memory = MmapMemory::new(&plan, minimum_bytes, maximum_bytes, None)?;
+        let mmap_memory = MmapMemory::new(&plan, minimum_bytes, maximum_bytes, None, huge_pages)?;
         Self::wrap(&plan, Box::new(mmap_memory), plan.memory)
     }
 
@@ -1,13 +1,27 @@
 //! Low-level abstraction for allocating and managing zero-filled pages
 //! of memory.
 
-use crate::sys::mmap;
+use crate::sys::{mmap, vm};
 use anyhow::{Context, Result};
 use std::fs::File;
 use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Hints to the OS that the `len` bytes at `ptr` should be backed by
+/// transparent huge pages.
+///
+/// Failures are not fatal: the memory falls back to being backed by normal
+/// pages, so they're only logged.
+pub(crate) unsafe fn advise_huge_pages(ptr: *mut u8, len: usize) {
+    if len == 0 || !vm::supports_huge_pages() {
+        return;
+    }
+    if let Err(e) = vm::madvise_huge_pages(ptr, len) {
+        log::debug!("failed to enable huge pages for {len:#x} bytes at {ptr:?}: {e}");
+    }
+}
+
 /// A simple struct consisting of a page-aligned pointer to page-aligned
 /// and initially-zeroed memory and a length.
 #[derive(Debug)]
@@ -100,6 +114,51 @@ impl Mmap {
         self.sys.make_accessible(start, len)
     }
 
+    /// Maps `len` bytes of `file`, starting at `offset` within the file, as
+    /// read/write memory at `start` within this mapping, replacing whatever
+    /// was previously mapped there.
+    ///
+    /// If `shared` is true then writes to the memory are written back to the
+    /// file, and otherwise they're private copy-on-write modifications.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `start`, `len`, or `offset` is not page
+    /// aligned or if the range is outside the bounds of this mapping.
+    pub fn map_file(
+        &mut self,
+        start: usize,
+        len: usize,
+        file: &File,
+        offset: u64,
+        shared: bool,
+    ) -> Result<()> {
+        let page_size = crate::page_size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_eq!(offset & (page_size as u64 - 1), 0);
+        assert!(len <= self.len());
+        assert!(start <= self.len() - len);
+
+        self.sys.map_file(start, len, file, offset, shared)
+    }
+
+    /// Requests that the `len` bytes starting at `start` within this mapping
+    /// be backed by transparent huge pages.
+    ///
+    /// This is only a hint: if the host doesn't support huge pages then this
+    /// does nothing and the memory continues to use normal pages.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the range is outside the bounds of this
+    /// mapping.
+    pub fn advise_huge_pages(&mut self, start: usize, len: usize) {
+        assert!(len <= self.len());
+        assert!(start <= self.len() - len);
+        unsafe { advise_huge_pages(self.as_mut_ptr().add(start), len) }
+    }
+
     /// Return the allocated memory as a slice of u8.
     ///
     /// # Safety
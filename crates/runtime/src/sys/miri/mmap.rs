@@ -56,6 +56,17 @@ impl Mmap {
         Ok(())
     }
 
+    pub fn map_file(
+        &mut self,
+        _start: usize,
+        _len: usize,
+        _file: &File,
+        _offset: u64,
+        _shared: bool,
+    ) -> Result<()> {
+        bail!("not supported on miri")
+    }
+
     pub fn as_ptr(&self) -> *const u8 {
         self.memory.as_ptr() as *const u8
     }
@@ -41,6 +41,14 @@ pub unsafe fn madvise_dontneed(_ptr: *mut u8, _len: usize) -> io::Result<()> {
     unreachable!()
 }
 
+pub fn supports_huge_pages() -> bool {
+    false
+}
+
+pub unsafe fn madvise_huge_pages(_ptr: *mut u8, _len: usize) -> io::Result<()> {
+    unreachable!()
+}
+
 #[derive(PartialEq, Debug)]
 pub enum MemoryImageSource {}
 
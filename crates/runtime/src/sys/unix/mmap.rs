@@ -84,6 +84,35 @@ impl Mmap {
         Ok(())
     }
 
+    pub fn map_file(
+        &mut self,
+        start: usize,
+        len: usize,
+        file: &File,
+        offset: u64,
+        shared: bool,
+    ) -> Result<()> {
+        let flags = if shared {
+            rustix::mm::MapFlags::SHARED
+        } else {
+            rustix::mm::MapFlags::PRIVATE
+        };
+        unsafe {
+            let ptr = self.memory.as_ptr().cast::<u8>().add(start);
+            let ret = rustix::mm::mmap(
+                ptr.cast(),
+                len,
+                rustix::mm::ProtFlags::READ | rustix::mm::ProtFlags::WRITE,
+                flags | rustix::mm::MapFlags::FIXED,
+                file,
+                offset,
+            )
+            .context(format!("mmap failed to map {len:#x} bytes of file"))?;
+            assert_eq!(ret, ptr.cast());
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn as_ptr(&self) -> *const u8 {
         self.memory.as_ptr() as *const u8
@@ -101,6 +101,22 @@ pub unsafe fn madvise_dontneed(ptr: *mut u8, len: usize) -> io::Result<()> {
     }
 }
 
+pub fn supports_huge_pages() -> bool {
+    cfg!(target_os = "linux")
+}
+
+pub unsafe fn madvise_huge_pages(ptr: *mut u8, len: usize) -> io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            rustix::mm::madvise(ptr.cast(), len, rustix::mm::Advice::LinuxHugepage)?;
+            Ok(())
+        } else {
+            let _ = (ptr, len);
+            unreachable!();
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MemoryImageSource {
     Mmap(Arc<File>),
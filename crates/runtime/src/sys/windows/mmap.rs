@@ -148,6 +148,17 @@ impl Mmap {
         Ok(())
     }
 
+    pub fn map_file(
+        &mut self,
+        _start: usize,
+        _len: usize,
+        _file: &File,
+        _offset: u64,
+        _shared: bool,
+    ) -> Result<()> {
+        bail!("mapping files into linear memories is not supported on Windows")
+    }
+
     #[inline]
     pub fn as_ptr(&self) -> *const u8 {
         self.memory.as_ptr() as *const u8
@@ -70,6 +70,7 @@ pub(crate) fn build_artifacts<T: FinishedObject>(
 
     let compile_inputs = CompileInputs::for_module(&types, &translation, functions);
     let unlinked_compile_outputs = compile_inputs.compile(engine)?;
+    translation.function_bodies = None;
     let types = types.finish();
     let (compiled_funcs, function_indices) = unlinked_compile_outputs.pre_link();
 
@@ -137,6 +138,9 @@ pub(crate) fn build_component_artifacts<T: FinishedObject>(
         }),
     );
     let unlinked_compile_outputs = compile_inputs.compile(&engine)?;
+    for (_, translation) in module_translations.iter_mut() {
+        translation.function_bodies = None;
+    }
 
     let (compiled_funcs, function_indices) = unlinked_compile_outputs.pre_link();
 
@@ -409,15 +413,21 @@ impl<'a> CompileInputs<'a> {
             for (def_func_index, func_body) in functions {
                 self.push_input(move |compiler| {
                     let func_index = translation.module.func_index(def_func_index);
-                    let (info, function) =
-                        compiler.compile_function(translation, def_func_index, func_body, types)?;
+                    let symbol = format!(
+                        "wasm[{}]::function[{}]",
+                        module.as_u32(),
+                        func_index.as_u32()
+                    );
+                    let (info, function) = compiler.compile_function(
+                        translation,
+                        def_func_index,
+                        func_body,
+                        types,
+                        &symbol,
+                    )?;
                     Ok(CompileOutput {
                         key: CompileKey::wasm_function(module, def_func_index),
-                        symbol: format!(
-                            "wasm[{}]::function[{}]",
-                            module.as_u32(),
-                            func_index.as_u32()
-                        ),
+                        symbol,
                         function: CompiledFunction::Function(function),
                         info: Some(info),
                     })
@@ -127,6 +127,7 @@ pub struct Config {
     pub(crate) memory_init_cow: bool,
     pub(crate) memory_guaranteed_dense_image_size: u64,
     pub(crate) force_memory_init_memfd: bool,
+    pub(crate) memory_huge_pages: bool,
     pub(crate) wmemcheck: bool,
     pub(crate) coredump_on_trap: bool,
     pub(crate) macos_use_mach_ports: bool,
@@ -161,7 +162,10 @@ struct CompilerConfig {
     #[cfg(any(feature = "cranelift", feature = "winch"))]
     cache_store: Option<Arc<dyn CacheStore>>,
     clif_dir: Option<std::path::PathBuf>,
+    clif_stages: bool,
     wmemcheck: bool,
+    pgo_instrument: Option<std::path::PathBuf>,
+    pgo_profile: Option<std::path::PathBuf>,
 }
 
 #[cfg(any(feature = "cranelift", feature = "winch"))]
@@ -174,7 +178,10 @@ impl CompilerConfig {
             flags: HashSet::new(),
             cache_store: None,
             clif_dir: None,
+            clif_stages: false,
             wmemcheck: false,
+            pgo_instrument: None,
+            pgo_profile: None,
         }
     }
 
@@ -242,6 +249,7 @@ impl Config {
             memory_init_cow: true,
             memory_guaranteed_dense_image_size: 16 << 20,
             force_memory_init_memfd: false,
+            memory_huge_pages: false,
             wmemcheck: false,
             coredump_on_trap: false,
             macos_use_mach_ports: !cfg!(miri),
@@ -1010,6 +1018,102 @@ impl Config {
         self
     }
 
+    /// Configures whether Cranelift inlines small functions into their
+    /// callers.
+    ///
+    /// When enabled, direct `call` instructions to small functions defined in
+    /// the same module are replaced with the body of the callee. This only
+    /// takes effect when [`Config::cranelift_opt_level`] is
+    /// [`OptLevel::Speed`], and is skipped when native debug information is
+    /// being generated.
+    ///
+    /// Inlined functions don't get frames of their own, so they don't appear
+    /// in [`WasmBacktrace`](crate::WasmBacktrace)s or in profiles.
+    ///
+    /// The default value for this is `false`
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "cranelift", feature = "winch"))))]
+    pub fn cranelift_inlining(&mut self, enable: bool) -> &mut Self {
+        let val = if enable { "true" } else { "false" };
+        self.compiler_config
+            .settings
+            .insert("enable_inlining".to_string(), val.to_string());
+        self
+    }
+
+    /// Instruments functions compiled by Cranelift to count how often each of
+    /// their blocks is executed, for profile-guided optimization.
+    ///
+    /// The counts are added to the profile at `path` when the [`Engine`] and
+    /// all modules compiled with it are dropped. A profile which already
+    /// exists is updated, so the counts of several runs are summed up. The
+    /// profile is then used with [`Config::cranelift_pgo_profile`].
+    ///
+    /// Instrumented code refers to counters in the process which compiled it,
+    /// so it can't be cached or compiled for another target, and
+    /// [`Module::serialize`] and [`Engine::precompile_module`] return an error.
+    /// Functions aren't inlined into their callers while they're instrumented.
+    ///
+    /// [`Engine`]: crate::Engine
+    /// [`Engine::precompile_module`]: crate::Engine::precompile_module
+    /// [`Module::serialize`]: crate::Module::serialize
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "cranelift", feature = "winch"))))]
+    pub fn cranelift_pgo_instrument(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.compiler_config.pgo_instrument = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Optimizes functions compiled by Cranelift with the profile at `path`,
+    /// which was written by an engine configured with
+    /// [`Config::cranelift_pgo_instrument`].
+    ///
+    /// Cranelift places blocks which are executed often after their
+    /// predecessors, moves blocks which were never executed out of the way,
+    /// and inlines larger functions at calls which are executed often, when
+    /// [`Config::cranelift_inlining`] is enabled. The counts aren't given to
+    /// the register allocator, so its spill weights only change through the
+    /// new block order.
+    ///
+    /// The profile only applies to modules with the same contents as the
+    /// profiled ones, compiled with the same settings, other than the
+    /// optimization level; other functions are compiled as usual.
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "cranelift", feature = "winch"))))]
+    pub fn cranelift_pgo_profile(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.compiler_config.pgo_profile = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Configures whether Cranelift removes bounds checks of linear memory
+    /// accesses which are made redundant by an earlier check.
+    ///
+    /// This only affects memories which are bounds checked explicitly, such
+    /// as 64-bit memories and memories using
+    /// [`Config::static_memory_maximum_size`] of zero. A check is removed when
+    /// every path to it passes through a check of the same address, or of the
+    /// same address minus a constant, which covered at least as many bytes.
+    /// When Spectre mitigations are enabled for memory accesses the Spectre
+    /// guard itself is kept, but reuses the earlier comparison.
+    ///
+    /// The check of an index which steps by a constant in every iteration of a
+    /// counted loop, such as `p + 4 * i` for a counter `i`, is replaced with a
+    /// check of the whole range of indices before the loop, as long as the
+    /// loop doesn't store or call anything. This has no effect when
+    /// [`Config::cranelift_pcc`] is enabled.
+    ///
+    /// The default value for this is `false`
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "cranelift", feature = "winch"))))]
+    pub fn cranelift_bounds_check_elimination(&mut self, enable: bool) -> &mut Self {
+        let val = if enable { "true" } else { "false" };
+        self.compiler_config.settings.insert(
+            "enable_heap_bounds_check_elimination".to_string(),
+            val.to_string(),
+        );
+        self
+    }
+
     /// Controls whether proof-carrying code (PCC) is used to validate
     /// lowering of Wasm sandbox checks.
     ///
@@ -1126,6 +1230,14 @@ impl Config {
         self
     }
 
+    /// Returns the cache configuration previously loaded into this [`Config`],
+    /// which is disabled if none was loaded.
+    #[cfg(feature = "cache")]
+    #[doc(hidden)]
+    pub fn cache_config(&self) -> &CacheConfig {
+        &self.cache_config
+    }
+
     /// Loads cache configuration from the system default path.
     ///
     /// This commit is the same as [`Config::cache_config_load`] except that it
@@ -1570,6 +1682,36 @@ impl Config {
         self
     }
 
+    /// Configures whether linear memories are backed by huge pages.
+    ///
+    /// Large linear memories touched in a scattered fashion can spend a
+    /// significant amount of time in TLB misses when backed by normal 4 KiB
+    /// pages. When this option is enabled Wasmtime requests that the kernel
+    /// back linear memories, as well as the copy-on-write mappings used by
+    /// [`Config::memory_init_cow`], with transparent huge pages instead. This
+    /// applies to both the on-demand and the
+    /// [pooling](crate::InstanceAllocationStrategy::Pooling) instance
+    /// allocators, but not to memories created by a custom
+    /// [`Config::with_host_memory`] implementation.
+    ///
+    /// This is only a hint and memory will continue to use normal pages if
+    /// huge pages aren't available, for example if transparent huge pages are
+    /// disabled in the kernel. Note that huge pages can increase the resident
+    /// memory of instances since memory is committed in larger chunks.
+    ///
+    /// Platform support:
+    ///
+    /// * Linux - this is implemented with `madvise(MADV_HUGEPAGE)`. Whether
+    ///   huge pages are used is ultimately determined by the system's
+    ///   `/sys/kernel/mm/transparent_hugepage` settings.
+    /// * Other platforms - this option has no effect.
+    ///
+    /// This option is disabled by default.
+    pub fn memory_huge_pages(&mut self, enable: bool) -> &mut Self {
+        self.memory_huge_pages = enable;
+        self
+    }
+
     /// Configures whether or not a coredump should be generated and attached to
     /// the anyhow::Error when a trap is raised.
     ///
@@ -1631,6 +1773,18 @@ impl Config {
         self
     }
 
+    /// Returns an error if code compiled with this configuration can't be used
+    /// by other processes, which is the case when it's instrumented for
+    /// profile-guided optimization.
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    pub(crate) fn check_serializable(&self) -> Result<()> {
+        ensure!(
+            self.compiler_config.pgo_instrument.is_none(),
+            "cannot serialize code instrumented for profile-guided optimization"
+        );
+        Ok(())
+    }
+
     pub(crate) fn validate(&self) -> Result<Tunables> {
         if self.features.reference_types && !self.features.bulk_memory {
             bail!("feature 'reference_types' requires 'bulk_memory' to be enabled");
@@ -1725,6 +1879,7 @@ impl Config {
                     self.mem_creator.clone(),
                     stack_size,
                 ));
+                allocator.set_huge_pages(self.memory_huge_pages);
                 #[cfg(feature = "async")]
                 if let Some(stack_creator) = &self.stack_creator {
                     allocator.set_stack_creator(stack_creator.clone());
@@ -1735,6 +1890,7 @@ impl Config {
             InstanceAllocationStrategy::Pooling(config) => {
                 let mut config = config.config;
                 config.stack_size = stack_size;
+                config.huge_pages = self.memory_huge_pages;
                 Ok(Box::new(wasmtime_runtime::PoolingInstanceAllocator::new(
                     &config, tunables,
                 )?))
@@ -1776,6 +1932,29 @@ impl Config {
 
         if let Some(path) = &self.compiler_config.clif_dir {
             compiler.clif_dir(path)?;
+            compiler.clif_stages(self.compiler_config.clif_stages);
+        }
+        if let Some(path) = &self.compiler_config.pgo_instrument {
+            ensure!(
+                self.compiler_config
+                    .target
+                    .as_ref()
+                    .map_or(true, |target| *target == target_lexicon::Triple::host()),
+                "cannot instrument code compiled for another target"
+            );
+            ensure!(
+                self.compiler_config.cache_store.is_none(),
+                "cannot instrument code with incremental compilation enabled"
+            );
+            #[cfg(feature = "cache")]
+            ensure!(
+                !self.cache_config.enabled(),
+                "cannot instrument code with the module cache enabled"
+            );
+            compiler.pgo_instrument(path)?;
+        }
+        if let Some(path) = &self.compiler_config.pgo_profile {
+            compiler.pgo_profile(path)?;
         }
 
         // If probestack is enabled for a target, Wasmtime will always use the
@@ -1885,6 +2064,23 @@ impl Config {
         self
     }
 
+    /// Configures whether the clif output enabled by [`Config::emit_clif`]
+    /// also includes the later stages of compiling each function.
+    ///
+    /// When enabled, each function's optimized CLIF is written to an
+    /// `.opt.clif` file, the rewrites made by Cranelift's mid-end optimization
+    /// rules to a `.rewrites` file and its VCode after register allocation to
+    /// a `.vcode` file. These files are named after the function's symbol with
+    /// `:` replaced by `-`, e.g. `wasm[0]--function[1].vcode`, which unlike the
+    /// `wasm_func_1.clif` name of the unoptimized CLIF is unique among the
+    /// modules of a component. This makes compilation slower, so it's disabled
+    /// by default.
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    pub fn emit_clif_stages(&mut self, enable: bool) -> &mut Self {
+        self.compiler_config.clif_stages = enable;
+        self
+    }
+
     /// Configures whether, when on macOS, Mach ports are used for exception
     /// handling instead of traditional Unix-based signal handling.
     ///
@@ -1956,7 +2152,8 @@ impl fmt::Debug for Config {
             .field("wasm_simd", &self.features.simd)
             .field("wasm_relaxed_simd", &self.features.relaxed_simd)
             .field("wasm_multi_value", &self.features.multi_value)
-            .field("parallel_compilation", &self.parallel_compilation);
+            .field("parallel_compilation", &self.parallel_compilation)
+            .field("memory_huge_pages", &self.memory_huge_pages);
         #[cfg(any(feature = "cranelift", feature = "winch"))]
         {
             f.field("compiler_config", &self.compiler_config);
@@ -163,10 +163,23 @@ impl Engine {
         }
     }
 
-    pub(crate) fn tunables(&self) -> &Tunables {
+    /// Returns the tunables that this engine compiles with.
+    ///
+    /// This is only intended for tooling, such as `wasmtime explore`, which
+    /// needs to reproduce Wasmtime's own translation of a module.
+    #[doc(hidden)]
+    pub fn tunables(&self) -> &Tunables {
         &self.inner.tunables
     }
 
+    /// Returns the wasm features that this engine validates with.
+    ///
+    /// Like [`Engine::tunables`] this is only intended for tooling.
+    #[doc(hidden)]
+    pub fn wasm_features(&self) -> wasmparser::WasmFeatures {
+        self.inner.config.features.clone()
+    }
+
     /// Returns whether the engine `a` and `b` refer to the same configuration.
     #[inline]
     pub fn same(a: &Engine, b: &Engine) -> bool {
@@ -315,6 +328,7 @@ impl Engine {
             // the module itself, so their configuration values shouldn't
             // matter.
             "enable_heap_access_spectre_mitigation"
+            | "enable_heap_bounds_check_elimination"
             | "enable_table_access_spectre_mitigation"
             | "enable_nan_canonicalization"
             | "enable_jump_tables"
@@ -329,6 +343,7 @@ impl Engine {
             | "tls_model" // wasmtime doesn't use tls right now
             | "opt_level" // opt level doesn't change semantics
             | "enable_alias_analysis" // alias analysis-based opts don't change semantics
+            | "enable_inlining" // inlining doesn't change semantics
             | "probestack_func_adjusts_sp" // probestack above asserted disabled
             | "probestack_size_log2" // probestack above asserted disabled
             | "regalloc" // shouldn't change semantics
@@ -514,6 +529,7 @@ impl Engine {
     pub fn precompile_module(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         #[cfg(feature = "wat")]
         let bytes = wat::parse_bytes(&bytes)?;
+        self.config().check_serializable()?;
         let (v, _) = crate::compile::build_artifacts::<Vec<u8>>(self, &bytes)?;
         Ok(v)
     }
@@ -525,6 +541,7 @@ impl Engine {
     pub fn precompile_component(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         #[cfg(feature = "wat")]
         let bytes = wat::parse_bytes(&bytes)?;
+        self.config().check_serializable()?;
         let (v, _) = crate::compile::build_component_artifacts::<Vec<u8>>(self, &bytes)?;
         Ok(v)
     }
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle which can be used to cancel an in-progress component function
+/// call.
+///
+/// Tokens are created with [`CancelToken::new`] and passed to
+/// [`Func::call_async_cancellable`](crate::component::Func::call_async_cancellable)
+/// or
+/// [`TypedFunc::call_async_cancellable`](crate::component::TypedFunc::call_async_cancellable).
+/// A token is cheaply cloneable and all clones refer to the same underlying
+/// cancellation state, so one clone can be handed to the call while another
+/// is retained by the embedder, possibly on another thread, to request
+/// cancellation with [`CancelToken::cancel`].
+///
+/// Cancellation is cooperative: it's observed whenever a host function returns
+/// to WebAssembly, and at the points where WebAssembly would otherwise yield
+/// to the host. Guest code which doesn't call the host is therefore only
+/// interrupted if the [`Engine`](crate::Engine) is configured with
+/// [`Config::epoch_interruption`](crate::Config::epoch_interruption), and the
+/// epoch is incremented, or with
+/// [`Config::consume_fuel`](crate::Config::consume_fuel) and a
+/// [`Store::fuel_async_yield_interval`](crate::Store::fuel_async_yield_interval).
+/// When a cancellation is observed the call returns with a
+/// [`Trap::Cancelled`](crate::Trap::Cancelled) error.
+///
+/// Like any other trap the component instance which was executing is
+/// considered poisoned afterwards and cannot be entered again, but the
+/// [`Store`](crate::Store) itself remains usable for other instances.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Creates a new token which has not yet been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Requests cancellation of any call associated with this token.
+    ///
+    /// This can be called from any thread. Calls in progress will be
+    /// interrupted the next time they return from the host or reach an epoch
+    /// or fuel check, and calls started afterwards with this token will fail
+    /// immediately without entering WebAssembly.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether [`CancelToken::cancel`] has been called on this token
+    /// or any of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "component-model-wit")]
+use crate::component::wit::ComponentWit;
 use crate::{
     code::CodeObject, code_memory::CodeMemory, instantiate::MmapVecWrapper,
     type_registry::TypeCollection, Engine, Module, ResourcesRequired,
@@ -73,6 +75,11 @@ struct ComponentInner {
 
     /// Metadata produced during compilation.
     info: CompiledComponentInfo,
+
+    /// The original binary of this component, if it was compiled from one,
+    /// which is decoded on demand by [`Component::wit`].
+    #[cfg(feature = "component-model-wit")]
+    binary: Option<Box<[u8]>>,
 }
 
 pub(crate) struct AllCallFuncPointers {
@@ -231,7 +238,7 @@ impl Component {
             }
         };
 
-        return Component::from_parts(engine, code, artifacts);
+        return Component::from_parts(engine, code, artifacts, Some(binary));
 
         fn publish_mmap(mmap: MmapVec) -> Result<Arc<CodeMemory>> {
             let mut code = CodeMemory::new(mmap)?;
@@ -256,7 +263,7 @@ impl Component {
     /// [`Module::deserialize`]: crate::Module::deserialize
     pub unsafe fn deserialize(engine: &Engine, bytes: impl AsRef<[u8]>) -> Result<Component> {
         let code = engine.load_code_bytes(bytes.as_ref(), ObjectKind::Component)?;
-        Component::from_parts(engine, code, None)
+        Component::from_parts(engine, code, None, None)
     }
 
     /// Same as [`Module::deserialize_file`], but for components.
@@ -275,18 +282,22 @@ impl Component {
     /// [`Module::deserialize_file`]: crate::Module::deserialize_file
     pub unsafe fn deserialize_file(engine: &Engine, path: impl AsRef<Path>) -> Result<Component> {
         let code = engine.load_code_file(path.as_ref(), ObjectKind::Component)?;
-        Component::from_parts(engine, code, None)
+        Component::from_parts(engine, code, None, None)
     }
 
     /// Final assembly step for a component from its in-memory representation.
     ///
     /// If the `artifacts` are specified as `None` here then they will be
-    /// deserialized from `code_memory`.
+    /// deserialized from `code_memory`. The `binary` is the original binary
+    /// of the component, if it was compiled from one.
     fn from_parts(
         engine: &Engine,
         code_memory: Arc<CodeMemory>,
         artifacts: Option<ComponentArtifacts>,
+        binary: Option<&[u8]>,
     ) -> Result<Component> {
+        #[cfg(not(feature = "component-model-wit"))]
+        let _ = binary;
         let ComponentArtifacts {
             ty,
             info,
@@ -329,6 +340,8 @@ impl Component {
                 static_modules,
                 code,
                 info,
+                #[cfg(feature = "component-model-wit")]
+                binary: binary.map(Into::into),
             }),
         })
     }
@@ -354,10 +367,47 @@ impl Component {
         self.inner.code.signatures()
     }
 
-    pub(crate) fn text(&self) -> &[u8] {
+    /// Get this component's code object's `.text` section, containing the
+    /// compiled executable code of all of its core wasm modules and
+    /// trampolines.
+    #[doc(hidden)]
+    pub fn text(&self) -> &[u8] {
         self.inner.code.code_memory().text()
     }
 
+    /// Returns the core wasm modules compiled as part of this component.
+    ///
+    /// This includes both the modules defined within the component itself, in
+    /// the order they appear in the original binary, and any adapter modules
+    /// that Wasmtime generated to implement calls between component
+    /// instances, which come last. All of these modules share this
+    /// component's [`Component::text`], so their
+    /// [`Module::function_locations`] are offsets within it.
+    ///
+    /// This is primarily intended for tooling which inspects the compiled
+    /// code, such as `wasmtime explore`.
+    #[doc(hidden)]
+    pub fn static_modules(&self) -> impl ExactSizeIterator<Item = &Module> + '_ {
+        self.inner.static_modules.values()
+    }
+
+    /// Get the locations of this component's trampolines in its `.text`
+    /// section.
+    ///
+    /// Trampolines are the compiled code, such as canonical ABI lowerings of
+    /// host functions, which is called by core wasm when it invokes a
+    /// component-level intrinsic. This yields a (`.text` section offset,
+    /// length) pair for the wasm-call variant of each trampoline, in the
+    /// order in which they're defined during translation of the component.
+    #[doc(hidden)]
+    pub fn trampoline_locations(&self) -> impl ExactSizeIterator<Item = (usize, usize)> + '_ {
+        self.inner
+            .info
+            .trampolines
+            .values()
+            .map(|f| (f.wasm_call.start as usize, f.wasm_call.length as usize))
+    }
+
     pub(crate) fn trampoline_ptrs(&self, index: TrampolineIndex) -> AllCallFuncPointers {
         let AllCallFunc {
             wasm_call,
@@ -394,6 +444,13 @@ impl Component {
     /// [`Module::serialize`]: crate::Module::serialize
     /// [`Module`]: crate::Module
     pub fn serialize(&self) -> Result<Vec<u8>> {
+        // Only the functions of core wasm modules are instrumented for
+        // profile-guided optimization, so a component without them can always
+        // be serialized.
+        #[cfg(any(feature = "cranelift", feature = "winch"))]
+        if let Some(module) = self.inner.static_modules.values().next() {
+            module.engine().config().check_serializable()?;
+        }
         Ok(self.code_object().code_memory().mmap().to_vec())
     }
 
@@ -520,6 +577,39 @@ impl Component {
     pub fn image_range(&self) -> Range<*const u8> {
         self.inner.code.code_memory().mmap().image_range()
     }
+
+    /// Returns the WIT world describing this component's imports and exports.
+    ///
+    /// Unlike [`Linker::substituted_component_type`] this description is
+    /// expressed in WIT terms: interface names including package versions,
+    /// function signatures with parameter names, resource types, and any docs
+    /// embedded in the component. This can be used to validate a component
+    /// against a host before attempting to instantiate it.
+    ///
+    /// The world is decoded from the original binary of this component each
+    /// time this is called, so components retain their binary when the
+    /// `component-model-wit` feature is enabled. Use
+    /// [`ComponentWit::from_binary`] to inspect a component without compiling
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this component was loaded with
+    /// [`Component::deserialize`], as precompiled components don't contain
+    /// their original binary, or if the component's imports and exports can't
+    /// be expressed in WIT, for example because it imports a core module.
+    ///
+    /// [`Linker::substituted_component_type`]: crate::component::Linker::substituted_component_type
+    #[cfg(feature = "component-model-wit")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "component-model-wit")))]
+    pub fn wit(&self) -> Result<ComponentWit> {
+        let binary = self
+            .inner
+            .binary
+            .as_deref()
+            .context("WIT information is not available for precompiled components")?;
+        ComponentWit::from_binary(binary)
+    }
 }
 
 impl ComponentRuntimeInfo for ComponentInner {
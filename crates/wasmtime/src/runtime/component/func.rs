@@ -2,6 +2,8 @@ use crate::component::instance::{Instance, InstanceData};
 use crate::component::storage::storage_as_slice;
 use crate::component::types::Type;
 use crate::component::values::Val;
+#[cfg(feature = "async")]
+use crate::component::CancelToken;
 use crate::store::{StoreOpaque, Stored};
 use crate::{AsContext, AsContextMut, StoreContextMut, ValRaw};
 use anyhow::{bail, Context, Result};
@@ -369,7 +371,58 @@ impl Func {
             .await?
     }
 
-    fn call_impl(
+    /// Exactly like [`Self::call_async`] except that the call can be cancelled
+    /// with the provided [`CancelToken`].
+    ///
+    /// If `token` is already cancelled then this returns a
+    /// [`Trap::Cancelled`](crate::Trap::Cancelled) error without entering the
+    /// component. Otherwise if [`CancelToken::cancel`] is invoked while the
+    /// call is in progress then WebAssembly is interrupted the next time a host
+    /// function returns to it, or at its next epoch or fuel check, and the
+    /// call returns a [`Trap::Cancelled`](crate::Trap::Cancelled) error. See
+    /// [`CancelToken`] for the configuration needed to interrupt guest code
+    /// which doesn't call the host.
+    ///
+    /// Cancellation is otherwise treated like any other trap: the component
+    /// instance that was running may not be entered again, but the store may
+    /// continue to be used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called on a function in a synchronous store. This
+    /// only works with functions defined within an asynchronous store. Also
+    /// panics if `store` does not own this function.
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn call_async_cancellable<T>(
+        &self,
+        mut store: impl AsContextMut<Data = T>,
+        params: &[Val],
+        results: &mut [Val],
+        token: &CancelToken,
+    ) -> Result<()>
+    where
+        T: Send,
+    {
+        let mut store = store.as_context_mut();
+        assert!(
+            store.0.async_support(),
+            "cannot use `call_async_cancellable` without enabling async support in the config"
+        );
+        if token.is_cancelled() {
+            bail!(crate::Trap::Cancelled);
+        }
+        store
+            .on_fiber(|store| {
+                let prev = store.0.replace_cancel_token(Some(token.clone()));
+                let result = self.call_impl(&mut *store, params, results);
+                store.0.replace_cancel_token(prev);
+                result
+            })
+            .await?
+    }
+
+    pub(crate) fn call_impl(
         &self,
         mut store: impl AsContextMut,
         params: &[Val],
@@ -630,7 +683,7 @@ impl Func {
         store.on_fiber(|store| self.post_return_impl(store)).await?
     }
 
-    fn post_return_impl(&self, mut store: impl AsContextMut) -> Result<()> {
+    pub(crate) fn post_return_impl(&self, mut store: impl AsContextMut) -> Result<()> {
         let mut store = store.as_context_mut();
         let data = &mut store.0[self.0];
         let instance = data.instance;
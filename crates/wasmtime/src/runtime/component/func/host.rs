@@ -1,7 +1,7 @@
 use crate::component::func::{LiftContext, LowerContext, Options};
 use crate::component::matching::InstanceType;
 use crate::component::storage::slice_to_storage_mut;
-use crate::component::{ComponentNamedList, ComponentType, Lift, Lower, Type, Val};
+use crate::component::{types, ComponentNamedList, ComponentType, Lift, Lower, Type, Val};
 use crate::{AsContextMut, StoreContextMut, ValRaw};
 use anyhow::{anyhow, bail, Context, Result};
 use std::any::Any;
@@ -96,6 +96,32 @@ impl HostFunc {
         })
     }
 
+    /// Same as [`HostFunc::new_dynamic`] except that the function's type is
+    /// described by `ty`, which may come from a different component than the
+    /// one importing this function.
+    ///
+    /// Type-checking is structural in this case, so this is suitable for
+    /// forwarding imports of one component to the exports of another.
+    pub(crate) fn new_dynamic_with_type<T, F>(func: F, ty: types::ComponentFunc) -> Arc<HostFunc>
+    where
+        F: Fn(StoreContextMut<'_, T>, &[Val], &mut [Val]) -> Result<()> + Send + Sync + 'static,
+    {
+        Arc::new(HostFunc {
+            entrypoint: dynamic_entrypoint::<T, F>,
+            typecheck: Box::new(move |expected_index, expected_types| {
+                let expected = types::ComponentFunc::from(expected_index, expected_types);
+                if !expected.params().eq(ty.params()) {
+                    bail!("type mismatch with parameters");
+                }
+                if !expected.results().eq(ty.results()) {
+                    bail!("type mismatch with results");
+                }
+                Ok(())
+            }),
+            func: Box::new(func),
+        })
+    }
+
     pub fn typecheck(&self, ty: TypeFuncIndex, types: &InstanceType<'_>) -> Result<()> {
         (self.typecheck)(ty, types)
     }
@@ -225,6 +251,7 @@ where
     let params = storage.lift_params(&mut lift, param_tys)?;
 
     let ret = closure(cx.as_context_mut(), params)?;
+    cx.0.check_cancelled()?;
     flags.set_may_leave(false);
     let mut lower = LowerContext::new(cx, &options, types, instance);
     storage.lower_results(&mut lower, result_tys, ret)?;
@@ -380,6 +407,7 @@ where
         result_vals.push(Val::Bool(false));
     }
     closure(store.as_context_mut(), &args, &mut result_vals)?;
+    store.0.check_cancelled()?;
     flags.set_may_leave(false);
 
     let mut cx = LowerContext::new(store, &options, types, instance);
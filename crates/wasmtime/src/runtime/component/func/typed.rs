@@ -1,6 +1,8 @@
 use crate::component::func::{Func, LiftContext, LowerContext, Options};
 use crate::component::matching::InstanceType;
 use crate::component::storage::{storage_as_slice, storage_as_slice_mut};
+#[cfg(feature = "async")]
+use crate::component::CancelToken;
 use crate::{AsContextMut, StoreContext, StoreContextMut, ValRaw};
 use anyhow::{anyhow, bail, Context, Result};
 use std::borrow::Cow;
@@ -191,6 +193,47 @@ where
             .await?
     }
 
+    /// Exactly like [`Self::call_async`], except that the call can be
+    /// cancelled with the provided [`CancelToken`].
+    ///
+    /// For more information see [`Func::call_async_cancellable`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called on a function in a synchronous store. This
+    /// only works with functions defined within an asynchronous store. Also
+    /// panics if `store` does not own this function.
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn call_async_cancellable<T>(
+        &self,
+        mut store: impl AsContextMut<Data = T>,
+        params: Params,
+        token: &CancelToken,
+    ) -> Result<Return>
+    where
+        T: Send,
+        Params: Send + Sync,
+        Return: Send + Sync,
+    {
+        let mut store = store.as_context_mut();
+        assert!(
+            store.0.async_support(),
+            "cannot use `call_async_cancellable` when async support is not enabled on the config"
+        );
+        if token.is_cancelled() {
+            bail!(crate::Trap::Cancelled);
+        }
+        store
+            .on_fiber(|store| {
+                let prev = store.0.replace_cancel_token(Some(token.clone()));
+                let result = self.call_impl(&mut *store, params);
+                store.0.replace_cancel_token(prev);
+                result
+            })
+            .await?
+    }
+
     fn call_impl(&self, mut store: impl AsContextMut, params: Params) -> Result<Return> {
         let store = &mut store.as_context_mut();
         // Note that this is in theory simpler than it might read at this time.
@@ -0,0 +1,402 @@
+//! Conversion of component model values to and from JSON.
+//!
+//! This module converts between [`Val`] and [`serde_json::Value`], using the
+//! expected [`Type`] of a value to interpret its JSON. Values are represented
+//! as:
+//!
+//! | Type                     | JSON                                           |
+//! |--------------------------|------------------------------------------------|
+//! | `bool`                   | `true`, `false`                                |
+//! | integers                 | `123`, `-1`                                    |
+//! | `float32`, `float64`     | `1.5`, or `"nan"`, `"inf"`, and `"-inf"`       |
+//! | `char`, `string`         | `"x"`, `"hello"`                               |
+//! | `list<T>`, `tuple<T, U>` | `[1, 2, 3]`, `[1, "two"]`                      |
+//! | `record`                 | `{"name": "x", "size": 1}`                     |
+//! | `variant`                | `{"circle": 2.5}`, `{"unit": null}`            |
+//! | `enum`                   | `"red"`                                        |
+//! | `option<T>`              | `null`, or the JSON of the `T`                 |
+//! | `result<T, E>`           | `{"ok": 1}`, `{"err": "oops"}`, `{"ok": null}` |
+//! | `flags`                  | `["read", "write"]`                            |
+//!
+//! Fields of a record whose type is an `option` may be omitted, in which case
+//! they are `none`. A variant case without a payload may also be written as
+//! just its name, such as `"unit"`. Since `null` can't distinguish the
+//! `none`s of nested options, an `option` whose payload type is also an
+//! `option` represents `some` with an object instead, such as
+//! `{"some": null}`.
+//!
+//! Resources can't be represented as JSON.
+//!
+//! When JSON doesn't match the expected type the returned error includes the
+//! path to the mismatch within the JSON, such as `$.shapes[2].circle`.
+
+use crate::component::types::{self, Type};
+use crate::component::Val;
+use anyhow::{anyhow, bail, Context, Error, Result};
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+/// Converts `value` to a value of type `ty`.
+///
+/// # Errors
+///
+/// Returns an error if `value` does not represent a value of type `ty`, or if
+/// `ty` contains a resource.
+pub fn from_value(ty: &Type, value: &Value) -> Result<Val> {
+    Path(Vec::new()).decode(ty, value)
+}
+
+/// Converts `val` to JSON.
+///
+/// # Errors
+///
+/// Returns an error if `val` contains a resource.
+pub fn to_value(val: &Val) -> Result<Value> {
+    Path(Vec::new()).encode(val)
+}
+
+/// The location of a value within the JSON being converted.
+struct Path(Vec<Segment>);
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.0 {
+            match segment {
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes the kind of `value` for error messages.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+impl Path {
+    fn mismatch(&self, expected: impl fmt::Display, value: &Value) -> Error {
+        anyhow!("expected {expected} at `{self}`, found {}", kind(value))
+    }
+
+    fn nested<T>(&mut self, segment: Segment, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.0.push(segment);
+        let result = f(self);
+        self.0.pop();
+        result
+    }
+
+    fn key<T>(&mut self, key: &str, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.nested(Segment::Key(key.to_string()), f)
+    }
+
+    fn index<T>(&mut self, index: usize, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.nested(Segment::Index(index), f)
+    }
+
+    fn decode(&mut self, ty: &Type, value: &Value) -> Result<Val> {
+        let val = match ty {
+            Type::Bool => match value {
+                Value::Bool(b) => Val::Bool(*b),
+                _ => return Err(self.mismatch("a boolean", value)),
+            },
+            Type::S8 => Val::S8(self.int(ty, value)?),
+            Type::U8 => Val::U8(self.int(ty, value)?),
+            Type::S16 => Val::S16(self.int(ty, value)?),
+            Type::U16 => Val::U16(self.int(ty, value)?),
+            Type::S32 => Val::S32(self.int(ty, value)?),
+            Type::U32 => Val::U32(self.int(ty, value)?),
+            Type::S64 => Val::S64(self.int(ty, value)?),
+            Type::U64 => Val::U64(self.int(ty, value)?),
+            Type::Float32 => Val::Float32(self.float(value)? as f32),
+            Type::Float64 => Val::Float64(self.float(value)?),
+            Type::Char => {
+                let s = self.str(value)?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Val::Char(c),
+                    _ => bail!("expected a single character at `{self}`, found {s:?}"),
+                }
+            }
+            Type::String => Val::String(self.str(value)?.into()),
+            Type::List(ty) => {
+                let element = ty.ty();
+                let vals = self
+                    .array(value)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| self.index(i, |p| p.decode(&element, value)))
+                    .collect::<Result<Vec<_>>>()?;
+                ty.new_val(vals.into())?
+            }
+            Type::Tuple(ty) => {
+                let array = self.array(value)?;
+                if array.len() != ty.types().len() {
+                    bail!(
+                        "expected an array of {} element(s) at `{self}`, found {}",
+                        ty.types().len(),
+                        array.len()
+                    );
+                }
+                let vals = ty
+                    .types()
+                    .zip(array)
+                    .enumerate()
+                    .map(|(i, (ty, value))| self.index(i, |p| p.decode(&ty, value)))
+                    .collect::<Result<Vec<_>>>()?;
+                ty.new_val(vals.into())?
+            }
+            Type::Record(ty) => self.record(ty, value)?,
+            Type::Variant(ty) => {
+                let (name, payload) = match value {
+                    Value::String(name) => (name.as_str(), None),
+                    _ => {
+                        let (name, payload) = self.single_key(value, "a variant")?;
+                        (name, Some(payload))
+                    }
+                };
+                let case = ty
+                    .cases()
+                    .find(|case| case.name == name)
+                    .ok_or_else(|| anyhow!("unknown variant case `{name}` at `{self}`"))?;
+                let payload = self.key(name, |p| p.payload(case.ty.as_ref(), payload))?;
+                ty.new_val(name, payload)?
+            }
+            Type::Enum(ty) => {
+                let name = self.str(value)?;
+                if !ty.names().any(|n| n == name) {
+                    bail!("unknown enum case `{name}` at `{self}`");
+                }
+                ty.new_val(name)?
+            }
+            Type::Option(ty) => {
+                let payload = ty.ty();
+                match value {
+                    Value::Null => ty.new_val(None)?,
+                    _ if matches!(payload, Type::Option(_)) => {
+                        let (name, value) = self.single_key(value, "`null` or `some`")?;
+                        if name != "some" {
+                            bail!("expected `some` at `{self}`, found `{name}`");
+                        }
+                        let val = self.key(name, |p| p.decode(&payload, value))?;
+                        ty.new_val(Some(val))?
+                    }
+                    _ => ty.new_val(Some(self.decode(&payload, value)?))?,
+                }
+            }
+            Type::Result(ty) => {
+                let (name, value) = self.single_key(value, "`ok` or `err`")?;
+                match name {
+                    "ok" => {
+                        let val = self.key(name, |p| p.payload(ty.ok().as_ref(), Some(value)))?;
+                        ty.new_val(Ok(val))?
+                    }
+                    "err" => {
+                        let val = self.key(name, |p| p.payload(ty.err().as_ref(), Some(value)))?;
+                        ty.new_val(Err(val))?
+                    }
+                    _ => bail!("expected `ok` or `err` at `{self}`, found `{name}`"),
+                }
+            }
+            Type::Flags(ty) => {
+                let names = self
+                    .array(value)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        self.index(i, |p| {
+                            let name = p.str(value)?;
+                            if !ty.names().any(|n| n == name) {
+                                bail!("unknown flag `{name}` at `{p}`");
+                            }
+                            Ok(name)
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                ty.new_val(&names)?
+            }
+            Type::Own(_) | Type::Borrow(_) => {
+                bail!("resources can't be represented as JSON at `{self}`")
+            }
+        };
+        Ok(val)
+    }
+
+    fn int<T>(&self, ty: &Type, value: &Value) -> Result<T>
+    where
+        T: TryFrom<u64> + TryFrom<i64>,
+    {
+        let Value::Number(n) = value else {
+            return Err(self.mismatch(format_args!("{} integer", ty.desc()), value));
+        };
+        let int = match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => T::try_from(u).ok(),
+            (None, Some(i)) => T::try_from(i).ok(),
+            (None, None) => None,
+        };
+        int.ok_or_else(|| anyhow!("expected {} integer at `{self}`, found {n}", ty.desc()))
+    }
+
+    fn float(&self, value: &Value) -> Result<f64> {
+        match value {
+            Value::Number(n) => Ok(n.as_f64().unwrap()),
+            Value::String(s) if s == "nan" => Ok(f64::NAN),
+            Value::String(s) if s == "inf" => Ok(f64::INFINITY),
+            Value::String(s) if s == "-inf" => Ok(f64::NEG_INFINITY),
+            _ => Err(self.mismatch("a number", value)),
+        }
+    }
+
+    fn str<'a>(&self, value: &'a Value) -> Result<&'a str> {
+        match value {
+            Value::String(s) => Ok(s),
+            _ => Err(self.mismatch("a string", value)),
+        }
+    }
+
+    fn array<'a>(&self, value: &'a Value) -> Result<&'a Vec<Value>> {
+        match value {
+            Value::Array(array) => Ok(array),
+            _ => Err(self.mismatch("an array", value)),
+        }
+    }
+
+    /// Returns the only key of the object `value` and its value.
+    fn single_key<'a>(&self, value: &'a Value, expected: &str) -> Result<(&'a str, &'a Value)> {
+        match value {
+            Value::Object(object) if object.len() == 1 => {
+                let (key, value) = object.iter().next().unwrap();
+                Ok((key, value))
+            }
+            Value::Object(object) => bail!(
+                "expected an object with a single key for {expected} at `{self}`, found {} keys",
+                object.len()
+            ),
+            _ => Err(self.mismatch(format_args!("an object for {expected}"), value)),
+        }
+    }
+
+    /// Converts the payload of a case, which must be absent or `null` if the
+    /// case has no payload type.
+    fn payload(&mut self, ty: Option<&Type>, value: Option<&Value>) -> Result<Option<Val>> {
+        match (ty, value) {
+            (Some(ty), Some(value)) => Ok(Some(self.decode(ty, value)?)),
+            (Some(_), None) => bail!("missing payload at `{self}`"),
+            (None, None | Some(Value::Null)) => Ok(None),
+            (None, Some(value)) => Err(self.mismatch("`null` for a case without a payload", value)),
+        }
+    }
+
+    fn record(&mut self, ty: &types::Record, value: &Value) -> Result<Val> {
+        let Value::Object(object) = value else {
+            return Err(self.mismatch("an object", value));
+        };
+        if let Some(key) = object
+            .keys()
+            .find(|key| !ty.fields().any(|f| f.name == *key))
+        {
+            bail!("unknown record field `{key}` at `{self}`");
+        }
+        let mut values = Vec::with_capacity(ty.fields().len());
+        for field in ty.fields() {
+            let val = match (object.get(field.name), &field.ty) {
+                (Some(value), ty) => self.key(field.name, |p| p.decode(ty, value))?,
+                (None, Type::Option(ty)) => ty.new_val(None)?,
+                (None, _) => bail!("missing record field `{}` at `{self}`", field.name),
+            };
+            values.push((field.name, val));
+        }
+        ty.new_val(values)
+            .with_context(|| format!("failed to construct record at `{self}`"))
+    }
+
+    fn encode(&mut self, val: &Val) -> Result<Value> {
+        let value = match val {
+            Val::Bool(b) => Value::Bool(*b),
+            Val::S8(n) => Value::from(*n),
+            Val::U8(n) => Value::from(*n),
+            Val::S16(n) => Value::from(*n),
+            Val::U16(n) => Value::from(*n),
+            Val::S32(n) => Value::from(*n),
+            Val::U32(n) => Value::from(*n),
+            Val::S64(n) => Value::from(*n),
+            Val::U64(n) => Value::from(*n),
+            Val::Float32(f) => float_to_value(f64::from(*f)),
+            Val::Float64(f) => float_to_value(*f),
+            Val::Char(c) => Value::String(c.to_string()),
+            Val::String(s) => Value::String(s.to_string()),
+            Val::List(list) => self.encode_array(list.iter())?,
+            Val::Tuple(tuple) => self.encode_array(tuple.values().iter())?,
+            Val::Record(record) => {
+                let mut object = Map::new();
+                for (name, val) in record.fields() {
+                    let value = self.key(name, |p| p.encode(val))?;
+                    object.insert(name.to_string(), value);
+                }
+                Value::Object(object)
+            }
+            Val::Variant(variant) => self.encode_case(variant.discriminant(), variant.payload())?,
+            Val::Enum(e) => Value::String(e.discriminant().to_string()),
+            Val::Option(option) => match option.value() {
+                None => Value::Null,
+                Some(val) if matches!(option.ty().ty(), Type::Option(_)) => {
+                    self.encode_case("some", Some(val))?
+                }
+                Some(val) => self.encode(val)?,
+            },
+            Val::Result(result) => match result.value() {
+                Ok(val) => self.encode_case("ok", val)?,
+                Err(val) => self.encode_case("err", val)?,
+            },
+            Val::Flags(flags) => Value::Array(
+                flags
+                    .flags()
+                    .map(|name| Value::String(name.to_string()))
+                    .collect(),
+            ),
+            Val::Resource(_) => bail!("resources can't be represented as JSON at `{self}`"),
+        };
+        Ok(value)
+    }
+
+    fn encode_array<'a>(&mut self, vals: impl Iterator<Item = &'a Val>) -> Result<Value> {
+        let values = vals
+            .enumerate()
+            .map(|(i, val)| self.index(i, |p| p.encode(val)))
+            .collect::<Result<_>>()?;
+        Ok(Value::Array(values))
+    }
+
+    fn encode_case(&mut self, name: &str, payload: Option<&Val>) -> Result<Value> {
+        let value = match payload {
+            Some(val) => self.key(name, |p| p.encode(val))?,
+            None => Value::Null,
+        };
+        let mut object = Map::new();
+        object.insert(name.to_string(), value);
+        Ok(Value::Object(object))
+    }
+}
+
+fn float_to_value(f: f64) -> Value {
+    match Number::from_f64(f) {
+        Some(n) => Value::Number(n),
+        None if f.is_nan() => Value::String("nan".to_string()),
+        None if f > 0.0 => Value::String("inf".to_string()),
+        None => Value::String("-inf".to_string()),
+    }
+}
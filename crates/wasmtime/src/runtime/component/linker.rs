@@ -14,7 +14,7 @@ use std::marker;
 use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::Arc;
-use wasmtime_environ::component::TypeDef;
+use wasmtime_environ::component::{ComponentTypes, Export, InterfaceType, TypeDef};
 use wasmtime_environ::{EntityRef, PrimaryMap};
 
 /// A type used to instantiate [`Component`]s.
@@ -532,6 +532,132 @@ impl<T> LinkerInstance<'_, T> {
         Ok(idx)
     }
 
+    /// Defines `name` within this instance as the export `export` of
+    /// instances of `component`.
+    ///
+    /// This can be used to link components together at runtime: the exports
+    /// of one component instance become available as imports to other
+    /// components instantiated with this linker. Exported functions, modules,
+    /// and instances (recursively) are supported. Calls to exported functions
+    /// are forwarded using dynamically typed [`Val`]s, so a component
+    /// importing `name` must import it with precisely the same type as
+    /// `component` exports it.
+    ///
+    /// The definition is not tied to any particular [`Store`](crate::Store).
+    /// Instead each call to a forwarded function invokes `instance` with the
+    /// store's data to find the [`Instance`] of `component` to call into,
+    /// which must be owned by that same store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `component` does not have an export named
+    /// `export`, if `name` is already defined in this linker, or if the
+    /// export is or contains a resource type, a function using resource
+    /// types, or a module imported by `component`. Resources cannot currently
+    /// be linked from one component into another. No definitions are added
+    /// to this linker when an error is returned.
+    pub fn instance_export(
+        &mut self,
+        name: &str,
+        component: &Component,
+        export: &str,
+        instance: impl Fn(&mut T) -> Instance + Send + Sync + Copy + 'static,
+    ) -> Result<()>
+    where
+        T: 'static,
+    {
+        let item = component
+            .env_component()
+            .exports
+            .get(export)
+            .ok_or_else(|| anyhow!("instance export `{export}` not found"))?;
+        let snapshot = self.map.clone();
+        let result = self.define_export(component, &mut Vec::new(), name, export, item, instance);
+        if result.is_err() {
+            *self.map = snapshot;
+        }
+        result
+    }
+
+    fn define_export(
+        &mut self,
+        component: &Component,
+        path: &mut Vec<String>,
+        name: &str,
+        export: &str,
+        item: &Export,
+        instance: impl Fn(&mut T) -> Instance + Send + Sync + Copy + 'static,
+    ) -> Result<()>
+    where
+        T: 'static,
+    {
+        match item {
+            Export::LiftedFunction { ty, .. } => {
+                let types = component.types();
+                let func_ty = &types[*ty];
+                if uses_resources(types, &InterfaceType::Tuple(func_ty.params))
+                    || uses_resources(types, &InterfaceType::Tuple(func_ty.results))
+                {
+                    bail!("cannot link function `{export}` using resources into another component");
+                }
+                let ty = types::ComponentFunc::from(
+                    *ty,
+                    &InstanceType {
+                        types,
+                        resources: &Arc::new(PrimaryMap::new()),
+                    },
+                );
+                let path = path.clone();
+                let export = export.to_string();
+                let call = move |mut store: StoreContextMut<'_, T>,
+                                 params: &[Val],
+                                 results: &mut [Val]| {
+                    let instance = instance(store.data_mut());
+                    let func = {
+                        let mut exports = instance.exports(&mut store);
+                        let mut exports = Some(exports.root());
+                        for name in path.iter() {
+                            exports = exports.and_then(|e| e.into_instance(name));
+                        }
+                        exports.and_then(|mut e| e.func(&export))
+                    };
+                    let func =
+                        func.ok_or_else(|| anyhow!("function export `{export}` not found"))?;
+                    func.call_impl(store.as_context_mut(), params, results)?;
+                    func.post_return_impl(store)
+                };
+                let name = self.strings.intern(name);
+                self.insert(
+                    name,
+                    Definition::Func(HostFunc::new_dynamic_with_type(call, ty)),
+                )
+            }
+            Export::ModuleStatic(idx) => {
+                let name = self.strings.intern(name);
+                let module = component.static_module(*idx).clone();
+                self.insert(name, Definition::Module(module))
+            }
+            Export::ModuleImport { .. } => {
+                bail!("cannot link imported module `{export}` into another component")
+            }
+            Export::Type(TypeDef::Resource(_)) => {
+                bail!("cannot link exported resource `{export}` into another component")
+            }
+            Export::Type(_) => Ok(()),
+            Export::Instance { exports, .. } => {
+                let mut linker = self.instance(name)?;
+                path.push(export.to_string());
+                for (item_name, item) in exports {
+                    linker
+                        .define_export(component, path, item_name, item_name, item, instance)
+                        .with_context(|| format!("failed to link instance export `{export}`"))?;
+                }
+                path.pop();
+                Ok(())
+            }
+        }
+    }
+
     /// Defines a nested instance within this instance.
     ///
     /// This can be used to describe arbitrarily nested levels of instances
@@ -598,3 +724,30 @@ impl Strings {
         self.string2idx.get(string).cloned()
     }
 }
+
+/// Returns whether `ty` is, or contains, a resource handle.
+fn uses_resources(types: &ComponentTypes, ty: &InterfaceType) -> bool {
+    match ty {
+        InterfaceType::Own(_) | InterfaceType::Borrow(_) => true,
+        InterfaceType::Record(i) => types[*i]
+            .fields
+            .iter()
+            .any(|field| uses_resources(types, &field.ty)),
+        InterfaceType::Variant(i) => types[*i]
+            .cases
+            .iter()
+            .filter_map(|case| case.ty.as_ref())
+            .any(|ty| uses_resources(types, ty)),
+        InterfaceType::Tuple(i) => types[*i].types.iter().any(|ty| uses_resources(types, ty)),
+        InterfaceType::List(i) => uses_resources(types, &types[*i].element),
+        InterfaceType::Option(i) => uses_resources(types, &types[*i].ty),
+        InterfaceType::Result(i) => {
+            let ty = &types[*i];
+            [&ty.ok, &ty.err]
+                .into_iter()
+                .filter_map(|ty| ty.as_ref())
+                .any(|ty| uses_resources(types, ty))
+        }
+        _ => false,
+    }
+}
@@ -41,9 +41,13 @@
 
 #![cfg_attr(docsrs, doc(cfg(feature = "component-model")))]
 
+mod cancel;
 mod component;
 mod func;
 mod instance;
+#[cfg(feature = "component-model-json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "component-model-json")))]
+pub mod json;
 mod linker;
 mod matching;
 mod resource_table;
@@ -52,6 +56,11 @@ mod storage;
 mod store;
 pub mod types;
 mod values;
+pub mod wave;
+#[cfg(feature = "component-model-wit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "component-model-wit")))]
+pub mod wit;
+pub use self::cancel::CancelToken;
 pub use self::component::Component;
 pub use self::func::{
     ComponentNamedList, ComponentType, Func, Lift, Lower, TypedFunc, WasmList, WasmStr,
@@ -319,6 +328,12 @@ pub(crate) use self::store::ComponentStoreData;
 /// // Parse the file `foo.wit` as a single-file WIT package with no
 /// // dependencies.
 /// bindgen!("foo" in "foo.wit");
+///
+/// // Load the WIT from a binary file. This can either be a WIT package encoded
+/// // to wasm, or an actual component in which case the world is inferred
+/// // from the component's imports and exports and a world name may not be
+/// // specified.
+/// bindgen!(in "my-component.wasm");
 /// ```
 ///
 /// A more configured version of invoking this macro looks like:
@@ -346,6 +361,17 @@ pub(crate) use self::store::ComponentStoreData;
 ///     // This option defaults to `false`.
 ///     tracing: true,
 ///
+///     // For each imported interface additionally generate a `HostHooks` trait
+///     // and a `HostInterceptor<H, I>` type. The interceptor implements the
+///     // interface's `Host` trait by forwarding each call to an inner `H: Host`
+///     // and invoking the `before_*` and `after_*` hooks of `I: HostHooks`
+///     // around it with the call's arguments and result. This can be used to
+///     // implement auditing, rate-limiting, or mocking of imports without
+///     // hand-writing forwarding code.
+///     //
+///     // This option defaults to `false`.
+///     interceptors: true,
+///
 ///     // Imports will be async functions through #[async_trait] and exports
 ///     // are also invoked as async functions. Requires `Config::async_support`
 ///     // to be `true`.
@@ -56,6 +56,38 @@ impl ResourceType {
         }
     }
 
+    /// Creates a new host resource type identified by `payload` within the
+    /// namespace of the Rust type `T`.
+    ///
+    /// This is an alternative to [`ResourceType::host`] for embedders which
+    /// define resource types at runtime, for example from a WIT file loaded
+    /// at startup, and therefore have no Rust type to name each resource
+    /// with. Two dynamic host resource types are the same if they were
+    /// created with the same `T` and `payload`, and they are never the same
+    /// as a resource type created with [`ResourceType::host`]. Using a type
+    /// private to the embedder for `T` ensures its resource types can't
+    /// collide with those of other libraries.
+    ///
+    /// Values of these types are created with [`ResourceAny::new_host_own`]
+    /// or [`ResourceAny::new_host_borrow`] and their representation can be
+    /// recovered with [`ResourceAny::try_into_host_rep`].
+    ///
+    /// ```rust
+    /// use wasmtime::component::ResourceType;
+    ///
+    /// struct MyResources;
+    ///
+    /// let file = ResourceType::host_dynamic::<MyResources>(0);
+    /// let socket = ResourceType::host_dynamic::<MyResources>(1);
+    /// assert_ne!(file, socket);
+    /// assert_eq!(file, ResourceType::host_dynamic::<MyResources>(0));
+    /// ```
+    pub fn host_dynamic<T: 'static>(payload: u32) -> ResourceType {
+        ResourceType {
+            kind: ResourceTypeKind::HostDynamic(TypeId::of::<T>(), payload),
+        }
+    }
+
     pub(crate) fn guest(
         store: StoreId,
         instance: &ComponentInstance,
@@ -74,6 +106,7 @@ impl ResourceType {
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum ResourceTypeKind {
     Host(TypeId),
+    HostDynamic(TypeId, u32),
     Guest {
         store: StoreId,
         // For now this is the `*mut ComponentInstance` pointer within the store
@@ -809,10 +842,11 @@ impl<T> fmt::Debug for Resource<T> {
 /// or a host-defined resource.
 ///
 /// This type is similar to [`Resource`] except that it can be used to represent
-/// any resource, either host or guest. This type cannot be directly constructed
-/// and is only available if the guest returns it to the host (e.g. a function
-/// returning a guest-defined resource) or by a conversion from [`Resource`] via
-/// [`ResourceAny::try_from_resource`].
+/// any resource, either host or guest. This type is available if the guest
+/// returns it to the host (e.g. a function returning a guest-defined resource),
+/// by a conversion from [`Resource`] via [`ResourceAny::try_from_resource`], or
+/// for dynamic host resource types via [`ResourceAny::new_host_own`] and
+/// [`ResourceAny::new_host_borrow`].
 /// This type also does not carry a static type parameter `T` for example and
 /// does not have as much information about its type.
 /// This means that it's possible to get runtime type-errors when
@@ -913,6 +947,116 @@ impl ResourceAny {
         Resource::try_from_resource_any(self, store)
     }
 
+    /// Creates a new owned resource of the dynamic host resource type `ty`.
+    ///
+    /// This is the dynamically typed equivalent of [`Resource::new_own`] and
+    /// is intended for use with types created by
+    /// [`ResourceType::host_dynamic`], for example to return resources from
+    /// host functions defined with [`LinkerInstance::func_new`].
+    ///
+    /// Like [`Resource`] the returned value does not have a destructor
+    /// associated with it while it's owned by the host: dropping it with
+    /// [`ResourceAny::resource_drop`] only removes it from the store. Once
+    /// ownership is transferred to a guest, however, the destructor given to
+    /// [`LinkerInstance::resource`] is invoked when the guest drops it.
+    ///
+    /// [`LinkerInstance::func_new`]: crate::component::LinkerInstance::func_new
+    /// [`LinkerInstance::resource`]: crate::component::LinkerInstance::resource
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ty` was not created with
+    /// [`ResourceType::host_dynamic`].
+    pub fn new_host_own(mut store: impl AsContextMut, ty: ResourceType, rep: u32) -> Result<Self> {
+        ensure!(
+            matches!(ty.kind, ResourceTypeKind::HostDynamic(..)),
+            "resource type is not a dynamic host resource type"
+        );
+        let store = store.as_context_mut();
+        let store_id = store.0.id();
+        let idx = HostResourceTables::new_host(store.0).host_resource_lower_own(rep);
+        Ok(ResourceAny {
+            idx,
+            ty,
+            own_state: Some(OwnState {
+                store: store_id,
+                flags: None,
+                dtor: None,
+            }),
+        })
+    }
+
+    /// Creates a new borrowed resource of the dynamic host resource type `ty`.
+    ///
+    /// This is the dynamically typed equivalent of [`Resource::new_borrow`].
+    /// See [`ResourceAny::new_host_own`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ty` was not created with
+    /// [`ResourceType::host_dynamic`].
+    pub fn new_host_borrow(
+        mut store: impl AsContextMut,
+        ty: ResourceType,
+        rep: u32,
+    ) -> Result<Self> {
+        ensure!(
+            matches!(ty.kind, ResourceTypeKind::HostDynamic(..)),
+            "resource type is not a dynamic host resource type"
+        );
+        let store = store.as_context_mut();
+        let idx = HostResourceTables::new_host(store.0).host_resource_lower_borrow(rep);
+        Ok(ResourceAny {
+            idx,
+            ty,
+            own_state: None,
+        })
+    }
+
+    /// Consumes this resource and returns the host representation of it.
+    ///
+    /// This is the dynamically typed equivalent of
+    /// [`Resource::try_from_resource_any`] followed by [`Resource::rep`] and
+    /// is intended for resources with a type created by
+    /// [`ResourceType::host_dynamic`], for example those received as
+    /// parameters by host functions defined with
+    /// [`LinkerInstance::func_new`].
+    ///
+    /// If this resource is owned then ownership is transferred to the host,
+    /// meaning that the host is responsible for releasing any state
+    /// associated with the returned representation. If this resource is
+    /// borrowed then the borrow is left untouched, just as with
+    /// [`Resource::try_from_resource_any`], and ends as usual, for example
+    /// when the host function it was passed to returns.
+    ///
+    /// [`LinkerInstance::func_new`]: crate::component::LinkerInstance::func_new
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this resource's type was not created with
+    /// [`ResourceType::host_dynamic`] or if this resource is no longer valid,
+    /// for example it was previously converted or is currently borrowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this resource does not belong to `store`.
+    pub fn try_into_host_rep(self, mut store: impl AsContextMut) -> Result<u32> {
+        let store = store.as_context_mut();
+        let store_id = store.0.id();
+        let mut tables = HostResourceTables::new_host(store.0);
+        ensure!(
+            matches!(self.ty.kind, ResourceTypeKind::HostDynamic(..)),
+            "resource type is not a dynamic host resource type"
+        );
+        match self.own_state {
+            Some(OwnState { store, .. }) => {
+                assert_eq!(store_id, store, "wrong store used to convert resource");
+                tables.host_resource_lift_own(self.idx)
+            }
+            None => tables.host_resource_lift_borrow(self.idx),
+        }
+    }
+
     /// Returns the corresponding type associated with this resource, either a
     /// host-defined type or a guest-defined type.
     ///
@@ -647,6 +647,9 @@ impl fmt::Debug for Flags {
 /// This type is used in conjunction with [`Func::call`] for example if the
 /// signature of a component is not statically known ahead of time.
 ///
+/// Values can be parsed from and printed to text with the
+/// [`wave`](crate::component::wave) module.
+///
 /// # Notes on Equality
 ///
 /// This type implements both the Rust `PartialEq` and `Eq` traits. This type
@@ -0,0 +1,530 @@
+//! A textual representation of component model values.
+//!
+//! This module implements parsing and printing of [`Val`]s using the syntax
+//! of the WebAssembly Value Encoding (WAVE), which mirrors the way that types
+//! are written in WIT:
+//!
+//! | Type                     | Examples                              |
+//! |--------------------------|---------------------------------------|
+//! | `bool`                   | `true`, `false`                       |
+//! | integers                 | `123`, `-1`                           |
+//! | `float32`, `float64`     | `1.5`, `-2e10`, `nan`, `inf`, `-inf`  |
+//! | `char`                   | `'x'`, `'\u{1f980}'`                  |
+//! | `string`                 | `"hello\n"`                           |
+//! | `list<T>`                | `[1, 2, 3]`                           |
+//! | `tuple<T, U>`            | `(1, "two")`                          |
+//! | `record`                 | `{name: "x", size: 1}`                |
+//! | `variant`                | `circle(2.5)`, `unit`                 |
+//! | `enum`                   | `red`                                 |
+//! | `option<T>`              | `some(1)`, `none`                     |
+//! | `result<T, E>`           | `ok(1)`, `err("oops")`, `ok`          |
+//! | `flags`                  | `{read, write}`, `{}`                 |
+//!
+//! Strings and chars support the escapes `\\`, `\"`, `\'`, `\t`, `\n`, `\r`
+//! and `\u{...}`. Fields of a record whose type is an `option` may be omitted,
+//! in which case they are `none`. Case labels which are also keywords of this
+//! syntax, such as a variant case named `none`, are printed with a `%` prefix
+//! and that prefix is accepted, but not required, on any label.
+//!
+//! Parsing is driven by the expected [`Type`] of the value. Resources have no
+//! textual representation and can be neither parsed nor printed.
+
+use crate::component::types::{self, Type};
+use crate::component::Val;
+use anyhow::{anyhow, bail, Context, Error, Result};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Labels which must be prefixed with `%` to be used as case names.
+const KEYWORDS: &[&str] = &["true", "false", "some", "none", "ok", "err", "inf", "nan"];
+
+/// Parses `text` as a value of type `ty`.
+///
+/// # Errors
+///
+/// Returns an error if `text` is not a valid value of type `ty`, or if `ty`
+/// contains a resource.
+pub fn from_str(ty: &Type, text: &str) -> Result<Val> {
+    let mut parser = Parser { text, pos: 0 };
+    let val = parser.val(ty)?;
+    parser.finish()?;
+    Ok(val)
+}
+
+/// Parses `text` as a comma-separated sequence of values, one for each of
+/// `types`, such as the arguments of a function call.
+///
+/// A trailing comma is permitted.
+///
+/// # Errors
+///
+/// Returns an error if `text` doesn't contain exactly one valid value for each
+/// of `types`.
+pub fn from_str_list(types: &[Type], text: &str) -> Result<Vec<Val>> {
+    let mut parser = Parser { text, pos: 0 };
+    let mut vals = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            break;
+        }
+        let ty = types
+            .get(vals.len())
+            .ok_or_else(|| parser.error(format!("too many values; expected {}", types.len())))?;
+        vals.push(parser.val(ty)?);
+        if !parser.eat(',') {
+            break;
+        }
+    }
+    parser.finish()?;
+    if vals.len() != types.len() {
+        bail!("expected {} value(s); found {}", types.len(), vals.len());
+    }
+    Ok(vals)
+}
+
+/// Prints `val` in the syntax accepted by [`from_str`].
+///
+/// # Errors
+///
+/// Returns an error if `val` contains a resource.
+pub fn to_string(val: &Val) -> Result<String> {
+    let mut out = String::new();
+    write_val(&mut out, val)?;
+    Ok(out)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, msg: impl std::fmt::Display) -> Error {
+        match self.peek() {
+            Some(c) => anyhow!("{msg} at offset {}, found `{c}`", self.pos),
+            None => anyhow!("{msg} at end of input"),
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek().is_some() {
+            return Err(self.error("unexpected trailing input"));
+        }
+        Ok(())
+    }
+
+    /// Consumes a run of characters which may make up a label or a number.
+    fn token(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "-+._%".contains(c)))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn label(&mut self) -> Result<&'a str> {
+        let token = self.token();
+        let label = token.strip_prefix('%').unwrap_or(token);
+        if label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            self.pos -= token.len();
+            return Err(self.error("expected a label"));
+        }
+        Ok(label)
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let start = self.pos;
+        let token = self.token();
+        if token.is_empty() {
+            return Err(self.error("expected a number"));
+        }
+        token.parse().map_err(|e| {
+            self.pos = start;
+            self.error(format!("invalid number `{token}`: {e}"))
+        })
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        self.expect(quote)?;
+        let mut s = String::new();
+        loop {
+            match self.next_char() {
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') => s.push(self.escape()?),
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated literal")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char> {
+        Ok(match self.next_char() {
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('u') => {
+                if self.next_char() != Some('{') {
+                    return Err(self.error("expected `{` in unicode escape"));
+                }
+                let rest = self.rest();
+                let len = rest.find('}').ok_or_else(|| self.error("expected `}`"))?;
+                let c = u32::from_str_radix(&rest[..len], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid unicode escape"))?;
+                self.pos += len + 1;
+                c
+            }
+            _ => return Err(self.error("invalid escape")),
+        })
+    }
+
+    /// Parses the parenthesized payload of a case, which must be present if
+    /// and only if `ty` is specified.
+    fn payload(&mut self, ty: Option<&Type>) -> Result<Option<Val>> {
+        match ty {
+            Some(ty) => {
+                self.expect('(')?;
+                let val = self.val(ty)?;
+                self.expect(')')?;
+                Ok(Some(val))
+            }
+            None => {
+                if self.eat('(') {
+                    bail!("unexpected payload for case without a payload type");
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Parses comma-separated items until `close`, which is consumed. The
+    /// opening delimiter must have already been consumed.
+    fn items(&mut self, close: char, mut item: impl FnMut(&mut Self) -> Result<()>) -> Result<()> {
+        loop {
+            if self.eat(close) {
+                return Ok(());
+            }
+            item(self)?;
+            if !self.eat(',') {
+                return self.expect(close);
+            }
+        }
+    }
+
+    fn val(&mut self, ty: &Type) -> Result<Val> {
+        Ok(match ty {
+            Type::Bool => {
+                let start = self.pos;
+                match self.token() {
+                    "true" => Val::Bool(true),
+                    "false" => Val::Bool(false),
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("expected `true` or `false`"));
+                    }
+                }
+            }
+            Type::S8 => Val::S8(self.number()?),
+            Type::U8 => Val::U8(self.number()?),
+            Type::S16 => Val::S16(self.number()?),
+            Type::U16 => Val::U16(self.number()?),
+            Type::S32 => Val::S32(self.number()?),
+            Type::U32 => Val::U32(self.number()?),
+            Type::S64 => Val::S64(self.number()?),
+            Type::U64 => Val::U64(self.number()?),
+            Type::Float32 => Val::Float32(self.number()?),
+            Type::Float64 => Val::Float64(self.number()?),
+            Type::Char => {
+                let s = self.quoted('\'')?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Val::Char(c),
+                    _ => bail!("char literal `{s}` must contain exactly one character"),
+                }
+            }
+            Type::String => Val::String(self.quoted('"')?.into()),
+            Type::List(ty) => {
+                let element = ty.ty();
+                let mut vals = Vec::new();
+                self.expect('[')?;
+                self.items(']', |p| {
+                    vals.push(p.val(&element)?);
+                    Ok(())
+                })?;
+                ty.new_val(vals.into())?
+            }
+            Type::Tuple(ty) => {
+                let types = ty.types().collect::<Vec<_>>();
+                let mut vals = Vec::new();
+                self.expect('(')?;
+                self.items(')', |p| {
+                    let ty = types.get(vals.len()).ok_or_else(|| {
+                        p.error(format!("too many tuple elements; expected {}", types.len()))
+                    })?;
+                    vals.push(p.val(ty)?);
+                    Ok(())
+                })?;
+                if vals.len() != types.len() {
+                    bail!(
+                        "expected {} tuple element(s); found {}",
+                        types.len(),
+                        vals.len()
+                    );
+                }
+                ty.new_val(vals.into())?
+            }
+            Type::Record(ty) => self.record(ty)?,
+            Type::Variant(ty) => {
+                let name = self.label()?;
+                let case = ty
+                    .cases()
+                    .find(|case| case.name == name)
+                    .ok_or_else(|| anyhow!("unknown variant case `{name}`"))?;
+                let payload = self.payload(case.ty.as_ref())?;
+                ty.new_val(name, payload)?
+            }
+            Type::Enum(ty) => ty.new_val(self.label()?)?,
+            Type::Option(ty) => match self.label()? {
+                "some" => {
+                    self.expect('(')?;
+                    let val = self.val(&ty.ty())?;
+                    self.expect(')')?;
+                    ty.new_val(Some(val))?
+                }
+                "none" => ty.new_val(None)?,
+                other => bail!("expected `some` or `none`, found `{other}`"),
+            },
+            Type::Result(ty) => match self.label()? {
+                "ok" => ty.new_val(Ok(self.payload(ty.ok().as_ref())?))?,
+                "err" => ty.new_val(Err(self.payload(ty.err().as_ref())?))?,
+                other => bail!("expected `ok` or `err`, found `{other}`"),
+            },
+            Type::Flags(ty) => {
+                let mut names = Vec::new();
+                self.expect('{')?;
+                self.items('}', |p| {
+                    names.push(p.label()?);
+                    Ok(())
+                })?;
+                ty.new_val(&names)?
+            }
+            Type::Own(_) | Type::Borrow(_) => {
+                bail!("resources have no textual representation")
+            }
+        })
+    }
+
+    fn record(&mut self, ty: &types::Record) -> Result<Val> {
+        let fields = ty.fields().collect::<Vec<_>>();
+        let mut vals = fields.iter().map(|_| None).collect::<Vec<_>>();
+        self.expect('{')?;
+        self.items('}', |p| {
+            let start = p.pos;
+            let name = p.label()?;
+            let index = fields
+                .iter()
+                .position(|field| field.name == name)
+                .ok_or_else(|| anyhow!("unknown record field `{name}`"))?;
+            if vals[index].is_some() {
+                p.pos = start;
+                return Err(p.error(format!("duplicate record field `{name}`")));
+            }
+            p.expect(':')?;
+            vals[index] = Some(p.val(&fields[index].ty)?);
+            Ok(())
+        })?;
+
+        let mut values = Vec::with_capacity(fields.len());
+        for (field, val) in fields.iter().zip(vals) {
+            let val = match (val, &field.ty) {
+                (Some(val), _) => val,
+                (None, Type::Option(ty)) => ty.new_val(None)?,
+                (None, _) => bail!("missing record field `{}`", field.name),
+            };
+            values.push((field.name, val));
+        }
+        ty.new_val(values)
+            .context("failed to construct record value")
+    }
+}
+
+fn write_val(out: &mut String, val: &Val) -> Result<()> {
+    match val {
+        Val::Bool(b) => write!(out, "{b}")?,
+        Val::S8(n) => write!(out, "{n}")?,
+        Val::U8(n) => write!(out, "{n}")?,
+        Val::S16(n) => write!(out, "{n}")?,
+        Val::U16(n) => write!(out, "{n}")?,
+        Val::S32(n) => write!(out, "{n}")?,
+        Val::U32(n) => write!(out, "{n}")?,
+        Val::S64(n) => write!(out, "{n}")?,
+        Val::U64(n) => write!(out, "{n}")?,
+        Val::Float32(f) => write_float(out, *f)?,
+        Val::Float64(f) => write_float(out, *f)?,
+        Val::Char(c) => write_quoted(out, '\'', [*c]),
+        Val::String(s) => write_quoted(out, '"', s.chars()),
+        Val::List(list) => {
+            out.push('[');
+            write_items(out, list.iter())?;
+            out.push(']');
+        }
+        Val::Tuple(tuple) => {
+            out.push('(');
+            write_items(out, tuple.values())?;
+            out.push(')');
+        }
+        Val::Record(record) => {
+            out.push('{');
+            for (i, (name, val)) in record.fields().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_label(out, name);
+                out.push_str(": ");
+                write_val(out, val)?;
+            }
+            out.push('}');
+        }
+        Val::Variant(variant) => {
+            write_label(out, variant.discriminant());
+            write_payload(out, variant.payload())?;
+        }
+        Val::Enum(e) => write_label(out, e.discriminant()),
+        Val::Option(option) => match option.value() {
+            Some(val) => {
+                out.push_str("some");
+                write_payload(out, Some(val))?;
+            }
+            None => out.push_str("none"),
+        },
+        Val::Result(result) => match result.value() {
+            Ok(val) => {
+                out.push_str("ok");
+                write_payload(out, val)?;
+            }
+            Err(val) => {
+                out.push_str("err");
+                write_payload(out, val)?;
+            }
+        },
+        Val::Flags(flags) => {
+            out.push('{');
+            for (i, name) in flags.flags().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_label(out, name);
+            }
+            out.push('}');
+        }
+        Val::Resource(_) => bail!("resources have no textual representation"),
+    }
+    Ok(())
+}
+
+fn write_float(out: &mut String, f: impl Into<f64> + std::fmt::Display + Copy) -> Result<()> {
+    let value = f.into();
+    if value.is_nan() {
+        out.push_str("nan");
+    } else if value.is_infinite() {
+        out.push_str(if value > 0.0 { "inf" } else { "-inf" });
+    } else {
+        write!(out, "{f}")?;
+    }
+    Ok(())
+}
+
+fn write_quoted(out: &mut String, quote: char, chars: impl IntoIterator<Item = char>) {
+    out.push(quote);
+    for c in chars {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+fn write_items<'a>(out: &mut String, vals: impl IntoIterator<Item = &'a Val>) -> Result<()> {
+    for (i, val) in vals.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_val(out, val)?;
+    }
+    Ok(())
+}
+
+fn write_payload(out: &mut String, payload: Option<&Val>) -> Result<()> {
+    if let Some(val) = payload {
+        out.push('(');
+        write_val(out, val)?;
+        out.push(')');
+    }
+    Ok(())
+}
+
+fn write_label(out: &mut String, name: &str) {
+    if KEYWORDS.contains(&name) {
+        out.push('%');
+    }
+    out.push_str(name);
+}
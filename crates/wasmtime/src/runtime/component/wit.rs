@@ -0,0 +1,88 @@
+//! WIT-level descriptions of components.
+//!
+//! This module provides [`ComponentWit`], which is returned from
+//! [`Component::wit`](super::Component::wit) and describes the imports and
+//! exports of a component as a [WIT world].
+//!
+//! [WIT world]: https://component-model.bytecodealliance.org/design/worlds.html
+
+use anyhow::{bail, Result};
+use std::fmt;
+use wit_component::{DecodedWasm, WitPrinter};
+use wit_parser::{Resolve, WorldId};
+
+/// The WIT world of a [`Component`](super::Component).
+///
+/// This contains every package that the component's world refers to along
+/// with the world itself. The [`Display`] implementation of this type prints
+/// all of these packages in WIT syntax, with the package containing the
+/// component's world last.
+///
+/// [`Display`]: fmt::Display
+#[derive(Clone)]
+pub struct ComponentWit {
+    resolve: Resolve,
+    world: WorldId,
+}
+
+impl ComponentWit {
+    /// Decodes the WIT world of the binary component `binary` without
+    /// compiling it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `binary` is not a valid component or if the
+    /// component's imports and exports can't be expressed in WIT.
+    pub fn from_binary(binary: &[u8]) -> Result<ComponentWit> {
+        match wit_component::decode(binary)? {
+            DecodedWasm::Component(resolve, world) => Ok(ComponentWit { resolve, world }),
+            DecodedWasm::WitPackage(..) => bail!("expected a component, found a WIT package"),
+        }
+    }
+
+    /// Returns the imports of this component's world.
+    ///
+    /// Each import is named as it would be in WIT, such as
+    /// `wasi:cli/environment@0.2.0` for an interface or `log` for a function.
+    pub fn imports(&self) -> impl ExactSizeIterator<Item = String> + '_ {
+        self.resolve.worlds[self.world]
+            .imports
+            .keys()
+            .map(|key| self.resolve.name_world_key(key))
+    }
+
+    /// Returns the exports of this component's world.
+    ///
+    /// Exports are named in the same manner as [`ComponentWit::imports`].
+    pub fn exports(&self) -> impl ExactSizeIterator<Item = String> + '_ {
+        self.resolve.worlds[self.world]
+            .exports
+            .keys()
+            .map(|key| self.resolve.name_world_key(key))
+    }
+}
+
+impl fmt::Display for ComponentWit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = WitPrinter::default();
+        for (i, pkg) in self.resolve.topological_packages().into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let wit = printer.print(&self.resolve, pkg).map_err(|_| fmt::Error)?;
+            f.write_str(&wit)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ComponentWit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let world = &self.resolve.worlds[self.world];
+        f.debug_struct("ComponentWit")
+            .field("world", &world.name)
+            .field("imports", &self.imports().collect::<Vec<_>>())
+            .field("exports", &self.exports().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -587,6 +587,67 @@ impl Instance {
         self.get_export(store, name)?.into_global()
     }
 
+    /// Replaces the code of this instance with the code of `module`, keeping
+    /// all of the instance's state.
+    ///
+    /// This is intended for "hot-swapping" the implementation of a running
+    /// instance: the memories, tables, and globals of this instance are left
+    /// untouched, but from this point on every function of this instance
+    /// which is called through an export, a table, or `ref.func` will run the
+    /// code compiled for `module`. Any [`Func`] previously obtained from this
+    /// instance will also call the new code. Invocations which are already
+    /// executing, for example if this is called from a host function, finish
+    /// running the previous code.
+    ///
+    /// Instances which imported a function from this instance are not
+    /// updated: they captured the function's code when they were instantiated,
+    /// and their direct calls to it keep running the previous code. Only calls
+    /// which go through a funcref, such as `call_indirect` into a table shared
+    /// with this instance, see the replacement. To update importers as well,
+    /// replace their modules too, or re-instantiate them.
+    ///
+    /// After replacement [`Instance::module`] returns `module`, and passive
+    /// data and element segments used by `memory.init` and `table.init` are
+    /// taken from `module`. Funcref tables keep their current contents.
+    ///
+    /// # Errors
+    ///
+    /// The replacement must be compatible with this instance's current module,
+    /// and an error is returned without modifying the instance if:
+    ///
+    /// * `module` belongs to a different [`Engine`] than `store`.
+    /// * The imports of `module` differ in name, kind, or type.
+    /// * `module` has a different number of functions, or any function has a
+    ///   different type, or is exported or referenced from tables or
+    ///   `ref.func` differently.
+    /// * Any table, memory, or global has a different type.
+    /// * The exports of `module` differ in name or exported item.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `store` does not own this instance.
+    pub fn replace_module(&self, mut store: impl AsContextMut, module: &Module) -> Result<()> {
+        let store = store.as_context_mut().0;
+        if !Engine::same(store.engine(), module.engine()) {
+            bail!("cross-`Engine` module replacement is not supported");
+        }
+        let current = self._module(store).clone();
+        check_replacement(&current, module)?;
+
+        // Register the new module with the store first so that its code is
+        // kept alive and traps within it can be symbolicated. The previous
+        // module remains registered as it may still be on the stack.
+        let module_id = store.modules_mut().register_module(module);
+        store.fill_func_refs();
+
+        let id = store[self.0].id;
+        unsafe {
+            store.instance_mut(id).replace_module(module.runtime_info());
+        }
+        store.set_module_for_instance(id, module_id);
+        Ok(())
+    }
+
     #[cfg(feature = "component-model")]
     pub(crate) fn id(&self, store: &StoreOpaque) -> InstanceId {
         store[self.0].id
@@ -962,6 +1023,127 @@ fn pre_instantiate_raw(
     Ok(imports)
 }
 
+/// Checks that `new` can be used to replace the code of an instance of
+/// `old` with `Instance::replace_module`.
+fn check_replacement(old: &Module, new: &Module) -> Result<()> {
+    let (a, b) = (old.env_module(), new.env_module());
+
+    if a.imports().len() != b.imports().len() {
+        bail!(
+            "replacement module has {} imports, but the original module has {}",
+            b.imports().len(),
+            a.imports().len()
+        );
+    }
+    for (i, ((m1, f1, ty1), (m2, f2, ty2))) in a.imports().zip(b.imports()).enumerate() {
+        if m1 != m2 || f1 != f2 || mem::discriminant(&ty1) != mem::discriminant(&ty2) {
+            bail!(
+                "import {i} is `{m1}::{f1}` in the original module but \
+                 `{m2}::{f2}` in the replacement module, or is of a different kind"
+            );
+        }
+    }
+
+    if a.functions.len() != b.functions.len() {
+        bail!(
+            "replacement module has {} functions, but the original module has {}",
+            b.functions.len(),
+            a.functions.len()
+        );
+    }
+    for ((index, f1), (_, f2)) in a.functions.iter().zip(b.functions.iter()) {
+        if old.signatures().shared_type(f1.signature) != new.signatures().shared_type(f2.signature)
+        {
+            bail!(
+                "function {} has a different type in the replacement module",
+                index.as_u32()
+            );
+        }
+        if f1.func_ref != f2.func_ref {
+            bail!(
+                "function {} is exported or referenced differently in the \
+                 replacement module",
+                index.as_u32()
+            );
+        }
+    }
+
+    if a.table_plans.len() != b.table_plans.len() {
+        bail!(
+            "replacement module has {} tables, but the original module has {}",
+            b.table_plans.len(),
+            a.table_plans.len()
+        );
+    }
+    for ((index, t1), (_, t2)) in a.table_plans.iter().zip(b.table_plans.iter()) {
+        if t1.table != t2.table {
+            bail!(
+                "table {} has type {:?} in the original module but {:?} in the \
+                 replacement module",
+                index.as_u32(),
+                t1.table,
+                t2.table
+            );
+        }
+    }
+
+    if a.memory_plans.len() != b.memory_plans.len() {
+        bail!(
+            "replacement module has {} memories, but the original module has {}",
+            b.memory_plans.len(),
+            a.memory_plans.len()
+        );
+    }
+    for ((index, m1), (_, m2)) in a.memory_plans.iter().zip(b.memory_plans.iter()) {
+        if m1.memory != m2.memory {
+            bail!(
+                "memory {} has type {:?} in the original module but {:?} in the \
+                 replacement module",
+                index.as_u32(),
+                m1.memory,
+                m2.memory
+            );
+        }
+    }
+
+    if a.globals.len() != b.globals.len() {
+        bail!(
+            "replacement module has {} globals, but the original module has {}",
+            b.globals.len(),
+            a.globals.len()
+        );
+    }
+    for ((index, g1), (_, g2)) in a.globals.iter().zip(b.globals.iter()) {
+        if g1 != g2 {
+            bail!(
+                "global {} has type {:?} in the original module but {:?} in the \
+                 replacement module",
+                index.as_u32(),
+                g1,
+                g2
+            );
+        }
+    }
+
+    for (name, index) in a.exports.iter() {
+        match b.exports.get(name) {
+            Some(other) if other == index => {}
+            Some(_) => {
+                bail!("export `{name}` refers to a different item in the replacement module")
+            }
+            None => bail!("export `{name}` is missing from the replacement module"),
+        }
+    }
+    if let Some(name) = b.exports.keys().find(|name| !a.exports.contains_key(*name)) {
+        bail!("export `{name}` is not present in the original module");
+    }
+    if a.exports.keys().ne(b.exports.keys()) {
+        bail!("exports are listed in a different order in the replacement module");
+    }
+
+    Ok(())
+}
+
 fn typecheck<I>(
     module: &Module,
     imports: &[I],
@@ -2,17 +2,33 @@ use crate::store::{StoreData, StoreOpaque, Stored};
 use crate::trampoline::generate_memory_export;
 use crate::Trap;
 use crate::{AsContext, AsContextMut, Engine, MemoryType, StoreContext, StoreContextMut};
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use std::cell::UnsafeCell;
 use std::convert::TryFrom;
+use std::fs::File;
 use std::ops::Range;
 use std::slice;
+use std::sync::Arc;
 use std::time::Instant;
 use wasmtime_environ::MemoryPlan;
-use wasmtime_runtime::{RuntimeLinearMemory, VMMemoryImport};
+use wasmtime_runtime::{FileMemoryCreator, RuntimeLinearMemory, VMMemoryImport};
 
 pub use wasmtime_runtime::WaitResult;
 
+/// How a file is mapped into a memory created with
+/// [`Memory::new_file_backed`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileMapping {
+    /// Modifications made to the memory are private to it and are never
+    /// written back to the file.
+    Private,
+    /// Modifications made to the memory are written back to the file and are
+    /// visible to other mappings of the same file. The file's length is never
+    /// changed, so only the part of the memory covering the file is written
+    /// back.
+    Shared,
+}
+
 /// Error for out of bounds [`Memory`] access.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -263,10 +279,100 @@ impl Memory {
         store.on_fiber(|store| Self::_new(store.0, ty)).await?
     }
 
+    /// Creates a new WebAssembly memory whose contents are backed by `file`.
+    ///
+    /// The file is mapped into the host address space as the linear memory,
+    /// so large inputs can be processed by WebAssembly without copying them
+    /// into memory first. The memory has the same guard regions and
+    /// [`Memory::grow`] semantics as a memory created with [`Memory::new`].
+    ///
+    /// The initial size of the memory is the larger of `ty`'s minimum size
+    /// and the length of `file` rounded up to a whole number of WebAssembly
+    /// pages, and it's this size which the store's
+    /// [`ResourceLimiter`](crate::ResourceLimiter) is asked to approve. Bytes
+    /// of the memory past the end of the file read as zero and are never
+    /// written to the file.
+    ///
+    /// The `mapping` argument selects whether writes by WebAssembly are
+    /// private to this memory or written back to `file`, see [`FileMapping`]
+    /// for more information. For [`FileMapping::Shared`] the file must be
+    /// opened for both reading and writing, and for [`FileMapping::Private`]
+    /// it only needs to be readable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///
+    /// * `ty` is a shared memory type, which isn't supported.
+    /// * The file is larger than the maximum size of `ty`.
+    /// * The file can't be mapped, for example on platforms which don't
+    ///   support file-backed memories, which currently includes Windows.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the [`Store`](`crate::Store`) has a
+    /// [`ResourceLimiterAsync`](`crate::ResourceLimiterAsync`).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let engine = Engine::default();
+    /// let mut store = Store::new(&engine, ());
+    ///
+    /// let file = std::fs::File::open("input.bin")?;
+    /// let memory_ty = MemoryType::new(0, None);
+    /// let memory = Memory::new_file_backed(&mut store, memory_ty, file, FileMapping::Private)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_file_backed(
+        mut store: impl AsContextMut,
+        ty: MemoryType,
+        file: File,
+        mapping: FileMapping,
+    ) -> Result<Memory> {
+        if ty.is_shared() {
+            bail!("file-backed memories cannot have a shared memory type");
+        }
+        // Size the memory to cover the file before it's created so that the
+        // store's limiter is consulted about the memory's real size.
+        let file_len = file
+            .metadata()
+            .context("failed to get file metadata")?
+            .len();
+        let file_pages = file_len.div_ceil(u64::from(wasmtime_environ::WASM_PAGE_SIZE));
+        let minimum = ty.minimum().max(file_pages);
+        if let Some(max) = ty.maximum() {
+            if minimum > max {
+                bail!(
+                    "file of {file_len} bytes does not fit in a linear memory \
+                     with a maximum size of {max} pages"
+                );
+            }
+        }
+        let ty = if ty.is_64() {
+            MemoryType::new64(minimum, ty.maximum())
+        } else {
+            let minimum = u32::try_from(minimum).map_err(|_| {
+                anyhow!("file of {file_len} bytes is too large for a 32-bit linear memory")
+            })?;
+            MemoryType::new(minimum, ty.maximum().map(|m| u32::try_from(m).unwrap()))
+        };
+
+        let store = store.as_context_mut().0;
+        let creator = FileMemoryCreator::new(file, mapping == FileMapping::Shared);
+        unsafe {
+            let export = generate_memory_export(store, &ty, None, Some(Arc::new(creator)))?;
+            Ok(Memory::from_wasmtime_memory(export, store))
+        }
+    }
+
     /// Helper function for attaching the memory to a "frankenstein" instance
     fn _new(store: &mut StoreOpaque, ty: MemoryType) -> Result<Memory> {
         unsafe {
-            let export = generate_memory_export(store, &ty, None)?;
+            let export = generate_memory_export(store, &ty, None, None)?;
             Ok(Memory::from_wasmtime_memory(export, store))
         }
     }
@@ -734,7 +840,7 @@ impl SharedMemory {
 
         let tunables = engine.tunables();
         let plan = MemoryPlan::for_memory(ty.wasmtime_memory().clone(), tunables);
-        let memory = wasmtime_runtime::SharedMemory::new(plan)?;
+        let memory = wasmtime_runtime::SharedMemory::new(plan, engine.config().memory_huge_pages)?;
         Ok(Self(memory, engine.clone()))
     }
 
@@ -902,7 +1008,7 @@ impl SharedMemory {
     /// Construct a single-memory instance to provide a way to import
     /// [`SharedMemory`] into other modules.
     pub(crate) fn vmimport(&self, store: &mut StoreOpaque) -> wasmtime_runtime::VMMemoryImport {
-        let export_memory = generate_memory_export(store, &self.ty(), Some(&self.0)).unwrap();
+        let export_memory = generate_memory_export(store, &self.ty(), Some(&self.0), None).unwrap();
         VMMemoryImport {
             from: export_memory.definition,
             vmctx: export_memory.vmctx,
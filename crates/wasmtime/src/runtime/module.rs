@@ -633,6 +633,7 @@ impl Module {
         if !self.inner.serializable {
             bail!("cannot serialize a module exported from a component");
         }
+        self.engine().config().check_serializable()?;
         Ok(self.compiled_module().mmap().to_vec())
     }
 
@@ -372,6 +372,12 @@ pub struct StoreOpaque {
     component_calls: wasmtime_runtime::component::CallContexts,
     #[cfg(feature = "component-model")]
     host_resource_data: crate::component::HostResourceData,
+
+    /// The cancellation token, if any, of the component function call which
+    /// is currently executing in this store. This is consulted whenever an
+    /// epoch check traps into the host.
+    #[cfg(feature = "component-model")]
+    cancel_token: Option<crate::component::CancelToken>,
 }
 
 #[cfg(feature = "async")]
@@ -526,6 +532,8 @@ impl<T> Store<T> {
                 component_calls: Default::default(),
                 #[cfg(feature = "component-model")]
                 host_resource_data: Default::default(),
+                #[cfg(feature = "component-model")]
+                cancel_token: None,
             },
             limiter: None,
             call_hook: None,
@@ -1100,11 +1108,10 @@ impl<T> StoreInner<T> {
 
     #[inline]
     pub fn call_hook(&mut self, s: CallHook) -> Result<()> {
-        if self.inner.pkey.is_none() && self.call_hook.is_none() {
-            Ok(())
-        } else {
-            self.call_hook_slow_path(s)
+        if self.inner.pkey.is_some() || self.call_hook.is_some() || self.inner.has_cancel_token() {
+            self.call_hook_slow_path(s)?;
         }
+        Ok(())
     }
 
     fn call_hook_slow_path(&mut self, s: CallHook) -> Result<()> {
@@ -1119,19 +1126,25 @@ impl<T> StoreInner<T> {
         }
 
         match &mut self.call_hook {
-            Some(CallHookInner::Sync(hook)) => hook(&mut self.data, s),
+            Some(CallHookInner::Sync(hook)) => hook(&mut self.data, s)?,
 
             #[cfg(feature = "async")]
             Some(CallHookInner::Async(handler)) => unsafe {
-                Ok(self
-                    .inner
+                self.inner
                     .async_cx()
                     .ok_or_else(|| anyhow!("couldn't grab async_cx for call hook"))?
-                    .block_on(handler.handle_call_event(&mut self.data, s).as_mut())??)
+                    .block_on(handler.handle_call_event(&mut self.data, s).as_mut())??
             },
 
-            None => Ok(()),
+            None => {}
+        }
+
+        // Returning from the host is where a cancelled component function
+        // call is noticed if its guest never hits an epoch or fuel check.
+        if let CallHook::ReturningFromHost = s {
+            self.inner.check_cancelled()?;
         }
+        Ok(())
     }
 }
 
@@ -1269,6 +1282,19 @@ impl StoreOpaque {
         }
     }
 
+    /// Records that the real instance `instance` is now backed by the module
+    /// registered as `module_id`.
+    pub(crate) fn set_module_for_instance(
+        &mut self,
+        instance: InstanceId,
+        module_id: RegisteredModuleId,
+    ) {
+        match &mut self.instances[instance.0].kind {
+            StoreInstanceKind::Dummy => unreachable!("cannot replace a dummy instance's module"),
+            StoreInstanceKind::Real { module_id: id } => *id = module_id,
+        }
+    }
+
     pub unsafe fn add_instance(
         &mut self,
         handle: InstanceHandle,
@@ -1657,6 +1683,41 @@ at https://bytecodealliance.org/security.
         )
     }
 
+    /// Installs `token` as the cancellation token for the currently executing
+    /// component function call, returning the previous token.
+    #[cfg(feature = "component-model")]
+    pub(crate) fn replace_cancel_token(
+        &mut self,
+        token: Option<crate::component::CancelToken>,
+    ) -> Option<crate::component::CancelToken> {
+        mem::replace(&mut self.cancel_token, token)
+    }
+
+    /// Returns whether the currently executing component function call can be
+    /// cancelled, in which case `check_cancelled` must be called as the call
+    /// returns from the host.
+    #[inline]
+    pub(crate) fn has_cancel_token(&self) -> bool {
+        #[cfg(feature = "component-model")]
+        if self.cancel_token.is_some() {
+            return true;
+        }
+        false
+    }
+
+    /// Returns an error if the currently executing component function call
+    /// has been cancelled.
+    #[inline]
+    pub(crate) fn check_cancelled(&self) -> Result<()> {
+        #[cfg(feature = "component-model")]
+        if let Some(token) = &self.cancel_token {
+            if token.is_cancelled() {
+                return Err(Trap::Cancelled.into());
+            }
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "component-model")]
     pub(crate) fn push_component_instance(&mut self, instance: crate::component::Instance) {
         // We don't actually need the instance itself right now, but it seems
@@ -2147,11 +2208,16 @@ unsafe impl<T> wasmtime_runtime::Store for StoreInner<T> {
         #[cfg(feature = "async")]
         if self.fuel_yield_interval.is_some() {
             self.async_yield_impl()?;
+            self.check_cancelled()?;
         }
         Ok(())
     }
 
     fn new_epoch(&mut self) -> Result<u64, anyhow::Error> {
+        // A cancelled component call takes precedence over whatever the
+        // configured deadline behavior is.
+        self.check_cancelled()?;
+
         // Temporarily take the configured behavior to avoid mutably borrowing
         // multiple times.
         let mut behavior = self.epoch_deadline_behavior.take();
@@ -2170,6 +2236,9 @@ unsafe impl<T> wasmtime_runtime::Store for StoreInner<T> {
                         // Do the async yield. May return a trap if future was
                         // canceled while we're yielded.
                         self.async_yield_impl()?;
+                        // The call itself may have been cancelled while we
+                        // were yielded as well.
+                        self.check_cancelled()?;
                         delta
                     }
                 };
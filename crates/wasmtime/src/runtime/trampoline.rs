@@ -19,8 +19,8 @@ use std::any::Any;
 use std::sync::Arc;
 use wasmtime_environ::{MemoryIndex, Module, TableIndex};
 use wasmtime_runtime::{
-    Imports, InstanceAllocationRequest, InstanceAllocator, OnDemandInstanceAllocator, SharedMemory,
-    StorePtr, VMFunctionImport, VMSharedTypeIndex,
+    Imports, InstanceAllocationRequest, InstanceAllocator, OnDemandInstanceAllocator,
+    RuntimeMemoryCreator, SharedMemory, StorePtr, VMFunctionImport, VMSharedTypeIndex,
 };
 
 fn create_handle(
@@ -59,8 +59,9 @@ pub fn generate_memory_export(
     store: &mut StoreOpaque,
     m: &MemoryType,
     preallocation: Option<&SharedMemory>,
+    mem_creator: Option<Arc<dyn RuntimeMemoryCreator>>,
 ) -> Result<wasmtime_runtime::ExportMemory> {
-    let instance = create_memory(store, m, preallocation)?;
+    let instance = create_memory(store, m, preallocation, mem_creator)?;
     Ok(store
         .instance_mut(instance)
         .get_exported_memory(MemoryIndex::from_u32(0)))
@@ -28,10 +28,14 @@ use wasmtime_environ::{
 /// This separate instance is necessary because Wasm objects in Wasmtime must be
 /// attached to instances (versus the store, e.g.) and some objects exist
 /// outside: a host-provided memory import, shared memory.
+///
+/// If `mem_creator` is provided then it's used to create the memory instead of
+/// the default mmap-based memory.
 pub fn create_memory(
     store: &mut StoreOpaque,
     memory_ty: &MemoryType,
     preallocation: Option<&SharedMemory>,
+    mem_creator: Option<Arc<dyn RuntimeMemoryCreator>>,
 ) -> Result<InstanceId> {
     let mut module = Module::new();
 
@@ -67,10 +71,13 @@ pub fn create_memory(
         pkey: None,
     };
 
+    let mut ondemand = OnDemandInstanceAllocator::new(mem_creator, 0);
+    ondemand.set_huge_pages(store.engine().config().memory_huge_pages);
+
     unsafe {
         let handle = SingleMemoryInstance {
             preallocation,
-            ondemand: OnDemandInstanceAllocator::default(),
+            ondemand,
         }
         .allocate_module(request)?;
         let instance_id = store.add_dummy_instance(handle.clone());
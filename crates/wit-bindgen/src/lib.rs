@@ -92,6 +92,10 @@ pub struct Opts {
     /// Whether or not to emit `tracing` macro calls on function entry/exit.
     pub tracing: bool,
 
+    /// Whether or not to generate a `HostInterceptor` wrapper and `HostHooks`
+    /// trait for each imported interface.
+    pub interceptors: bool,
+
     /// Whether or not to use async rust functions and traits.
     pub async_: AsyncConfig,
 
@@ -1538,6 +1542,7 @@ impl<'a> InterfaceGenerator<'a> {
         // into the representation required by Wasmtime's component API.
         let mut required_conversion_traits = IndexSet::new();
         let mut errors_converted = IndexSet::new();
+        let mut conversions = Vec::new();
         let my_error_types = iface
             .types
             .iter()
@@ -1565,10 +1570,11 @@ impl<'a> InterfaceGenerator<'a> {
                 None => {
                     if errors_converted.insert(err_name) {
                         let root = self.path_to_root();
-                        uwriteln!(
-                            self.src,
-                            "fn convert_{err_snake}(&mut self, err: {root}{custom_name}) -> wasmtime::Result<{err_camel}>;"
+                        let sig = format!(
+                            "fn convert_{err_snake}(&mut self, err: {root}{custom_name}) -> wasmtime::Result<{err_camel}>"
                         );
+                        uwriteln!(self.src, "{sig};");
+                        conversions.push((format!("convert_{err_snake}"), sig));
                     }
                 }
             }
@@ -1618,6 +1624,198 @@ impl<'a> InterfaceGenerator<'a> {
         }
         uwriteln!(self.src, "Ok(())");
         uwriteln!(self.src, "}}");
+
+        if self.gen.opts.interceptors {
+            self.generate_interceptor(id, &conversions);
+        }
+    }
+
+    /// Generates the `HostHooks` trait and `HostInterceptor` type for the
+    /// imported interface `id`.
+    ///
+    /// The interceptor implements `Host`, and the trait of each resource in
+    /// the interface, by forwarding to an inner implementation and invoking
+    /// hooks before and after each function and resource destructor.
+    fn generate_interceptor(&mut self, id: InterfaceId, conversions: &[(String, String)]) {
+        let iface = &self.resolve.interfaces[id];
+        let async_trait = if self.gen.opts.async_.maybe_async() {
+            "#[wasmtime::component::__internal::async_trait]"
+        } else {
+            ""
+        };
+        let send = if self.gen.opts.async_.maybe_async() {
+            " + Send"
+        } else {
+            ""
+        };
+        let resources = iface
+            .types
+            .iter()
+            .filter(|(_, ty)| matches!(self.resolve.types[**ty].kind, TypeDefKind::Resource))
+            .map(|(name, ty)| (name.clone(), *ty))
+            .collect::<Vec<_>>();
+
+        uwriteln!(
+            self.src,
+            "
+                /// Hooks invoked by [`HostInterceptor`] around each call to
+                /// the host implementation of this interface.
+                ///
+                /// Each `before_*` hook receives the arguments of the call and
+                /// may modify them. Returning an error from a `before_*` hook
+                /// skips the call and returns the error instead. Each `after_*`
+                /// hook receives the result of the call and may modify it. The
+                /// `before_drop_*` and `after_drop_*` hooks are invoked around
+                /// the destructor of each resource.
+                ///
+                /// Hooks of async functions are async themselves. All hooks do
+                /// nothing by default.
+                {async_trait}
+                #[allow(unused_variables)]
+                pub trait HostHooks {{
+            "
+        );
+        for (_, func) in iface.functions.iter() {
+            let field = func_field_name(self.resolve, func);
+            let async_ = if self.gen.opts.async_.is_import_async(&func.name) {
+                "async "
+            } else {
+                ""
+            };
+            uwrite!(self.src, "{async_}fn before_{field}(&mut self, ");
+            for (name, param) in func.params.iter() {
+                let name = to_rust_ident(name);
+                uwrite!(self.src, "{name}: &mut ");
+                self.print_ty(param, TypeMode::Owned);
+                self.push_str(", ");
+            }
+            let error = self.function_error_type(func);
+            uwriteln!(self.src, ") -> Result<(), {error}> {{ Ok(()) }}");
+            uwrite!(
+                self.src,
+                "{async_}fn after_{field}(&mut self, result: &mut "
+            );
+            self.print_function_result(func);
+            uwriteln!(self.src, ") {{}}");
+        }
+        for (name, _) in resources.iter() {
+            let camel = name.to_upper_camel_case();
+            let snake = to_rust_ident(name);
+            uwriteln!(
+                self.src,
+                "
+                    fn before_drop_{snake}(&mut self, rep: &mut wasmtime::component::Resource<{camel}>) -> wasmtime::Result<()> {{
+                        Ok(())
+                    }}
+                    fn after_drop_{snake}(&mut self, result: &mut wasmtime::Result<()>) {{}}
+                "
+            );
+        }
+        uwriteln!(self.src, "}}");
+
+        uwriteln!(
+            self.src,
+            "
+                /// An implementation of [`Host`] which forwards all calls to
+                /// `host`, invoking the [`HostHooks`] of `hooks` around each
+                /// call.
+                pub struct HostInterceptor<H, I> {{
+                    /// The implementation of [`Host`] that calls are
+                    /// forwarded to.
+                    pub host: H,
+                    /// The hooks invoked around each call.
+                    pub hooks: I,
+                }}
+
+                {async_trait}
+                impl<H: Host{send}, I: HostHooks{send}> Host for HostInterceptor<H, I> {{
+            "
+        );
+        for (_, func) in iface.functions.iter() {
+            if let FunctionKind::Freestanding = func.kind {
+                self.generate_interceptor_func(func, "Host");
+            }
+        }
+        for (name, sig) in conversions {
+            uwriteln!(self.src, "{sig} {{ Host::{name}(&mut self.host, err) }}");
+        }
+        uwriteln!(self.src, "}}");
+
+        for (name, ty) in resources.iter() {
+            let camel = name.to_upper_camel_case();
+            let snake = to_rust_ident(name);
+            uwriteln!(
+                self.src,
+                "
+                    {async_trait}
+                    impl<H: Host{send}, I: HostHooks{send}> Host{camel} for HostInterceptor<H, I> {{
+                "
+            );
+            for (_, func) in iface.functions.iter() {
+                match func.kind {
+                    FunctionKind::Method(resource)
+                    | FunctionKind::Static(resource)
+                    | FunctionKind::Constructor(resource)
+                        if resource == *ty => {}
+                    _ => continue,
+                }
+                self.generate_interceptor_func(func, &format!("Host{camel}"));
+            }
+            uwriteln!(
+                self.src,
+                "
+                        fn drop(&mut self, mut rep: wasmtime::component::Resource<{camel}>) -> wasmtime::Result<()> {{
+                            self.hooks.before_drop_{snake}(&mut rep)?;
+                            let mut result = Host{camel}::drop(&mut self.host, rep);
+                            self.hooks.after_drop_{snake}(&mut result);
+                            result
+                        }}
+                    }}
+                "
+            );
+        }
+    }
+
+    fn generate_interceptor_func(&mut self, func: &Function, host_trait: &str) {
+        let field = func_field_name(self.resolve, func);
+        let args = func
+            .params
+            .iter()
+            .map(|(name, _)| to_rust_ident(name))
+            .collect::<Vec<_>>();
+        let await_ = if self.gen.opts.async_.is_import_async(&func.name) {
+            ".await"
+        } else {
+            ""
+        };
+        self.print_function_sig(func, true);
+        self.push_str(" {\n");
+        uwrite!(self.src, "self.hooks.before_{field}(");
+        for arg in args.iter() {
+            uwrite!(self.src, "&mut {arg}, ");
+        }
+        uwriteln!(self.src, "){await_}?;");
+        uwrite!(
+            self.src,
+            "let mut result = {host_trait}::{}(&mut self.host, ",
+            rust_function_name(func)
+        );
+        for arg in args.iter() {
+            uwrite!(self.src, "{arg}, ");
+        }
+        uwriteln!(self.src, "){await_};");
+        uwriteln!(self.src, "self.hooks.after_{field}(&mut result){await_};");
+        self.push_str("result\n");
+        self.push_str("}\n");
+    }
+
+    /// Returns the error type of the `Result` returned by `func` in a `Host`
+    /// trait, which is either its trappable error type or `wasmtime::Error`.
+    fn function_error_type(&self, func: &Function) -> String {
+        match self.special_case_trappable_error(&func.results) {
+            Some((_, _, error_typename)) => error_typename,
+            None => "wasmtime::Error".to_string(),
+        }
     }
 
     fn generate_add_function_to_linker(&mut self, owner: TypeOwner, func: &Function, linker: &str) {
@@ -1770,7 +1968,14 @@ impl<'a> InterfaceGenerator<'a> {
 
     fn generate_function_trait_sig(&mut self, func: &Function) {
         self.rustdoc(&func.docs);
+        self.print_function_sig(func, false);
+        self.push_str(";\n");
+    }
 
+    /// Prints the signature of `func` as a method of a `Host` trait, without a
+    /// trailing `;` or a body. Parameters are declared as `mut` bindings if
+    /// `mut_params` is set.
+    fn print_function_sig(&mut self, func: &Function, mut_params: bool) {
         if self.gen.opts.async_.is_import_async(&func.name) {
             self.push_str("async ");
         }
@@ -1779,6 +1984,9 @@ impl<'a> InterfaceGenerator<'a> {
         self.push_str("(&mut self, ");
         for (name, param) in func.params.iter() {
             let name = to_rust_ident(name);
+            if mut_params {
+                self.push_str("mut ");
+            }
             self.push_str(&name);
             self.push_str(": ");
             self.print_ty(param, TypeMode::Owned);
@@ -1786,7 +1994,11 @@ impl<'a> InterfaceGenerator<'a> {
         }
         self.push_str(")");
         self.push_str(" -> ");
+        self.print_function_result(func);
+    }
 
+    /// Prints the return type of `func` as a method of a `Host` trait.
+    fn print_function_result(&mut self, func: &Function) {
         if let Some((r, _id, error_typename)) = self.special_case_trappable_error(&func.results) {
             // Functions which have a single result `result<ok,err>` get special
             // cased to use the host_wasmtime_rust::Error<err>, making it possible
@@ -1807,8 +2019,6 @@ impl<'a> InterfaceGenerator<'a> {
             self.print_result_ty(&func.results, TypeMode::Owned);
             self.push_str(">");
         }
-
-        self.push_str(";\n");
     }
 
     fn extract_typed_function(&mut self, func: &Function) -> (String, String) {
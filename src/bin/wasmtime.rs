@@ -58,6 +58,11 @@ enum Subcommand {
     #[cfg(feature = "cranelift")]
     Compile(wasmtime_cli::commands::CompileCommand),
 
+    /// Inspect WebAssembly components.
+    #[cfg(feature = "component")]
+    #[command(subcommand)]
+    Component(wasmtime_cli::commands::ComponentCommand),
+
     /// Explore the compilation of a WebAssembly module to native code.
     #[cfg(feature = "explore")]
     Explore(wasmtime_cli::commands::ExploreCommand),
@@ -88,6 +93,9 @@ impl Wasmtime {
             #[cfg(feature = "cranelift")]
             Subcommand::Compile(c) => c.execute(),
 
+            #[cfg(feature = "component")]
+            Subcommand::Component(c) => c.execute(),
+
             #[cfg(feature = "explore")]
             Subcommand::Explore(c) => c.execute(),
 
@@ -8,6 +8,11 @@ mod serve;
 #[cfg(feature = "serve")]
 pub use self::serve::*;
 
+#[cfg(feature = "component")]
+mod component;
+#[cfg(feature = "component")]
+pub use self::component::*;
+
 #[cfg(feature = "explore")]
 mod explore;
 #[cfg(feature = "explore")]
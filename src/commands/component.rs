@@ -0,0 +1,54 @@
+//! The module that implements the `wasmtime component` command.
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::PathBuf;
+use wasmtime::component::wit::ComponentWit;
+
+/// Inspect WebAssembly components.
+#[derive(Parser, PartialEq)]
+pub enum ComponentCommand {
+    /// Prints the WIT world of a component.
+    Wit(ComponentWitCommand),
+}
+
+impl ComponentCommand {
+    /// Executes the command.
+    pub fn execute(self) -> Result<()> {
+        match self {
+            ComponentCommand::Wit(c) => c.execute(),
+        }
+    }
+}
+
+/// Prints the WIT world of a component.
+///
+/// The component is decoded without being compiled. Precompiled components
+/// produced by `wasmtime compile` can't be inspected as they don't contain
+/// their original binary.
+#[derive(Parser, PartialEq)]
+pub struct ComponentWitCommand {
+    /// The path of the WebAssembly component to inspect
+    #[arg(required = true, value_name = "COMPONENT")]
+    component: PathBuf,
+}
+
+impl ComponentWitCommand {
+    /// Executes the command.
+    pub fn execute(self) -> Result<()> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_file(&self.component)?;
+        #[cfg(not(feature = "wat"))]
+        let bytes = std::fs::read(&self.component)
+            .with_context(|| format!("failed to read component: {}", self.component.display()))?;
+
+        let wit = ComponentWit::from_binary(&bytes).with_context(|| {
+            format!(
+                "failed to determine the WIT world of `{}`",
+                self.component.display()
+            )
+        })?;
+        print!("{wit}");
+        Ok(())
+    }
+}
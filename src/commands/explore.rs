@@ -5,7 +5,7 @@ use clap::Parser;
 use std::path::PathBuf;
 use wasmtime_cli_flags::CommonOptions;
 
-/// Explore the compilation of a WebAssembly module to native code.
+/// Explore the compilation of a WebAssembly module or component to native code.
 #[derive(Parser, PartialEq)]
 pub struct ExploreCommand {
     #[command(flatten)]
@@ -15,7 +15,7 @@ pub struct ExploreCommand {
     #[arg(long, value_name = "TARGET")]
     target: Option<String>,
 
-    /// The path of the WebAssembly module to compile
+    /// The path of the WebAssembly module or component to compile
     #[arg(required = true, value_name = "MODULE")]
     module: PathBuf,
 
@@ -23,6 +23,12 @@ pub struct ExploreCommand {
     /// provided)
     #[arg(short, long)]
     output: Option<PathBuf>,
+
+    /// Include each function's Cranelift IR before and after optimization,
+    /// the rewrites made by the optimizer, and its VCode after register
+    /// allocation, in the exploration
+    #[arg(long)]
+    clif: bool,
 }
 
 impl ExploreCommand {
@@ -43,7 +49,13 @@ impl ExploreCommand {
             .with_context(|| format!("failed to create file: {}", output.display()))?;
         let mut output_file = std::io::BufWriter::new(output_file);
 
-        wasmtime_explorer::generate(&config, self.target.as_deref(), &wasm, &mut output_file)?;
+        wasmtime_explorer::generate(
+            &config,
+            self.target.as_deref(),
+            self.clif,
+            &wasm,
+            &mut output_file,
+        )?;
         println!("Exploration written to {}", output.display());
         Ok(())
     }
@@ -79,6 +79,10 @@ pub struct RunCommand {
     pub vars: Vec<(String, Option<String>)>,
 
     /// The name of the function to run
+    ///
+    /// For components this is instead a call expression such as
+    /// `add(1, 2)` or `my:pkg/iface#greet("world")` whose arguments are
+    /// written in the WAVE text format for component values.
     #[arg(long, value_name = "FUNCTION")]
     pub invoke: Option<String>,
 
@@ -468,26 +472,34 @@ impl RunCommand {
             }
             #[cfg(feature = "component-model")]
             CliLinker::Component(linker) => {
-                if self.invoke.is_some() {
-                    bail!("using `--invoke` with components is not supported");
-                }
-
                 let component = module.unwrap_component();
 
-                let (command, _instance) =
-                    preview2::command::sync::Command::instantiate(&mut *store, component, linker)?;
-                let result = command
-                    .wasi_cli_run()
-                    .call_run(&mut *store)
-                    .context("failed to invoke `run` function")
-                    .map_err(|e| self.handle_core_dump(&mut *store, e));
-
-                // Translate the `Result<(),()>` produced by wasm into a feigned
-                // explicit exit here with status 1 if `Err(())` is returned.
-                result.and_then(|wasm_result| match wasm_result {
-                    Ok(()) => Ok(()),
-                    Err(()) => Err(wasmtime_wasi::preview2::I32Exit(1).into()),
-                })
+                if let Some(invoke) = &self.invoke {
+                    let instance = linker.instantiate(&mut *store, component).context(format!(
+                        "failed to instantiate {:?}",
+                        self.module_and_args[0]
+                    ))?;
+                    self.invoke_component_func(store, instance, invoke)
+                } else {
+                    let (command, _instance) = preview2::command::sync::Command::instantiate(
+                        &mut *store,
+                        component,
+                        linker,
+                    )?;
+                    let result = command
+                        .wasi_cli_run()
+                        .call_run(&mut *store)
+                        .context("failed to invoke `run` function")
+                        .map_err(|e| self.handle_core_dump(&mut *store, e));
+
+                    // Translate the `Result<(),()>` produced by wasm into a
+                    // feigned explicit exit here with status 1 if `Err(())` is
+                    // returned.
+                    result.and_then(|wasm_result| match wasm_result {
+                        Ok(()) => Ok(()),
+                        Err(()) => Err(wasmtime_wasi::preview2::I32Exit(1).into()),
+                    })
+                }
             }
         };
         finish_epoch_handler(store);
@@ -572,6 +584,57 @@ impl RunCommand {
         Ok(())
     }
 
+    #[cfg(feature = "component-model")]
+    fn invoke_component_func(
+        &self,
+        store: &mut Store<Host>,
+        instance: wasmtime::component::Instance,
+        invoke: &str,
+    ) -> Result<()> {
+        use wasmtime::component::wave;
+
+        // The function is named along with its arguments like a call, such as
+        // `add(1, 2)`, and the parentheses may be omitted if there are none.
+        let (name, args) = match invoke.split_once('(') {
+            Some((name, args)) => {
+                let args = args
+                    .trim_end()
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("expected `)` at the end of `{invoke}`"))?;
+                (name.trim(), args)
+            }
+            None => (invoke.trim(), ""),
+        };
+
+        // Functions exported from an instance are named `instance#func`.
+        let func = {
+            let mut exports = instance.exports(&mut *store);
+            match name.rsplit_once('#') {
+                Some((instance, func)) => exports.instance(instance).and_then(|mut i| i.func(func)),
+                None => exports.root().func(name),
+            }
+        }
+        .ok_or_else(|| anyhow!("no func export named `{}` found", name))?;
+
+        let params = func.params(&*store);
+        let values = wave::from_str_list(&params, args)
+            .with_context(|| format!("failed to parse arguments to `{}`", name))?;
+        let mut results = vec![wasmtime::component::Val::Bool(false); func.results(&*store).len()];
+        let invoke_res = func
+            .call(&mut *store, &values, &mut results)
+            .with_context(|| format!("failed to invoke `{}`", name));
+        if let Err(err) = invoke_res {
+            return Err(self.handle_core_dump(&mut *store, err));
+        }
+        func.post_return(&mut *store)?;
+
+        for result in results {
+            println!("{}", wave::to_string(&result)?);
+        }
+
+        Ok(())
+    }
+
     #[cfg(feature = "coredump")]
     fn handle_core_dump(&self, store: &mut Store<Host>, err: Error) -> Error {
         let coredump_path = match &self.run.common.debug.coredump {
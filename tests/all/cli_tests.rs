@@ -841,6 +841,72 @@ fn run_basic_component() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg_attr(not(feature = "component-model"), ignore)]
+fn invoke_component_func() -> Result<()> {
+    let path = "tests/all/cli_tests/component-invoke.wat";
+
+    let stdout = run_wasmtime(&["run", "-Ccache=n", "--invoke", "add(1, 41)", path])?;
+    assert_eq!(stdout, "42\n");
+
+    let stdout = run_wasmtime(&["run", "-Ccache=n", "--invoke", "math#is-even(-3)", path])?;
+    assert_eq!(stdout, "odd\n");
+
+    let stdout = run_wasmtime(&[
+        "run",
+        "-Ccache=n",
+        "--invoke",
+        "wasi:cli/run@0.2.0#run",
+        "tests/all/cli_tests/component-basic.wat",
+    ])?;
+    assert_eq!(stdout, "ok\n");
+
+    let output = run_wasmtime_for_output(
+        &["run", "-Ccache=n", "--invoke", "add(1, \"two\")", path],
+        None,
+    )?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("failed to parse arguments to `add`"),
+        "bad stderr: {stderr}"
+    );
+
+    let output =
+        run_wasmtime_for_output(&["run", "-Ccache=n", "--invoke", "sub(1, 2)", path], None)?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("no func export named `sub` found"),
+        "bad stderr: {stderr}"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "component"), ignore)]
+fn component_wit() -> Result<()> {
+    let path = "tests/all/cli_tests/component-wit.wat";
+    let stdout = run_wasmtime(&["component", "wit", path])?;
+    assert!(
+        stdout.contains("import log: func(msg: string);"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("export log-again: func(msg: string);"),
+        "{stdout}"
+    );
+
+    // Precompiled components don't contain their WIT.
+    let cwasm = NamedTempFile::new()?;
+    let cwasm_path = cwasm.path().to_str().unwrap();
+    run_wasmtime(&["compile", "-Ccache=n", "-o", cwasm_path, path])?;
+    let output = run_wasmtime_for_output(&["component", "wit", cwasm_path], None)?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "component-model"), ignore)]
 fn run_precompiled_component() -> Result<()> {
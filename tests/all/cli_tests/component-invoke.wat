@@ -0,0 +1,23 @@
+(component
+  (core module $m
+    (func (export "add") (param i32 i32) (result i32)
+      local.get 0
+      local.get 1
+      i32.add)
+    (func (export "is-even") (param i32) (result i32)
+      local.get 0
+      i32.const 1
+      i32.and
+      i32.eqz)
+  )
+  (core instance $i (instantiate $m))
+  (func (export "add") (param "x" u32) (param "y" u32) (result u32)
+    (canon lift (core func $i "add")))
+
+  (type $parity (enum "odd" "even"))
+  (func $is-even (param "x" s32) (result $parity)
+    (canon lift (core func $i "is-even")))
+  (instance (export "math")
+    (export "parity" (type $parity))
+    (export "is-even" (func $is-even) (func (param "x" s32) (result $parity))))
+)
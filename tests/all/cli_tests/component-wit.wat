@@ -0,0 +1,4 @@
+(component
+  (import "log" (func $log (param "msg" string)))
+  (export "log-again" (func $log))
+)
@@ -12,11 +12,15 @@ mod dynamic;
 mod func;
 mod import;
 mod instance;
+mod json;
 mod macros;
 mod nested;
 mod post_return;
 mod resources;
 mod strings;
+mod wave;
+#[cfg(feature = "component")]
+mod wit;
 
 #[test]
 #[cfg_attr(miri, ignore)]
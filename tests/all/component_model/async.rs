@@ -3,7 +3,7 @@
 use crate::async_functions::{execute_across_threads, PollOnce};
 use anyhow::Result;
 use wasmtime::component::*;
-use wasmtime::{Engine, Store, StoreContextMut, Trap};
+use wasmtime::{Config, Engine, Store, StoreContextMut, Trap, UpdateDeadline};
 use wasmtime_component_util::REALLOC_AND_FREE;
 
 /// This is super::func::thunks, except with an async store.
@@ -243,3 +243,136 @@ async fn poll_through_wasm_activation() -> Result<()> {
     .await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn cancel_call() -> Result<()> {
+    let component = r#"
+        (component
+            (core module $m
+                (func (export "loop") (loop br 0))
+                (func (export "thunk"))
+            )
+            (core instance $i (instantiate $m))
+            (func (export "loop")
+                (canon lift (core func $i "loop"))
+            )
+            (func (export "thunk")
+                (canon lift (core func $i "thunk"))
+            )
+        )
+    "#;
+
+    let mut config = Config::new();
+    config.async_support(true);
+    config.wasm_component_model(true);
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config)?;
+    let component = Component::new(&engine, component)?;
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, ());
+    store.set_epoch_deadline(1);
+
+    // An already-cancelled token never enters the guest.
+    let token = CancelToken::new();
+    token.cancel();
+    let instance = linker.instantiate_async(&mut store, &component).await?;
+    let thunk = instance.get_typed_func::<(), ()>(&mut store, "thunk")?;
+    let err = thunk
+        .call_async_cancellable(&mut store, (), &token)
+        .await
+        .unwrap_err();
+    assert_eq!(err.downcast::<Trap>()?, Trap::Cancelled);
+    thunk.call_async(&mut store, ()).await?;
+    thunk.post_return_async(&mut store).await?;
+
+    // Cancel from within an epoch callback while the guest is looping, and
+    // keep the deadline at the current epoch so the very next check observes
+    // the cancellation.
+    let token = CancelToken::new();
+    let callback_token = token.clone();
+    store.set_epoch_deadline(0);
+    store.epoch_deadline_callback(move |_| {
+        callback_token.cancel();
+        Ok(UpdateDeadline::Continue(0))
+    });
+    let instance = linker.instantiate_async(&mut store, &component).await?;
+    let looping = instance.get_func(&mut store, "loop").unwrap();
+    let err = looping
+        .call_async_cancellable(&mut store, &[], &mut [], &token)
+        .await
+        .unwrap_err();
+    assert_eq!(err.downcast::<Trap>()?, Trap::Cancelled);
+
+    // The store is still usable for other instances afterwards.
+    store.epoch_deadline_trap();
+    store.set_epoch_deadline(1);
+    let instance = linker.instantiate_async(&mut store, &component).await?;
+    let thunk = instance.get_typed_func::<(), ()>(&mut store, "thunk")?;
+    thunk
+        .call_async_cancellable(&mut store, (), &CancelToken::new())
+        .await?;
+    thunk.post_return_async(&mut store).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_call_from_another_thread() -> Result<()> {
+    let component = r#"
+        (component
+            (type $f (func))
+            (import "i" (func $f))
+
+            (core module $m
+                (import "imports" "i" (func $i))
+                (func (export "loop") (loop call $i br 0))
+            )
+
+            (core func $f (canon lower (func $f)))
+            (core instance $i (instantiate $m
+                (with "imports" (instance
+                    (export "i" (func $f))
+                ))
+             ))
+            (func (export "loop")
+                (canon lift (core func $i "loop"))
+            )
+        )
+    "#;
+
+    // Neither epochs nor fuel are configured here, so the cancellation is
+    // only noticed when the import returns to the guest.
+    let engine = super::async_engine();
+    let component = Component::new(&engine, component)?;
+    let mut store = Store::new(&engine, ());
+    let mut linker = Linker::new(&engine);
+    // The import tells the other thread once the guest is looping. Later
+    // notifications find the channel full or closed, which is fine.
+    let (looping_tx, looping_rx) = std::sync::mpsc::sync_channel(1);
+    linker
+        .root()
+        .func_wrap_async("i", move |_: StoreContextMut<()>, _: ()| {
+            let _ = looping_tx.try_send(());
+            Box::new(async { Ok(()) })
+        })?;
+
+    let instance = linker.instantiate_async(&mut store, &component).await?;
+    let looping = instance.get_typed_func::<(), ()>(&mut store, "loop")?;
+
+    let token = CancelToken::new();
+    let canceller = {
+        let token = token.clone();
+        std::thread::spawn(move || {
+            looping_rx.recv().unwrap();
+            token.cancel();
+        })
+    };
+    let err = looping
+        .call_async_cancellable(&mut store, (), &token)
+        .await
+        .unwrap_err();
+    canceller.join().unwrap();
+    assert_eq!(err.downcast::<Trap>()?, Trap::Cancelled);
+
+    Ok(())
+}
@@ -638,3 +638,106 @@ mod exported_resources {
         Ok(())
     }
 }
+
+mod interceptors {
+    use super::*;
+    use foo::foo::math::{Host, HostHooks, HostInterceptor};
+
+    wasmtime::component::bindgen!({
+        inline: "
+            package foo:foo;
+
+            interface math {
+                add: func(a: u32, b: u32) -> u32;
+            }
+
+            world interceptors {
+                import math;
+                export run: func(a: u32, b: u32) -> u32;
+            }
+        ",
+        interceptors: true,
+    });
+
+    struct Math;
+
+    impl Host for Math {
+        fn add(&mut self, a: u32, b: u32) -> Result<u32> {
+            Ok(a + b)
+        }
+    }
+
+    struct Audit {
+        calls: Vec<(u32, u32)>,
+        limit: usize,
+    }
+
+    impl HostHooks for Audit {
+        fn before_add(&mut self, a: &mut u32, b: &mut u32) -> Result<()> {
+            if self.calls.len() >= self.limit {
+                anyhow::bail!("rate limit exceeded");
+            }
+            self.calls.push((*a, *b));
+            *a += 1;
+            Ok(())
+        }
+
+        fn after_add(&mut self, result: &mut Result<u32>) {
+            if let Ok(r) = result {
+                *r *= 10;
+            }
+        }
+    }
+
+    #[test]
+    fn run() -> Result<()> {
+        let engine = engine();
+
+        let component = Component::new(
+            &engine,
+            r#"
+                (component
+                    (import (interface "foo:foo/math") (instance $i
+                        (export "add" (func (param "a" u32) (param "b" u32) (result u32)))
+                    ))
+                    (core func $add (canon lower (func $i "add")))
+                    (core module $m
+                        (import "" "add" (func $add (param i32 i32) (result i32)))
+                        (func (export "run") (param i32 i32) (result i32)
+                            (call $add (local.get 0) (local.get 1)))
+                    )
+                    (core instance $i (instantiate $m
+                        (with "" (instance (export "add" (func $add))))
+                    ))
+                    (func (export "run") (param "a" u32) (param "b" u32) (result u32)
+                        (canon lift (core func $i "run")))
+                )
+            "#,
+        )?;
+
+        let mut linker = Linker::new(&engine);
+        Interceptors::add_to_linker(&mut linker, |s: &mut HostInterceptor<Math, Audit>| s)?;
+        let mut store = Store::new(
+            &engine,
+            HostInterceptor {
+                host: Math,
+                hooks: Audit {
+                    calls: Vec::new(),
+                    limit: 1,
+                },
+            },
+        );
+        let (interceptors, _) = Interceptors::instantiate(&mut store, &component, &linker)?;
+
+        assert_eq!(interceptors.call_run(&mut store, 1, 2)?, 40);
+        assert_eq!(store.data().hooks.calls, [(1, 2)]);
+
+        let err = interceptors.call_run(&mut store, 3, 4).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("rate limit exceeded"),
+            "bad error: {err:?}"
+        );
+        assert_eq!(store.data().hooks.calls, [(1, 2)]);
+        Ok(())
+    }
+}
@@ -55,3 +55,133 @@ fn instance_exports() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn link_instance_exports() -> Result<()> {
+    let engine = super::engine();
+    let exporter = Component::new(
+        &engine,
+        r#"
+            (component
+                (core module $m
+                    (func (export "double") (param i32) (result i32)
+                        local.get 0
+                        i32.const 2
+                        i32.mul)
+                )
+                (core instance $i (instantiate $m))
+                (func $double (param "x" u32) (result u32)
+                    (canon lift (core func $i "double")))
+                (instance $api (export "double" (func $double)))
+                (export "api" (instance $api))
+            )
+        "#,
+    )?;
+    let importer = |ty: &str| {
+        format!(
+            r#"
+                (component
+                    (import "api" (instance $api
+                        (export "double" (func (param "x" {ty}) (result {ty})))
+                    ))
+                    (core func $double (canon lower (func $api "double")))
+                    (core module $m
+                        (import "" "double" (func $double (param i32) (result i32)))
+                        (func (export "run") (param i32) (result i32)
+                            local.get 0
+                            call $double
+                            i32.const 1
+                            i32.add)
+                    )
+                    (core instance $i (instantiate $m
+                        (with "" (instance (export "double" (func $double))))
+                    ))
+                    (func (export "run") (param "x" {ty}) (result {ty})
+                        (canon lift (core func $i "run")))
+                )
+            "#
+        )
+    };
+
+    let mut linker = Linker::<Option<Instance>>::new(&engine);
+    linker
+        .root()
+        .instance_export("api", &exporter, "api", |api| api.unwrap())?;
+    let component = Component::new(&engine, importer("u32"))?;
+
+    // The definition isn't tied to a store, so the same linker can be used
+    // with several stores, each forwarding to its own exporting instance.
+    for _ in 0..2 {
+        let mut store = Store::new(&engine, None);
+        let api = Linker::new(&engine).instantiate(&mut store, &exporter)?;
+        *store.data_mut() = Some(api);
+
+        let instance = linker.instantiate(&mut store, &component)?;
+        let run = instance.get_typed_func::<(u32,), (u32,)>(&mut store, "run")?;
+        assert_eq!(run.call(&mut store, (20,))?, (41,));
+        run.post_return(&mut store)?;
+
+        // The forwarded function can be called repeatedly.
+        assert_eq!(run.call(&mut store, (1,))?, (3,));
+        run.post_return(&mut store)?;
+    }
+
+    // Types must match precisely.
+    let mut store = Store::new(&engine, None);
+    let component = Component::new(&engine, importer("s32"))?;
+    assert!(linker.instantiate(&mut store, &component).is_err());
+
+    // Missing exports and duplicate definitions are errors.
+    assert!(linker
+        .root()
+        .instance_export("other", &exporter, "missing", |api| api.unwrap())
+        .is_err());
+    assert!(linker
+        .root()
+        .instance_export("api", &exporter, "api", |api| api.unwrap())
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn link_instance_exports_rolls_back() -> Result<()> {
+    let engine = super::engine();
+    let exporter = Component::new(
+        &engine,
+        r#"
+            (component
+                (core module $m
+                    (func (export "f"))
+                )
+                (core instance $i (instantiate $m))
+                (func $f (canon lift (core func $i "f")))
+                (type $r (resource (rep i32)))
+                (instance $api
+                    (export "f" (func $f))
+                    (export "r" (type $r))
+                )
+                (export "api" (instance $api))
+                (instance $funcs (export "f" (func $f)))
+                (export "funcs" (instance $funcs))
+            )
+        "#,
+    )?;
+
+    // Linking `api` fails on its resource after `api` and `api.f` have
+    // already been defined, and neither definition is left behind.
+    let mut linker = Linker::<Option<Instance>>::new(&engine);
+    let err = linker
+        .root()
+        .instance_export("api", &exporter, "api", |api| api.unwrap())
+        .unwrap_err();
+    assert!(
+        format!("{err:?}").contains("cannot link exported resource `r`"),
+        "{err:?}"
+    );
+    linker
+        .root()
+        .instance_export("api", &exporter, "funcs", |api| api.unwrap())?;
+
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use serde_json::json;
+use wasmtime::component::types::{ComponentItem, Type};
+use wasmtime::component::{json, Component, Linker, Val};
+
+fn exported_types(wat: &str) -> Result<Vec<(String, Type)>> {
+    let engine = super::engine();
+    let component = Component::new(&engine, wat)?;
+    let linker = Linker::<()>::new(&engine);
+    let component_ty = linker.substituted_component_type(&component)?;
+    Ok(component_ty
+        .exports(&engine)
+        .map(|(name, item)| match item {
+            ComponentItem::Type(ty) => (name.to_string(), ty),
+            _ => panic!("`{name}` export item of wrong type"),
+        })
+        .collect())
+}
+
+const TYPES: &str = r#"
+    (component
+        (type $e' (enum "red" "green"))
+        (export $e "e" (type $e'))
+        (type $f' (flags "read" "write" "exec"))
+        (export $f "f" (type $f'))
+        (type $v' (variant (case "circle" float64) (case "unit") (case "color" $e)))
+        (export $v "v" (type $v'))
+        (type $r' (record
+            (field "name" string)
+            (field "size" u32)
+            (field "tag" (option char))
+            (field "perms" $f)
+        ))
+        (export $r "r" (type $r'))
+        (type $shapes' (record (field "shapes" (list (tuple s8 $v)))))
+        (export $shapes "shapes" (type $shapes'))
+        (type $res' (result (option (option u64)) (error string)))
+        (export $res "res" (type $res'))
+        (type $unit' (result))
+        (export $unit "unit" (type $unit'))
+    )
+"#;
+
+#[test]
+fn round_trip() -> Result<()> {
+    let types = exported_types(TYPES)?;
+    let ty = |name: &str| types.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+    for (ty, value) in [
+        (Type::Bool, json!(true)),
+        (Type::S8, json!(-128)),
+        (Type::U64, json!(u64::MAX)),
+        (Type::S64, json!(i64::MIN)),
+        (Type::Float32, json!(1.5)),
+        (Type::Float64, json!("-inf")),
+        (Type::Float64, json!("nan")),
+        (Type::Char, json!("🦀")),
+        (Type::String, json!("a \"quoted\" string")),
+        (ty("e"), json!("green")),
+        (ty("f"), json!([])),
+        (ty("f"), json!(["read", "exec"])),
+        (ty("v"), json!({"circle": 2.5})),
+        (ty("v"), json!({"unit": null})),
+        (ty("v"), json!({"color": "red"})),
+        (
+            ty("r"),
+            json!({"name": "x", "size": 1, "tag": "t", "perms": ["write"]}),
+        ),
+        (
+            ty("r"),
+            json!({"name": "", "size": 0, "tag": null, "perms": []}),
+        ),
+        (
+            ty("shapes"),
+            json!({"shapes": [[1, {"unit": null}], [-2, {"circle": 0.0}]]}),
+        ),
+        (ty("res"), json!({"ok": {"some": 7}})),
+        (ty("res"), json!({"ok": {"some": null}})),
+        (ty("res"), json!({"ok": null})),
+        (ty("res"), json!({"err": "oops"})),
+        (ty("unit"), json!({"ok": null})),
+        (ty("unit"), json!({"err": null})),
+    ] {
+        let val = json::from_value(&ty, &value)?;
+        assert_eq!(json::to_value(&val)?, value);
+        assert_eq!(json::from_value(&ty, &json::to_value(&val)?)?, val);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn shorthands() -> Result<()> {
+    let types = exported_types(TYPES)?;
+    let ty = |name: &str| types.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+    // Omitted `option` fields are `none`.
+    let val = json::from_value(
+        &ty("r"),
+        &json!({"perms": ["read"], "size": 3, "name": "n"}),
+    )?;
+    assert_eq!(
+        json::to_value(&val)?,
+        json!({"name": "n", "size": 3, "tag": null, "perms": ["read"]})
+    );
+
+    // Cases without a payload may be written as just their name.
+    assert_eq!(
+        json::from_value(&ty("v"), &json!("unit"))?,
+        json::from_value(&ty("v"), &json!({"unit": null}))?
+    );
+
+    assert_eq!(
+        json::from_value(&Type::Float32, &json!(2))?,
+        Val::Float32(2.0)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<()> {
+    let types = exported_types(TYPES)?;
+    let ty = |name: &str| types.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+    for (ty, value, error) in [
+        (
+            Type::Bool,
+            json!(1),
+            "expected a boolean at `$`, found a number",
+        ),
+        (
+            Type::U8,
+            json!(256),
+            "expected u8 integer at `$`, found 256",
+        ),
+        (
+            Type::U32,
+            json!(-1),
+            "expected u32 integer at `$`, found -1",
+        ),
+        (
+            Type::S32,
+            json!(1.5),
+            "expected s32 integer at `$`, found 1.5",
+        ),
+        (
+            Type::Char,
+            json!("ab"),
+            "expected a single character at `$`",
+        ),
+        (ty("e"), json!("blue"), "unknown enum case `blue` at `$`"),
+        (
+            ty("f"),
+            json!(["read", "run"]),
+            "unknown flag `run` at `$[1]`",
+        ),
+        (ty("v"), json!({"unit": 1}), "at `$.unit`, found a number"),
+        (ty("v"), json!("circle"), "missing payload at `$.circle`"),
+        (
+            ty("v"),
+            json!({"circle": 1, "unit": null}),
+            "expected an object with a single key for a variant at `$`, found 2 keys",
+        ),
+        (
+            ty("r"),
+            json!({"name": "x"}),
+            "missing record field `size` at `$`",
+        ),
+        (
+            ty("r"),
+            json!({"name": "x", "size": 1, "perms": [], "extra": 1}),
+            "unknown record field `extra` at `$`",
+        ),
+        (
+            ty("r"),
+            json!({"name": "x", "size": 1, "perms": [], "tag": 2}),
+            "expected a string at `$.tag`, found a number",
+        ),
+        (
+            ty("shapes"),
+            json!({"shapes": [[1, "unit"], [2, {"circle": "big"}]]}),
+            "expected a number at `$.shapes[1][1].circle`, found a string",
+        ),
+        (
+            ty("shapes"),
+            json!({"shapes": [[1]]}),
+            "expected an array of 2 element(s) at `$.shapes[0]`, found 1",
+        ),
+        (
+            ty("res"),
+            json!({"ok": 7}),
+            "expected an object for `null` or `some` at `$.ok`, found a number",
+        ),
+        (
+            ty("res"),
+            json!({"fine": null}),
+            "expected `ok` or `err` at `$`, found `fine`",
+        ),
+        (ty("unit"), json!({"ok": 1}), "at `$.ok`, found a number"),
+    ] {
+        let err = json::from_value(&ty, &value).unwrap_err();
+        assert!(
+            format!("{err:?}").contains(error),
+            "unexpected error for `{value}`: {err:?}"
+        );
+    }
+
+    Ok(())
+}
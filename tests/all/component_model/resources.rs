@@ -1472,3 +1472,113 @@ fn guest_different_host_same() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn host_dynamic_resource_types() -> Result<()> {
+    let engine = super::engine();
+    let c = Component::new(
+        &engine,
+        r#"
+            (component
+                (import "t" (type $t (sub resource)))
+                (import "new" (func $new (param "rep" u32) (result (own $t))))
+                (import "get" (func $get (param "r" (borrow $t)) (result u32)))
+
+                (core func $new (canon lower (func $new)))
+                (core func $get (canon lower (func $get)))
+                (core func $drop (canon resource.drop $t))
+
+                (core module $m
+                    (import "" "new" (func $new (param i32) (result i32)))
+                    (import "" "get" (func $get (param i32) (result i32)))
+                    (import "" "drop" (func $drop (param i32)))
+
+                    (func (export "run") (param i32) (result i32)
+                        (local $r i32)
+                        (local $ret i32)
+                        (local.set $r (call $new (local.get 0)))
+                        (local.set $ret (call $get (local.get $r)))
+                        (call $drop (local.get $r))
+                        local.get $ret
+                    )
+                )
+                (core instance $i (instantiate $m
+                    (with "" (instance
+                        (export "new" (func $new))
+                        (export "get" (func $get))
+                        (export "drop" (func $drop))
+                    ))
+                ))
+
+                (func (export "run") (param "x" u32) (result u32)
+                    (canon lift (core func $i "run")))
+            )
+        "#,
+    )?;
+
+    struct T;
+    struct Other;
+    let ty = ResourceType::host_dynamic::<T>(7);
+    assert_eq!(ty, ResourceType::host_dynamic::<T>(7));
+    assert!(ty != ResourceType::host_dynamic::<T>(8));
+    assert!(ty != ResourceType::host_dynamic::<Other>(7));
+    assert!(ty != ResourceType::host::<T>());
+
+    let mut store = Store::new(&engine, Vec::new());
+    let mut linker = Linker::new(&engine);
+    linker.root().resource("t", ty, |mut cx, rep| {
+        cx.data_mut().push(rep);
+        Ok(())
+    })?;
+    linker
+        .root()
+        .func_new(&c, "new", move |mut cx, params, results| {
+            let rep = match params[0] {
+                Val::U32(rep) => rep,
+                _ => unreachable!(),
+            };
+            results[0] = Val::Resource(ResourceAny::new_host_own(&mut cx, ty, rep)?);
+            Ok(())
+        })?;
+    linker
+        .root()
+        .func_new(&c, "get", move |mut cx, params, results| {
+            let rep = match &params[0] {
+                Val::Resource(r) => {
+                    assert!(!r.owned());
+                    assert_eq!(r.ty(), ty);
+                    // Borrows stay valid, so they can be converted repeatedly.
+                    let rep = r.try_into_host_rep(&mut cx)?;
+                    assert_eq!(r.try_into_host_rep(&mut cx)?, rep);
+                    rep
+                }
+                _ => unreachable!(),
+            };
+            results[0] = Val::U32(rep + 1);
+            Ok(())
+        })?;
+    let i = linker.instantiate(&mut store, &c)?;
+    let run = i.get_typed_func::<(u32,), (u32,)>(&mut store, "run")?;
+
+    assert_eq!(run.call(&mut store, (42,))?, (43,));
+    run.post_return(&mut store)?;
+    assert_eq!(store.data(), &[42]);
+
+    // Host-created resources are owned by the host until they're given to the
+    // guest, and can be converted back to their representation.
+    let r = ResourceAny::new_host_own(&mut store, ty, 100)?;
+    assert!(r.owned());
+    assert_eq!(r.try_into_host_rep(&mut store)?, 100);
+    let r = ResourceAny::new_host_own(&mut store, ty, 101)?;
+    r.resource_drop(&mut store)?;
+    assert_eq!(store.data(), &[42]);
+
+    // Only dynamic host resource types can be used with these constructors,
+    // and statically typed resources can't be created from them.
+    assert!(ResourceAny::new_host_own(&mut store, ResourceType::host::<T>(), 1).is_err());
+    assert!(ResourceAny::new_host_borrow(&mut store, ResourceType::host::<T>(), 1).is_err());
+    let r = ResourceAny::new_host_own(&mut store, ty, 102)?;
+    assert!(r.try_into_resource::<T>(&mut store).is_err());
+
+    Ok(())
+}
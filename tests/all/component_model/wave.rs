@@ -0,0 +1,168 @@
+use anyhow::Result;
+use wasmtime::component::types::{ComponentItem, Type};
+use wasmtime::component::{wave, Component, Linker, Val};
+
+fn exported_types(wat: &str) -> Result<Vec<(String, Type)>> {
+    let engine = super::engine();
+    let component = Component::new(&engine, wat)?;
+    let linker = Linker::<()>::new(&engine);
+    let component_ty = linker.substituted_component_type(&component)?;
+    Ok(component_ty
+        .exports(&engine)
+        .map(|(name, item)| match item {
+            ComponentItem::Type(ty) => (name.to_string(), ty),
+            _ => panic!("`{name}` export item of wrong type"),
+        })
+        .collect())
+}
+
+const TYPES: &str = r#"
+    (component
+        (type $e' (enum "red" "green" "none"))
+        (export $e "e" (type $e'))
+        (type $f' (flags "read" "write" "exec"))
+        (export $f "f" (type $f'))
+        (type $v' (variant (case "circle" float64) (case "unit") (case "true" $e)))
+        (export $v "v" (type $v'))
+        (type $r' (record
+            (field "name" string)
+            (field "size" u32)
+            (field "tag" (option char))
+            (field "perms" $f)
+        ))
+        (export $r "r" (type $r'))
+        (type $l' (list (tuple s8 $v)))
+        (export $l "l" (type $l'))
+        (type $res' (result (option u64) (error string)))
+        (export $res "res" (type $res'))
+        (type $unit' (result))
+        (export $unit "unit" (type $unit'))
+        (type $kf' (flags "ok" "nan"))
+        (export $kf "kf" (type $kf'))
+        (type $kr' (record (field "true" bool) (field "some" $kf)))
+        (export $kr "kr" (type $kr'))
+    )
+"#;
+
+#[test]
+fn round_trip() -> Result<()> {
+    let types = exported_types(TYPES)?;
+    let ty = |name: &str| types.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+    for (ty, text) in [
+        (Type::Bool, "true"),
+        (Type::S8, "-128"),
+        (Type::U64, "18446744073709551615"),
+        (Type::Float32, "1.5"),
+        (Type::Float64, "-inf"),
+        (Type::Float64, "nan"),
+        (Type::Char, "'\\''"),
+        (Type::Char, "'🦀'"),
+        (Type::String, "\"a \\\"quoted\\\"\\n\\u{7f} string\""),
+        (ty("e"), "green"),
+        (ty("e"), "%none"),
+        (ty("f"), "{}"),
+        (ty("f"), "{read, exec}"),
+        (ty("v"), "circle(2.5)"),
+        (ty("v"), "unit"),
+        (ty("v"), "%true(red)"),
+        (
+            ty("r"),
+            "{name: \"x\", size: 1, tag: some('t'), perms: {write}}",
+        ),
+        (ty("r"), "{name: \"\", size: 0, tag: none, perms: {}}"),
+        (ty("l"), "[]"),
+        (ty("l"), "[(1, unit), (-2, circle(0))]"),
+        (ty("res"), "ok(some(7))"),
+        (ty("res"), "ok(none)"),
+        (ty("res"), "err(\"oops\")"),
+        (ty("unit"), "ok"),
+        (ty("unit"), "err"),
+        (ty("kf"), "{%ok, %nan}"),
+        (ty("kr"), "{%true: false, %some: {%nan}}"),
+    ] {
+        let val = wave::from_str(&ty, text)?;
+        assert_eq!(wave::to_string(&val)?, text);
+        assert_eq!(wave::from_str(&ty, &wave::to_string(&val)?)?, val);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn flexible_syntax() -> Result<()> {
+    let types = exported_types(TYPES)?;
+    let ty = |name: &str| types.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+    // Whitespace, trailing commas, out-of-order and omitted `option` fields,
+    // and unnecessary `%` prefixes are all accepted.
+    let val = wave::from_str(
+        &ty("r"),
+        " {\n  perms: { %read , },\n  size: 3,\n  name: \"n\",\n}\n",
+    )?;
+    assert_eq!(
+        wave::to_string(&val)?,
+        "{name: \"n\", size: 3, tag: none, perms: {read}}"
+    );
+    assert_eq!(
+        wave::from_str(&ty("e"), "%red")?,
+        wave::from_str(&ty("e"), "red")?
+    );
+    assert_eq!(
+        wave::from_str(&Type::Char, "'\\u{1F980}'")?,
+        Val::Char('🦀')
+    );
+
+    let vals = wave::from_str_list(&[Type::U32, ty("v"), Type::String], "1, unit, \"s\",")?;
+    assert_eq!(vals.len(), 3);
+    assert_eq!(vals[0], Val::U32(1));
+    assert_eq!(vals[2], Val::String("s".into()));
+    assert!(wave::from_str_list(&[], " ")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<()> {
+    let types = exported_types(TYPES)?;
+    let ty = |name: &str| types.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+    for (ty, text, error) in [
+        (Type::U8, "256", "invalid number `256`"),
+        (Type::S32, "1.0", "invalid number `1.0`"),
+        (Type::Bool, "yes", "expected `true` or `false` at offset 0"),
+        (Type::Char, "'ab'", "exactly one character"),
+        (Type::String, "\"abc", "unterminated literal"),
+        (Type::String, "\"\\q\"", "invalid escape"),
+        (Type::U32, "1 2", "unexpected trailing input at offset 2"),
+        (ty("e"), "blue", "unknown enum case"),
+        (ty("v"), "unit(1)", "unexpected payload"),
+        (ty("v"), "circle", "expected `(`"),
+        (ty("r"), "{name: \"x\"}", "missing record field `size`"),
+        (
+            ty("r"),
+            "{size: 1, size: 2}",
+            "duplicate record field `size`",
+        ),
+        (ty("r"), "{nope: 1}", "unknown record field `nope`"),
+        (ty("l"), "[(1, unit, 2)]", "too many tuple elements"),
+        (ty("res"), "ok", "expected `(`"),
+        (ty("res"), "some(1)", "expected `ok` or `err`"),
+    ] {
+        let err = wave::from_str(&ty, text).unwrap_err();
+        assert!(
+            format!("{err:?}").contains(error),
+            "unexpected error for `{text}`: {err:?}"
+        );
+    }
+
+    let err = wave::from_str_list(&[Type::U32], "1, 2").unwrap_err();
+    assert!(err.to_string().contains("too many values"), "{err}");
+    let err = wave::from_str_list(&[Type::U32, Type::U32], "1").unwrap_err();
+    assert!(
+        err.to_string().contains("expected 2 value(s); found 1"),
+        "{err}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use wasmtime::component::wit::ComponentWit;
+use wasmtime::component::Component;
+
+const COMPONENT: &str = r#"
+    (component
+        (import "wasi:cli/environment@0.2.0" (instance
+            (export "get-arguments" (func (result (list string))))
+        ))
+        (import "log" (func $log (param "msg" string)))
+        (export "log-again" (func $log))
+    )
+"#;
+
+#[test]
+fn component_wit() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(&engine, COMPONENT)?;
+    let wit = component.wit()?;
+
+    let imports = wit.imports().collect::<Vec<_>>();
+    assert_eq!(imports, ["wasi:cli/environment@0.2.0", "log"]);
+    let exports = wit.exports().collect::<Vec<_>>();
+    assert_eq!(exports, ["log-again"]);
+
+    let text = wit.to_string();
+    assert!(text.contains("package wasi:cli@0.2.0;"), "{text}");
+    assert!(
+        text.contains("get-arguments: func() -> list<string>;"),
+        "{text}"
+    );
+    assert!(text.contains("import log: func(msg: string);"), "{text}");
+    assert!(
+        text.contains("export log-again: func(msg: string);"),
+        "{text}"
+    );
+
+    // The WIT can be decoded without compiling the component, but isn't
+    // available for precompiled components.
+    let binary = wat::parse_str(COMPONENT)?;
+    assert_eq!(ComponentWit::from_binary(&binary)?.to_string(), text);
+    let serialized = component.serialize()?;
+    let component = unsafe { Component::deserialize(&engine, &serialized)? };
+    assert!(component.wit().is_err());
+    Ok(())
+}
+
+#[test]
+fn component_wit_unavailable() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(
+        &engine,
+        r#"
+            (component
+                (import "m" (core module))
+            )
+        "#,
+    )?;
+    assert!(component.wit().is_err());
+    Ok(())
+}
@@ -78,3 +78,135 @@ fn linear_memory_limits() -> Result<()> {
         Ok(())
     }
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn replace_module() -> Result<()> {
+    let v1 = r#"
+        (module
+            (memory (export "memory") 1)
+            (global $count (mut i32) (i32.const 0))
+            (table 1 funcref)
+            (elem (i32.const 0) $step)
+            (func $step (result i32)
+                (global.set $count (i32.add (global.get $count) (i32.const 1)))
+                (i32.store (i32.const 0) (global.get $count))
+                global.get $count)
+            (func (export "step") (result i32)
+                (call_indirect (result i32) (i32.const 0)))
+        )
+    "#;
+    let v2 = r#"
+        (module $v2
+            (memory (export "memory") 1)
+            (global $count (mut i32) (i32.const 0))
+            (table 1 funcref)
+            (elem (i32.const 0) $step)
+            (func $step (result i32)
+                (global.set $count (i32.add (global.get $count) (i32.const 10)))
+                (i32.store (i32.const 0) (global.get $count))
+                global.get $count)
+            (func (export "step") (result i32)
+                (call_indirect (result i32) (i32.const 0)))
+        )
+    "#;
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &Module::new(&engine, v1)?, &[])?;
+    let step = instance.get_typed_func::<(), i32>(&mut store, "step")?;
+    assert_eq!(step.call(&mut store, ())?, 1);
+    assert_eq!(step.call(&mut store, ())?, 2);
+
+    let new = Module::new(&engine, v2)?;
+    instance.replace_module(&mut store, &new)?;
+    assert_eq!(instance.module(&store).name(), Some("v2"));
+
+    // Both the previously-acquired export and the table entry it calls
+    // through now run the new code, while the global keeps its value.
+    assert_eq!(step.call(&mut store, ())?, 12);
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    assert_eq!(memory.data(&store)[0], 12);
+    let step = instance.get_typed_func::<(), i32>(&mut store, "step")?;
+    assert_eq!(step.call(&mut store, ())?, 22);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn replace_module_incompatible() -> Result<()> {
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    let module = Module::new(
+        &engine,
+        r#"(module (memory 1) (func (export "f") (param i32)))"#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+
+    let cases = [
+        (
+            r#"(module (memory 1) (func (export "f") (param i64)))"#,
+            "function 0 has a different type",
+        ),
+        (
+            r#"(module (memory 2) (func (export "f") (param i32)))"#,
+            "memory 0 has type",
+        ),
+        (
+            r#"(module (memory 1) (func (export "g") (param i32)))"#,
+            "export `f` is missing",
+        ),
+        (
+            r#"(module (memory 1) (func (export "f") (param i32)) (func))"#,
+            "replacement module has 2 functions",
+        ),
+        (
+            r#"(module (import "" "" (func)) (memory 1) (func (export "f") (param i32)))"#,
+            "replacement module has 1 imports",
+        ),
+    ];
+    for (wat, expected) in cases {
+        let replacement = Module::new(&engine, wat)?;
+        let err = instance
+            .replace_module(&mut store, &replacement)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(expected), "bad error: {err}");
+    }
+
+    let other = Engine::default();
+    let replacement = Module::new(
+        &other,
+        r#"(module (memory 1) (func (export "f") (param i32)))"#,
+    )?;
+    assert!(instance.replace_module(&mut store, &replacement).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn replace_module_with_importer() -> Result<()> {
+    let v1 = r#"(module (func (export "f") (result i32) i32.const 1))"#;
+    let v2 = r#"(module (func (export "f") (result i32) i32.const 2))"#;
+    let importer = r#"
+        (module
+            (import "" "f" (func $f (result i32)))
+            (func (export "call") (result i32) call $f)
+        )
+    "#;
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &Module::new(&engine, v1)?, &[])?;
+    let f = instance.get_func(&mut store, "f").unwrap();
+    let importer = Instance::new(&mut store, &Module::new(&engine, importer)?, &[f.into()])?;
+    let call = importer.get_typed_func::<(), i32>(&mut store, "call")?;
+    assert_eq!(call.call(&mut store, ())?, 1);
+
+    instance.replace_module(&mut store, &Module::new(&engine, v2)?)?;
+
+    // The export itself runs the new code, but the importer captured the
+    // previous code when it was instantiated and keeps calling it.
+    let f = f.typed::<(), i32>(&store)?;
+    assert_eq!(f.call(&mut store, ())?, 2);
+    assert_eq!(call.call(&mut store, ())?, 1);
+    Ok(())
+}
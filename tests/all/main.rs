@@ -27,6 +27,7 @@ mod memory_creator;
 mod module;
 mod module_serialize;
 mod name;
+mod pgo;
 mod piped_tests;
 mod pooling_allocator;
 mod relocs;
@@ -660,3 +660,248 @@ fn init_with_negative_segment() -> Result<()> {
     Instance::new(&mut store, &module, &[])?;
     Ok(())
 }
+
+#[test]
+#[cfg_attr(any(miri, windows), ignore)]
+fn file_backed_memory_private() -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut file = tempfile::tempfile()?;
+    let mut contents = vec![0xab; 70_000];
+    contents[..5].copy_from_slice(b"hello");
+    file.write_all(&contents)?;
+
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    let memory = Memory::new_file_backed(
+        &mut store,
+        MemoryType::new(1, Some(4)),
+        file.try_clone()?,
+        FileMapping::Private,
+    )?;
+
+    // The memory covers the whole file, and bytes past its end are zero.
+    assert_eq!(memory.size(&store), 2);
+    assert_eq!(&memory.data(&store)[..70_000], &contents[..]);
+    assert!(memory.data(&store)[70_000..].iter().all(|b| *b == 0));
+
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (import "" "") 1)
+                (func (export "store") (param i32 i32)
+                    (i32.store8 (local.get 0) (local.get 1)))
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0)))
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[memory.into()])?;
+    let store8 = instance.get_typed_func::<(i32, i32), ()>(&mut store, "store")?;
+    let grow = instance.get_typed_func::<i32, i32>(&mut store, "grow")?;
+
+    store8.call(&mut store, (0, i32::from(b'j')))?;
+    assert_eq!(&memory.data(&store)[..5], b"jello");
+    assert_eq!(grow.call(&mut store, 2)?, 2);
+    assert_eq!(grow.call(&mut store, 1)?, -1);
+    assert_eq!(memory.size(&store), 4);
+    assert!(memory.data(&store)[70_000..].iter().all(|b| *b == 0));
+    assert_eq!(&memory.data(&store)[..5], b"jello");
+
+    // Private modifications never make it back to the file.
+    let mut on_disk = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut on_disk)?;
+    assert_eq!(on_disk, contents);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(miri, windows), ignore)]
+fn file_backed_memory_shared() -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut file = tempfile::tempfile()?;
+    file.write_all(b"hello")?;
+
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    let memory = Memory::new_file_backed(
+        &mut store,
+        MemoryType::new(0, None),
+        file.try_clone()?,
+        FileMapping::Shared,
+    )?;
+    assert_eq!(memory.size(&store), 1);
+    assert_eq!(&memory.data(&store)[..5], b"hello");
+
+    memory.data_mut(&mut store)[0] = b'j';
+    memory.grow(&mut store, 1)?;
+    memory.data_mut(&mut store)[65536] = b'!';
+    assert_eq!(&memory.data(&store)[..5], b"jello");
+    assert_eq!(memory.data(&store)[65536], b'!');
+
+    // Writes within the file are reflected in the file itself, but neither
+    // creating nor growing the memory changes the file's length.
+    let mut contents = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut contents)?;
+    assert_eq!(contents, b"jello");
+
+    // A file that doesn't fit in the memory is rejected.
+    let err = Memory::new_file_backed(
+        &mut store,
+        MemoryType::new(0, Some(1)),
+        file,
+        FileMapping::Shared,
+    )
+    .unwrap_err();
+    assert!(
+        format!("{err:?}").contains("does not fit"),
+        "bad error: {err:?}"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn huge_pages() -> Result<()> {
+    let wat = r#"
+        (module
+            (memory (export "memory") 1)
+            (data (i32.const 100) "hello")
+            (func (export "grow") (param i32) (result i32)
+                local.get 0
+                memory.grow)
+        )
+    "#;
+
+    let mut on_demand = Config::new();
+    on_demand.memory_huge_pages(true);
+    on_demand.static_memory_maximum_size(0);
+    on_demand.dynamic_memory_reserved_for_growth(0);
+
+    let mut pool = PoolingAllocationConfig::default();
+    pool.total_memories(1).memory_pages(10);
+    let mut pooling = Config::new();
+    pooling.memory_huge_pages(true);
+    pooling.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+
+    for config in [on_demand, pooling] {
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, wat)?;
+
+        // Instantiate twice to exercise reuse of pooled slots and their
+        // copy-on-write images.
+        for _ in 0..2 {
+            let mut store = Store::new(&engine, ());
+            let instance = Instance::new(&mut store, &module, &[])?;
+            let memory = instance.get_memory(&mut store, "memory").unwrap();
+            let grow = instance.get_typed_func::<i32, i32>(&mut store, "grow")?;
+
+            assert_eq!(&memory.data(&store)[100..105], b"hello");
+            assert_huge_pages_advised(memory.data(&store));
+            memory.data_mut(&mut store)[100] = b'j';
+            assert_eq!(grow.call(&mut store, 2)?, 1);
+            assert_eq!(&memory.data(&store)[100..105], b"jello");
+            assert!(memory.data(&store)[65536..].iter().all(|b| *b == 0));
+            assert_huge_pages_advised(memory.data(&store));
+        }
+
+        // Memories created by the host use the setting as well, whether
+        // they're grown or not.
+        let mut store = Store::new(&engine, ());
+        let host = Memory::new(&mut store, MemoryType::new(1, None))?;
+        assert_huge_pages_advised(host.data(&store));
+        host.grow(&mut store, 1)?;
+        assert_huge_pages_advised(host.data(&store));
+
+        let shared = SharedMemory::new(&engine, MemoryType::shared(1, 2))?;
+        let data = unsafe { std::slice::from_raw_parts(shared.data().as_ptr().cast(), 65536) };
+        assert_huge_pages_advised(data);
+    }
+    Ok(())
+}
+
+/// Asserts that all of `data` lies in mappings which have been advised to use
+/// transparent huge pages.
+#[cfg(target_os = "linux")]
+fn assert_huge_pages_advised(data: &[u8]) {
+    // Without transparent huge page support in the kernel the advice is
+    // rejected, so there's nothing to check.
+    if !std::path::Path::new("/sys/kernel/mm/transparent_hugepage").exists() {
+        return;
+    }
+    let start = data.as_ptr() as usize;
+    let end = start + data.len();
+    let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+    let mut range = 0..0;
+    let mut covered = start;
+    for line in smaps.lines() {
+        if let Some(flags) = line.strip_prefix("VmFlags:") {
+            if range.start < end && start < range.end {
+                assert!(
+                    flags.split_whitespace().any(|f| f == "hg"),
+                    "mapping {range:#x?} isn't advised to use huge pages: {flags}"
+                );
+                covered = covered.max(range.end);
+            }
+        } else if let Some((addrs, _)) = line.split_once(' ') {
+            if let Some((lo, hi)) = addrs.split_once('-') {
+                if let (Ok(lo), Ok(hi)) =
+                    (usize::from_str_radix(lo, 16), usize::from_str_radix(hi, 16))
+                {
+                    range = lo..hi;
+                }
+            }
+        }
+    }
+    assert!(covered >= end, "{start:#x}..{end:#x} isn't fully mapped");
+}
+
+#[cfg(not(target_os = "linux"))]
+fn assert_huge_pages_advised(_data: &[u8]) {}
+
+#[test]
+#[cfg_attr(any(miri, windows), ignore)]
+fn file_backed_memory_limited() -> Result<()> {
+    use std::io::Write;
+
+    let mut file = tempfile::tempfile()?;
+    file.write_all(&[0; 70_000])?;
+
+    // The limiter sees the size needed to cover the file, not just the
+    // minimum of the memory type.
+    let engine = Engine::default();
+    let mut store = Store::new(
+        &engine,
+        StoreLimitsBuilder::new().memory_size(65536).build(),
+    );
+    store.limiter(|limits| limits);
+    let err = Memory::new_file_backed(
+        &mut store,
+        MemoryType::new(0, None),
+        file.try_clone()?,
+        FileMapping::Private,
+    )
+    .unwrap_err();
+    assert!(
+        format!("{err:?}").contains("exceeds memory limits"),
+        "bad error: {err:?}"
+    );
+
+    let mut store = Store::new(
+        &engine,
+        StoreLimitsBuilder::new().memory_size(2 * 65536).build(),
+    );
+    store.limiter(|limits| limits);
+    let memory = Memory::new_file_backed(
+        &mut store,
+        MemoryType::new(0, None),
+        file,
+        FileMapping::Private,
+    )?;
+    assert_eq!(memory.size(&store), 2);
+    Ok(())
+}
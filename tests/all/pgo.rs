@@ -0,0 +1,96 @@
+//! Tests for profile-guided optimization with
+//! `Config::cranelift_pgo_instrument` and `Config::cranelift_pgo_profile`.
+
+#![cfg(not(miri))]
+
+use anyhow::Result;
+use wasmtime::*;
+
+const WAT: &str = r#"
+    (module
+        (func (export "count") (param i32) (result i32)
+            (local i32)
+            (block
+                (loop
+                    (br_if 1 (i32.eqz (local.get 0)))
+                    (if (i32.and (local.get 0) (i32.const 1))
+                        (then (local.set 1 (i32.add (local.get 1) (i32.const 1)))))
+                    (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                    (br 0)))
+            local.get 1))
+"#;
+
+fn run(config: &Config) -> Result<i32> {
+    let engine = Engine::new(config)?;
+    let mut store = Store::new(&engine, ());
+    let module = Module::new(&engine, WAT)?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let count = instance.get_typed_func::<i32, i32>(&mut store, "count")?;
+    count.call(&mut store, 100)
+}
+
+#[test]
+fn instrument_and_optimize() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("profile");
+
+    let mut config = Config::new();
+    config.cranelift_pgo_instrument(&path);
+    assert_eq!(run(&config)?, 50);
+    assert_eq!(run(&config)?, 50);
+
+    // The counts of both runs were summed up, and the loop ran 101 times in
+    // each.
+    let profile = std::fs::read_to_string(&path)?;
+    let counts = profile
+        .lines()
+        .find(|line| !line.starts_with('#'))
+        .unwrap()
+        .split_ascii_whitespace()
+        .skip(2)
+        .map(|count| count.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(counts.contains(&2));
+    assert!(counts.contains(&202));
+
+    let mut config = Config::new();
+    config
+        .cranelift_opt_level(OptLevel::Speed)
+        .cranelift_inlining(true)
+        .cranelift_pgo_profile(&path);
+    assert_eq!(run(&config)?, 50);
+    Ok(())
+}
+
+#[test]
+fn instrument_rejects_other_targets() -> Result<()> {
+    let target = if cfg!(target_arch = "s390x") {
+        "x86_64-unknown-linux-gnu"
+    } else {
+        "s390x-unknown-linux-gnu"
+    };
+    let mut config = Config::new();
+    config.cranelift_pgo_instrument("profile").target(target)?;
+    assert!(Engine::new(&config).is_err());
+    Ok(())
+}
+
+#[test]
+fn instrument_rejects_serialization() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut config = Config::new();
+    config.cranelift_pgo_instrument(dir.path().join("profile"));
+    let engine = Engine::new(&config)?;
+    let module = Module::new(&engine, WAT)?;
+    assert!(module.serialize().is_err());
+    assert!(engine.precompile_module(WAT.as_bytes()).is_err());
+    Ok(())
+}
+
+#[test]
+fn missing_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config::new();
+    config.cranelift_pgo_profile(dir.path().join("missing"));
+    assert!(Engine::new(&config).is_err());
+}
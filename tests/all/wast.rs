@@ -61,6 +61,12 @@ fn run_wast(wast: &str, strategy: Strategy, pooling: bool) -> anyhow::Result<()>
     if feature_found(wast, "canonicalize-nan") && is_cranelift {
         cfg.cranelift_nan_canonicalization(true);
     }
+    if feature_found(wast, "inlining") && is_cranelift {
+        cfg.cranelift_inlining(true);
+    }
+    if feature_found(wast, "bounds-check-elimination") && is_cranelift {
+        cfg.cranelift_bounds_check_elimination(true);
+    }
     let test_allocates_lots_of_memory = wast.ends_with("more-than-4gb.wast");
 
     // By default we'll allocate huge chunks (6gb) of the address space for each
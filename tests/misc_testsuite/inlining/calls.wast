@@ -0,0 +1,121 @@
+;; Direct calls to small functions are inlined when this directory is run, so
+;; these exercise inlined callees which use the caller's instance state,
+;; return in several places, trap, and call other functions.
+
+(module
+  (import "spectest" "print_i32" (func $print (param i32)))
+
+  (memory 1)
+  (global $g (mut i32) (i32.const 0))
+  (table 1 funcref)
+  (elem (i32.const 0) $add1)
+
+  (func $add1 (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add)
+
+  (func $max (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.gt_s
+    if
+      local.get 0
+      return
+    end
+    local.get 1)
+
+  (func $load (param i32) (result i32)
+    local.get 0
+    i32.load)
+
+  (func $store (param i32 i32)
+    local.get 0
+    local.get 1
+    i32.store)
+
+  (func $bump (result i32)
+    global.get $g
+    i32.const 1
+    i32.add
+    global.set $g
+    global.get $g)
+
+  (func $div (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.div_s)
+
+  (func $log (param i32) (result i32)
+    local.get 0
+    call $print
+    local.get 0)
+
+  (func $fac (param i64) (result i64)
+    local.get 0
+    i64.eqz
+    if (result i64)
+      i64.const 1
+    else
+      local.get 0
+      local.get 0
+      i64.const 1
+      i64.sub
+      call $fac
+      i64.mul
+    end)
+
+  (func (export "add1") (param i32) (result i32)
+    local.get 0
+    call $add1
+    call $add1)
+
+  (func (export "max") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    call $max)
+
+  (func (export "memory") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    call $store
+    local.get 0
+    call $load)
+
+  (func (export "global") (result i32)
+    call $bump
+    drop
+    call $bump)
+
+  (func (export "div") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    call $div)
+
+  (func (export "log") (param i32) (result i32)
+    local.get 0
+    call $log)
+
+  (func (export "fac") (param i64) (result i64)
+    local.get 0
+    call $fac)
+
+  (func (export "indirect") (param i32) (result i32)
+    local.get 0
+    i32.const 0
+    call_indirect (param i32) (result i32))
+)
+
+(assert_return (invoke "add1" (i32.const 1)) (i32.const 3))
+(assert_return (invoke "max" (i32.const 1) (i32.const 2)) (i32.const 2))
+(assert_return (invoke "max" (i32.const 3) (i32.const -2)) (i32.const 3))
+(assert_return (invoke "memory" (i32.const 8) (i32.const 42)) (i32.const 42))
+(assert_trap (invoke "memory" (i32.const 65536) (i32.const 42)) "out of bounds memory access")
+(assert_return (invoke "global") (i32.const 2))
+(assert_return (invoke "global") (i32.const 4))
+(assert_return (invoke "div" (i32.const 7) (i32.const 2)) (i32.const 3))
+(assert_trap (invoke "div" (i32.const 7) (i32.const 0)) "integer divide by zero")
+(assert_trap (invoke "div" (i32.const 0x80000000) (i32.const -1)) "integer overflow")
+(assert_return (invoke "log" (i32.const 5)) (i32.const 5))
+(assert_return (invoke "fac" (i64.const 10)) (i64.const 3628800))
+(assert_return (invoke "indirect" (i32.const 4)) (i32.const 5))
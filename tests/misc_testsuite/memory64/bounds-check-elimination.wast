@@ -0,0 +1,141 @@
+;; Accesses whose bounds checks are covered by an earlier check of the same
+;; address must still behave exactly as if they were checked themselves.
+
+(module
+  (memory i64 1)
+
+  ;; The second and third loads are covered by the first one's check.
+  (func (export "sum") (param $p i64) (result i32)
+    (i32.add
+      (i32.load offset=8 (local.get $p))
+      (i32.add
+        (i32.load offset=4 (local.get $p))
+        (i32.load offset=4 (i64.add (local.get $p) (i64.const 4))))))
+
+  ;; The load in the loop is covered by the load before it.
+  (func (export "loop") (param $p i64) (param $n i32) (result i32)
+    (local $acc i32)
+    (local.set $acc (i32.load (local.get $p)))
+    (loop $l
+      (local.set $acc (i32.add (local.get $acc) (i32.load (local.get $p))))
+      (br_if $l (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+    (local.get $acc))
+
+  ;; A narrower access doesn't cover a wider one.
+  (func (export "wider") (param $p i64) (result i64)
+    (drop (i32.load8_u (local.get $p)))
+    (i64.load (local.get $p)))
+
+  ;; The first check still holds after the memory grows.
+  (func (export "grow") (param $p i64) (result i32)
+    (drop (i32.load (local.get $p)))
+    (drop (memory.grow (i64.const 1)))
+    (i32.load (local.get $p)))
+
+  (data (i64.const 0) "\01\00\00\00\02\00\00\00\03\00\00\00\04\00\00\00")
+)
+
+(assert_return (invoke "sum" (i64.const 0)) (i32.const 8))
+(assert_return (invoke "sum" (i64.const 0xfff4)) (i32.const 0))
+(assert_trap (invoke "sum" (i64.const 0xfff8)) "out of bounds memory access")
+(assert_trap (invoke "sum" (i64.const 0xfffc)) "out of bounds memory access")
+
+(assert_return (invoke "loop" (i64.const 4) (i32.const 3)) (i32.const 8))
+(assert_trap (invoke "loop" (i64.const 0xfffd) (i32.const 1)) "out of bounds memory access")
+
+(assert_return (invoke "wider" (i64.const 0)) (i64.const 0x2_0000_0001))
+(assert_return (invoke "wider" (i64.const 0xfff8)) (i64.const 0))
+(assert_trap (invoke "wider" (i64.const 0xfff9)) "out of bounds memory access")
+
+(assert_trap (invoke "grow" (i64.const 0x10000)) "out of bounds memory access")
+(assert_return (invoke "grow" (i64.const 0xfffc)) (i32.const 0))
+(assert_return (invoke "grow" (i64.const 0x10000)) (i32.const 0))
+(assert_return (invoke "sum" (i64.const 0xfffc)) (i32.const 0))
+
+;; The checks in counted loops are hoisted out of them, and must trap exactly
+;; when some iteration's access is out of bounds.
+(module
+  (memory i64 1)
+
+  ;; Counts up while `i + 1 < n`.
+  (func (export "sum") (param $p i64) (param $n i64) (result i32)
+    (local $i i64)
+    (local $acc i32)
+    (loop $l
+      (local.set $acc
+        (i32.add (local.get $acc)
+          (i32.load (i64.add (local.get $p) (i64.shl (local.get $i) (i64.const 2))))))
+      (br_if $l (i64.lt_u (local.tee $i (i64.add (local.get $i) (i64.const 1))) (local.get $n))))
+    (local.get $acc))
+
+  ;; Bumps a pointer until it reaches the end.
+  (func (export "bump") (param $p i64) (param $end i64) (result i32)
+    (local $acc i32)
+    (loop $l
+      (local.set $acc (i32.add (local.get $acc) (i32.load offset=4 (local.get $p))))
+      (br_if $l (i64.ne (local.tee $p (i64.add (local.get $p) (i64.const 4))) (local.get $end))))
+    (local.get $acc))
+
+  ;; Counts down to zero.
+  (func (export "down") (param $p i64) (param $n i64) (result i32)
+    (local $acc i32)
+    (loop $l
+      (local.set $acc
+        (i32.add (local.get $acc)
+          (i32.load16_u offset=2 (i64.add (local.get $p) (i64.mul (local.get $n) (i64.const 2))))))
+      (br_if $l (i64.ne (local.tee $n (i64.sub (local.get $n) (i64.const 1))) (i64.const 0))))
+    (local.get $acc))
+
+  ;; Loads through a pointer that isn't the counter.
+  (func (export "two") (param $p i64) (param $n i64) (result i64)
+    (local $i i64)
+    (local $acc i64)
+    (loop $l
+      (local.set $acc (i64.add (local.get $acc) (i64.load (local.get $p))))
+      (local.set $p (i64.add (local.get $p) (i64.const 8)))
+      (br_if $l (i64.ne (local.tee $i (i64.add (local.get $i) (i64.const 1))) (local.get $n))))
+    (local.get $acc))
+
+  ;; The check of a loop that stores stays in it.
+  (func (export "fill") (param $p i64) (param $n i64)
+    (local $i i64)
+    (loop $l
+      (i32.store8 (i64.add (local.get $p) (local.get $i)) (i32.const 7))
+      (br_if $l (i64.lt_u (local.tee $i (i64.add (local.get $i) (i64.const 1))) (local.get $n)))))
+
+  (func (export "load8") (param $p i64) (result i32)
+    (i32.load8_u (local.get $p)))
+
+  (data (i64.const 0) "\01\00\00\00\02\00\00\00\03\00\00\00\04\00\00\00")
+)
+
+(assert_return (invoke "sum" (i64.const 0) (i64.const 4)) (i32.const 10))
+(assert_return (invoke "sum" (i64.const 0) (i64.const 0)) (i32.const 1))
+(assert_return (invoke "sum" (i64.const 4) (i64.const 2)) (i32.const 5))
+(assert_return (invoke "sum" (i64.const 0xfff8) (i64.const 2)) (i32.const 0))
+(assert_return (invoke "sum" (i64.const 0) (i64.const 0x4000)) (i32.const 10))
+(assert_trap (invoke "sum" (i64.const 0) (i64.const 0x4001)) "out of bounds memory access")
+(assert_trap (invoke "sum" (i64.const 0xfff8) (i64.const 3)) "out of bounds memory access")
+(assert_trap (invoke "sum" (i64.const 0x10000) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "sum" (i64.const -4) (i64.const 2)) "out of bounds memory access")
+(assert_trap (invoke "sum" (i64.const 0) (i64.const -1)) "out of bounds memory access")
+
+(assert_return (invoke "bump" (i64.const 0) (i64.const 12)) (i32.const 9))
+(assert_return (invoke "bump" (i64.const 0xfff0) (i64.const 0xfffc)) (i32.const 0))
+(assert_trap (invoke "bump" (i64.const 0xfff0) (i64.const 0x10000)) "out of bounds memory access")
+;; The pointer never reaches a misaligned end.
+(assert_trap (invoke "bump" (i64.const 0) (i64.const 10)) "out of bounds memory access")
+
+(assert_return (invoke "down" (i64.const 0) (i64.const 3)) (i32.const 5))
+(assert_return (invoke "down" (i64.const 0xfffa) (i64.const 1)) (i32.const 0))
+(assert_trap (invoke "down" (i64.const 0xfffa) (i64.const 2)) "out of bounds memory access")
+(assert_trap (invoke "down" (i64.const 0) (i64.const 0)) "out of bounds memory access")
+
+(assert_return (invoke "two" (i64.const 0) (i64.const 2)) (i64.const 0x6_0000_0004))
+(assert_return (invoke "two" (i64.const 0xfff0) (i64.const 2)) (i64.const 0))
+(assert_trap (invoke "two" (i64.const 0xfff8) (i64.const 2)) "out of bounds memory access")
+
+;; The stores before the one that is out of bounds still happen.
+(assert_trap (invoke "fill" (i64.const 0xfffe) (i64.const 4)) "out of bounds memory access")
+(assert_return (invoke "load8" (i64.const 0xfffe)) (i32.const 7))
+(assert_return (invoke "load8" (i64.const 0xffff)) (i32.const 7))